@@ -0,0 +1,11 @@
+//! Feeds arbitrary frame contents to the decoder
+//!
+//! Run with `cargo +nightly fuzz run decode_record` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = ge_dri_prototype::decode::decode_record(data);
+});
@@ -5,22 +5,32 @@
 //!
 //! Usage:
 //!   cargo run --bin diagnostic
+//!   cargo run --bin diagnostic -- capture.bin   (replay a captured serial stream)
 //!
 //! This will:
-//! 1. Let you select the serial port
+//! 1. Auto-detect the serial port (or let you select it)
 //! 2. Request all physiological data every 5 seconds
 //! 3. Request common waveforms (ECG1, PLETH)
 //! 4. Log ALL received data in a verbose, readable format
+//! 5. Detect whether the checksums include the byte stuffing escapes
 //!
 //! Press Ctrl+C to stop
 
 use anyhow::Result;
 use std::io::Write;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use ge_dri_prototype::decode::{Decoder, DriRecord};
-use ge_dri_prototype::device::SerialDevice;
-use ge_dri_prototype::protocol::DriHeader;
+use chrono::Utc;
+use ge_dri_prototype::DriError;
+use ge_dri_prototype::decode::{DecodeMetrics, Decoder, DriRecord, WaveformCommand};
+use ge_dri_prototype::device::{Device, FileTransport, SerialConfig, SerialDevice, Transport};
+use ge_dri_prototype::protocol::{ChecksumMode, SequenceMonitor};
+
+/// Clock skew beyond which the monitor's RTC is reported as misconfigured
+const CLOCK_SKEW_WARNING_SECS: i64 = 60;
+
+/// How often the decode counters are printed
+const METRICS_INTERVAL: Duration = Duration::from_secs(30);
 
 fn main() -> Result<()> {
     // Initialize logger
@@ -33,16 +43,32 @@ fn main() -> Result<()> {
     println!("╚══════════════════════════════════════════════════════════════╝");
     println!();
 
-    // Select serial port (interactive)
-    let port_name = ge_dri_prototype::device::select_port()?;
+    if let Some(path) = std::env::args().nth(1) {
+        println!("📂 Replaying captured stream {}", path);
+        println!();
+        let config = SerialConfig::new().checksum_mode(ChecksumMode::Auto);
+        return run(Device::with_transport(FileTransport::open(&path)?, config));
+    }
+
+    // Select serial port (auto-detect, then interactive)
+    let port_name = match ge_dri_prototype::device::find_ge_port()? {
+        Some(port_name) => port_name,
+        None => ge_dri_prototype::device::select_port()?,
+    };
     println!("✅ Selected port: {}", port_name);
 
     // Connect to device
     println!("🔌 Connecting to monitor...");
-    let mut device = SerialDevice::open(&port_name)?;
+    let config = SerialConfig::new().checksum_mode(ChecksumMode::Auto);
+    let device = SerialDevice::open_with_config(&port_name, &config)?;
     println!("✅ Connected successfully!");
     println!();
 
+    run(device)
+}
+
+/// Request data, then log everything received until stopped or the input ends
+fn run<T: Transport>(mut device: Device<T>) -> Result<()> {
     // Fixed settings for diagnostic mode
     let interval: u16 = 5; // 5 seconds
     let waveforms = vec!["ECG1", "PLETH"];
@@ -54,9 +80,12 @@ fn main() -> Result<()> {
 
     // Request data from monitor
     println!("📡 Requesting data from monitor...");
-    device.request_displayed_values(interval)?;
-    device.request_waveforms(&waveforms)?;
-    println!("✅ Requests sent!");
+    let interval = device.request_displayed_values(interval)?;
+    let requested = device.request_waveforms(&waveforms)?;
+    println!(
+        "✅ Requests sent: values every {} seconds, waveforms {:?}",
+        interval, requested
+    );
     println!();
 
     println!("═══════════════════════════════════════════════════════════════");
@@ -65,16 +94,31 @@ fn main() -> Result<()> {
     println!("═══════════════════════════════════════════════════════════════");
     println!();
 
-    let decoder = Decoder::new();
+    let mut decoder = Decoder::new();
     let start_time = Instant::now();
     let mut frame_count: u32 = 0;
     let mut phys_count: u32 = 0;
     let mut wave_count: u32 = 0;
+    let mut sequence = SequenceMonitor::new();
+    let metrics = DecodeMetrics::new();
+    let mut last_metrics = Instant::now();
+    let mut last_command: Option<WaveformCommand> = None;
+    let mut checksum_mode = device.checksum_mode();
 
     loop {
+        if last_metrics.elapsed() >= METRICS_INTERVAL {
+            println!("📈 METRICS: {}", metrics.snapshot());
+            last_metrics = Instant::now();
+        }
+
         match device.read_frame() {
             Ok(frame) => {
                 frame_count += 1;
+                metrics.record_frame();
+                if device.checksum_mode() != checksum_mode {
+                    checksum_mode = device.checksum_mode();
+                    println!("🔎 Checksum convention detected: {:?}", checksum_mode);
+                }
                 let elapsed = start_time.elapsed().as_secs();
 
                 println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
@@ -85,231 +129,349 @@ fn main() -> Result<()> {
                     frame.data.len()
                 );
 
-                // Parse header
-                let header = match DriHeader::parse(&frame.data) {
-                    Ok(h) => h,
-                    Err(e) => {
-                        println!("   ❌ Header parse error: {}", e);
-                        continue;
-                    }
+                // Parse header and decode
+                let decoded = match decoder.decode_with_meta(&frame.data) {
+                    Ok(d) => d,
+                    Err(e) => match e.downcast_ref() {
+                        Some(DriError::FramingError(len)) => {
+                            println!("   ℹ️  Short frame ({} bytes), not a data record", len);
+                            continue;
+                        }
+                        _ => {
+                            println!("   ❌ Header parse error: {}", e);
+                            metrics.record_decode_error();
+                            continue;
+                        }
+                    },
                 };
+                let meta = &decoded.meta;
 
                 println!(
-                    "   📋 Header: type={:?}, level={:?}, time={}",
-                    header.r_maintype,
-                    header.dri_level,
-                    header.timestamp()
+                    "   📋 Header: type={:?}, level={:?}, plug_id={}, record #{}, time={}",
+                    meta.maintype, meta.dri_level, meta.plug_id, meta.record_number, meta.timestamp
+                );
+                println!(
+                    "   📋 Subrecords: {} (types {:?})",
+                    meta.subrecord_types.len(),
+                    meta.subrecord_types
                 );
-                println!("   📋 Subrecords: {}", header.subrecords.len());
 
-                // Extract data
-                let data = match header.extract_data(&frame.data) {
-                    Ok(d) => d,
-                    Err(e) => {
-                        println!("   ❌ Data extract error: {}", e);
-                        continue;
+                let skew = meta.timestamp - Utc::now();
+                println!("   🕒 Monitor clock skew: {:+}s", skew.num_seconds());
+                if skew.num_seconds().abs() > CLOCK_SKEW_WARNING_SECS {
+                    println!(
+                        "   ⚠️  Monitor clock differs from host clock; check the monitor's RTC"
+                    );
+                }
+
+                let missed = sequence.observe(meta.record_number);
+                if missed > 0 {
+                    println!(
+                        "   ⚠️  {} record(s) missed before record #{}",
+                        missed, meta.record_number
+                    );
+                }
+
+                match &decoded.records {
+                    Ok(records) => metrics.record_records(records),
+                    Err(_) => metrics.record_decode_error(),
+                }
+
+                if let Some(command) = decoder.waveform_command()
+                    && last_command.as_ref() != Some(command)
+                {
+                    println!(
+                        "   🎛️  Monitor accepted waveforms {:?} ({} samples/s)",
+                        command.waveforms,
+                        command.total_rate()
+                    );
+                    let dropped = command.dropped(&requested);
+                    if !dropped.is_empty() {
+                        println!("   ⚠️  Requested but not accepted: {:?}", dropped);
                     }
-                };
+                    last_command = Some(command.clone());
+                }
 
-                // Decode
-                match decoder.decode_frame(&header, data) {
-                    Ok(Some(record)) => {
-                        match &record {
-                            DriRecord::Physiological(phys) => {
-                                phys_count += 1;
-                                println!();
-                                println!(
-                                    "   🏥 PHYSIOLOGICAL DATA (#{}) - class={:?}, subtype={:?}",
-                                    phys_count, phys.class, phys.subtype
-                                );
-                                println!(
-                                    "   ─────────────────────────────────────────────────────"
-                                );
-
-                                // ECG
-                                println!("   💓 ECG:");
-                                println!(
-                                    "      • Status: exists={}, active={}, asystole={}, noise={}",
-                                    phys.ecg_status.exists,
-                                    phys.ecg_status.active,
-                                    phys.ecg_status.asystole,
-                                    phys.ecg_status.noise
-                                );
-                                print_value("      • Heart Rate", phys.ecg_hr, "bpm");
-                                print_value("      • ST1", phys.ecg_st1, "mm");
-                                print_value("      • ST2", phys.ecg_st2, "mm");
-                                print_value("      • ST3", phys.ecg_st3, "mm");
-                                print_value("      • Resp Rate (imp)", phys.ecg_rr, "/min");
-                                if let Some(src) = &phys.ecg_hr_source {
-                                    println!("      • HR Source: {:?}", src);
-                                }
-                                if let Some(lead) = &phys.ecg_lead1 {
-                                    println!("      • Lead 1: {:?}", lead);
-                                }
+                match decoded.records {
+                    Ok(records) if !records.is_empty() => {
+                        for record in &records {
+                            match record {
+                                DriRecord::Physiological(phys) => {
+                                    phys_count += 1;
+                                    println!();
+                                    println!(
+                                        "   🏥 PHYSIOLOGICAL DATA (#{}) - class={:?}, subtype={:?}",
+                                        phys_count, phys.class, phys.subtype
+                                    );
+                                    println!(
+                                        "   ─────────────────────────────────────────────────────"
+                                    );
+
+                                    // ECG
+                                    println!("   💓 ECG:");
+                                    println!(
+                                        "      • Status: exists={}, active={}, asystole={}, noise={}",
+                                        phys.ecg_status.exists,
+                                        phys.ecg_status.active,
+                                        phys.ecg_status.asystole,
+                                        phys.ecg_status.noise
+                                    );
+                                    print_value("      • Heart Rate", phys.ecg_hr, "bpm");
+                                    print_value("      • ST1", phys.ecg_st1, "mm");
+                                    print_value("      • ST2", phys.ecg_st2, "mm");
+                                    print_value("      • ST3", phys.ecg_st3, "mm");
+                                    print_value("      • Resp Rate (imp)", phys.ecg_rr, "/min");
+                                    if let Some(src) = &phys.ecg_hr_source {
+                                        println!("      • HR Source: {:?}", src);
+                                    }
+                                    if let Some(lead) = &phys.ecg_lead1 {
+                                        println!("      • Lead 1: {:?}", lead);
+                                    }
 
-                                // SpO2
-                                println!("   🩸 SpO2:");
-                                println!(
-                                    "      • Status: exists={}, active={}",
-                                    phys.spo2_status.exists, phys.spo2_status.active
-                                );
-                                print_value("      • SpO2", phys.spo2, "%");
-                                print_value("      • Pulse Rate", phys.spo2_pr, "bpm");
-                                print_value("      • IR Amplitude", phys.spo2_ir_amp, "%");
-
-                                // NIBP
-                                println!("   🩺 NIBP:");
-                                println!(
-                                    "      • Status: exists={}, active={}, measuring={}",
-                                    phys.nibp_status.exists,
-                                    phys.nibp_status.active,
-                                    phys.nibp_status.measuring
-                                );
-                                print_value("      • Systolic", phys.nibp_sys, "mmHg");
-                                print_value("      • Diastolic", phys.nibp_dia, "mmHg");
-                                print_value("      • Mean", phys.nibp_mean, "mmHg");
-                                print_value("      • HR", phys.nibp_hr, "bpm");
-
-                                // Invasive Pressure 1
-                                if phys.invp1_status.exists {
-                                    println!("   📈 Invasive Pressure 1:");
+                                    // SpO2
+                                    println!("   🩸 SpO2:");
                                     println!(
                                         "      • Status: exists={}, active={}",
-                                        phys.invp1_status.exists, phys.invp1_status.active
+                                        phys.spo2_status.exists, phys.spo2_status.active
                                     );
-                                    if let Some(label) = &phys.invp1_label {
-                                        println!("      • Label: {:?}", label);
+                                    print_value("      • SpO2", phys.spo2, "%");
+                                    print_value("      • Pulse Rate", phys.spo2_pr, "bpm");
+                                    print_value("      • IR Amplitude", phys.spo2_ir_amp, "%");
+
+                                    // NIBP
+                                    println!("   🩺 NIBP:");
+                                    println!(
+                                        "      • Status: exists={}, active={}, measuring={}",
+                                        phys.nibp_status.exists,
+                                        phys.nibp_status.active,
+                                        phys.nibp_status.measuring
+                                    );
+                                    print_value("      • Systolic", phys.nibp_sys, "mmHg");
+                                    print_value("      • Diastolic", phys.nibp_dia, "mmHg");
+                                    print_value("      • Mean", phys.nibp_mean, "mmHg");
+                                    print_value("      • HR", phys.nibp_hr, "bpm");
+
+                                    // Invasive Pressure 1
+                                    if phys.invp1_status.exists {
+                                        println!("   📈 Invasive Pressure 1:");
+                                        println!(
+                                            "      • Status: exists={}, active={}",
+                                            phys.invp1_status.exists, phys.invp1_status.active
+                                        );
+                                        if let Some(label) = &phys.invp1_label {
+                                            println!("      • Label: {:?}", label);
+                                        }
+                                        print_value("      • Systolic", phys.invp1_sys, "mmHg");
+                                        print_value("      • Diastolic", phys.invp1_dia, "mmHg");
+                                        print_value("      • Mean", phys.invp1_mean, "mmHg");
                                     }
-                                    print_value("      • Systolic", phys.invp1_sys, "mmHg");
-                                    print_value("      • Diastolic", phys.invp1_dia, "mmHg");
-                                    print_value("      • Mean", phys.invp1_mean, "mmHg");
-                                }
 
-                                // Temperature
-                                println!("   🌡️  Temperature:");
-                                println!(
-                                    "      • Temp1 Status: exists={}, active={}",
-                                    phys.temp1_status.exists, phys.temp1_status.active
-                                );
-                                if let Some(label) = &phys.temp1_label {
-                                    println!("      • Temp1 Label: {:?}", label);
-                                }
-                                print_value("      • Temp1", phys.temp1, "°C");
-                                if phys.temp2_status.exists {
-                                    print_value("      • Temp2", phys.temp2, "°C");
-                                }
+                                    // Temperature
+                                    println!("   🌡️  Temperature:");
+                                    println!(
+                                        "      • Temp1 Status: exists={}, active={}",
+                                        phys.temp1_status.exists, phys.temp1_status.active
+                                    );
+                                    if let Some(label) = &phys.temp1_label {
+                                        println!("      • Temp1 Label: {:?}", label);
+                                    }
+                                    print_value("      • Temp1", phys.temp1, "°C");
+                                    if phys.temp2_status.exists {
+                                        print_value("      • Temp2", phys.temp2, "°C");
+                                    }
 
-                                // CO2
-                                println!("   💨 CO2:");
-                                println!(
-                                    "      • Status: exists={}, active={}, apnea={}",
-                                    phys.co2_status.exists,
-                                    phys.co2_status.active,
-                                    phys.co2_status.apnea_co2
-                                );
-                                print_value("      • EtCO2", phys.co2_et, "%");
-                                print_value("      • FiCO2", phys.co2_fi, "%");
-                                print_value("      • Resp Rate", phys.co2_rr, "/min");
-
-                                // O2
-                                println!("   🫁 O2:");
-                                println!(
-                                    "      • Status: exists={}, active={}",
-                                    phys.o2_status.exists, phys.o2_status.active
-                                );
-                                print_value("      • EtO2", phys.o2_et, "%");
-                                print_value("      • FiO2", phys.o2_fi, "%");
-
-                                // N2O
-                                if phys.n2o_status.exists {
-                                    println!("   🔵 N2O:");
-                                    print_value("      • EtN2O", phys.n2o_et, "%");
-                                    print_value("      • FiN2O", phys.n2o_fi, "%");
-                                }
+                                    // CO2
+                                    println!("   💨 CO2:");
+                                    println!(
+                                        "      • Status: exists={}, active={}, apnea={}",
+                                        phys.co2_status.exists,
+                                        phys.co2_status.active,
+                                        phys.co2_status.apnea_co2
+                                    );
+                                    print_value("      • EtCO2", phys.co2_et, "%");
+                                    print_value("      • FiCO2", phys.co2_fi, "%");
+                                    print_value("      • Resp Rate", phys.co2_rr, "/min");
+                                    print_value("      • Ambient", phys.co2_amb_press, "mmHg");
+                                    print_value(
+                                        "      • PetCO2",
+                                        phys.co2_partial_pressure_mmhg(),
+                                        "mmHg",
+                                    );
+
+                                    // O2
+                                    println!("   🫁 O2:");
+                                    println!(
+                                        "      • Status: exists={}, active={}",
+                                        phys.o2_status.exists, phys.o2_status.active
+                                    );
+                                    print_value("      • EtO2", phys.o2_et, "%");
+                                    print_value("      • FiO2", phys.o2_fi, "%");
+
+                                    // N2O
+                                    if phys.n2o_status.exists {
+                                        println!("   🔵 N2O:");
+                                        print_value("      • EtN2O", phys.n2o_et, "%");
+                                        print_value("      • FiN2O", phys.n2o_fi, "%");
+                                    }
 
-                                // Anesthesia Agent
-                                if phys.aa_status.exists {
-                                    println!("   💊 Anesthesia Agent:");
-                                    if let Some(agent) = &phys.aa_agent {
-                                        println!("      • Agent: {:?}", agent);
+                                    // Anesthesia Agent
+                                    if phys.aa_status.exists {
+                                        println!("   💊 Anesthesia Agent:");
+                                        if let Some(agent) = &phys.aa_agent {
+                                            println!("      • Agent: {:?}", agent);
+                                        }
+                                        print_value("      • Et", phys.aa_et, "%");
+                                        print_value("      • Fi", phys.aa_fi, "%");
+                                        print_value("      • MAC", phys.aa_mac, "");
                                     }
-                                    print_value("      • Et", phys.aa_et, "%");
-                                    print_value("      • Fi", phys.aa_fi, "%");
-                                    print_value("      • MAC", phys.aa_mac, "");
-                                }
 
-                                // Ventilator / Flow & Volume
-                                println!("   🌬️  Ventilator (Flow & Volume):");
-                                println!(
-                                    "      • Status: exists={}, active={}, disconnection={}",
-                                    phys.flow_status.exists,
-                                    phys.flow_status.active,
-                                    phys.flow_status.disconnection
-                                );
-                                print_value("      • Resp Rate", phys.flow_rr, "/min");
-                                print_value("      • Ppeak", phys.flow_ppeak, "cmH2O");
-                                print_value("      • PEEP", phys.flow_peep, "cmH2O");
-                                print_value("      • Pplat", phys.flow_pplat, "cmH2O");
-                                print_value("      • TV insp", phys.flow_tv_insp, "ml");
-                                print_value("      • TV exp", phys.flow_tv_exp, "ml");
-                                print_value("      • Compliance", phys.flow_compliance, "ml/cmH2O");
-                                print_value("      • MV exp", phys.flow_mv_exp, "L/min");
-
-                                println!();
-                            }
-                            DriRecord::Waveform { waveforms } => {
-                                wave_count += 1;
-                                println!();
-                                println!(
-                                    "   📈 WAVEFORM DATA (#{}) - {} waveforms",
-                                    wave_count,
-                                    waveforms.len()
-                                );
-                                println!(
-                                    "   ─────────────────────────────────────────────────────"
-                                );
-
-                                for wf in waveforms {
+                                    // Ventilator / Flow & Volume
+                                    println!("   🌬️  Ventilator (Flow & Volume):");
                                     println!(
-                                        "   • {:?}: {} samples @ {} Hz (gap={}, pacer={}, lead_off={})",
-                                        wf.waveform_type,
-                                        wf.samples.len(),
-                                        wf.sample_rate,
-                                        wf.status.gap,
-                                        wf.status.pacer_detected,
-                                        wf.status.lead_off
+                                        "      • Status: exists={}, active={}, disconnection={}",
+                                        phys.flow_status.exists,
+                                        phys.flow_status.active,
+                                        phys.flow_status.disconnection
+                                    );
+                                    print_value("      • Resp Rate", phys.flow_rr, "/min");
+                                    print_value("      • Ppeak", phys.flow_ppeak, "cmH2O");
+                                    print_value("      • PEEP", phys.flow_peep, "cmH2O");
+                                    print_value("      • Pplat", phys.flow_pplat, "cmH2O");
+                                    print_value("      • TV insp", phys.flow_tv_insp, "ml");
+                                    print_value("      • TV exp", phys.flow_tv_exp, "ml");
+                                    print_value(
+                                        "      • Compliance",
+                                        phys.flow_compliance,
+                                        "ml/cmH2O",
                                     );
+                                    print_value("      • MV exp", phys.flow_mv_exp, "L/min");
+
+                                    // Cardiac output
+                                    if phys.co_status.exists {
+                                        println!("   ❤️  Cardiac Output:");
+                                        if phys.co_not_updated {
+                                            println!("      • (no new measurement)");
+                                        }
+                                        print_value("      • CO", phys.co, "L/min");
+                                        print_value("      • Blood Temp", phys.co_blood_temp, "°C");
+                                        print_value("      • REF", phys.co_ref, "%");
+                                        print_value("      • PCWP", phys.co_pcwp, "mmHg");
+                                    }
+
+                                    // SvO2
+                                    if phys.svo2_status.exists {
+                                        println!("   🩸 SvO2:");
+                                        print_value("      • SvO2", phys.svo2, "%");
+                                    }
+
+                                    // Neuromuscular transmission
+                                    if phys.nmt_status.exists {
+                                        println!("   💪 NMT:");
+                                        print_value("      • T1", phys.nmt_t1, "%");
+                                        print_value("      • TOF ratio", phys.nmt_tof_ratio, "%");
+                                        print_value("      • PTC", phys.nmt_ptc, "");
+                                    }
 
-                                    // Show first few samples
-                                    if !wf.samples.is_empty() {
-                                        let preview: Vec<String> = wf
-                                            .samples
-                                            .iter()
-                                            .take(10)
-                                            .map(|s| s.to_string())
-                                            .collect();
+                                    // Values outside clinically plausible ranges
+                                    let implausible = phys.sanity_flags();
+                                    if !implausible.is_empty() {
                                         println!(
-                                            "     First 10 samples: [{}{}]",
-                                            preview.join(", "),
-                                            if wf.samples.len() > 10 { ", ..." } else { "" }
+                                            "   ⚠️  IMPLAUSIBLE VALUES (check offsets/scaling):"
                                         );
+                                        for flag in &implausible {
+                                            println!(
+                                                "      ❗ {} = {:.2} (expected {} to {})",
+                                                flag.field, flag.value, flag.min, flag.max
+                                            );
+                                        }
+                                    }
 
-                                        // Calculate min/max/avg
-                                        let min = wf.samples.iter().min().unwrap_or(&0);
-                                        let max = wf.samples.iter().max().unwrap_or(&0);
-                                        let sum: i64 = wf.samples.iter().map(|&x| x as i64).sum();
-                                        let avg = sum as f64 / wf.samples.len() as f64;
+                                    println!();
+                                }
+                                DriRecord::Waveform { waveforms } => {
+                                    wave_count += 1;
+                                    println!();
+                                    println!(
+                                        "   📈 WAVEFORM DATA (#{}) - {} waveforms",
+                                        wave_count,
+                                        waveforms.len()
+                                    );
+                                    println!(
+                                        "   ─────────────────────────────────────────────────────"
+                                    );
+
+                                    for wf in waveforms {
+                                        let mut flags =
+                                            wf.status.flag_names(wf.waveform_type).join(", ");
+                                        if wf.status.other_bits != 0 {
+                                            if !flags.is_empty() {
+                                                flags.push_str(", ");
+                                            }
+                                            flags.push_str(&format!(
+                                                "other=0x{:04X}",
+                                                wf.status.other_bits
+                                            ));
+                                        }
                                         println!(
-                                            "     Stats: min={}, max={}, avg={:.1}",
-                                            min, max, avg
+                                            "   • {:?}: {} samples @ {} Hz (flags: {})",
+                                            wf.waveform_type,
+                                            wf.samples.len(),
+                                            wf.sample_rate(),
+                                            if flags.is_empty() { "none" } else { &flags }
                                         );
+
+                                        // Show first few samples
+                                        if !wf.samples.is_empty() {
+                                            let preview: Vec<String> = wf
+                                                .samples
+                                                .iter()
+                                                .take(10)
+                                                .map(|s| s.to_string())
+                                                .collect();
+                                            println!(
+                                                "     First 10 samples: [{}{}]",
+                                                preview.join(", "),
+                                                if wf.samples.len() > 10 { ", ..." } else { "" }
+                                            );
+
+                                            // Calculate min/max/avg
+                                            let min = wf.samples.iter().min().unwrap_or(&0);
+                                            let max = wf.samples.iter().max().unwrap_or(&0);
+                                            let sum: i64 =
+                                                wf.samples.iter().map(|&x| x as i64).sum();
+                                            let avg = sum as f64 / wf.samples.len() as f64;
+                                            println!(
+                                                "     Stats: min={}, max={}, avg={:.1}",
+                                                min, max, avg
+                                            );
+                                        }
                                     }
+                                    println!();
+                                }
+                                DriRecord::Aux(aux) => {
+                                    println!(
+                                        "   🧩 AUX SUBRECORD - {} bytes (not decoded)",
+                                        aux.data.len()
+                                    );
+                                }
+                                DriRecord::Network(network) => {
+                                    println!(
+                                        "   🛜 NETWORK - gateway {}, {} bytes (not decoded)",
+                                        network.plug_id,
+                                        network.data.len()
+                                    );
+                                }
+                                DriRecord::Event(event) => {
+                                    println!(
+                                        "   📝 EVENT - subrecord={}, {} bytes (not decoded)",
+                                        event.subrecord_type,
+                                        event.data.len()
+                                    );
                                 }
-                                println!();
                             }
                         }
                     }
-                    Ok(None) => {
+                    Ok(_) => {
                         println!("   ⚪ No decodable data in this frame");
                     }
                     Err(e) => {
@@ -319,15 +481,30 @@ fn main() -> Result<()> {
 
                 // Summary line
                 println!(
-                    "   📊 TOTALS: {} frames, {} phys records, {} waveform batches",
-                    frame_count, phys_count, wave_count
+                    "   📊 TOTALS: {} frames, {} phys records, {} waveform batches, {} missed records",
+                    frame_count,
+                    phys_count,
+                    wave_count,
+                    sequence.missed()
                 );
             }
+            Err(e)
+                if e.downcast_ref::<std::io::Error>()
+                    .is_some_and(|e| e.kind() == std::io::ErrorKind::UnexpectedEof) =>
+            {
+                println!();
+                println!("🏁 End of input");
+                println!("📈 METRICS: {}", metrics.snapshot());
+                return Ok(());
+            }
             Err(e) => {
+                if let Some(e) = e.downcast_ref::<DriError>() {
+                    metrics.record_framing_error(e);
+                }
                 println!();
                 println!("❌ Read error: {}", e);
                 println!("   Waiting for more data...");
-                std::thread::sleep(std::time::Duration::from_millis(500));
+                std::thread::sleep(Duration::from_millis(500));
             }
         }
     }
@@ -1,9 +1,12 @@
 //! DRI protocol type definitions
 
+use super::WaveformType;
 use serde::{Deserialize, Serialize};
 
 /// DRI Interface Level - indicates protocol version
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Levels are ordered, newer levels compare greater.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum DriLevel {
     Level95 = 2,
@@ -48,6 +51,45 @@ impl DriLevel {
             DriLevel::Level04 => "'09",
         }
     }
+
+    /// Whether a monitor at this level can provide the feature
+    pub fn supports(&self, feature: DriFeature) -> bool {
+        *self >= feature.min_level()
+    }
+}
+
+/// Protocol features that need a minimum DRI level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriFeature {
+    /// A physiological data class
+    Class(PhdbClass),
+    /// A waveform type
+    Waveform(WaveformType),
+}
+
+impl DriFeature {
+    /// Oldest DRI level providing the feature
+    ///
+    /// Ext2/Ext3 classes and the EEG, entropy, BIS, INVP7/8 and second
+    /// pleth waveforms arrived with Level02; everything else is available
+    /// at every level.
+    pub fn min_level(&self) -> DriLevel {
+        match self {
+            DriFeature::Class(PhdbClass::Ext2 | PhdbClass::Ext3) => DriLevel::Level02,
+            DriFeature::Waveform(
+                WaveformType::Eeg1
+                | WaveformType::Eeg2
+                | WaveformType::Eeg3
+                | WaveformType::Eeg4
+                | WaveformType::Ent100
+                | WaveformType::EegBis
+                | WaveformType::Invp7
+                | WaveformType::Invp8
+                | WaveformType::Pleth2,
+            ) => DriLevel::Level02,
+            _ => DriLevel::Level95,
+        }
+    }
 }
 
 /// Main record types
@@ -144,3 +186,97 @@ pub const PHDBCL_REQ_EXT3_MASK: u32 = 0x0008;
 /// Request all physiological data classes
 pub const PHDBCL_REQ_ALL: u32 =
     PHDBCL_REQ_BASIC_MASK | PHDBCL_REQ_EXT1_MASK | PHDBCL_REQ_EXT2_MASK | PHDBCL_REQ_EXT3_MASK;
+
+/// Set of physiological classes to request, built on the `PHDBCL_*` masks
+///
+/// ```
+/// use ge_dri_prototype::constants::dri_types::PhdbClassSet;
+/// let classes = PhdbClassSet::basic().with_ext1();
+/// assert_eq!(classes.mask(), 0x0002);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhdbClassSet {
+    mask: u32,
+}
+
+impl PhdbClassSet {
+    /// Basic class only
+    pub fn basic() -> Self {
+        Self {
+            mask: PHDBCL_REQ_BASIC_MASK,
+        }
+    }
+
+    /// Basic and all extended classes
+    pub fn all() -> Self {
+        Self {
+            mask: PHDBCL_REQ_ALL,
+        }
+    }
+
+    /// Also request extended class 1
+    pub fn with_ext1(mut self) -> Self {
+        self.mask |= PHDBCL_REQ_EXT1_MASK;
+        self
+    }
+
+    /// Also request extended class 2
+    pub fn with_ext2(mut self) -> Self {
+        self.mask |= PHDBCL_REQ_EXT2_MASK;
+        self
+    }
+
+    /// Also request extended class 3
+    pub fn with_ext3(mut self) -> Self {
+        self.mask |= PHDBCL_REQ_EXT3_MASK;
+        self
+    }
+
+    /// Do not send the basic class (only the requested extended classes)
+    pub fn without_basic(mut self) -> Self {
+        self.mask |= PHDBCL_DENY_BASIC_MASK;
+        self
+    }
+
+    /// Class mask as sent in the request
+    pub fn mask(&self) -> u32 {
+        self.mask
+    }
+}
+
+impl From<PhdbClassSet> for u32 {
+    fn from(classes: PhdbClassSet) -> Self {
+        classes.mask()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_supports() {
+        let eeg = DriFeature::Waveform(WaveformType::Eeg1);
+        assert!(!DriLevel::Level95.supports(eeg));
+        assert!(!DriLevel::Level01.supports(eeg));
+        assert!(DriLevel::Level02.supports(eeg));
+        assert!(DriLevel::Level04.supports(eeg));
+        assert!(DriLevel::Level95.supports(DriFeature::Waveform(WaveformType::Ecg1)));
+        assert!(DriLevel::Level95.supports(DriFeature::Class(PhdbClass::Ext1)));
+        assert!(!DriLevel::Level99.supports(DriFeature::Class(PhdbClass::Ext3)));
+    }
+
+    #[test]
+    fn test_class_set_masks() {
+        assert_eq!(PhdbClassSet::basic().mask(), 0);
+        assert_eq!(PhdbClassSet::all().mask(), PHDBCL_REQ_ALL);
+        assert_eq!(
+            PhdbClassSet::basic().with_ext2().with_ext3().mask(),
+            PHDBCL_REQ_EXT2_MASK | PHDBCL_REQ_EXT3_MASK
+        );
+        assert_eq!(
+            u32::from(PhdbClassSet::basic().with_ext1().without_basic()),
+            PHDBCL_DENY_BASIC_MASK | PHDBCL_REQ_EXT1_MASK
+        );
+    }
+}
@@ -1,5 +1,33 @@
 //! Constants and type definitions for the DRI protocol
 
+/// Serialize an enum as its `name()` string, deserializing via `from_name`
+///
+/// Keeps JSON output in the vocabulary used to request waveforms and shown
+/// by the monitor (e.g. `"ECG1"`, `"ART"`) rather than Rust variant names.
+macro_rules! serde_as_name {
+    ($ty:ty, $what:literal) => {
+        impl serde::Serialize for $ty {
+            fn serialize<S: serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.name())
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $ty {
+            fn deserialize<D: serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> std::result::Result<Self, D::Error> {
+                let name = String::deserialize(deserializer)?;
+                Self::from_name(&name).ok_or_else(|| {
+                    serde::de::Error::custom(format!("unknown {} {:?}", $what, name))
+                })
+            }
+        }
+    };
+}
+
 pub mod dri_types;
 pub mod physiological;
 pub mod scaling;
@@ -7,7 +35,7 @@ pub mod special_values;
 pub mod waveforms;
 
 // Re-export commonly used types
-pub use dri_types::{DriLevel, DriMainType, PhdbClass, PhdbSubrecordType};
+pub use dri_types::{DriFeature, DriLevel, DriMainType, PhdbClass, PhdbSubrecordType};
 pub use physiological::{EcgLeadType, InvasivePressureLabel, ParameterGroup};
 pub use scaling::*;
 pub use special_values::SpecialValue;
@@ -34,6 +62,9 @@ pub const HEADER_SIZE: usize = 40;
 /// Maximum data size
 pub const MAX_DATA_SIZE: usize = 1450;
 
+/// Size of one PHDB sample (displayed values or one trend sample)
+pub const PHDB_SUBRECORD_SIZE: usize = 1088;
+
 /// Maximum number of subrecords per record
 pub const MAX_SUBRECORDS: usize = 8;
 
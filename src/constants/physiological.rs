@@ -86,7 +86,9 @@ impl HrSource {
 }
 
 /// Invasive pressure labels
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Serialized as the name shown by the monitor, e.g. `"ART"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u16)]
 pub enum InvasivePressureLabel {
     NotDefined = 0,
@@ -144,6 +146,13 @@ impl InvasivePressureLabel {
         }
     }
 
+    /// Label by [`name`](Self::name), ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        (0..=InvasivePressureLabel::Femv as u16)
+            .filter_map(Self::from_u16)
+            .find(|value| value.name().eq_ignore_ascii_case(name))
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             InvasivePressureLabel::NotDefined => "NOT_DEFINED",
@@ -173,8 +182,12 @@ impl InvasivePressureLabel {
     }
 }
 
+serde_as_name!(InvasivePressureLabel, "invasive pressure label");
+
 /// Temperature labels
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Serialized as the name shown by the monitor, e.g. `"ESO"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u16)]
 pub enum TemperatureLabel {
     NotUsed = 0,
@@ -224,6 +237,13 @@ impl TemperatureLabel {
         }
     }
 
+    /// Label by [`name`](Self::name), ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        (0..=TemperatureLabel::T6 as u16)
+            .filter_map(Self::from_u16)
+            .find(|value| value.name().eq_ignore_ascii_case(name))
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             TemperatureLabel::NotUsed => "NOT_USED",
@@ -249,8 +269,12 @@ impl TemperatureLabel {
     }
 }
 
+serde_as_name!(TemperatureLabel, "temperature label");
+
 /// Anesthesia agent types
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Serialized as the name shown by the monitor, e.g. `"SEV"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u16)]
 pub enum AnesthesiaAgent {
     Unknown = 0,
@@ -276,6 +300,13 @@ impl AnesthesiaAgent {
         }
     }
 
+    /// Agent by [`name`](Self::name), ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        (0..=AnesthesiaAgent::Sev as u16)
+            .filter_map(Self::from_u16)
+            .find(|value| value.name().eq_ignore_ascii_case(name))
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             AnesthesiaAgent::Unknown => "UNKNOWN",
@@ -289,6 +320,8 @@ impl AnesthesiaAgent {
     }
 }
 
+serde_as_name!(AnesthesiaAgent, "anesthesia agent");
+
 /// Parameter groups in physiological data
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ParameterGroup {
@@ -307,3 +340,64 @@ pub enum ParameterGroup {
     EcgExtra,
     Svo2,
 }
+
+/// Clinically plausible range `(name, min, max)` of numeric fields, inclusive
+///
+/// Deliberately wide: a value outside these bounds is far more likely a
+/// decoding error (wrong offset or scale factor) than a real measurement.
+/// Names match [`PhysiologicalData::iter_vitals`](crate::decode::PhysiologicalData::iter_vitals).
+pub const PLAUSIBLE_RANGES: &[(&str, f64, f64)] = &[
+    ("ecg_hr", 0.0, 350.0),
+    ("ecg_st1", -20.0, 20.0),
+    ("ecg_st2", -20.0, 20.0),
+    ("ecg_st3", -20.0, 20.0),
+    ("ecg_rr", 0.0, 200.0),
+    ("nibp_sys", 0.0, 350.0),
+    ("nibp_dia", 0.0, 300.0),
+    ("nibp_mean", 0.0, 300.0),
+    ("nibp_hr", 0.0, 350.0),
+    ("invp1_sys", -50.0, 400.0),
+    ("invp1_dia", -50.0, 400.0),
+    ("invp1_mean", -50.0, 400.0),
+    ("invp1_hr", 0.0, 350.0),
+    ("spo2", 0.0, 100.0),
+    ("spo2_pr", 0.0, 350.0),
+    ("spo2_ir_amp", 0.0, 100.0),
+    ("temp1", 10.0, 50.0),
+    ("temp2", 10.0, 50.0),
+    ("co2_et", 0.0, 20.0),
+    ("co2_fi", 0.0, 20.0),
+    ("co2_rr", 0.0, 200.0),
+    ("co2_amb_press", 400.0, 900.0),
+    ("o2_et", 0.0, 100.0),
+    ("o2_fi", 0.0, 100.0),
+    ("n2o_et", 0.0, 100.0),
+    ("n2o_fi", 0.0, 100.0),
+    ("aa_et", 0.0, 20.0),
+    ("aa_fi", 0.0, 20.0),
+    ("aa_mac", 0.0, 10.0),
+    ("flow_rr", 0.0, 200.0),
+    ("flow_ppeak", -20.0, 150.0),
+    ("flow_peep", -20.0, 60.0),
+    ("flow_pplat", -20.0, 150.0),
+    ("flow_tv_insp", 0.0, 5000.0),
+    ("flow_tv_exp", 0.0, 5000.0),
+    ("flow_compliance", 0.0, 500.0),
+    ("flow_mv_exp", 0.0, 100.0),
+    ("co", 0.0, 30.0),
+    ("co_blood_temp", 10.0, 50.0),
+    ("co_ref", 0.0, 100.0),
+    ("co_pcwp", 0.0, 60.0),
+    ("svo2", 0.0, 100.0),
+    ("nmt_t1", 0.0, 200.0),
+    ("nmt_tof_ratio", 0.0, 200.0),
+    ("nmt_ptc", 0.0, 20.0),
+];
+
+/// Plausible range of the named field, if one is defined
+pub fn plausible_range(name: &str) -> Option<(f64, f64)> {
+    PLAUSIBLE_RANGES
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .map(|&(_, min, max)| (min, max))
+}
@@ -1,8 +1,14 @@
 //! Scaling factors and unit conversions for physiological parameters
 
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+
 /// Scaling factor for percentage values (stored as 1/100 %)
 pub const SCALE_PERCENT_100: f64 = 0.01;
 
+/// Scaling factor for NMT responses (stored as 1/10 %)
+pub const SCALE_PERCENT_10: f64 = 0.1;
+
 /// Scaling factor for temperature (stored as 1/100 °C)
 pub const SCALE_TEMP_100: f64 = 0.01;
 
@@ -18,6 +24,9 @@ pub const SCALE_FLOW_10: f64 = 0.1;
 /// Scaling factor for volume (stored as 1/10 ml)
 pub const SCALE_VOLUME_10: f64 = 0.1;
 
+/// Scaling factor for minute volume (stored as 1/100 l/min)
+pub const SCALE_MV_100: f64 = 0.01;
+
 /// Scaling factor for compliance (stored as 1/100 ml/cmH2O)
 pub const SCALE_COMPLIANCE_100: f64 = 0.01;
 
@@ -30,9 +39,74 @@ pub const SCALE_AWP_100: f64 = 0.01;
 /// Scaling factor for SpO2 IR amplitude (stored as 1/10 %)
 pub const SCALE_IR_AMP_10: f64 = 0.1;
 
+/// Scaling factor for ambient pressure (stored as 1/10 mmHg)
+pub const SCALE_AMB_PRESS_10: f64 = 0.1;
+
+/// Scaling factor for cardiac output (stored as 1/100 l/min)
+pub const SCALE_CO_100: f64 = 0.01;
+
 /// Scaling factor for impedance (stored as 1/100 Ω)
 pub const SCALE_IMPEDANCE_100: f64 = 0.01;
 
+/// Per-parameter scaling factors overriding the documented ones
+///
+/// Some firmware revisions scale a few parameters differently (e.g. tidal
+/// volume in ml rather than 1/10 ml). A profile maps field names, as in
+/// [`PhysiologicalData::iter_vitals`](crate::decode::PhysiologicalData::iter_vitals),
+/// to the factor from the raw code to the value. Fields without an
+/// override keep the factors above; only Basic class fields can be
+/// overridden, and [`with_factor`](Self::with_factor) rejects the others.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScalingProfile {
+    /// Name for logs, e.g. the device or firmware revision
+    pub name: String,
+    overrides: BTreeMap<String, f64>,
+}
+
+impl ScalingProfile {
+    /// The documented factors, without overrides
+    pub fn standard() -> Self {
+        Self::new("standard")
+    }
+
+    /// Create an empty profile to add overrides to
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            overrides: BTreeMap::new(),
+        }
+    }
+
+    /// Scale `field` by `factor` instead of its documented factor
+    ///
+    /// Fails if `field` is not a Basic class field, so a typo cannot
+    /// silently leave the documented factor in place.
+    pub fn with_factor(mut self, field: &str, factor: f64) -> Result<Self> {
+        if !crate::decode::physiological::is_basic_field(field) {
+            return Err(anyhow!("Unknown field {:?} in scaling profile", field));
+        }
+        self.overrides.insert(field.to_string(), factor);
+        Ok(self)
+    }
+
+    /// Overriding factor of `field`, if any
+    pub fn factor(&self, field: &str) -> Option<f64> {
+        self.overrides.get(field).copied()
+    }
+
+    /// Overridden fields and their factors, in name order
+    pub fn overrides(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.overrides
+            .iter()
+            .map(|(field, &factor)| (field.as_str(), factor))
+    }
+
+    /// Whether the profile leaves every factor as documented
+    pub fn is_standard(&self) -> bool {
+        self.overrides.is_empty()
+    }
+}
+
 /// Apply scaling to an optional i16 value
 pub fn scale_i16(value: Option<i16>, scale: f64) -> Option<f64> {
     value.map(|v| v as f64 * scale)
@@ -58,6 +132,15 @@ mod tests {
         assert_eq!(scale_i16(None, SCALE_PERCENT_100), None);
     }
 
+    #[test]
+    fn test_with_factor_rejects_unknown_fields() {
+        let profile = ScalingProfile::new("firmware 4.2")
+            .with_factor("svo2", 1.0)
+            .unwrap();
+        assert_eq!(profile.factor("svo2"), Some(1.0));
+        assert!(profile.with_factor("flow_tv", 1.0).is_err());
+    }
+
     #[test]
     fn test_scale_valid_i16() {
         assert_eq!(scale_valid_i16(9800, SCALE_PERCENT_100), Some(98.0));
@@ -7,6 +7,8 @@ pub enum SpecialValue {
     Invalid,
     /// Data has not been updated
     NotUpdated,
+    /// Measurement was interrupted (e.g. sensor disconnected) in a trend
+    Discontinuity,
     /// Data exceeds lower valid limit
     UnderRange,
     /// Data exceeds upper valid limit
@@ -36,7 +38,21 @@ pub const DATA_OVER_RANGE: i16 = -32763;
 /// Data not calibrated
 pub const DATA_NOT_CALIBRATED: i16 = -32762;
 
+/// Pacer spike marker stuffed into ECG waveform samples
+///
+/// `0x8000` is the one code below [`DATA_INVALID`] (`0x8001`). The DRI
+/// specification does not assign it, and its use as a pacer mark is an
+/// assumption of this decoder, not yet confirmed against a capture.
+/// It lies in the reserved range, so
+/// [`is_invalid`] and [`get_special_value`] report it as invalid; run
+/// [`extract_pacer_markers`](crate::decode::waveforms::extract_pacer_markers)
+/// on ECG samples before checking them.
+pub const WF_PACER_MARKER: i16 = i16::MIN;
+
 /// Check if a value represents invalid data
+///
+/// Also true for [`WF_PACER_MARKER`]; ECG samples need their pacer marks
+/// extracted first.
 pub fn is_invalid(value: i16) -> bool {
     value <= DATA_INVALID_LIMIT
 }
@@ -47,10 +63,14 @@ pub fn check_valid(value: i16) -> Option<i16> {
 }
 
 /// Get the special value type if the value is invalid
+///
+/// Unassigned codes in the reserved range, including
+/// [`WF_PACER_MARKER`], are [`SpecialValue::Invalid`].
 pub fn get_special_value(value: i16) -> Option<SpecialValue> {
     match value {
         DATA_INVALID => Some(SpecialValue::Invalid),
         DATA_NOT_UPDATED => Some(SpecialValue::NotUpdated),
+        DATA_DISCONT => Some(SpecialValue::Discontinuity),
         DATA_UNDER_RANGE => Some(SpecialValue::UnderRange),
         DATA_OVER_RANGE => Some(SpecialValue::OverRange),
         DATA_NOT_CALIBRATED => Some(SpecialValue::NotCalibrated),
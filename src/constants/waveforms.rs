@@ -1,9 +1,9 @@
 //! Waveform type definitions and sampling rates
 
-use serde::{Deserialize, Serialize};
-
 /// Waveform types available in DRI protocol
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// Serialized as [`WaveformType::name`], e.g. `"ECG1"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
 pub enum WaveformType {
     /// Waveform command (control)
@@ -69,6 +69,39 @@ pub enum WaveformType {
 }
 
 impl WaveformType {
+    /// Every waveform that can be requested (all but [`WaveformType::Cmd`])
+    pub const ALL: &'static [WaveformType] = &[
+        WaveformType::Ecg1,
+        WaveformType::Ecg2,
+        WaveformType::Ecg3,
+        WaveformType::Invp1,
+        WaveformType::Invp2,
+        WaveformType::Invp3,
+        WaveformType::Invp4,
+        WaveformType::Pleth,
+        WaveformType::Co2,
+        WaveformType::O2,
+        WaveformType::N2o,
+        WaveformType::Aa,
+        WaveformType::Awp,
+        WaveformType::Flow,
+        WaveformType::Resp,
+        WaveformType::Invp5,
+        WaveformType::Invp6,
+        WaveformType::Eeg1,
+        WaveformType::Eeg2,
+        WaveformType::Eeg3,
+        WaveformType::Eeg4,
+        WaveformType::Vol,
+        WaveformType::TonoPress,
+        WaveformType::SpiLoopStatus,
+        WaveformType::Ent100,
+        WaveformType::EegBis,
+        WaveformType::Invp7,
+        WaveformType::Invp8,
+        WaveformType::Pleth2,
+    ];
+
     pub fn from_u8(value: u8) -> Option<Self> {
         match value {
             0 => Some(WaveformType::Cmd),
@@ -105,6 +138,14 @@ impl WaveformType {
         }
     }
 
+    /// Whether this is an ECG channel (pacer and lead-off status apply)
+    pub fn is_ecg(&self) -> bool {
+        matches!(
+            self,
+            WaveformType::Ecg1 | WaveformType::Ecg2 | WaveformType::Ecg3
+        )
+    }
+
     /// Get the string name for this waveform
     pub fn name(&self) -> &'static str {
         match self {
@@ -141,28 +182,86 @@ impl WaveformType {
         }
     }
 
+    /// Waveform by [`name`](Self::name), ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        std::iter::once(&WaveformType::Cmd)
+            .chain(Self::ALL)
+            .find(|wf| wf.name().eq_ignore_ascii_case(name))
+            .copied()
+    }
+
     /// Get waveform information (sample rate, unit, etc.)
     pub fn info(&self) -> WaveformInfo {
         get_waveform_info(*self)
     }
+
+    /// Physical unit of the samples once divided by the scale factor
+    pub fn base_unit(&self) -> &'static str {
+        waveform_spec(*self).base_unit
+    }
+
+    /// Divisor converting raw samples to `base_unit`, e.g. 100.0 for CO2
+    ///
+    /// `Cmd` carries no samples and returns 1.0.
+    pub fn scale_factor(&self) -> f64 {
+        waveform_spec(*self).scale_factor
+    }
 }
 
+serde_as_name!(WaveformType, "waveform");
+
 /// Waveform metadata
 #[derive(Debug, Clone)]
 pub struct WaveformInfo {
     pub waveform_type: WaveformType,
     pub samples_per_second: u16,
-    pub unit: &'static str,
+    /// Unit with its resolution, e.g. "mmHg (1/100)"
+    pub unit: String,
     pub description: &'static str,
 }
 
+impl WaveformInfo {
+    /// Physical unit without the resolution suffix (e.g. "mmHg")
+    pub fn base_unit(&self) -> &'static str {
+        self.waveform_type.base_unit()
+    }
+
+    /// Physical value of one sample count in `base_unit`
+    pub fn scale(&self) -> f64 {
+        1.0 / self.waveform_type.scale_factor()
+    }
+}
+
 /// Get waveform information for a given type
 pub fn get_waveform_info(wf_type: WaveformType) -> WaveformInfo {
+    let spec = waveform_spec(wf_type);
+    let unit = if spec.scale_factor == 1.0 {
+        spec.base_unit.to_string()
+    } else {
+        format!("{} (1/{})", spec.base_unit, spec.scale_factor)
+    };
+    WaveformInfo {
+        waveform_type: wf_type,
+        samples_per_second: spec.samples_per_second,
+        unit,
+        description: spec.description,
+    }
+}
+
+/// Static properties of a waveform type
+struct WaveformSpec {
+    samples_per_second: u16,
+    base_unit: &'static str,
+    scale_factor: f64,
+    description: &'static str,
+}
+
+fn waveform_spec(wf_type: WaveformType) -> WaveformSpec {
     match wf_type {
-        WaveformType::Ecg1 | WaveformType::Ecg2 | WaveformType::Ecg3 => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::Ecg1 | WaveformType::Ecg2 | WaveformType::Ecg3 => WaveformSpec {
             samples_per_second: 300,
-            unit: "μV",
+            base_unit: "μV",
+            scale_factor: 1.0,
             description: "ECG waveform",
         },
         WaveformType::Invp1
@@ -172,102 +271,102 @@ pub fn get_waveform_info(wf_type: WaveformType) -> WaveformInfo {
         | WaveformType::Invp5
         | WaveformType::Invp6
         | WaveformType::Invp7
-        | WaveformType::Invp8 => WaveformInfo {
-            waveform_type: wf_type,
+        | WaveformType::Invp8 => WaveformSpec {
             samples_per_second: 100,
-            unit: "mmHg (1/100)",
+            base_unit: "mmHg",
+            scale_factor: 100.0,
             description: "Invasive blood pressure",
         },
-        WaveformType::Pleth | WaveformType::Pleth2 => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::Pleth | WaveformType::Pleth2 => WaveformSpec {
             samples_per_second: 100,
-            unit: "% (1/10)",
+            base_unit: "%",
+            scale_factor: 10.0,
             description: "Plethysmograph",
         },
-        WaveformType::Co2 => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::Co2 => WaveformSpec {
             samples_per_second: 25,
-            unit: "% (1/100)",
+            base_unit: "%",
+            scale_factor: 100.0,
             description: "CO2 concentration",
         },
-        WaveformType::O2 => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::O2 => WaveformSpec {
             samples_per_second: 25,
-            unit: "% (1/100)",
+            base_unit: "%",
+            scale_factor: 100.0,
             description: "O2 concentration",
         },
-        WaveformType::N2o => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::N2o => WaveformSpec {
             samples_per_second: 25,
-            unit: "% (1/100)",
+            base_unit: "%",
+            scale_factor: 100.0,
             description: "N2O concentration",
         },
-        WaveformType::Aa => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::Aa => WaveformSpec {
             samples_per_second: 25,
-            unit: "% (1/100)",
+            base_unit: "%",
+            scale_factor: 100.0,
             description: "Anesthesia agent",
         },
-        WaveformType::Awp => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::Awp => WaveformSpec {
             samples_per_second: 25,
-            unit: "cmH2O (1/10)",
+            base_unit: "cmH2O",
+            scale_factor: 10.0,
             description: "Airway pressure",
         },
-        WaveformType::Flow => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::Flow => WaveformSpec {
             samples_per_second: 25,
-            unit: "l/min (1/10)",
+            base_unit: "l/min",
+            scale_factor: 10.0,
             description: "Airway flow",
         },
-        WaveformType::Vol => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::Vol => WaveformSpec {
             samples_per_second: 25,
-            unit: "ml",
+            base_unit: "ml",
+            scale_factor: 1.0,
             description: "Airway volume",
         },
-        WaveformType::Resp => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::Resp => WaveformSpec {
             samples_per_second: 25,
-            unit: "Ω (1/100)",
+            base_unit: "Ω",
+            scale_factor: 100.0,
             description: "ECG impedance respiration",
         },
         WaveformType::Eeg1 | WaveformType::Eeg2 | WaveformType::Eeg3 | WaveformType::Eeg4 => {
-            WaveformInfo {
-                waveform_type: wf_type,
+            WaveformSpec {
                 samples_per_second: 100,
-                unit: "μV (1/10)",
+                base_unit: "μV",
+                scale_factor: 10.0,
                 description: "EEG channel",
             }
         }
-        WaveformType::TonoPress => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::TonoPress => WaveformSpec {
             samples_per_second: 25,
-            unit: "mmHg (1/10)",
+            base_unit: "mmHg",
+            scale_factor: 10.0,
             description: "Tonometry catheter pressure",
         },
-        WaveformType::SpiLoopStatus => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::SpiLoopStatus => WaveformSpec {
             samples_per_second: 25,
-            unit: "bit pattern",
+            base_unit: "bit pattern",
+            scale_factor: 1.0,
             description: "Spirometry loop status",
         },
-        WaveformType::Ent100 => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::Ent100 => WaveformSpec {
             samples_per_second: 100,
-            unit: "μV (1/10)",
+            base_unit: "μV",
+            scale_factor: 10.0,
             description: "Entropy",
         },
-        WaveformType::EegBis => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::EegBis => WaveformSpec {
             samples_per_second: 300,
-            unit: "μV",
+            base_unit: "μV",
+            scale_factor: 1.0,
             description: "BIS",
         },
-        WaveformType::Cmd => WaveformInfo {
-            waveform_type: wf_type,
+        WaveformType::Cmd => WaveformSpec {
             samples_per_second: 0,
-            unit: "",
+            base_unit: "",
+            scale_factor: 1.0,
             description: "Command",
         },
     }
@@ -297,3 +396,56 @@ pub fn validate_waveform_set(waveforms: &[WaveformType]) -> anyhow::Result<()> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::physiological::{AnesthesiaAgent, TemperatureLabel};
+
+    #[test]
+    fn test_types_serialize_as_names() {
+        assert_eq!(
+            serde_json::to_string(&WaveformType::Ecg1).unwrap(),
+            "\"ECG1\""
+        );
+        assert_eq!(
+            serde_json::from_str::<WaveformType>("\"spi_loop_status\"").unwrap(),
+            WaveformType::SpiLoopStatus
+        );
+        for wf in WaveformType::ALL {
+            let json = serde_json::to_string(wf).unwrap();
+            assert_eq!(serde_json::from_str::<WaveformType>(&json).unwrap(), *wf);
+        }
+
+        let labels = (Some(TemperatureLabel::Eso), AnesthesiaAgent::Sev);
+        let json = serde_json::to_string(&labels).unwrap();
+        assert_eq!(json, r#"["ESO","SEV"]"#);
+        assert_eq!(
+            serde_json::from_str::<(Option<TemperatureLabel>, AnesthesiaAgent)>(&json).unwrap(),
+            labels
+        );
+
+        let err = serde_json::from_str::<WaveformType>("\"Ecg9\"").unwrap_err();
+        assert!(err.to_string().contains("unknown waveform"), "{}", err);
+    }
+
+    #[test]
+    fn test_waveform_scale() {
+        let invp = WaveformType::Invp1.info();
+        assert_eq!(invp.base_unit(), "mmHg");
+        assert_eq!(invp.scale(), 0.01);
+
+        let ecg = WaveformType::Ecg1.info();
+        assert_eq!(ecg.base_unit(), "μV");
+        assert_eq!(ecg.scale(), 1.0);
+    }
+
+    #[test]
+    fn test_scale_factor_matches_unit() {
+        assert_eq!(WaveformType::Co2.scale_factor(), 100.0);
+        assert_eq!(WaveformType::Pleth.scale_factor(), 10.0);
+        assert_eq!(WaveformType::EegBis.scale_factor(), 1.0);
+        assert_eq!(WaveformType::Co2.info().unit, "% (1/100)");
+        assert_eq!(WaveformType::Vol.info().unit, "ml");
+    }
+}
@@ -0,0 +1,217 @@
+//! Capnography: breath-by-breath EtCO2 and FiCO2 from the `Co2` waveform
+//!
+//! The numeric CO2 group only carries the monitor's latest EtCO2 and FiCO2.
+//! [`CapnogramAnalyzer`] measures them on every breath of the capnogram:
+//! CO2 rising above a threshold starts expiration, falling below a lower
+//! one starts inspiration. The expiratory plateau gives EtCO2 and the
+//! inspiratory baseline FiCO2.
+
+use super::waveforms::WaveformData;
+use crate::constants::WaveformType;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// CO2 (%) above which expiration starts
+pub const DEFAULT_EXPIRATION_THRESHOLD: f64 = 1.0;
+
+/// CO2 (%) below which inspiration starts
+pub const DEFAULT_INSPIRATION_THRESHOLD: f64 = 0.5;
+
+/// One breath of the capnogram, from the start of expiration to the next
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapnoBreath {
+    /// Start of expiration
+    pub start: DateTime<Utc>,
+    pub duration: Duration,
+    /// Highest CO2 of the expiratory plateau (%)
+    pub et_co2: f64,
+    /// Lowest CO2 of the inspiratory baseline (%)
+    pub fi_co2: f64,
+}
+
+impl CapnoBreath {
+    /// Breaths per minute at this breath's duration
+    pub fn respiratory_rate(&self) -> f64 {
+        60.0 / self.duration.as_secs_f64()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Not seen an inspiration yet, e.g. started mid-breath
+    Unknown,
+    Expiration,
+    Inspiration,
+}
+
+/// Breath in progress, times in seconds since `origin`
+struct PartialBreath {
+    start: f64,
+    et_co2: f64,
+    fi_co2: f64,
+}
+
+/// Splits a capnogram into breaths
+///
+/// State carries across frames, so feed consecutive `Co2` frames of one
+/// monitor to the same analyzer. A breath is returned once the next one
+/// starts, since only then is its inspiration complete; breaths cut by
+/// the start of the stream or by a gap are dropped.
+pub struct CapnogramAnalyzer {
+    expiration_threshold: f64,
+    inspiration_threshold: f64,
+    phase: Phase,
+    breath: Option<PartialBreath>,
+    /// Timestamp of the first frame since the last reset
+    origin: Option<DateTime<Utc>>,
+    /// Seconds of samples seen since `origin`
+    elapsed: f64,
+}
+
+impl CapnogramAnalyzer {
+    /// Create an analyzer with the default thresholds
+    pub fn new() -> Self {
+        Self {
+            expiration_threshold: DEFAULT_EXPIRATION_THRESHOLD,
+            inspiration_threshold: DEFAULT_INSPIRATION_THRESHOLD,
+            phase: Phase::Unknown,
+            breath: None,
+            origin: None,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Detect phases with other thresholds (CO2 in %), e.g. when rebreathing
+    /// raises the baseline; `inspiration` must be below `expiration`
+    pub fn with_thresholds(mut self, expiration: f64, inspiration: f64) -> Self {
+        self.expiration_threshold = expiration;
+        self.inspiration_threshold = inspiration;
+        self
+    }
+
+    /// Breaths completed by the samples of `co2`
+    ///
+    /// Samples are scaled to % by the waveform's scale factor. Fails if
+    /// `co2` is not a `Co2` waveform.
+    pub fn analyze(&mut self, co2: &WaveformData) -> Result<Vec<CapnoBreath>> {
+        if co2.waveform_type != WaveformType::Co2 {
+            return Err(anyhow!(
+                "Cannot analyze {:?} samples as a capnogram",
+                co2.waveform_type
+            ));
+        }
+        if co2.status.gap {
+            self.reset();
+        }
+        let origin = *self.origin.get_or_insert(co2.timestamp);
+        let step = 1.0 / co2.sample_rate();
+
+        let mut breaths = Vec::new();
+        for value in co2.scaled_samples() {
+            let now = self.elapsed;
+            self.elapsed += step;
+
+            match self.phase {
+                Phase::Unknown if value < self.inspiration_threshold => {
+                    self.phase = Phase::Inspiration;
+                }
+                Phase::Unknown => {}
+                Phase::Inspiration if value > self.expiration_threshold => {
+                    if let Some(breath) = self.breath.take() {
+                        breaths.push(CapnoBreath {
+                            start: origin
+                                + chrono::Duration::microseconds(
+                                    (breath.start * 1_000_000.0).round() as i64,
+                                ),
+                            duration: Duration::from_secs_f64(now - breath.start),
+                            et_co2: breath.et_co2,
+                            fi_co2: breath.fi_co2,
+                        });
+                    }
+                    self.breath = Some(PartialBreath {
+                        start: now,
+                        et_co2: value,
+                        fi_co2: f64::INFINITY,
+                    });
+                    self.phase = Phase::Expiration;
+                }
+                Phase::Inspiration => {
+                    if let Some(breath) = &mut self.breath {
+                        breath.fi_co2 = breath.fi_co2.min(value);
+                    }
+                }
+                Phase::Expiration => {
+                    if let Some(breath) = &mut self.breath {
+                        breath.et_co2 = breath.et_co2.max(value);
+                    }
+                    if value < self.inspiration_threshold {
+                        self.phase = Phase::Inspiration;
+                        if let Some(breath) = &mut self.breath {
+                            breath.fi_co2 = value;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(breaths)
+    }
+
+    /// Drop the breath in progress and wait for the next inspiration
+    pub fn reset(&mut self) {
+        self.phase = Phase::Unknown;
+        self.breath = None;
+        self.origin = None;
+        self.elapsed = 0.0;
+    }
+}
+
+impl Default for CapnogramAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::waveforms::WaveformStatus;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_gap_drops_the_breath_in_progress() {
+        // 1 s of baseline then 2 s of plateau per breath, at 25 Hz
+        let capnogram: Vec<i16> = (0..300)
+            .map(|i| if i % 75 < 25 { 30 } else { 480 })
+            .collect();
+        let frame = |samples: &[i16], gap: bool| WaveformData {
+            timestamp: Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+            waveform_type: WaveformType::Co2,
+            plug_id: 0,
+            nominal_rate: 25,
+            samples: samples.to_vec(),
+            effective_rate: None,
+            status: WaveformStatus {
+                gap,
+                ..WaveformStatus::default()
+            },
+            pacer_indices: Vec::new(),
+        };
+
+        let mut analyzer = CapnogramAnalyzer::new();
+        let breaths = analyzer.analyze(&frame(&capnogram, false)).unwrap();
+        assert_eq!(breaths.len(), 3);
+        assert_eq!(breaths[0].et_co2, 4.8);
+        assert_eq!(breaths[0].fi_co2, 0.3);
+        assert!((breaths[0].respiratory_rate() - 20.0).abs() < 1e-9);
+        assert_eq!(
+            breaths[1].start,
+            Utc.timestamp_opt(1_700_000_004, 0).unwrap()
+        );
+
+        // The gap discards the breath cut at the end of the first frame
+        let after_gap = analyzer.analyze(&frame(&capnogram, true)).unwrap();
+        assert_eq!(after_gap.len(), 3);
+        assert_eq!(after_gap[0].start, breaths[0].start);
+    }
+}
@@ -0,0 +1,58 @@
+//! Anesthesia record keeping events (`DriMainType::Fo`)
+//!
+//! Event records mark drug administrations and case events for charting.
+//! Their subrecord layout is not documented here and no capture has been
+//! checked yet, so each subrecord is kept as received, like aux data.
+
+use crate::protocol::DriHeader;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// One anesthesia record keeping subrecord, not decoded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventData {
+    /// Record timestamp from the header
+    pub timestamp: DateTime<Utc>,
+    /// Plug identifier of the sending monitor (from the record header)
+    pub plug_id: u16,
+    /// Subrecord type from the header descriptor
+    pub subrecord_type: u8,
+    /// Raw subrecord bytes
+    pub data: Vec<u8>,
+}
+
+/// Collect every event subrecord of an `Fo` frame
+pub fn decode_events(header: &DriHeader, data: &[u8]) -> Result<Vec<EventData>> {
+    (0..header.subrecords.len())
+        .map(|i| {
+            Ok(EventData {
+                timestamp: header.timestamp(),
+                plug_id: header.plug_id,
+                subrecord_type: header.subrecords[i].sr_type,
+                data: header.get_subrecord_data(data, i)?.to_vec(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constants::DriMainType;
+    use crate::decode::{Decoder, DriRecord, test_frame};
+
+    #[test]
+    fn test_event_subrecords_are_kept_raw() {
+        let mut frame = test_frame(DriMainType::Fo, &[(2, &[0x02, 0x01, b'x', 0])]);
+        frame[4..6].copy_from_slice(&3u16.to_le_bytes());
+
+        match Decoder::new().decode_bytes(&frame).unwrap() {
+            Some(DriRecord::Event(event)) => {
+                assert_eq!(event.plug_id, 3);
+                assert_eq!(event.subrecord_type, 2);
+                assert_eq!(event.data, vec![0x02, 0x01, b'x', 0]);
+            }
+            other => panic!("unexpected record: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Fixed-size ring buffers of the latest waveform samples, for live displays
+//!
+//! Unlike [`WaveformBuffer`](super::WaveformBuffer), samples are never moved
+//! once written: each waveform owns a ring of fixed capacity and a write
+//! position, so a display can read the latest samples as borrowed slices
+//! on every refresh, or draw the whole ring as a sweep.
+
+use super::waveforms::{WaveformData, lost_since, samples_for};
+use crate::constants::WaveformType;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Latest samples of every waveform fed to it
+///
+/// Lost samples (see [`WaveformBuffer`](super::WaveformBuffer)) are written
+/// as `NaN`, so a scope breaks the trace there.
+pub struct LiveWaveforms {
+    capacity: Duration,
+    channels: HashMap<WaveformType, LiveChannel>,
+}
+
+/// Ring buffer of one waveform
+pub struct LiveChannel {
+    sample_rate: u16,
+    ring: Vec<f64>,
+    written: u64,
+    gaps: u64,
+    /// Time just after the last written sample
+    end: DateTime<Utc>,
+}
+
+/// The latest samples of a waveform, oldest first, borrowed from its ring
+///
+/// The samples wrap around the end of the ring, hence the two parts.
+#[derive(Debug, Clone, Copy)]
+pub struct LiveView<'a> {
+    pub older: &'a [f64],
+    pub newer: &'a [f64],
+}
+
+impl LiveWaveforms {
+    /// Keep up to `capacity` of signal per waveform
+    pub fn new(capacity: Duration) -> Self {
+        Self {
+            capacity,
+            channels: HashMap::new(),
+        }
+    }
+
+    /// Write a decoded waveform chunk
+    ///
+    /// A change of sample rate restarts the waveform's ring.
+    pub fn push(&mut self, data: &WaveformData) {
+        let rate = data.nominal_rate;
+        if rate == 0 {
+            return;
+        }
+
+        let capacity = samples_for(self.capacity, rate).max(1);
+        let channel = self
+            .channels
+            .entry(data.waveform_type)
+            .or_insert_with(|| LiveChannel::new(rate, capacity, data.timestamp));
+        if channel.sample_rate != rate {
+            *channel = LiveChannel::new(rate, capacity, data.timestamp);
+        }
+
+        if channel.written > 0
+            && let Some(lag) = lost_since(channel.end, data)
+        {
+            let missing = samples_for(lag, rate).min(capacity);
+            channel.gaps += 1;
+            channel.write(std::iter::repeat_n(f64::NAN, missing));
+            channel.end = data.timestamp;
+        }
+
+        channel.write(data.samples.iter().map(|&s| s as f64));
+        channel.end +=
+            chrono::Duration::microseconds(data.samples.len() as i64 * 1_000_000 / rate as i64);
+    }
+
+    /// Ring of a waveform, if it has been received
+    pub fn channel(&self, waveform_type: WaveformType) -> Option<&LiveChannel> {
+        self.channels.get(&waveform_type)
+    }
+
+    /// The latest `n` samples of a waveform (fewer if not yet written)
+    pub fn latest(&self, waveform_type: WaveformType, n: usize) -> Option<LiveView<'_>> {
+        self.channel(waveform_type).map(|channel| channel.latest(n))
+    }
+
+    /// Waveforms received so far
+    pub fn waveform_types(&self) -> impl Iterator<Item = WaveformType> + '_ {
+        self.channels.keys().copied()
+    }
+
+    /// Drop all rings
+    pub fn clear(&mut self) {
+        self.channels.clear();
+    }
+}
+
+impl LiveChannel {
+    fn new(sample_rate: u16, capacity: usize, start: DateTime<Utc>) -> Self {
+        Self {
+            sample_rate,
+            ring: vec![f64::NAN; capacity],
+            written: 0,
+            gaps: 0,
+            end: start,
+        }
+    }
+
+    fn write(&mut self, samples: impl Iterator<Item = f64>) {
+        let capacity = self.ring.len() as u64;
+        for sample in samples {
+            self.ring[(self.written % capacity) as usize] = sample;
+            self.written += 1;
+        }
+    }
+
+    /// Nominal sample rate of the waveform
+    pub fn sample_rate(&self) -> u16 {
+        self.sample_rate
+    }
+
+    /// The whole ring in storage order, `NaN` where nothing was written
+    ///
+    /// Drawn as is, with a cursor at [`LiveChannel::write_position`], it
+    /// gives a sweeping display.
+    pub fn ring(&self) -> &[f64] {
+        &self.ring
+    }
+
+    /// Index in [`LiveChannel::ring`] of the next sample to be written
+    pub fn write_position(&self) -> usize {
+        (self.written % self.ring.len() as u64) as usize
+    }
+
+    /// Samples written since the ring was created, including gap filling
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Number of gaps filled with `NaN`
+    pub fn gap_count(&self) -> u64 {
+        self.gaps
+    }
+
+    /// The latest `n` samples (fewer if not yet written)
+    pub fn latest(&self, n: usize) -> LiveView<'_> {
+        let n = n.min(self.ring.len()).min(self.written as usize);
+        let position = self.write_position();
+        if n <= position {
+            LiveView {
+                older: &[],
+                newer: &self.ring[position - n..position],
+            }
+        } else {
+            LiveView {
+                older: &self.ring[self.ring.len() - (n - position)..],
+                newer: &self.ring[..position],
+            }
+        }
+    }
+}
+
+impl<'a> LiveView<'a> {
+    pub fn len(&self) -> usize {
+        self.older.len() + self.newer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Samples, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = f64> + 'a {
+        self.older.iter().chain(self.newer).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::waveforms::WaveformStatus;
+    use chrono::TimeZone;
+
+    fn chunk(secs: i64, first: i16, count: i16) -> WaveformData {
+        WaveformData {
+            timestamp: Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap(),
+            waveform_type: WaveformType::Pleth,
+            plug_id: 0,
+            samples: (first..first + count).collect(),
+            nominal_rate: 4,
+            effective_rate: None,
+            status: WaveformStatus::default(),
+            pacer_indices: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_ring_wraps_and_marks_gaps() {
+        // 2 seconds at 4 Hz: 8 samples
+        let mut live = LiveWaveforms::new(Duration::from_secs(2));
+        live.push(&chunk(0, 0, 4));
+        live.push(&chunk(1, 4, 4));
+        live.push(&chunk(2, 8, 2));
+
+        let channel = live.channel(WaveformType::Pleth).unwrap();
+        assert_eq!(channel.write_position(), 2);
+        let view = live.latest(WaveformType::Pleth, 3).unwrap();
+        assert_eq!((view.older, view.newer), (&[7.0][..], &[8.0, 9.0][..]));
+
+        // 3 seconds late: 2.5 seconds lost, capped at the ring size
+        live.push(&chunk(5, 20, 1));
+        let channel = live.channel(WaveformType::Pleth).unwrap();
+        assert_eq!(channel.gap_count(), 1);
+        let latest: Vec<f64> = channel.latest(2).iter().collect();
+        assert!(latest[0].is_nan());
+        assert_eq!(latest[1], 20.0);
+        assert!(live.latest(WaveformType::Ecg1, 1).is_none());
+    }
+}
@@ -0,0 +1,115 @@
+//! Decode outcome counters for monitoring
+//!
+//! [`DecodeMetrics`] can be shared between the decoding thread and whatever
+//! exposes the numbers (a Prometheus exporter, a periodic log line, ...).
+
+use super::DriRecord;
+use crate::DriError;
+use serde::Serialize;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counters of frames and records seen by a decoder
+#[derive(Debug, Default)]
+pub struct DecodeMetrics {
+    frames: AtomicU64,
+    checksum_errors: AtomicU64,
+    framing_errors: AtomicU64,
+    decode_errors: AtomicU64,
+    physiological_records: AtomicU64,
+    waveform_records: AtomicU64,
+    aux_records: AtomicU64,
+    event_records: AtomicU64,
+    network_records: AtomicU64,
+}
+
+impl DecodeMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a frame that passed framing and checksum
+    pub fn record_frame(&self) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a frame lost to framing
+    pub fn record_framing_error(&self, error: &DriError) {
+        let counter = match error {
+            DriError::ChecksumError => &self.checksum_errors,
+            _ => &self.framing_errors,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a frame whose header or data could not be decoded
+    pub fn record_decode_error(&self) {
+        self.decode_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count decoded records by type
+    pub fn record_records(&self, records: &[DriRecord]) {
+        for record in records {
+            let counter = match record {
+                DriRecord::Physiological(_) => &self.physiological_records,
+                DriRecord::Waveform { .. } => &self.waveform_records,
+                DriRecord::Aux(_) => &self.aux_records,
+                DriRecord::Event(_) => &self.event_records,
+                DriRecord::Network(_) => &self.network_records,
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Current counter values
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let get = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        MetricsSnapshot {
+            frames: get(&self.frames),
+            checksum_errors: get(&self.checksum_errors),
+            framing_errors: get(&self.framing_errors),
+            decode_errors: get(&self.decode_errors),
+            physiological_records: get(&self.physiological_records),
+            waveform_records: get(&self.waveform_records),
+            aux_records: get(&self.aux_records),
+            event_records: get(&self.event_records),
+            network_records: get(&self.network_records),
+        }
+    }
+}
+
+/// Point-in-time copy of [`DecodeMetrics`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MetricsSnapshot {
+    /// Frames with a valid checksum
+    pub frames: u64,
+    pub checksum_errors: u64,
+    /// Other framing errors, e.g. frames too short to hold a checksum
+    pub framing_errors: u64,
+    pub decode_errors: u64,
+    pub physiological_records: u64,
+    /// Waveform records (one per frame, each holding several channels)
+    pub waveform_records: u64,
+    pub aux_records: u64,
+    pub event_records: u64,
+    pub network_records: u64,
+}
+
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} frames ({} checksum errors, {} framing errors, {} decode errors), \
+             records: {} physiological, {} waveform, {} aux, {} event, {} network",
+            self.frames,
+            self.checksum_errors,
+            self.framing_errors,
+            self.decode_errors,
+            self.physiological_records,
+            self.waveform_records,
+            self.aux_records,
+            self.event_records,
+            self.network_records
+        )
+    }
+}
@@ -1,19 +1,40 @@
 //! Data decoding module
 
+pub mod capnography;
+pub mod events;
+pub mod live_waveforms;
+pub mod metrics;
+pub mod network;
 pub mod physiological;
+pub mod pipeline;
+pub mod spirometry;
 pub mod status_bits;
 pub mod subrecords;
 pub mod waveforms;
 
 // Re-export main types for convenience
-pub use physiological::PhysiologicalData;
-pub use waveforms::WaveformData;
+pub use capnography::{CapnoBreath, CapnogramAnalyzer};
+pub use events::EventData;
+pub use live_waveforms::{LiveChannel, LiveView, LiveWaveforms};
+pub use metrics::{DecodeMetrics, MetricsSnapshot};
+pub use network::NetworkData;
+pub use physiological::{InvalidPolicy, PhysiologicalData, SanityFlag};
+pub use pipeline::DecodePipeline;
+pub use spirometry::{Breath, FlowIntegrator};
+pub use waveforms::{
+    AlignedWindow, WaveformBuffer, WaveformCommand, WaveformData, WaveformDecimator,
+};
 
-use crate::constants::dri_types::{DriMainType, PhdbClass, PhdbSubrecordType};
+use waveforms::RateEstimator;
+
+use crate::constants::dri_types::{DriLevel, DriMainType, PhdbClass, PhdbSubrecordType};
+use crate::constants::{ParameterGroup, ScalingProfile};
 use crate::protocol::DriHeader;
 use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
 use log::debug;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 
 /// Decoded DRI record
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,80 +44,685 @@ pub enum DriRecord {
     Physiological(PhysiologicalData),
     /// Waveform data record
     Waveform { waveforms: Vec<WaveformData> },
+    /// Auxiliary physiological subrecord
+    Aux(AuxData),
+    /// Anesthesia record keeping event
+    Event(EventData),
+    /// Network management subrecord, e.g. a gateway's bed list
+    Network(NetworkData),
+}
+
+/// Auxiliary (`PhdbSubrecordType::Aux`) subrecord
+///
+/// Its layout depends on the connected devices and is not decoded; the
+/// bytes are kept as received.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuxData {
+    /// Record timestamp from the header
+    pub timestamp: DateTime<Utc>,
+    /// Plug identifier of the sending monitor (from the record header)
+    pub plug_id: u16,
+    /// Raw subrecord bytes
+    pub data: Vec<u8>,
+}
+
+impl DriRecord {
+    /// Stamp physiological data with the host receive time
+    pub fn mark_received(&mut self, received_at: DateTime<Utc>) {
+        if let DriRecord::Physiological(phys) = self {
+            phys.received_at = Some(received_at);
+        }
+    }
+
+    /// Encode as [`BINARY_FORMAT_VERSION`] followed by the record in CBOR
+    ///
+    /// CBOR keeps field names, so records written before a field was added
+    /// still decode; the version byte marks changes that break this.
+    #[cfg(feature = "binary")]
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![BINARY_FORMAT_VERSION];
+        ciborium::into_writer(self, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Decode a record encoded by [`DriRecord::to_bytes`]
+    ///
+    /// Fails if the bytes were written with another format version.
+    #[cfg(feature = "binary")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        match bytes.split_first() {
+            Some((&BINARY_FORMAT_VERSION, data)) => Ok(ciborium::from_reader(data)?),
+            Some((version, _)) => Err(anyhow!(
+                "Unsupported binary record version {} (expected {})",
+                version,
+                BINARY_FORMAT_VERSION
+            )),
+            None => Err(anyhow!("Empty binary record")),
+        }
+    }
+}
+
+/// Version of the [`DriRecord::to_bytes`] encoding
+#[cfg(feature = "binary")]
+pub const BINARY_FORMAT_VERSION: u8 = 1;
+
+/// Structure of a frame as announced by its header
+///
+/// Available even when the payload cannot be decoded, for diagnostics.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordMeta {
+    pub maintype: DriMainType,
+    pub dri_level: DriLevel,
+    pub plug_id: u16,
+    /// Record number, wraps at 255
+    pub record_number: u8,
+    /// Monitor time of the record
+    pub timestamp: DateTime<Utc>,
+    /// Raw subrecord type codes, in header order
+    pub subrecord_types: Vec<u8>,
+}
+
+impl RecordMeta {
+    /// Extract the metadata of a parsed header
+    pub fn from_header(header: &DriHeader) -> Self {
+        Self {
+            maintype: header.r_maintype,
+            dri_level: header.dri_level,
+            plug_id: header.plug_id,
+            record_number: header.r_nbr,
+            timestamp: header.timestamp(),
+            subrecord_types: header.subrecords.iter().map(|sr| sr.sr_type).collect(),
+        }
+    }
+}
+
+/// A decode result together with the frame's metadata
+#[derive(Debug)]
+pub struct DecodedFrame {
+    pub meta: RecordMeta,
+    /// Outcome of decoding the payload, as from [`Decoder::decode_frame_all`]
+    pub records: Result<Vec<DriRecord>>,
+}
+
+/// Decode one complete frame payload (header + data, unstuffed)
+///
+/// Runs header parsing, data extraction and [`Decoder::decode_frame`] in
+/// one call. Returns `Ok(None)` for frames without decodable data.
+pub fn decode_record(frame_data: &[u8]) -> Result<Option<DriRecord>> {
+    Decoder::new().decode_bytes(frame_data)
+}
+
+/// Optional decoder behaviour
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecoderOptions {
+    /// Keep the unscaled `i16` codes in [`PhysiologicalData::raw_codes`]
+    pub keep_raw_codes: bool,
+    /// How invalid codes are reported in physiological values
+    pub invalid_policy: InvalidPolicy,
+    /// Parameter groups to decode, all if `None`
+    ///
+    /// Other groups are left empty without reading their bytes.
+    pub groups: Option<Vec<ParameterGroup>>,
+    /// Scaling overrides for every DRI level, standard if `None`
+    pub scaling: Option<ScalingProfile>,
+    /// Scaling overrides for records of one DRI level, taking precedence
+    /// over [`DecoderOptions::scaling`]
+    pub level_scaling: BTreeMap<DriLevel, ScalingProfile>,
+}
+
+impl DecoderOptions {
+    /// Create the default options (lean output)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep raw codes alongside scaled values, for debugging scaling
+    pub fn keep_raw_codes(mut self, keep_raw_codes: bool) -> Self {
+        self.keep_raw_codes = keep_raw_codes;
+        self
+    }
+
+    /// Report invalid codes as `None` (default), `NaN` or the raw sentinel
+    pub fn invalid_policy(mut self, invalid_policy: InvalidPolicy) -> Self {
+        self.invalid_policy = invalid_policy;
+        self
+    }
+
+    /// Decode only the given parameter groups
+    pub fn groups(mut self, groups: &[ParameterGroup]) -> Self {
+        self.groups = Some(groups.to_vec());
+        self
+    }
+
+    /// Scale parameters as `profile` says, whatever the DRI level
+    pub fn scaling(mut self, profile: ScalingProfile) -> Self {
+        self.scaling = Some(profile);
+        self
+    }
+
+    /// Scale parameters of records from `level` monitors as `profile` says
+    pub fn scaling_for_level(mut self, level: DriLevel, profile: ScalingProfile) -> Self {
+        self.level_scaling.insert(level, profile);
+        self
+    }
+
+    /// Profile applied to records of `level`, if any
+    pub fn scaling_profile(&self, level: DriLevel) -> Option<&ScalingProfile> {
+        self.level_scaling.get(&level).or(self.scaling.as_ref())
+    }
 }
 
 /// Main decoder
-pub struct Decoder;
+///
+/// Keeps per-waveform state across frames to measure effective sample
+/// rates, so use one decoder per monitor connection.
+#[derive(Default)]
+pub struct Decoder {
+    options: DecoderOptions,
+    rates: RateEstimator,
+    unknown_waveform_types: BTreeSet<u8>,
+    waveform_command: Option<WaveformCommand>,
+}
 
 impl Decoder {
     /// Create a new decoder
     pub fn new() -> Self {
-        Self
+        Self::new_with_options(DecoderOptions::default())
+    }
+
+    /// Create a decoder that only decodes the given parameter groups
+    ///
+    /// For hosts that need a few vitals, e.g.
+    /// `&[ParameterGroup::Ecg, ParameterGroup::Spo2]` for HR, SpO2 and RR.
+    /// The savings are modest: a 1088-byte displayed-values frame took
+    /// about 250 ns to decode in full and 220 ns with those two groups
+    /// (release build, x86-64), as header parsing and building the record
+    /// cost more than the group parsing itself.
+    pub fn new_with_groups(groups: &[ParameterGroup]) -> Self {
+        Self::new_with_options(DecoderOptions::new().groups(groups))
+    }
+
+    /// Create a decoder with the given options
+    pub fn new_with_options(options: DecoderOptions) -> Self {
+        Self {
+            options,
+            rates: RateEstimator::default(),
+            unknown_waveform_types: BTreeSet::new(),
+            waveform_command: None,
+        }
+    }
+
+    /// Waveform type codes seen so far that this version does not decode
+    pub fn unknown_waveform_types(&self) -> &BTreeSet<u8> {
+        &self.unknown_waveform_types
+    }
+
+    /// Last waveform command echoed by the monitor
+    ///
+    /// Compare its accepted set with the request to detect waveforms the
+    /// monitor dropped.
+    pub fn waveform_command(&self) -> Option<&WaveformCommand> {
+        self.waveform_command.as_ref()
+    }
+
+    /// Decode one complete frame payload (header + data), returning its first record
+    pub fn decode_bytes(&mut self, frame_data: &[u8]) -> Result<Option<DriRecord>> {
+        Ok(self.decode_bytes_all(frame_data)?.into_iter().next())
+    }
+
+    /// Decode one complete frame payload, keeping the header metadata
+    ///
+    /// Fails only if the header itself cannot be parsed; payload errors
+    /// are returned in [`DecodedFrame::records`].
+    pub fn decode_with_meta(&mut self, frame_data: &[u8]) -> Result<DecodedFrame> {
+        let header = DriHeader::parse(frame_data)?;
+        let meta = RecordMeta::from_header(&header);
+        let records = header
+            .extract_data(frame_data)
+            .map_err(Into::into)
+            .and_then(|data| self.decode_frame_all(&header, data));
+        Ok(DecodedFrame { meta, records })
+    }
+
+    /// Decode one complete frame payload into all the records it carries
+    pub fn decode_bytes_all(&mut self, frame_data: &[u8]) -> Result<Vec<DriRecord>> {
+        let header = DriHeader::parse(frame_data)?;
+        let data = header.extract_data(frame_data)?;
+        self.decode_frame_all(&header, data)
+    }
+
+    /// Decode a DRI frame, returning its first record
+    ///
+    /// A PHDB frame can carry several subrecords (e.g. displayed values and
+    /// a trend); use [`Decoder::decode_frame_all`] to get all of them.
+    pub fn decode_frame(&mut self, header: &DriHeader, data: &[u8]) -> Result<Option<DriRecord>> {
+        Ok(self.decode_frame_all(header, data)?.into_iter().next())
     }
 
-    /// Decode a DRI frame
-    pub fn decode_frame(&self, header: &DriHeader, data: &[u8]) -> Result<Option<DriRecord>> {
+    /// Decode a DRI frame into all the records it carries
+    ///
+    /// Each PHDB subrecord becomes its own record, and so does each sample
+    /// of a trend subrecord. The result is empty for frames without
+    /// decodable data.
+    pub fn decode_frame_all(&mut self, header: &DriHeader, data: &[u8]) -> Result<Vec<DriRecord>> {
         match header.r_maintype {
             DriMainType::Phdb => {
-                // Get the first subrecord to determine type and class
                 if header.subrecords.is_empty() {
                     return Err(anyhow!("No subrecords in physiological data frame"));
                 }
 
-                // Get subrecord type from first subrecord
-                let subtype =
-                    PhdbSubrecordType::from_u8(header.subrecords[0].sr_type).ok_or_else(|| {
-                        anyhow!("Invalid subrecord type: {}", header.subrecords[0].sr_type)
-                    })?;
-
-                // Get subrecord data
-                let sub_data = header.get_subrecord_data(data, 0)?;
+                let mut records = Vec::new();
+                for (i, subrecord) in header.subrecords.iter().enumerate() {
+                    let subtype = PhdbSubrecordType::from_u8(subrecord.sr_type)
+                        .ok_or_else(|| anyhow!("Invalid subrecord type: {}", subrecord.sr_type))?;
+                    let sub_data = header.get_subrecord_data(data, i)?;
 
-                // Determine class from the last word of the subrecord (offset 1086-1087 in 1088-byte subrecord)
-                // Bits 8-11 contain the class
-                if sub_data.len() < 1088 {
-                    return Err(anyhow!("Physiological subrecord too short"));
+                    match subtype {
+                        // Requests only travel from host to monitor
+                        PhdbSubrecordType::XmitReq => continue,
+                        PhdbSubrecordType::Aux => records.push(DriRecord::Aux(AuxData {
+                            timestamp: header.timestamp(),
+                            plug_id: header.plug_id,
+                            data: sub_data.to_vec(),
+                        })),
+                        PhdbSubrecordType::Trend10s | PhdbSubrecordType::Trend60s => {
+                            for sample in physiological::trend_samples(sub_data) {
+                                let phys = self.decode_physiological(header, sample, subtype)?;
+                                records.push(DriRecord::Physiological(phys));
+                            }
+                        }
+                        _ => {
+                            let phys = self.decode_physiological(header, sub_data, subtype)?;
+                            records.push(DriRecord::Physiological(phys));
+                        }
+                    }
                 }
 
-                let cl_drilvl_subt = u16::from_le_bytes([sub_data[1086], sub_data[1087]]);
-                let class_bits = ((cl_drilvl_subt >> 8) & 0x0F) as u8;
-                let class = PhdbClass::from_u8(class_bits)
-                    .ok_or_else(|| anyhow!("Invalid class: {}", class_bits))?;
-
-                debug!(
-                    "Decoding physiological data: subtype={:?}, class={:?}",
-                    subtype, class
-                );
-
-                let phys = physiological::decode_physiological(sub_data, subtype, class)?;
-                Ok(Some(DriRecord::Physiological(phys)))
+                // Physiological data first, as callers of decode_frame expect
+                records.sort_by_key(|record| matches!(record, DriRecord::Aux(_)));
+                Ok(records)
             }
             DriMainType::Wave => {
-                let waveforms = waveforms::decode_waveforms(header, data)?;
+                if let Some(command) = waveforms::decode_waveform_command(header, data) {
+                    self.waveform_command = Some(command);
+                }
+                let (mut waveforms, unknown_types) =
+                    waveforms::decode_waveforms_detailed(header, data)?;
+                self.unknown_waveform_types.extend(unknown_types);
+                for waveform in &mut waveforms {
+                    waveform.effective_rate = self.rates.observe(waveform);
+                }
                 if waveforms.is_empty() {
-                    Ok(None)
+                    Ok(Vec::new())
                 } else {
-                    Ok(Some(DriRecord::Waveform { waveforms }))
+                    Ok(vec![DriRecord::Waveform { waveforms }])
                 }
             }
             DriMainType::Alarm => {
                 debug!("Alarm records not yet implemented");
-                Ok(None)
+                Ok(Vec::new())
             }
-            DriMainType::Network => {
-                debug!("Network management records not yet implemented");
-                Ok(None)
+            DriMainType::Network => Ok(network::decode_network(header, data)?
+                .into_iter()
+                .map(DriRecord::Network)
+                .collect()),
+            DriMainType::Fo => Ok(events::decode_events(header, data)?
+                .into_iter()
+                .map(DriRecord::Event)
+                .collect()),
+        }
+    }
+
+    /// Decode one 1088-byte displayed values or trend sample
+    fn decode_physiological(
+        &self,
+        header: &DriHeader,
+        sub_data: &[u8],
+        subtype: PhdbSubrecordType,
+    ) -> Result<PhysiologicalData> {
+        // The class is in bits 8-11 of the last word of the subrecord
+        let cl_drilvl_subt = subrecords::read_u16(sub_data, 1086)
+            .ok_or_else(|| anyhow!("Physiological subrecord too short"))?;
+        let class_bits = ((cl_drilvl_subt >> 8) & 0x0F) as u8;
+        let class = PhdbClass::from_u8(class_bits)
+            .ok_or_else(|| anyhow!("Invalid class: {}", class_bits))?;
+
+        debug!(subtype:?, class:?; "Decoding physiological data");
+
+        let mut phys = physiological::decode_physiological_groups(
+            sub_data,
+            subtype,
+            class,
+            self.options.invalid_policy,
+            self.options.groups.as_deref(),
+        )?;
+        phys.plug_id = header.plug_id;
+        if let Some(profile) = self.options.scaling_profile(header.dri_level) {
+            physiological::apply_scaling(&mut phys, sub_data, profile, self.options.invalid_policy);
+        }
+        if self.options.keep_raw_codes {
+            phys.raw_codes = Some(physiological::raw_codes(sub_data, class));
+        }
+        Ok(phys)
+    }
+}
+
+/// Level 02 record of `maintype` with one subrecord per `(type, bytes)`
+///
+/// Descriptor offsets follow the subrecord lengths; `plug_id` and the
+/// timestamp are zero.
+#[cfg(test)]
+pub(crate) fn test_frame(maintype: DriMainType, subrecords: &[(u8, &[u8])]) -> Vec<u8> {
+    use crate::constants::HEADER_SIZE;
+    use crate::protocol::header::SubrecordDescriptor;
+
+    let mut data = Vec::new();
+    let mut descriptors = Vec::new();
+    for &(sr_type, bytes) in subrecords {
+        descriptors.push(SubrecordDescriptor {
+            offset: data.len() as u16,
+            sr_type,
+        });
+        data.extend_from_slice(bytes);
+    }
+    let header = DriHeader {
+        r_len: (HEADER_SIZE + data.len()) as u16,
+        r_nbr: 0,
+        dri_level: DriLevel::Level02,
+        plug_id: 0,
+        r_time: 0,
+        r_maintype: maintype,
+        subrecords: descriptors,
+    };
+    let mut frame = header.to_bytes();
+    frame.extend(data);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::HEADER_SIZE;
+    use crate::constants::special_values::{DATA_DISCONT, DATA_INVALID};
+
+    /// Record with one Basic class subrecord of `subtype`, zeroed except for
+    /// what `fill` writes into its class data (after the timestamp)
+    fn phdb_frame(subtype: PhdbSubrecordType, fill: impl FnOnce(&mut [u8])) -> Vec<u8> {
+        let mut subrecord = vec![0u8; 1088];
+        fill(&mut subrecord[4..]);
+        test_frame(DriMainType::Phdb, &[(subtype as u8, &subrecord)])
+    }
+
+    #[test]
+    fn test_trend_subtype_is_tagged() {
+        let frame = phdb_frame(PhdbSubrecordType::Trend10s, |_| {});
+
+        match decode_record(&frame).unwrap() {
+            Some(DriRecord::Physiological(phys)) => {
+                assert_eq!(phys.subtype, PhdbSubrecordType::Trend10s);
+                assert_eq!(phys.class, PhdbClass::Basic);
             }
-            DriMainType::Fo => {
-                debug!("Event records not yet implemented");
-                Ok(None)
+            other => panic!("unexpected record: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_group_allow_list_skips_other_groups() {
+        let frame = phdb_frame(PhdbSubrecordType::Displ, |class| {
+            // NIBP systolic at class offset 76 + 6, SpO2 at 122 + 6
+            class[82..84].copy_from_slice(&12000i16.to_le_bytes());
+            class[128..130].copy_from_slice(&9800i16.to_le_bytes());
+        });
+
+        let decode = |mut decoder: Decoder| match decoder.decode_bytes(&frame).unwrap() {
+            Some(DriRecord::Physiological(phys)) => (phys.spo2, phys.nibp_sys),
+            other => panic!("unexpected record: {:?}", other),
+        };
+
+        assert_eq!(decode(Decoder::new()), (Some(98.0), Some(120.0)));
+        let spo2_only = Decoder::new_with_groups(&[ParameterGroup::Spo2]);
+        assert_eq!(decode(spo2_only), (Some(98.0), None));
+    }
+
+    #[test]
+    fn test_raw_codes_are_opt_in() {
+        let frame = phdb_frame(PhdbSubrecordType::Displ, |class| {
+            // SpO2 value at class offset 122 + 6
+            class[128..130].copy_from_slice(&9800i16.to_le_bytes());
+        });
+
+        let spo2_code = |mut decoder: Decoder| match decoder.decode_bytes(&frame).unwrap() {
+            Some(DriRecord::Physiological(phys)) => {
+                assert_eq!(phys.spo2, Some(98.0));
+                phys.raw_codes.map(|codes| codes["spo2"])
             }
+            other => panic!("unexpected record: {:?}", other),
+        };
+
+        assert_eq!(spo2_code(Decoder::new()), None);
+        let options = DecoderOptions::new().keep_raw_codes(true);
+        assert_eq!(spo2_code(Decoder::new_with_options(options)), Some(9800));
+    }
+
+    #[test]
+    fn test_scaling_profile_overrides_a_factor() {
+        let frame = phdb_frame(PhdbSubrecordType::Displ, |class| {
+            // Expired tidal volume at class offset 182 + 16
+            class[198..200].copy_from_slice(&480i16.to_le_bytes());
+        });
+
+        let tv_exp = |options: DecoderOptions| match Decoder::new_with_options(options)
+            .decode_bytes(&frame)
+            .unwrap()
+        {
+            Some(DriRecord::Physiological(phys)) => phys.flow_tv_exp,
+            other => panic!("unexpected record: {:?}", other),
+        };
+        let ml = ScalingProfile::new("tv in ml")
+            .with_factor("flow_tv_exp", 1.0)
+            .unwrap();
+
+        assert_eq!(tv_exp(DecoderOptions::new()), Some(48.0));
+        assert_eq!(
+            tv_exp(DecoderOptions::new().scaling(ml.clone())),
+            Some(480.0)
+        );
+        let other_level = DecoderOptions::new().scaling_for_level(DriLevel::Level04, ml.clone());
+        assert_eq!(tv_exp(other_level), Some(48.0));
+        let this_level = DecoderOptions::new()
+            .scaling(ScalingProfile::standard())
+            .scaling_for_level(DriLevel::Level02, ml);
+        assert_eq!(tv_exp(this_level), Some(480.0));
+    }
+
+    #[test]
+    fn test_invalid_policy_applies_to_invalid_codes() {
+        let frame = phdb_frame(PhdbSubrecordType::Displ, |class| {
+            // SpO2 invalid, pulse rate valid
+            class[128..130].copy_from_slice(&DATA_INVALID.to_le_bytes());
+            class[130..132].copy_from_slice(&72i16.to_le_bytes());
+        });
+
+        let decode = |policy| {
+            let options = DecoderOptions::new().invalid_policy(policy);
+            match Decoder::new_with_options(options)
+                .decode_bytes(&frame)
+                .unwrap()
+            {
+                Some(DriRecord::Physiological(phys)) => {
+                    assert_eq!(phys.spo2_pr, Some(72.0));
+                    phys.spo2
+                }
+                other => panic!("unexpected record: {:?}", other),
+            }
+        };
+
+        assert_eq!(decode(InvalidPolicy::None), None);
+        assert!(decode(InvalidPolicy::NaN).unwrap().is_nan());
+        assert_eq!(decode(InvalidPolicy::Sentinel), Some(DATA_INVALID as f64));
+    }
+
+    #[test]
+    fn test_trend_discontinuities_are_reported() {
+        let decode = |subtype: PhdbSubrecordType| {
+            let frame = phdb_frame(subtype, |class| {
+                class[128..130].copy_from_slice(&DATA_DISCONT.to_le_bytes());
+                class[130..132].copy_from_slice(&72i16.to_le_bytes());
+            });
+            match Decoder::new().decode_bytes(&frame).unwrap() {
+                Some(DriRecord::Physiological(phys)) => phys,
+                other => panic!("unexpected record: {:?}", other),
+            }
+        };
+
+        let trend = decode(PhdbSubrecordType::Trend60s);
+        assert_eq!(trend.discontinuities, ["spo2"]);
+        assert_eq!(trend.spo2, None);
+        assert_eq!(trend.spo2_pr, Some(72.0));
+        assert!(decode(PhdbSubrecordType::Displ).discontinuities.is_empty());
+    }
+
+    #[test]
+    fn test_aux_subrecord_is_not_parsed_as_phdb() {
+        let aux = (PhdbSubrecordType::Aux as u8, &[1u8, 2, 3, 4][..]);
+        let frame = test_frame(DriMainType::Phdb, &[aux]);
+
+        match decode_record(&frame).unwrap() {
+            Some(DriRecord::Aux(aux)) => assert_eq!(aux.data, vec![1, 2, 3, 4]),
+            other => panic!("unexpected record: {:?}", other),
         }
+
+        // An aux subrecord ahead of displayed values does not hide them
+        let displ = [0u8; 1088];
+        let frame = test_frame(
+            DriMainType::Phdb,
+            &[aux, (PhdbSubrecordType::Displ as u8, &displ)],
+        );
+        assert!(matches!(
+            decode_record(&frame).unwrap(),
+            Some(DriRecord::Physiological(_))
+        ));
     }
-}
 
-impl Default for Decoder {
-    fn default() -> Self {
-        Self::new()
+    #[test]
+    fn test_all_phdb_subrecords_are_decoded() {
+        let displ = [0u8; 1088];
+        let mut trend = [0u8; 1088];
+        // Class word of the second subrecord only
+        trend[1087] = PhdbClass::Ext1 as u8;
+        let frame = test_frame(
+            DriMainType::Phdb,
+            &[
+                (PhdbSubrecordType::Displ as u8, &displ),
+                (PhdbSubrecordType::Trend10s as u8, &trend),
+            ],
+        );
+
+        let records = Decoder::new().decode_bytes_all(&frame).unwrap();
+        let decoded: Vec<_> = records
+            .iter()
+            .map(|record| match record {
+                DriRecord::Physiological(phys) => (phys.subtype, phys.class),
+                other => panic!("unexpected record: {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            decoded,
+            vec![
+                (PhdbSubrecordType::Displ, PhdbClass::Basic),
+                (PhdbSubrecordType::Trend10s, PhdbClass::Ext1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trend_subrecord_samples_are_decoded_separately() {
+        let mut samples = Vec::new();
+        for (minute, class) in [(0u32, PhdbClass::Basic), (1, PhdbClass::Ext1)] {
+            let mut sample = vec![0u8; 1088];
+            sample[0..4].copy_from_slice(&(1_700_000_000 + minute * 60).to_le_bytes());
+            sample[1087] = class as u8;
+            samples.extend(sample);
+        }
+        // Padding shorter than a sample
+        samples.extend([0u8; 2]);
+        let frame = test_frame(
+            DriMainType::Phdb,
+            &[(PhdbSubrecordType::Trend60s as u8, &samples)],
+        );
+
+        let records = Decoder::new().decode_bytes_all(&frame).unwrap();
+        let decoded: Vec<_> = records
+            .iter()
+            .map(|record| match record {
+                DriRecord::Physiological(phys) => {
+                    (phys.subtype, phys.class, phys.timestamp.timestamp())
+                }
+                other => panic!("unexpected record: {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            decoded,
+            vec![
+                (PhdbSubrecordType::Trend60s, PhdbClass::Basic, 1_700_000_000),
+                (PhdbSubrecordType::Trend60s, PhdbClass::Ext1, 1_700_000_060),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_meta_survives_decode_errors() {
+        // Both too short for a PHDB subrecord
+        let short = [0u8; 100];
+        let mut frame = test_frame(
+            DriMainType::Phdb,
+            &[
+                (PhdbSubrecordType::Displ as u8, &short),
+                (PhdbSubrecordType::Trend10s as u8, &short),
+            ],
+        );
+        frame[4] = 7; // plug id
+
+        let decoded = Decoder::new().decode_with_meta(&frame).unwrap();
+        assert!(decoded.records.is_err());
+        assert_eq!(decoded.meta.maintype, DriMainType::Phdb);
+        assert_eq!(decoded.meta.plug_id, 7);
+        assert_eq!(decoded.meta.subrecord_types, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_decode_record_survives_arbitrary_input() {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let mut next_byte = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        };
+
+        for maintype in [DriMainType::Phdb, DriMainType::Wave] {
+            let mut frame: Vec<u8> = (0..HEADER_SIZE + 1200).map(|_| next_byte()).collect();
+            frame[3] = 8;
+            frame[16..18].copy_from_slice(&(maintype as u16).to_le_bytes());
+
+            // Every truncation, with random and with zeroed descriptor offsets
+            for len in 0..frame.len() {
+                let _ = decode_record(&frame[..len]);
+            }
+            frame[18..20].fill(0);
+            frame[21..23].copy_from_slice(&600u16.to_le_bytes());
+            for len in 0..frame.len() {
+                let _ = decode_record(&frame[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_record_rejects_short_frames() {
+        assert!(decode_record(&[0u8; 10]).is_err());
+
+        // Alarm records are not decoded yet
+        let frame = test_frame(DriMainType::Alarm, &[]);
+        assert!(decode_record(&frame).unwrap().is_none());
     }
 }
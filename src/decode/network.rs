@@ -0,0 +1,57 @@
+//! Network management records (`DriMainType::Network`)
+//!
+//! On a central-station gateway these list the connected beds. Their
+//! subrecord layout is not documented here and no capture has been
+//! checked yet, so each subrecord is kept as received, like aux data.
+
+use crate::protocol::DriHeader;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// One network management subrecord, not decoded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkData {
+    /// Record timestamp from the header
+    pub timestamp: DateTime<Utc>,
+    /// Plug id of the sending gateway
+    pub plug_id: u16,
+    /// Subrecord type from the header descriptor
+    pub subrecord_type: u8,
+    /// Raw subrecord bytes
+    pub data: Vec<u8>,
+}
+
+/// Collect every subrecord of a network management frame
+pub fn decode_network(header: &DriHeader, data: &[u8]) -> Result<Vec<NetworkData>> {
+    (0..header.subrecords.len())
+        .map(|i| {
+            Ok(NetworkData {
+                timestamp: header.timestamp(),
+                plug_id: header.plug_id,
+                subrecord_type: header.subrecords[i].sr_type,
+                data: header.get_subrecord_data(data, i)?.to_vec(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constants::DriMainType;
+    use crate::decode::{Decoder, DriRecord, test_frame};
+
+    #[test]
+    fn test_network_subrecords_are_kept_raw() {
+        let mut frame = test_frame(DriMainType::Network, &[(0, &[2, 0, 3, 0, 1, 0])]);
+        frame[4..6].copy_from_slice(&7u16.to_le_bytes());
+
+        match Decoder::new().decode_bytes(&frame).unwrap() {
+            Some(DriRecord::Network(network)) => {
+                assert_eq!(network.plug_id, 7);
+                assert_eq!(network.data, vec![2, 0, 3, 0, 1, 0]);
+            }
+            other => panic!("unexpected record: {:?}", other),
+        }
+    }
+}
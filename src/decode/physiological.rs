@@ -3,17 +3,23 @@
 use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 // Import from constants
+use crate::constants::PHDB_SUBRECORD_SIZE;
 use crate::constants::dri_types::{PhdbClass, PhdbSubrecordType};
 use crate::constants::physiological::{
-    AnesthesiaAgent, EcgLeadType, HrSource, InvasivePressureLabel, TemperatureLabel,
+    AnesthesiaAgent, EcgLeadType, HrSource, InvasivePressureLabel, ParameterGroup,
+    TemperatureLabel, plausible_range,
 };
 use crate::constants::scaling::{
-    SCALE_AWP_100, SCALE_COMPLIANCE_100, SCALE_IR_AMP_10, SCALE_MAC_100, SCALE_PERCENT_100,
-    SCALE_PRESSURE_100, SCALE_ST_100, SCALE_TEMP_100, SCALE_VOLUME_10, scale_valid_i16,
+    SCALE_AMB_PRESS_10, SCALE_AWP_100, SCALE_CO_100, SCALE_COMPLIANCE_100, SCALE_IR_AMP_10,
+    SCALE_MAC_100, SCALE_MV_100, SCALE_PERCENT_10, SCALE_PERCENT_100, SCALE_PRESSURE_100,
+    SCALE_ST_100, SCALE_TEMP_100, SCALE_VOLUME_10, ScalingProfile, scale_valid_i16,
+};
+use crate::constants::special_values::{
+    DATA_DISCONT, DATA_INVALID_LIMIT, DATA_NOT_UPDATED, is_invalid,
 };
-use crate::constants::special_values::is_invalid;
 
 // Import from same module
 use super::status_bits::*;
@@ -22,12 +28,18 @@ use super::subrecords::*;
 /// Physiological data record with properly scaled values
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PhysiologicalData {
-    /// Timestamp
+    /// Timestamp (monitor clock)
     pub timestamp: DateTime<Utc>,
+    /// Host time when the record was received (None when decoded offline)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received_at: Option<DateTime<Utc>>,
     /// Record class
     pub class: PhdbClass,
     /// Subrecord type (displayed, trend 10s, trend 60s)
     pub subtype: PhdbSubrecordType,
+    /// Plug identifier of the sending monitor (from the record header)
+    #[serde(default)]
+    pub plug_id: u16,
 
     // ECG data (with status)
     pub ecg_status: EcgStatus,
@@ -35,7 +47,12 @@ pub struct PhysiologicalData {
     pub ecg_st1: Option<f64>, // mm (scaled from 1/100)
     pub ecg_st2: Option<f64>, // mm (scaled from 1/100)
     pub ecg_st3: Option<f64>, // mm (scaled from 1/100)
-    pub ecg_rr: Option<f64>,  // breaths/min (no scaling)
+    /// Impedance respiration rate, breaths/min (no scaling)
+    ///
+    /// The Basic class has no separate respiration group: impedance RR is
+    /// only carried by the ECG group, and the impedance apnea flag is
+    /// `co2_status.apnea_from_resp` (see [`PhysiologicalData::resp_apnea`]).
+    pub ecg_rr: Option<f64>,
     pub ecg_hr_source: Option<HrSource>,
     pub ecg_lead1: Option<EcgLeadType>,
     pub ecg_lead2: Option<EcgLeadType>,
@@ -72,9 +89,10 @@ pub struct PhysiologicalData {
 
     // CO2 (with status)
     pub co2_status: Co2Status,
-    pub co2_et: Option<f64>, // % (scaled from 1/100)
-    pub co2_fi: Option<f64>, // % (scaled from 1/100)
-    pub co2_rr: Option<f64>, // breaths/min (no scaling)
+    pub co2_et: Option<f64>,        // % (scaled from 1/100)
+    pub co2_fi: Option<f64>,        // % (scaled from 1/100)
+    pub co2_rr: Option<f64>,        // breaths/min (no scaling)
+    pub co2_amb_press: Option<f64>, // mmHg (scaled from 1/10)
 
     // O2 (with status)
     pub o2_status: GasStatus,
@@ -103,15 +121,115 @@ pub struct PhysiologicalData {
     pub flow_tv_exp: Option<f64>,     // ml (scaled from 1/10)
     pub flow_compliance: Option<f64>, // ml/cmH2O (scaled from 1/100)
     pub flow_mv_exp: Option<f64>,     // l/min (scaled from 1/100)
+
+    // Cardiac output (with status), measured intermittently
+    pub co_status: GenericStatus,
+    pub co: Option<f64>,            // l/min (scaled from 1/100)
+    pub co_blood_temp: Option<f64>, // °C (scaled from 1/100)
+    pub co_ref: Option<f64>,        // right ventricle ejection fraction, % (no scaling)
+    pub co_pcwp: Option<f64>,       // mmHg (scaled from 1/100)
+    /// No new CO measurement since the previous report
+    pub co_not_updated: bool,
+
+    // Mixed/central venous O2 saturation (with status)
+    pub svo2_status: Svo2Status,
+    pub svo2: Option<f64>, // % (scaled from 1/100)
+
+    // Neuromuscular transmission (with status)
+    pub nmt_status: NmtStatus,
+    pub nmt_t1: Option<f64>,        // % of reference (scaled from 1/10)
+    pub nmt_tof_ratio: Option<f64>, // % (scaled from 1/10)
+    pub nmt_ptc: Option<f64>,       // post-tetanic count (no scaling)
+
+    /// Unscaled codes by field name, see [`DecoderOptions`](super::DecoderOptions)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_codes: Option<BTreeMap<String, i16>>,
+    /// Trend fields reported as `DATA_DISCONT`, by field name
+    ///
+    /// The measurement was interrupted during the trend interval (e.g. the
+    /// sensor was disconnected); the value itself is treated as invalid.
+    /// Only set on `Trend10s` and `Trend60s` records.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub discontinuities: Vec<String>,
+}
+
+/// Whether a decoded value is a measurement rather than an invalid marker
+///
+/// [`InvalidPolicy::NaN`] and [`InvalidPolicy::Sentinel`] keep invalid
+/// readings as NaN or as the raw code; outputs that aggregate or publish
+/// values skip both.
+pub fn is_measured(value: f64) -> bool {
+    value.is_finite() && value > f64::from(DATA_INVALID_LIMIT)
 }
 
 impl PhysiologicalData {
+    /// Numeric parameters as `(name, value, unit)`, in a fixed order
+    ///
+    /// Names are the serialized field names; the unit is empty for
+    /// dimensionless values.
+    pub fn iter_vitals(&self) -> impl Iterator<Item = (&'static str, Option<f64>, &'static str)> {
+        VITALS
+            .iter()
+            .map(move |(name, unit, get)| (*name, get(self), *unit))
+    }
+
+    /// Fields whose value falls outside [`PLAUSIBLE_RANGES`](crate::constants::physiological::PLAUSIBLE_RANGES)
+    ///
+    /// A diagnostic aid for spotting decoding errors; nothing is filtered.
+    /// NaN and raw invalid codes (see [`InvalidPolicy`]) are not flagged.
+    pub fn sanity_flags(&self) -> Vec<SanityFlag> {
+        self.iter_vitals()
+            .filter_map(|(field, value, _)| {
+                let value = value.filter(|&v| is_measured(v))?;
+                let (min, max) = plausible_range(field)?;
+                (!(min..=max).contains(&value)).then_some(SanityFlag {
+                    field,
+                    value,
+                    min,
+                    max,
+                })
+            })
+            .collect()
+    }
+
+    /// Whether no field is flagged by [`sanity_flags`](Self::sanity_flags)
+    pub fn is_physiologically_plausible(&self) -> bool {
+        self.sanity_flags().is_empty()
+    }
+
+    /// Impedance respiration rate in breaths/min, from the ECG group
+    pub fn resp_rr(&self) -> Option<f64> {
+        self.ecg_rr
+    }
+
+    /// Apnea detected from impedance respiration, from the CO2 status word
+    pub fn resp_apnea(&self) -> bool {
+        self.co2_status.apnea_from_resp
+    }
+
+    /// Monitor clock minus host receive time, if known
+    pub fn clock_skew(&self) -> Option<chrono::Duration> {
+        self.received_at.map(|received| self.timestamp - received)
+    }
+
+    /// End-tidal CO2 partial pressure in mmHg, from the ambient pressure
+    pub fn co2_partial_pressure_mmhg(&self) -> Option<f64> {
+        partial_pressure_mmhg(self.co2_et, self.co2_amb_press)
+    }
+
+    /// End-tidal O2 partial pressure in mmHg, from the CO2 group's ambient pressure
+    pub fn o2_partial_pressure_mmhg(&self) -> Option<f64> {
+        partial_pressure_mmhg(self.o2_et, self.co2_amb_press)
+    }
+
     /// Create an empty physiological data record
     pub fn empty(timestamp: DateTime<Utc>, class: PhdbClass, subtype: PhdbSubrecordType) -> Self {
         Self {
             timestamp,
+            received_at: None,
             class,
             subtype,
+            plug_id: 0,
 
             // ECG
             ecg_status: EcgStatus::default(),
@@ -159,6 +277,7 @@ impl PhysiologicalData {
             co2_et: None,
             co2_fi: None,
             co2_rr: None,
+            co2_amb_press: None,
 
             // O2
             o2_status: GasStatus::default(),
@@ -187,17 +306,116 @@ impl PhysiologicalData {
             flow_tv_exp: None,
             flow_compliance: None,
             flow_mv_exp: None,
+
+            // Cardiac output
+            co_status: GenericStatus::default(),
+            co: None,
+            co_blood_temp: None,
+            co_ref: None,
+            co_pcwp: None,
+            co_not_updated: false,
+
+            // SvO2
+            svo2_status: Svo2Status::default(),
+            svo2: None,
+
+            // NMT
+            nmt_status: NmtStatus::default(),
+            nmt_t1: None,
+            nmt_tof_ratio: None,
+            nmt_ptc: None,
+
+            raw_codes: None,
+            discontinuities: Vec::new(),
+        }
+    }
+}
+
+/// How invalid and special codes (e.g. `DATA_INVALID`) are reported
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InvalidPolicy {
+    /// As `None`: omitted from JSON, empty in CSV
+    #[default]
+    None,
+    /// As `NaN`, keeping CSV columns numeric for analytics tools
+    NaN,
+    /// As the raw code (e.g. -32767), unscaled
+    Sentinel,
+}
+
+/// A value outside its plausible range, see [`PhysiologicalData::sanity_flags`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SanityFlag {
+    pub field: &'static str,
+    pub value: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl InvalidPolicy {
+    /// Value reported for the invalid code `raw`
+    pub fn apply(self, raw: i16) -> Option<f64> {
+        match self {
+            InvalidPolicy::None => None,
+            InvalidPolicy::NaN => Some(f64::NAN),
+            InvalidPolicy::Sentinel => Some(f64::from(raw)),
         }
     }
 }
 
+/// Convert a gas fraction in % to a partial pressure at the given ambient pressure
+///
+/// Returns `None` if either value is missing or the pressure is not positive.
+pub fn partial_pressure_mmhg(percent: Option<f64>, ambient_mmhg: Option<f64>) -> Option<f64> {
+    match (percent, ambient_mmhg) {
+        (Some(percent), Some(ambient)) if ambient > 0.0 => Some(percent / 100.0 * ambient),
+        _ => None,
+    }
+}
+
+/// Split a trend subrecord into its samples
+///
+/// Each sample has the displayed-values layout, with its own timestamp and
+/// class word. A subrecord spanning several samples is cut at
+/// [`PHDB_SUBRECORD_SIZE`] strides and trailing padding is dropped;
+/// anything shorter is returned whole.
+pub fn trend_samples(subrecord_data: &[u8]) -> Vec<&[u8]> {
+    if subrecord_data.len() < 2 * PHDB_SUBRECORD_SIZE {
+        return vec![subrecord_data];
+    }
+    subrecord_data.chunks_exact(PHDB_SUBRECORD_SIZE).collect()
+}
+
 /// Decode physiological data from a DRI subrecord
+///
+/// Invalid codes become `None`; see [`decode_physiological_with_policy`].
 pub fn decode_physiological(
     subrecord_data: &[u8],
     subtype: PhdbSubrecordType,
     class: PhdbClass,
 ) -> Result<PhysiologicalData> {
-    if subrecord_data.len() < 1088 {
+    decode_physiological_with_policy(subrecord_data, subtype, class, InvalidPolicy::None)
+}
+
+/// Decode physiological data, reporting invalid codes as `policy` says
+pub fn decode_physiological_with_policy(
+    subrecord_data: &[u8],
+    subtype: PhdbSubrecordType,
+    class: PhdbClass,
+    policy: InvalidPolicy,
+) -> Result<PhysiologicalData> {
+    decode_physiological_groups(subrecord_data, subtype, class, policy, None)
+}
+
+/// Decode physiological data, parsing only `groups` (all if `None`)
+pub fn decode_physiological_groups(
+    subrecord_data: &[u8],
+    subtype: PhdbSubrecordType,
+    class: PhdbClass,
+    policy: InvalidPolicy,
+    groups: Option<&[ParameterGroup]>,
+) -> Result<PhysiologicalData> {
+    if subrecord_data.len() < PHDB_SUBRECORD_SIZE {
         return Err(anyhow!(
             "Physiological subrecord too short: {} bytes",
             subrecord_data.len()
@@ -205,7 +423,8 @@ pub fn decode_physiological(
     }
 
     // Parse timestamp (first 4 bytes)
-    let timestamp_raw = read_u32(&subrecord_data[0..4]);
+    let timestamp_raw = read_u32(subrecord_data, 0)
+        .ok_or_else(|| anyhow!("Physiological subrecord has no timestamp"))?;
     let timestamp = DateTime::from_timestamp(timestamp_raw as i64, 0)
         .ok_or_else(|| anyhow!("Invalid timestamp: {}", timestamp_raw))?;
 
@@ -215,9 +434,16 @@ pub fn decode_physiological(
     // Decode based on class (data starts at offset 4, after timestamp)
     let class_data = &subrecord_data[4..];
 
+    if matches!(
+        subtype,
+        PhdbSubrecordType::Trend10s | PhdbSubrecordType::Trend60s
+    ) {
+        phys.discontinuities = discontinuities(subrecord_data, class);
+    }
+
     match class {
         PhdbClass::Basic => {
-            decode_basic_class(class_data, &mut phys)?;
+            decode_basic_class(class_data, &mut phys, policy, groups)?;
         }
         PhdbClass::Ext1 => {
             // TODO: Implement Ext1 class decoding in Phase 2
@@ -236,12 +462,187 @@ pub fn decode_physiological(
     Ok(phys)
 }
 
-/// Decode Basic class physiological data
-fn decode_basic_class(data: &[u8], phys: &mut PhysiologicalData) -> Result<()> {
+type VitalGetter = fn(&PhysiologicalData) -> Option<f64>;
+
+/// Numeric fields: serialized name, unit and accessor, in output order
+///
+/// Single source for [`PhysiologicalData::iter_vitals`]. Dimensionless
+/// values (MAC, counts) have an empty unit.
+const VITALS: &[(&str, &str, VitalGetter)] = &[
+    ("ecg_hr", "/min", |d| d.ecg_hr),
+    ("ecg_st1", "mm", |d| d.ecg_st1),
+    ("ecg_st2", "mm", |d| d.ecg_st2),
+    ("ecg_st3", "mm", |d| d.ecg_st3),
+    ("ecg_rr", "/min", |d| d.ecg_rr),
+    ("nibp_sys", "mmHg", |d| d.nibp_sys),
+    ("nibp_dia", "mmHg", |d| d.nibp_dia),
+    ("nibp_mean", "mmHg", |d| d.nibp_mean),
+    ("nibp_hr", "/min", |d| d.nibp_hr),
+    ("invp1_sys", "mmHg", |d| d.invp1_sys),
+    ("invp1_dia", "mmHg", |d| d.invp1_dia),
+    ("invp1_mean", "mmHg", |d| d.invp1_mean),
+    ("invp1_hr", "/min", |d| d.invp1_hr),
+    ("spo2", "%", |d| d.spo2),
+    ("spo2_pr", "/min", |d| d.spo2_pr),
+    ("spo2_ir_amp", "%", |d| d.spo2_ir_amp),
+    ("temp1", "C", |d| d.temp1),
+    ("temp2", "C", |d| d.temp2),
+    ("co2_et", "%", |d| d.co2_et),
+    ("co2_fi", "%", |d| d.co2_fi),
+    ("co2_rr", "/min", |d| d.co2_rr),
+    ("co2_amb_press", "mmHg", |d| d.co2_amb_press),
+    ("o2_et", "%", |d| d.o2_et),
+    ("o2_fi", "%", |d| d.o2_fi),
+    ("n2o_et", "%", |d| d.n2o_et),
+    ("n2o_fi", "%", |d| d.n2o_fi),
+    ("aa_et", "%", |d| d.aa_et),
+    ("aa_fi", "%", |d| d.aa_fi),
+    ("aa_mac", "", |d| d.aa_mac),
+    ("flow_rr", "/min", |d| d.flow_rr),
+    ("flow_ppeak", "cmH2O", |d| d.flow_ppeak),
+    ("flow_peep", "cmH2O", |d| d.flow_peep),
+    ("flow_pplat", "cmH2O", |d| d.flow_pplat),
+    ("flow_tv_insp", "ml", |d| d.flow_tv_insp),
+    ("flow_tv_exp", "ml", |d| d.flow_tv_exp),
+    ("flow_compliance", "ml/cmH2O", |d| d.flow_compliance),
+    ("flow_mv_exp", "l/min", |d| d.flow_mv_exp),
+    ("co", "l/min", |d| d.co),
+    ("co_blood_temp", "C", |d| d.co_blood_temp),
+    ("co_ref", "%", |d| d.co_ref),
+    ("co_pcwp", "mmHg", |d| d.co_pcwp),
+    ("svo2", "%", |d| d.svo2),
+    ("nmt_t1", "%", |d| d.nmt_t1),
+    ("nmt_tof_ratio", "%", |d| d.nmt_tof_ratio),
+    ("nmt_ptc", "", |d| d.nmt_ptc),
+];
+
+type VitalSlot = fn(&mut PhysiologicalData) -> &mut Option<f64>;
+
+/// Offsets of the numeric fields in Basic class data (after the timestamp),
+/// with the field each one decodes into
+const BASIC_RAW_OFFSETS: &[(&str, usize, VitalSlot)] = &[
+    ("ecg_hr", 6, |d| &mut d.ecg_hr),
+    ("ecg_st1", 8, |d| &mut d.ecg_st1),
+    ("ecg_st2", 10, |d| &mut d.ecg_st2),
+    ("ecg_st3", 12, |d| &mut d.ecg_st3),
+    ("ecg_rr", 14, |d| &mut d.ecg_rr),
+    ("invp1_sys", 16 + 6, |d| &mut d.invp1_sys),
+    ("invp1_dia", 16 + 8, |d| &mut d.invp1_dia),
+    ("invp1_mean", 16 + 10, |d| &mut d.invp1_mean),
+    ("invp1_hr", 16 + 12, |d| &mut d.invp1_hr),
+    ("nibp_sys", 76 + 6, |d| &mut d.nibp_sys),
+    ("nibp_dia", 76 + 8, |d| &mut d.nibp_dia),
+    ("nibp_mean", 76 + 10, |d| &mut d.nibp_mean),
+    ("nibp_hr", 76 + 12, |d| &mut d.nibp_hr),
+    ("temp1", 90 + 6, |d| &mut d.temp1),
+    ("temp2", 98 + 6, |d| &mut d.temp2),
+    ("spo2", 122 + 6, |d| &mut d.spo2),
+    ("spo2_pr", 122 + 8, |d| &mut d.spo2_pr),
+    ("spo2_ir_amp", 122 + 10, |d| &mut d.spo2_ir_amp),
+    ("co2_et", 136 + 6, |d| &mut d.co2_et),
+    ("co2_fi", 136 + 8, |d| &mut d.co2_fi),
+    ("co2_rr", 136 + 10, |d| &mut d.co2_rr),
+    ("co2_amb_press", 136 + 12, |d| &mut d.co2_amb_press),
+    ("o2_et", 150 + 6, |d| &mut d.o2_et),
+    ("o2_fi", 150 + 8, |d| &mut d.o2_fi),
+    ("n2o_et", 160 + 6, |d| &mut d.n2o_et),
+    ("n2o_fi", 160 + 8, |d| &mut d.n2o_fi),
+    ("aa_et", 170 + 6, |d| &mut d.aa_et),
+    ("aa_fi", 170 + 8, |d| &mut d.aa_fi),
+    ("aa_mac", 170 + 10, |d| &mut d.aa_mac),
+    ("flow_rr", 182 + 6, |d| &mut d.flow_rr),
+    ("flow_ppeak", 182 + 8, |d| &mut d.flow_ppeak),
+    ("flow_peep", 182 + 10, |d| &mut d.flow_peep),
+    ("flow_pplat", 182 + 12, |d| &mut d.flow_pplat),
+    ("flow_tv_insp", 182 + 14, |d| &mut d.flow_tv_insp),
+    ("flow_tv_exp", 182 + 16, |d| &mut d.flow_tv_exp),
+    ("flow_compliance", 182 + 18, |d| &mut d.flow_compliance),
+    ("flow_mv_exp", 182 + 20, |d| &mut d.flow_mv_exp),
+    ("co", 204 + 6, |d| &mut d.co),
+    ("co_blood_temp", 204 + 8, |d| &mut d.co_blood_temp),
+    ("co_ref", 204 + 10, |d| &mut d.co_ref),
+    ("co_pcwp", 204 + 12, |d| &mut d.co_pcwp),
+    ("nmt_t1", 218 + 6, |d| &mut d.nmt_t1),
+    ("nmt_tof_ratio", 218 + 8, |d| &mut d.nmt_tof_ratio),
+    ("nmt_ptc", 218 + 10, |d| &mut d.nmt_ptc),
+    ("svo2", 236 + 6, |d| &mut d.svo2),
+];
+
+/// Whether `field` is a Basic class field with a known offset
+pub(crate) fn is_basic_field(field: &str) -> bool {
+    BASIC_RAW_OFFSETS.iter().any(|&(name, _, _)| name == field)
+}
+
+/// Read the unscaled codes of a physiological subrecord, keyed by field name
+///
+/// Only Basic class fields are known; other classes give an empty map.
+pub fn raw_codes(subrecord_data: &[u8], class: PhdbClass) -> BTreeMap<String, i16> {
+    if class != PhdbClass::Basic {
+        return BTreeMap::new();
+    }
+
+    BASIC_RAW_OFFSETS
+        .iter()
+        .filter_map(|&(name, offset, _)| {
+            read_i16(subrecord_data, 4 + offset).map(|code| (name.to_string(), code))
+        })
+        .collect()
+}
+
+/// Names of the fields coded as `DATA_DISCONT` in a PHDB subrecord
+///
+/// Only Basic class fields are known; other classes give an empty list.
+pub fn discontinuities(subrecord_data: &[u8], class: PhdbClass) -> Vec<String> {
+    if class != PhdbClass::Basic {
+        return Vec::new();
+    }
+
+    BASIC_RAW_OFFSETS
+        .iter()
+        .filter(|&&(_, offset, _)| read_i16(subrecord_data, 4 + offset) == Some(DATA_DISCONT))
+        .map(|&(name, _, _)| name.to_string())
+        .collect()
+}
+
+/// Re-scale the fields `profile` overrides from their raw codes
+///
+/// Only fields that were decoded (`Some`) are touched, so groups left out
+/// by [`decode_physiological_groups`] stay empty; invalid codes are
+/// reported as `policy` says. Only Basic class fields are known.
+pub fn apply_scaling(
+    phys: &mut PhysiologicalData,
+    subrecord_data: &[u8],
+    profile: &ScalingProfile,
+    policy: InvalidPolicy,
+) {
+    if phys.class != PhdbClass::Basic || profile.is_standard() {
+        return;
+    }
+
+    for &(name, offset, slot) in BASIC_RAW_OFFSETS {
+        let Some(factor) = profile.factor(name) else {
+            continue;
+        };
+        let value = slot(phys);
+        if value.is_some() {
+            *value = scaled_at(&subrecord_data[4..], offset, factor, policy);
+        }
+    }
+}
+
+fn decode_basic_class(
+    data: &[u8],
+    phys: &mut PhysiologicalData,
+    policy: InvalidPolicy,
+    groups: Option<&[ParameterGroup]>,
+) -> Result<()> {
+    // Skipped groups keep their empty status and `None` values
+    let wanted = |group| groups.is_none_or(|groups| groups.contains(&group));
+
     // ECG (offset 0, 16 bytes)
-    if data.len() >= 16 {
+    if data.len() >= 16 && wanted(ParameterGroup::Ecg) {
         let (status, hr, st1, st2, st3, rr, hr_source, lead1, lead2, lead3) =
-            parse_ecg_group(&data[0..16])?;
+            parse_ecg_group(&data[0..16], policy)?;
         phys.ecg_status = status;
         phys.ecg_hr = hr;
         phys.ecg_st1 = st1;
@@ -255,8 +656,8 @@ fn decode_basic_class(data: &[u8], phys: &mut PhysiologicalData) -> Result<()> {
     }
 
     // INVP1 (offset 16, 14 bytes)
-    if data.len() >= 30 {
-        let (status, sys, dia, mean, hr, label) = parse_invp_group(&data[16..30])?;
+    if data.len() >= 30 && wanted(ParameterGroup::InvasivePressure) {
+        let (status, sys, dia, mean, hr, label) = parse_invp_group(&data[16..30], policy)?;
         phys.invp1_status = status;
         phys.invp1_sys = sys;
         phys.invp1_dia = dia;
@@ -266,8 +667,8 @@ fn decode_basic_class(data: &[u8], phys: &mut PhysiologicalData) -> Result<()> {
     }
 
     // NIBP (offset 76, 14 bytes)
-    if data.len() >= 90 {
-        let (status, sys, dia, mean, hr) = parse_nibp_group(&data[76..90])?;
+    if data.len() >= 90 && wanted(ParameterGroup::Nibp) {
+        let (status, sys, dia, mean, hr) = parse_nibp_group(&data[76..90], policy)?;
         phys.nibp_status = status;
         phys.nibp_sys = sys;
         phys.nibp_dia = dia;
@@ -276,24 +677,24 @@ fn decode_basic_class(data: &[u8], phys: &mut PhysiologicalData) -> Result<()> {
     }
 
     // TEMP1 (offset 90, 8 bytes)
-    if data.len() >= 98 {
-        let (status, temp, label) = parse_temp_group(&data[90..98])?;
+    if data.len() >= 98 && wanted(ParameterGroup::Temperature) {
+        let (status, temp, label) = parse_temp_group(&data[90..98], policy)?;
         phys.temp1_status = status;
         phys.temp1 = temp;
         phys.temp1_label = label;
     }
 
     // TEMP2 (offset 98, 8 bytes)
-    if data.len() >= 106 {
-        let (status, temp, label) = parse_temp_group(&data[98..106])?;
+    if data.len() >= 106 && wanted(ParameterGroup::Temperature) {
+        let (status, temp, label) = parse_temp_group(&data[98..106], policy)?;
         phys.temp2_status = status;
         phys.temp2 = temp;
         phys.temp2_label = label;
     }
 
     // SpO2 (offset 122, 14 bytes)
-    if data.len() >= 136 {
-        let (status, spo2, pr, ir_amp) = parse_spo2_group(&data[122..136])?;
+    if data.len() >= 136 && wanted(ParameterGroup::Spo2) {
+        let (status, spo2, pr, ir_amp) = parse_spo2_group(&data[122..136], policy)?;
         phys.spo2_status = status;
         phys.spo2 = spo2;
         phys.spo2_pr = pr;
@@ -301,33 +702,34 @@ fn decode_basic_class(data: &[u8], phys: &mut PhysiologicalData) -> Result<()> {
     }
 
     // CO2 (offset 136, 14 bytes)
-    if data.len() >= 150 {
-        let (status, et, fi, rr) = parse_co2_group(&data[136..150])?;
+    if data.len() >= 150 && wanted(ParameterGroup::Co2) {
+        let (status, et, fi, rr, amb_press) = parse_co2_group(&data[136..150], policy)?;
         phys.co2_status = status;
         phys.co2_et = et;
         phys.co2_fi = fi;
         phys.co2_rr = rr;
+        phys.co2_amb_press = amb_press;
     }
 
     // O2 (offset 150, 10 bytes)
-    if data.len() >= 160 {
-        let (status, et, fi) = parse_o2_group(&data[150..160])?;
+    if data.len() >= 160 && wanted(ParameterGroup::O2) {
+        let (status, et, fi) = parse_o2_group(&data[150..160], policy)?;
         phys.o2_status = status;
         phys.o2_et = et;
         phys.o2_fi = fi;
     }
 
     // N2O (offset 160, 10 bytes)
-    if data.len() >= 170 {
-        let (status, et, fi) = parse_n2o_group(&data[160..170])?;
+    if data.len() >= 170 && wanted(ParameterGroup::N2o) {
+        let (status, et, fi) = parse_n2o_group(&data[160..170], policy)?;
         phys.n2o_status = status;
         phys.n2o_et = et;
         phys.n2o_fi = fi;
     }
 
     // AA (offset 170, 12 bytes)
-    if data.len() >= 182 {
-        let (status, et, fi, mac, agent) = parse_aa_group(&data[170..182])?;
+    if data.len() >= 182 && wanted(ParameterGroup::AnesthesiaAgent) {
+        let (status, et, fi, mac, agent) = parse_aa_group(&data[170..182], policy)?;
         phys.aa_status = status;
         phys.aa_et = et;
         phys.aa_fi = fi;
@@ -336,9 +738,9 @@ fn decode_basic_class(data: &[u8], phys: &mut PhysiologicalData) -> Result<()> {
     }
 
     // Flow/Volume (offset 182, 22 bytes) - VENTILATOR DATA
-    if data.len() >= 204 {
+    if data.len() >= 204 && wanted(ParameterGroup::FlowVolume) {
         let (status, rr, ppeak, peep, pplat, tv_insp, tv_exp, compliance, mv_exp) =
-            parse_flow_vol_group(&data[182..204])?;
+            parse_flow_vol_group(&data[182..204], policy)?;
         phys.flow_status = status;
         phys.flow_rr = rr;
         phys.flow_ppeak = ppeak;
@@ -350,11 +752,63 @@ fn decode_basic_class(data: &[u8], phys: &mut PhysiologicalData) -> Result<()> {
         phys.flow_mv_exp = mv_exp;
     }
 
+    // Cardiac output (offset 204, 14 bytes)
+    if data.len() >= 218 && wanted(ParameterGroup::CardiacOutput) {
+        let (status, co, blood_temp, ejection_fraction, pcwp) =
+            parse_co_group(&data[204..218], policy)?;
+        phys.co_status = status;
+        // Not-connected groups may carry zeros rather than invalid codes
+        if status.exists {
+            phys.co = co;
+            phys.co_blood_temp = blood_temp;
+            phys.co_ref = ejection_fraction;
+            phys.co_pcwp = pcwp;
+            phys.co_not_updated = read_i16(data, 210) == Some(DATA_NOT_UPDATED);
+        }
+    }
+
+    // NMT (offset 218, 12 bytes)
+    if data.len() >= 230 && wanted(ParameterGroup::Nmt) {
+        let (status, t1, tof_ratio, ptc) = parse_nmt_group(&data[218..230], policy)?;
+        phys.nmt_status = status;
+        phys.nmt_t1 = t1;
+        phys.nmt_tof_ratio = tof_ratio;
+        phys.nmt_ptc = ptc;
+    }
+
+    // SvO2 (offset 236, 10 bytes)
+    if data.len() >= 246 && wanted(ParameterGroup::Svo2) {
+        let (status, svo2) = parse_svo2_group(&data[236..246], policy)?;
+        phys.svo2_status = status;
+        if status.exists {
+            phys.svo2 = svo2;
+        }
+    }
+
     Ok(())
 }
 
 // Group parsing functions
 
+/// Scaled value at `offset`, None if out of bounds
+fn scaled_at(data: &[u8], offset: usize, scale: f64, policy: InvalidPolicy) -> Option<f64> {
+    read_i16(data, offset).and_then(|raw| match scale_valid_i16(raw, scale) {
+        Some(value) => Some(value),
+        None => policy.apply(raw),
+    })
+}
+
+/// Unscaled value at `offset`, None if out of bounds
+fn unscaled_at(data: &[u8], offset: usize, policy: InvalidPolicy) -> Option<f64> {
+    read_i16(data, offset).and_then(|raw| {
+        if is_invalid(raw) {
+            policy.apply(raw)
+        } else {
+            Some(f64::from(raw))
+        }
+    })
+}
+
 struct EcgGroup {
     hr: Option<i16>,
     st1: Option<i16>,
@@ -370,6 +824,7 @@ struct EcgGroup {
 /// Parse ECG group (offset 0 in basic class, 16 bytes)
 fn parse_ecg_group(
     data: &[u8],
+    policy: InvalidPolicy,
 ) -> Result<(
     EcgStatus,
     Option<f64>,
@@ -386,29 +841,19 @@ fn parse_ecg_group(
         return Err(anyhow!("ECG group data too short"));
     }
 
-    let header = GroupHeader::parse(&data[0..6])?;
+    let header = GroupHeader::parse(data)?;
     let ecg_status = EcgStatus::from_status(header.status);
 
     // HR - no scaling needed (already in beats/min)
-    let hr_raw = read_i16(&data[6..8]);
-    let hr = if is_invalid(hr_raw) {
-        None
-    } else {
-        Some(hr_raw as f64)
-    };
+    let hr = unscaled_at(data, 6, policy);
 
     // ST levels - scale from 1/100 mm to mm
-    let st1 = scale_valid_i16(read_i16(&data[8..10]), SCALE_ST_100);
-    let st2 = scale_valid_i16(read_i16(&data[10..12]), SCALE_ST_100);
-    let st3 = scale_valid_i16(read_i16(&data[12..14]), SCALE_ST_100);
+    let st1 = scaled_at(data, 8, SCALE_ST_100, policy);
+    let st2 = scaled_at(data, 10, SCALE_ST_100, policy);
+    let st3 = scaled_at(data, 12, SCALE_ST_100, policy);
 
     // Impedance RR - no scaling needed
-    let rr_raw = read_i16(&data[14..16]);
-    let rr = if is_invalid(rr_raw) {
-        None
-    } else {
-        Some(rr_raw as f64)
-    };
+    let rr = unscaled_at(data, 14, policy);
 
     // Parse HR source from status bits 3-6
     let hr_source_bits = ((header.status >> 3) & 0x0F) as u8;
@@ -438,6 +883,7 @@ struct InvpGroup {
 /// Parse invasive pressure group (14 bytes)
 fn parse_invp_group(
     data: &[u8],
+    policy: InvalidPolicy,
 ) -> Result<(
     GenericStatus,
     Option<f64>,
@@ -450,22 +896,17 @@ fn parse_invp_group(
         return Err(anyhow!("Invasive pressure group data too short"));
     }
 
-    let header = GroupHeader::parse(&data[0..6])?;
+    let header = GroupHeader::parse(data)?;
     let status = GenericStatus::from_status(header.status);
     let label = InvasivePressureLabel::from_u16(header.label);
 
     // Scale from 1/100 mmHg to mmHg
-    let sys = scale_valid_i16(read_i16(&data[6..8]), SCALE_PRESSURE_100);
-    let dia = scale_valid_i16(read_i16(&data[8..10]), SCALE_PRESSURE_100);
-    let mean = scale_valid_i16(read_i16(&data[10..12]), SCALE_PRESSURE_100);
+    let sys = scaled_at(data, 6, SCALE_PRESSURE_100, policy);
+    let dia = scaled_at(data, 8, SCALE_PRESSURE_100, policy);
+    let mean = scaled_at(data, 10, SCALE_PRESSURE_100, policy);
 
     // HR - no scaling
-    let hr_raw = read_i16(&data[12..14]);
-    let hr = if is_invalid(hr_raw) {
-        None
-    } else {
-        Some(hr_raw as f64)
-    };
+    let hr = unscaled_at(data, 12, policy);
 
     Ok((status, sys, dia, mean, hr, label))
 }
@@ -480,6 +921,7 @@ struct NibpGroup {
 /// Parse NIBP group (offset 76 in basic class, 14 bytes)
 fn parse_nibp_group(
     data: &[u8],
+    policy: InvalidPolicy,
 ) -> Result<(
     NibpStatus,
     Option<f64>,
@@ -491,21 +933,16 @@ fn parse_nibp_group(
         return Err(anyhow!("NIBP group data too short"));
     }
 
-    let header = GroupHeader::parse(&data[0..6])?;
-    let nibp_status = NibpStatus::from_label(header.label);
+    let header = GroupHeader::parse(data)?;
+    let nibp_status = NibpStatus::from_status(header.status);
 
     // Scale from 1/100 mmHg to mmHg
-    let sys = scale_valid_i16(read_i16(&data[6..8]), SCALE_PRESSURE_100);
-    let dia = scale_valid_i16(read_i16(&data[8..10]), SCALE_PRESSURE_100);
-    let mean = scale_valid_i16(read_i16(&data[10..12]), SCALE_PRESSURE_100);
+    let sys = scaled_at(data, 6, SCALE_PRESSURE_100, policy);
+    let dia = scaled_at(data, 8, SCALE_PRESSURE_100, policy);
+    let mean = scaled_at(data, 10, SCALE_PRESSURE_100, policy);
 
     // HR - no scaling
-    let hr_raw = read_i16(&data[12..14]);
-    let hr = if is_invalid(hr_raw) {
-        None
-    } else {
-        Some(hr_raw as f64)
-    };
+    let hr = unscaled_at(data, 12, policy);
 
     Ok((nibp_status, sys, dia, mean, hr))
 }
@@ -516,17 +953,20 @@ struct TempGroup {
 }
 
 /// Parse temperature group (8 bytes)
-fn parse_temp_group(data: &[u8]) -> Result<(GenericStatus, Option<f64>, Option<TemperatureLabel>)> {
+fn parse_temp_group(
+    data: &[u8],
+    policy: InvalidPolicy,
+) -> Result<(GenericStatus, Option<f64>, Option<TemperatureLabel>)> {
     if data.len() < 8 {
         return Err(anyhow!("Temperature group data too short"));
     }
 
-    let header = GroupHeader::parse(&data[0..6])?;
+    let header = GroupHeader::parse(data)?;
     let status = GenericStatus::from_status(header.status);
     let label = TemperatureLabel::from_u16(header.label);
 
     // Scale from 1/100 °C to °C
-    let temp = scale_valid_i16(read_i16(&data[6..8]), SCALE_TEMP_100);
+    let temp = scaled_at(data, 6, SCALE_TEMP_100, policy);
 
     Ok((status, temp, label))
 }
@@ -538,27 +978,25 @@ struct Spo2Group {
 }
 
 /// Parse SpO2 group (offset 122 in basic class, 14 bytes)
-fn parse_spo2_group(data: &[u8]) -> Result<(Spo2Status, Option<f64>, Option<f64>, Option<f64>)> {
+fn parse_spo2_group(
+    data: &[u8],
+    policy: InvalidPolicy,
+) -> Result<(Spo2Status, Option<f64>, Option<f64>, Option<f64>)> {
     if data.len() < 14 {
         return Err(anyhow!("SpO2 group data too short"));
     }
 
-    let header = GroupHeader::parse(&data[0..6])?;
+    let header = GroupHeader::parse(data)?;
     let spo2_status = Spo2Status::from_status(header.status);
 
     // Scale from 1/100 % to %
-    let spo2 = scale_valid_i16(read_i16(&data[6..8]), SCALE_PERCENT_100);
+    let spo2 = scaled_at(data, 6, SCALE_PERCENT_100, policy);
 
     // Pulse rate - no scaling
-    let pr_raw = read_i16(&data[8..10]);
-    let pr = if is_invalid(pr_raw) {
-        None
-    } else {
-        Some(pr_raw as f64)
-    };
+    let pr = unscaled_at(data, 8, policy);
 
     // IR amplitude - scale from 1/10 % to %
-    let ir_amp = scale_valid_i16(read_i16(&data[10..12]), SCALE_IR_AMP_10);
+    let ir_amp = scaled_at(data, 10, SCALE_IR_AMP_10, policy);
 
     Ok((spo2_status, spo2, pr, ir_amp))
 }
@@ -567,30 +1005,38 @@ struct Co2Group {
     et: Option<i16>,
     fi: Option<i16>,
     rr: Option<i16>,
+    amb_press: Option<i16>,
 }
 
 /// Parse CO2 group (offset 136 in basic class, 14 bytes)
-fn parse_co2_group(data: &[u8]) -> Result<(Co2Status, Option<f64>, Option<f64>, Option<f64>)> {
+fn parse_co2_group(
+    data: &[u8],
+    policy: InvalidPolicy,
+) -> Result<(
+    Co2Status,
+    Option<f64>,
+    Option<f64>,
+    Option<f64>,
+    Option<f64>,
+)> {
     if data.len() < 14 {
         return Err(anyhow!("CO2 group data too short"));
     }
 
-    let header = GroupHeader::parse(&data[0..6])?;
+    let header = GroupHeader::parse(data)?;
     let co2_status = Co2Status::from_status(header.status);
 
     // Scale from 1/100 % to %
-    let et = scale_valid_i16(read_i16(&data[6..8]), SCALE_PERCENT_100);
-    let fi = scale_valid_i16(read_i16(&data[8..10]), SCALE_PERCENT_100);
+    let et = scaled_at(data, 6, SCALE_PERCENT_100, policy);
+    let fi = scaled_at(data, 8, SCALE_PERCENT_100, policy);
 
     // RR - no scaling
-    let rr_raw = read_i16(&data[10..12]);
-    let rr = if is_invalid(rr_raw) {
-        None
-    } else {
-        Some(rr_raw as f64)
-    };
+    let rr = unscaled_at(data, 10, policy);
+
+    // Ambient pressure - scale from 1/10 mmHg
+    let amb_press = scaled_at(data, 12, SCALE_AMB_PRESS_10, policy);
 
-    Ok((co2_status, et, fi, rr))
+    Ok((co2_status, et, fi, rr, amb_press))
 }
 
 struct O2Group {
@@ -599,17 +1045,20 @@ struct O2Group {
 }
 
 /// Parse O2 group (offset 150 in basic class, 10 bytes)
-fn parse_o2_group(data: &[u8]) -> Result<(GasStatus, Option<f64>, Option<f64>)> {
+fn parse_o2_group(
+    data: &[u8],
+    policy: InvalidPolicy,
+) -> Result<(GasStatus, Option<f64>, Option<f64>)> {
     if data.len() < 10 {
         return Err(anyhow!("O2 group data too short"));
     }
 
-    let header = GroupHeader::parse(&data[0..6])?;
+    let header = GroupHeader::parse(data)?;
     let o2_status = GasStatus::from_status(header.status);
 
     // Scale from 1/100 % to %
-    let et = scale_valid_i16(read_i16(&data[6..8]), SCALE_PERCENT_100);
-    let fi = scale_valid_i16(read_i16(&data[8..10]), SCALE_PERCENT_100);
+    let et = scaled_at(data, 6, SCALE_PERCENT_100, policy);
+    let fi = scaled_at(data, 8, SCALE_PERCENT_100, policy);
 
     Ok((o2_status, et, fi))
 }
@@ -620,17 +1069,20 @@ struct N2oGroup {
 }
 
 /// Parse N2O group (offset 160 in basic class, 10 bytes)
-fn parse_n2o_group(data: &[u8]) -> Result<(GasStatus, Option<f64>, Option<f64>)> {
+fn parse_n2o_group(
+    data: &[u8],
+    policy: InvalidPolicy,
+) -> Result<(GasStatus, Option<f64>, Option<f64>)> {
     if data.len() < 10 {
         return Err(anyhow!("N2O group data too short"));
     }
 
-    let header = GroupHeader::parse(&data[0..6])?;
+    let header = GroupHeader::parse(data)?;
     let n2o_status = GasStatus::from_status(header.status);
 
     // Scale from 1/100 % to %
-    let et = scale_valid_i16(read_i16(&data[6..8]), SCALE_PERCENT_100);
-    let fi = scale_valid_i16(read_i16(&data[8..10]), SCALE_PERCENT_100);
+    let et = scaled_at(data, 6, SCALE_PERCENT_100, policy);
+    let fi = scaled_at(data, 8, SCALE_PERCENT_100, policy);
 
     Ok((n2o_status, et, fi))
 }
@@ -645,6 +1097,7 @@ struct AaGroup {
 /// Parse anesthesia agent group (offset 170 in basic class, 12 bytes)
 fn parse_aa_group(
     data: &[u8],
+    policy: InvalidPolicy,
 ) -> Result<(
     GasStatus,
     Option<f64>,
@@ -656,14 +1109,14 @@ fn parse_aa_group(
         return Err(anyhow!("AA group data too short"));
     }
 
-    let header = GroupHeader::parse(&data[0..6])?;
+    let header = GroupHeader::parse(data)?;
     let aa_status = GasStatus::from_status(header.status);
     let agent = AnesthesiaAgent::from_u16(header.label);
 
     // Scale from 1/100 % to %
-    let et = scale_valid_i16(read_i16(&data[6..8]), SCALE_PERCENT_100);
-    let fi = scale_valid_i16(read_i16(&data[8..10]), SCALE_PERCENT_100);
-    let mac = scale_valid_i16(read_i16(&data[10..12]), SCALE_MAC_100);
+    let et = scaled_at(data, 6, SCALE_PERCENT_100, policy);
+    let fi = scaled_at(data, 8, SCALE_PERCENT_100, policy);
+    let mac = scaled_at(data, 10, SCALE_MAC_100, policy);
 
     Ok((aa_status, et, fi, mac, agent))
 }
@@ -680,6 +1133,7 @@ struct FlowVolGroup {
 /// Parse flow & volume group (offset 182 in basic class, 22 bytes)
 fn parse_flow_vol_group(
     data: &[u8],
+    policy: InvalidPolicy,
 ) -> Result<(
     FlowVolStatus,
     Option<f64>,
@@ -695,31 +1149,26 @@ fn parse_flow_vol_group(
         return Err(anyhow!("Flow/volume group data too short"));
     }
 
-    let header = GroupHeader::parse(&data[0..6])?;
+    let header = GroupHeader::parse(data)?;
     let flow_status = FlowVolStatus::from_status(header.status);
 
     // RR - no scaling
-    let rr_raw = read_i16(&data[6..8]);
-    let rr = if is_invalid(rr_raw) {
-        None
-    } else {
-        Some(rr_raw as f64)
-    };
+    let rr = unscaled_at(data, 6, policy);
 
     // Scale pressures from 1/100 cmH2O to cmH2O
-    let ppeak = scale_valid_i16(read_i16(&data[8..10]), SCALE_AWP_100);
-    let peep = scale_valid_i16(read_i16(&data[10..12]), SCALE_AWP_100);
-    let pplat = scale_valid_i16(read_i16(&data[12..14]), SCALE_AWP_100);
+    let ppeak = scaled_at(data, 8, SCALE_AWP_100, policy);
+    let peep = scaled_at(data, 10, SCALE_AWP_100, policy);
+    let pplat = scaled_at(data, 12, SCALE_AWP_100, policy);
 
     // Scale volumes from 1/10 ml to ml
-    let tv_insp = scale_valid_i16(read_i16(&data[14..16]), SCALE_VOLUME_10);
-    let tv_exp = scale_valid_i16(read_i16(&data[16..18]), SCALE_VOLUME_10);
+    let tv_insp = scaled_at(data, 14, SCALE_VOLUME_10, policy);
+    let tv_exp = scaled_at(data, 16, SCALE_VOLUME_10, policy);
 
     // Scale compliance from 1/100 ml/cmH2O to ml/cmH2O
-    let compliance = scale_valid_i16(read_i16(&data[18..20]), SCALE_COMPLIANCE_100);
+    let compliance = scaled_at(data, 18, SCALE_COMPLIANCE_100, policy);
 
     // Scale MV from 1/100 l/min to l/min
-    let mv_exp = scale_valid_i16(read_i16(&data[20..22]), SCALE_PERCENT_100);
+    let mv_exp = scaled_at(data, 20, SCALE_MV_100, policy);
 
     Ok((
         flow_status,
@@ -733,3 +1182,207 @@ fn parse_flow_vol_group(
         mv_exp,
     ))
 }
+
+/// Parse cardiac output group (offset 204 in basic class, 14 bytes)
+///
+/// After the header: CO, blood temperature, REF (right ventricle ejection
+/// fraction) and PCWP (pulmonary capillary wedge pressure).
+/// CO is measured intermittently; between measurements the values are
+/// `DATA_NOT_UPDATED` and decode to `None`.
+#[allow(clippy::type_complexity)]
+fn parse_co_group(
+    data: &[u8],
+    policy: InvalidPolicy,
+) -> Result<(
+    GenericStatus,
+    Option<f64>,
+    Option<f64>,
+    Option<f64>,
+    Option<f64>,
+)> {
+    if data.len() < 14 {
+        return Err(anyhow!("Cardiac output group data too short"));
+    }
+
+    let header = GroupHeader::parse(data)?;
+    let co_status = GenericStatus::from_status(header.status);
+
+    // Scale CO from 1/100 l/min, blood temperature from 1/100 °C
+    let co = scaled_at(data, 6, SCALE_CO_100, policy);
+    let blood_temp = scaled_at(data, 8, SCALE_TEMP_100, policy);
+
+    // REF - no scaling
+    let ejection_fraction = unscaled_at(data, 10, policy);
+
+    // Scale PCWP from 1/100 mmHg
+    let pcwp = scaled_at(data, 12, SCALE_PRESSURE_100, policy);
+
+    Ok((co_status, co, blood_temp, ejection_fraction, pcwp))
+}
+
+/// Parse NMT group (offset 218 in basic class, 12 bytes)
+#[allow(clippy::type_complexity)]
+fn parse_nmt_group(
+    data: &[u8],
+    policy: InvalidPolicy,
+) -> Result<(NmtStatus, Option<f64>, Option<f64>, Option<f64>)> {
+    if data.len() < 12 {
+        return Err(anyhow!("NMT group data too short"));
+    }
+
+    let header = GroupHeader::parse(data)?;
+    let nmt_status = NmtStatus::from_status(header.status);
+
+    // Scale T1 and TOF ratio from 1/10 % to %
+    let t1 = scaled_at(data, 6, SCALE_PERCENT_10, policy);
+    let tof_ratio = scaled_at(data, 8, SCALE_PERCENT_10, policy);
+
+    // PTC - no scaling
+    let ptc = unscaled_at(data, 10, policy);
+
+    Ok((nmt_status, t1, tof_ratio, ptc))
+}
+
+/// Parse SvO2 group (offset 236 in basic class, 10 bytes)
+fn parse_svo2_group(data: &[u8], policy: InvalidPolicy) -> Result<(Svo2Status, Option<f64>)> {
+    if data.len() < 10 {
+        return Err(anyhow!("SvO2 group data too short"));
+    }
+
+    let header = GroupHeader::parse(data)?;
+    let svo2_status = Svo2Status::from_status(header.status);
+
+    // Scale from 1/100 % to %
+    let svo2 = scaled_at(data, 6, SCALE_PERCENT_100, policy);
+
+    Ok((svo2_status, svo2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::special_values::{DATA_INVALID, DATA_NOT_UPDATED};
+
+    /// Decode a displayed values subrecord, zeroed except for what `fill`
+    /// writes into its Basic class data (after the timestamp)
+    fn decode_basic(fill: impl FnOnce(&mut [u8])) -> PhysiologicalData {
+        let mut subrecord = vec![0u8; PHDB_SUBRECORD_SIZE];
+        fill(&mut subrecord[4..]);
+        decode_physiological(&subrecord, PhdbSubrecordType::Displ, PhdbClass::Basic).unwrap()
+    }
+
+    #[test]
+    fn test_partial_pressure_needs_both_inputs() {
+        assert_eq!(partial_pressure_mmhg(Some(5.0), Some(760.0)), Some(38.0));
+        assert_eq!(partial_pressure_mmhg(None, Some(760.0)), None);
+        assert_eq!(partial_pressure_mmhg(Some(5.0), None), None);
+        assert_eq!(partial_pressure_mmhg(Some(5.0), Some(0.0)), None);
+    }
+
+    #[test]
+    fn test_sanity_flags_report_implausible_values() {
+        let mut phys =
+            PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+        phys.ecg_hr = Some(72.0);
+        phys.spo2 = Some(250.0);
+        phys.temp1 = Some(f64::NAN);
+        phys.nibp_sys = Some(f64::from(DATA_INVALID));
+
+        let flags = phys.sanity_flags();
+        assert_eq!(flags.len(), 1);
+        assert_eq!(flags[0].field, "spo2");
+        assert_eq!((flags[0].min, flags[0].max), (0.0, 100.0));
+        assert!(!phys.is_physiologically_plausible());
+    }
+
+    #[test]
+    fn test_resp_rr_is_the_ecg_impedance_rate() {
+        let phys = decode_basic(|class| {
+            // Impedance RR at ECG group offset 14
+            class[14..16].copy_from_slice(&16i16.to_le_bytes());
+        });
+
+        assert_eq!(phys.resp_rr(), Some(16.0));
+        assert_eq!(phys.resp_rr(), phys.ecg_rr);
+    }
+
+    #[test]
+    fn test_resp_apnea_is_the_co2_impedance_flag() {
+        let phys = decode_basic(|class| {
+            // CO2 group at class offset 136: exists, apnea from resp (bit 7)
+            class[136..140].copy_from_slice(&0x0081u32.to_le_bytes());
+        });
+
+        assert!(phys.resp_apnea());
+        assert!(!phys.co2_status.apnea_co2);
+        assert!(!decode_basic(|_| {}).resp_apnea());
+    }
+
+    #[test]
+    fn test_nibp_mode_bits_come_from_status_word() {
+        let phys = decode_basic(|class| {
+            // NIBP group at class offset 76: exists, active, measuring (bit 5)
+            class[76..80].copy_from_slice(&0x0023u32.to_le_bytes());
+            // A label with the same bit set must not count
+            class[80..82].copy_from_slice(&(1u16 << 3).to_le_bytes());
+        });
+
+        assert!(phys.nibp_status.exists);
+        assert!(phys.nibp_status.measuring);
+        assert!(!phys.nibp_status.auto_mode);
+    }
+
+    #[test]
+    fn test_co_between_measurements() {
+        let phys = decode_basic(|class| {
+            // CO group at class offset 204
+            let co = &mut class[204..218];
+            co[0..4].copy_from_slice(&0x0003u32.to_le_bytes());
+            for value in co[6..14].chunks_exact_mut(2) {
+                value.copy_from_slice(&DATA_NOT_UPDATED.to_le_bytes());
+            }
+            co[8..10].copy_from_slice(&3700i16.to_le_bytes());
+        });
+
+        assert!(phys.co_status.active);
+        assert!(phys.co_not_updated);
+        assert_eq!(phys.co, None);
+        assert_eq!(phys.co_pcwp, None);
+        assert_eq!(phys.co_blood_temp, Some(37.0));
+    }
+
+    #[test]
+    fn test_absent_svo2_has_no_value() {
+        // SvO2 group at class offset 236: status 0 (not connected), label 0,
+        // SvO2 7250 (1/100 %), then a word the decoder ignores
+        let group = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x52, 0x1C, 0x00, 0x00];
+        let svo2 = |status: u8| {
+            decode_basic(|class| {
+                class[236..246].copy_from_slice(&group);
+                class[236] = status;
+            })
+            .svo2
+        };
+
+        assert_eq!(svo2(0x00), None);
+        // Status word: exists + active
+        assert_eq!(svo2(0x03), Some(72.5));
+    }
+
+    #[test]
+    fn test_nmt_group_is_decoded() {
+        let phys = decode_basic(|class| {
+            // NMT group at class offset 218
+            let nmt = &mut class[218..230];
+            nmt[0..4].copy_from_slice(&0x0003u32.to_le_bytes());
+            nmt[6..8].copy_from_slice(&955i16.to_le_bytes());
+            nmt[8..10].copy_from_slice(&870i16.to_le_bytes());
+            nmt[10..12].copy_from_slice(&DATA_INVALID.to_le_bytes());
+        });
+
+        assert!(phys.nmt_status.exists && phys.nmt_status.active);
+        assert!((phys.nmt_t1.unwrap() - 95.5).abs() < 1e-9);
+        assert!((phys.nmt_tof_ratio.unwrap() - 87.0).abs() < 1e-9);
+        assert_eq!(phys.nmt_ptc, None);
+    }
+}
@@ -0,0 +1,134 @@
+//! Sans-IO decode pipeline
+//!
+//! [`DecodePipeline`] composes frame parsing, header parsing and decoding
+//! for callers that bring their own transport (TCP, files, test vectors).
+
+use super::{DecodeMetrics, Decoder, DriRecord};
+use crate::Result;
+use crate::protocol::FrameParser;
+use std::sync::Arc;
+
+/// Turns raw bytes from any source into decoded records
+///
+/// Bytes may be pushed in chunks of any size; partial frames are kept until
+/// the rest arrives. Records are not stamped with a receive time, call
+/// [`DriRecord::mark_received`] if needed.
+#[derive(Default)]
+pub struct DecodePipeline {
+    parser: FrameParser,
+    decoder: Decoder,
+    metrics: Arc<DecodeMetrics>,
+}
+
+impl DecodePipeline {
+    /// Create a pipeline with a default decoder
+    pub fn new() -> Self {
+        Self::with_decoder(Decoder::new())
+    }
+
+    /// Create a pipeline around a configured decoder
+    pub fn with_decoder(decoder: Decoder) -> Self {
+        Self {
+            parser: FrameParser::new(),
+            decoder,
+            metrics: Arc::default(),
+        }
+    }
+
+    /// Counters of everything pushed so far, shareable with other threads
+    pub fn metrics(&self) -> Arc<DecodeMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Feed bytes and collect the records they complete
+    ///
+    /// Framing and decode errors are returned in place of the affected
+    /// frame and processing continues with the next one. Frames without
+    /// decodable data produce no item.
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> Vec<Result<DriRecord>> {
+        let mut records = Vec::new();
+
+        for &byte in bytes {
+            match self.parser.process_byte(byte) {
+                Ok(Some(frame)) => {
+                    self.metrics.record_frame();
+                    match self.decoder.decode_bytes_all(&frame.data) {
+                        Ok(decoded) => {
+                            self.metrics.record_records(&decoded);
+                            records.extend(decoded.into_iter().map(Ok));
+                        }
+                        Err(e) => {
+                            self.metrics.record_decode_error();
+                            records.push(Err(e));
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    self.metrics.record_framing_error(&e);
+                    records.push(Err(e.into()));
+                }
+            }
+        }
+
+        records
+    }
+
+    /// Drop any partially received frame
+    pub fn reset(&mut self) {
+        self.parser.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::HEADER_SIZE;
+    use crate::constants::dri_types::PhdbSubrecordType;
+    use crate::protocol::framing::create_frame;
+
+    fn phdb_frame() -> Vec<u8> {
+        let mut data = vec![0u8; HEADER_SIZE];
+        data[3] = 8;
+        data[20] = PhdbSubrecordType::Displ as u8;
+        data[23] = 0xFF;
+        data.extend(vec![0u8; 1088]);
+        create_frame(&data)
+    }
+
+    #[test]
+    fn test_records_across_chunks() {
+        let mut bytes = phdb_frame();
+        bytes.extend(create_frame(&[0x01, 0x02, 0x03]));
+        bytes.extend(phdb_frame());
+
+        let mut pipeline = DecodePipeline::new();
+        let (first, rest) = bytes.split_at(100);
+        assert!(pipeline.push_bytes(first).is_empty());
+
+        let results = pipeline.push_bytes(rest);
+        assert_eq!(results.len(), 3);
+        assert!(matches!(results[0], Ok(DriRecord::Physiological(_))));
+        // Too short for a header: reported, then decoding continues
+        assert!(results[1].is_err());
+        assert!(matches!(results[2], Ok(DriRecord::Physiological(_))));
+
+        let metrics = pipeline.metrics().snapshot();
+        assert_eq!(metrics.frames, 3);
+        assert_eq!(metrics.decode_errors, 1);
+        assert_eq!(metrics.physiological_records, 2);
+    }
+
+    #[test]
+    fn test_checksum_errors_are_counted() {
+        let mut bytes = phdb_frame();
+        let checksum_at = bytes.len() - 2;
+        bytes[checksum_at] ^= 0x01;
+
+        let mut pipeline = DecodePipeline::new();
+        assert!(pipeline.push_bytes(&bytes)[0].is_err());
+        let metrics = pipeline.metrics().snapshot();
+        assert_eq!(metrics.checksum_errors, 1);
+        assert_eq!(metrics.frames, 0);
+    }
+}
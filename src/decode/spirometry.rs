@@ -0,0 +1,252 @@
+//! Spirometry: airway volume and pressure-volume / flow-volume loops
+//!
+//! [`breaths`] cuts buffered `AWP`, `FLOW` and `VOL` waveforms into breaths
+//! for plotting loops. For monitors that send `FLOW` but not `VOL`,
+//! [`FlowIntegrator`] rebuilds the volume series.
+
+use super::waveforms::{WaveformBuffer, WaveformData};
+use crate::constants::WaveformType;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// Waveforms needed by [`breaths`], in the order they are read
+const LOOP_WAVEFORMS: [WaveformType; 4] = [
+    WaveformType::Awp,
+    WaveformType::Flow,
+    WaveformType::Vol,
+    WaveformType::SpiLoopStatus,
+];
+
+/// One breath of airway signals, sample-aligned for loop plots
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breath {
+    /// Time of the first sample
+    pub start: DateTime<Utc>,
+    /// Samples per second of the three series
+    pub sample_rate: u16,
+    /// Airway pressure in cmH2O
+    pub pressure: Vec<f64>,
+    /// Airway flow in l/min
+    pub flow: Vec<f64>,
+    /// Airway volume in ml
+    pub volume: Vec<f64>,
+}
+
+impl Breath {
+    /// `(volume, pressure)` points of the pressure-volume loop
+    pub fn pressure_volume(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.volume
+            .iter()
+            .copied()
+            .zip(self.pressure.iter().copied())
+    }
+
+    /// `(volume, flow)` points of the flow-volume loop
+    pub fn flow_volume(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.volume.iter().copied().zip(self.flow.iter().copied())
+    }
+}
+
+/// Complete breaths in the most recent `duration` of `buffer`
+///
+/// Channels are aligned with [`WaveformBuffer::aligned`] and scaled to
+/// physical units. A breath starts where the loop status waveform turns
+/// from zero to non-zero, and ends where the next one starts; the partial
+/// breaths at either end of the window are dropped, as are breaths with
+/// missing samples. Fails, naming the waveforms, if any of `AWP`, `FLOW`,
+/// `VOL` or `SPI_LOOP_STATUS` was never buffered.
+pub fn breaths(buffer: &WaveformBuffer, duration: Duration) -> Result<Vec<Breath>> {
+    let missing: Vec<&str> = LOOP_WAVEFORMS
+        .iter()
+        .filter(|wf| buffer.sample_rate(**wf).is_none())
+        .map(|wf| wf.name())
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "Missing waveforms for spirometry loops: {}",
+            missing.join(", ")
+        ));
+    }
+
+    let window = buffer
+        .aligned(&LOOP_WAVEFORMS, duration)
+        .ok_or_else(|| anyhow!("No spirometry waveforms buffered"))?;
+    let [pressure, flow, volume, status] = [0, 1, 2, 3].map(|i| {
+        let (wf, samples) = &window.channels[i];
+        let factor = wf.scale_factor();
+        samples.iter().map(|s| s / factor).collect::<Vec<f64>>()
+    });
+
+    let starts: Vec<usize> = (1..status.len())
+        .filter(|&i| status[i - 1] == 0.0 && status[i] != 0.0)
+        .collect();
+    let step_us = 1_000_000.0 / f64::from(window.sample_rate);
+
+    Ok(starts
+        .windows(2)
+        .map(|pair| pair[0]..pair[1])
+        .filter(|range| {
+            [&pressure, &flow, &volume]
+                .iter()
+                .all(|series| series[range.clone()].iter().all(|s| !s.is_nan()))
+        })
+        .map(|range| Breath {
+            start: window.start
+                + chrono::Duration::microseconds((range.start as f64 * step_us).round() as i64),
+            sample_rate: window.sample_rate,
+            pressure: pressure[range.clone()].to_vec(),
+            flow: flow[range.clone()].to_vec(),
+            volume: volume[range].to_vec(),
+        })
+        .collect())
+}
+
+/// Integrates airway flow into volume, one breath at a time
+///
+/// Flow (l/min, inspiration positive) is integrated with the trapezoidal
+/// rule into ml. Offsets in the flow signal make a running integral drift
+/// without bound, so the volume is reset to zero at the start of every
+/// breath, detected as flow turning from non-positive to positive; drift
+/// then only accumulates within one breath. A gap in the waveform also
+/// resets the volume, since the breath phase is unknown afterwards.
+///
+/// State carries across frames, so feed consecutive `Flow` frames of one
+/// monitor to the same integrator.
+pub struct FlowIntegrator {
+    sample_rate: f64,
+    volume: f64,
+    last_flow: Option<f64>,
+}
+
+impl FlowIntegrator {
+    /// Create an integrator for flow sampled at `sample_rate` samples/s
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            sample_rate,
+            volume: 0.0,
+            last_flow: None,
+        }
+    }
+
+    /// Volume in ml at each sample of `flow`
+    ///
+    /// Fails if `flow` is not a `Flow` waveform.
+    pub fn integrate(&mut self, flow: &WaveformData) -> Result<Vec<f64>> {
+        if flow.waveform_type != WaveformType::Flow {
+            return Err(anyhow!(
+                "Cannot integrate {:?} samples as flow",
+                flow.waveform_type
+            ));
+        }
+        if flow.status.gap {
+            self.reset();
+        }
+
+        // l/min to ml per sample
+        let dt_min = 1.0 / (self.sample_rate * 60.0);
+        let volumes = flow
+            .scaled_samples()
+            .into_iter()
+            .map(|sample| {
+                match self.last_flow {
+                    Some(previous) if previous <= 0.0 && sample > 0.0 => self.volume = 0.0,
+                    Some(previous) => self.volume += (previous + sample) / 2.0 * dt_min * 1000.0,
+                    None => {}
+                }
+                self.last_flow = Some(sample);
+                self.volume
+            })
+            .collect();
+        Ok(volumes)
+    }
+
+    /// Forget the current breath; the next sample starts from zero volume
+    pub fn reset(&mut self) {
+        self.volume = 0.0;
+        self.last_flow = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::waveforms::WaveformStatus;
+    use chrono::TimeZone;
+    use std::f64::consts::PI;
+
+    fn frame(waveform_type: WaveformType, secs: i64, samples: Vec<i16>) -> WaveformData {
+        WaveformData {
+            timestamp: Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap(),
+            waveform_type,
+            plug_id: 0,
+            nominal_rate: samples.len() as u16,
+            samples,
+            effective_rate: None,
+            status: WaveformStatus::default(),
+            pacer_indices: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_breaths_are_cut_at_loop_status_edges() {
+        let mut buffer = WaveformBuffer::new(Duration::from_secs(10));
+        let err = breaths(&buffer, Duration::from_secs(4)).unwrap_err();
+        assert!(err.to_string().contains("AWP, FLOW, VOL, SPI_LOOP_STATUS"));
+
+        // 4 s at 25 Hz, a breath every 1.2 s from sample 5: 3 complete ones
+        for secs in 0..4 {
+            let n = |i: usize| secs as usize * 25 + i;
+            let series = |f: &dyn Fn(usize) -> i16| (0..25).map(|i| f(n(i))).collect();
+            buffer.push(&frame(
+                WaveformType::Awp,
+                secs,
+                series(&|k| (k % 30) as i16 * 5),
+            ));
+            buffer.push(&frame(WaveformType::Flow, secs, series(&|_| 300)));
+            buffer.push(&frame(
+                WaveformType::Vol,
+                secs,
+                series(&|k| (k % 30) as i16),
+            ));
+            let status = |k: usize| i16::from(k >= 5 && (k - 5) % 30 < 15);
+            buffer.push(&frame(WaveformType::SpiLoopStatus, secs, series(&status)));
+        }
+
+        let loops = breaths(&buffer, Duration::from_secs(4)).unwrap();
+        assert_eq!(loops.len(), 3);
+        assert_eq!(loops[0].volume.len(), 30);
+        assert_eq!(loops[0].flow[0], 30.0);
+        assert_eq!(loops[0].pressure_volume().next(), Some((5.0, 2.5)));
+    }
+
+    #[test]
+    fn test_sinusoidal_flow_integrates_to_breath_volume() {
+        // 30 l/min peak flow, 4 s breaths, sampled at 25 Hz
+        let (rate, period, peak) = (25.0, 4.0, 30.0);
+        let samples: Vec<i16> = (0..200)
+            .map(|i| {
+                let t = i as f64 / rate;
+                (peak * (2.0 * PI * t / period).sin() * 10.0).round() as i16
+            })
+            .collect();
+
+        let mut integrator = FlowIntegrator::new(rate);
+        let volumes: Vec<f64> = samples
+            .chunks(25)
+            .flat_map(|chunk| {
+                integrator
+                    .integrate(&frame(WaveformType::Flow, 0, chunk.to_vec()))
+                    .unwrap()
+            })
+            .collect();
+
+        // Tidal volume: peak flow in ml/s times period / pi
+        let expected = peak * 1000.0 / 60.0 * period / PI;
+        for breath in volumes.chunks(100) {
+            let tidal = breath.iter().cloned().fold(f64::MIN, f64::max);
+            assert!((tidal - expected).abs() < expected * 0.01, "{tidal}");
+            assert!(breath[99].abs() < expected * 0.02, "{}", breath[99]);
+        }
+    }
+}
@@ -48,16 +48,17 @@ pub struct NibpStatus {
 }
 
 impl NibpStatus {
-    pub fn from_label(label: u16) -> Self {
+    /// Mode bits live in the group's status word, not its label
+    pub fn from_status(status: u32) -> Self {
         Self {
-            exists: true, // Determined by group header
-            active: true, // Determined by group header
-            auto_mode: (label & (1 << 3)) != 0,
-            stat_mode: (label & (1 << 4)) != 0,
-            measuring: (label & (1 << 5)) != 0,
-            stasis_on: (label & (1 << 6)) != 0,
-            calibrating: (label & (1 << 7)) != 0,
-            data_older_than_60s: (label & (1 << 8)) != 0,
+            exists: (status & (1 << 0)) != 0,
+            active: (status & (1 << 1)) != 0,
+            auto_mode: (status & (1 << 3)) != 0,
+            stat_mode: (status & (1 << 4)) != 0,
+            measuring: (status & (1 << 5)) != 0,
+            stasis_on: (status & (1 << 6)) != 0,
+            calibrating: (status & (1 << 7)) != 0,
+            data_older_than_60s: (status & (1 << 8)) != 0,
         }
     }
 }
@@ -179,6 +180,44 @@ impl GasStatus {
     }
 }
 
+/// Neuromuscular transmission status flags
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+pub struct NmtStatus {
+    pub exists: bool,
+    pub active: bool,
+    pub calibrating: bool,
+    pub measurement_off: bool,
+}
+
+impl NmtStatus {
+    pub fn from_status(status: u32) -> Self {
+        Self {
+            exists: (status & (1 << 0)) != 0,
+            active: (status & (1 << 1)) != 0,
+            calibrating: (status & (1 << 2)) != 0,
+            measurement_off: (status & (1 << 3)) != 0,
+        }
+    }
+}
+
+/// SvO2 status flags
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+pub struct Svo2Status {
+    pub exists: bool,
+    pub active: bool,
+    pub calibrating: bool,
+}
+
+impl Svo2Status {
+    pub fn from_status(status: u32) -> Self {
+        Self {
+            exists: (status & (1 << 0)) != 0,
+            active: (status & (1 << 1)) != 0,
+            calibrating: (status & (1 << 2)) != 0,
+        }
+    }
+}
+
 /// Generic status flags (for parameters with just exists/active)
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
 pub struct GenericStatus {
@@ -2,25 +2,27 @@
 
 use crate::constants::special_values;
 
-/// Read a 16-bit signed integer from data (little-endian)
-pub fn read_i16(data: &[u8]) -> i16 {
-    i16::from_le_bytes([data[0], data[1]])
+/// Read a little-endian i16 at `offset`, None if out of bounds
+pub fn read_i16(data: &[u8], offset: usize) -> Option<i16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?;
+    Some(i16::from_le_bytes([bytes[0], bytes[1]]))
 }
 
-/// Read a 32-bit unsigned integer from data (little-endian)
-pub fn read_u32(data: &[u8]) -> u32 {
-    u32::from_le_bytes([data[0], data[1], data[2], data[3]])
+/// Read a little-endian u16 at `offset`, None if out of bounds
+pub fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
 }
 
-/// Read a 16-bit unsigned integer from data (little-endian)
-pub fn read_u16(data: &[u8]) -> u16 {
-    u16::from_le_bytes([data[0], data[1]])
+/// Read a little-endian u32 at `offset`, None if out of bounds
+pub fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
 }
 
-/// Read and validate an i16, returning None if invalid
-pub fn read_valid_i16(data: &[u8]) -> Option<i16> {
-    let value = read_i16(data);
-    special_values::check_valid(value)
+/// Read and validate an i16 at `offset`, None if invalid or out of bounds
+pub fn read_valid_i16(data: &[u8], offset: usize) -> Option<i16> {
+    read_i16(data, offset).and_then(special_values::check_valid)
 }
 
 /// Group header structure (common to many parameter groups)
@@ -33,14 +35,10 @@ pub struct GroupHeader {
 impl GroupHeader {
     /// Parse group header from data (6 bytes: 4 bytes status + 2 bytes label)
     pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
-        if data.len() < 6 {
-            return Err(anyhow::anyhow!("Group header data too short"));
+        match (read_u32(data, 0), read_u16(data, 4)) {
+            (Some(status), Some(label)) => Ok(GroupHeader { status, label }),
+            _ => Err(anyhow::anyhow!("Group header data too short")),
         }
-
-        let status = read_u32(&data[0..4]);
-        let label = read_u16(&data[4..6]);
-
-        Ok(GroupHeader { status, label })
     }
 
     /// Check if module exists
@@ -87,8 +85,17 @@ mod tests {
     #[test]
     fn test_read_i16() {
         let data = vec![0x34, 0x12, 0xFF, 0xFF];
-        assert_eq!(read_i16(&data[0..2]), 0x1234);
-        assert_eq!(read_i16(&data[2..4]), -1);
+        assert_eq!(read_i16(&data, 0), Some(0x1234));
+        assert_eq!(read_i16(&data, 2), Some(-1));
+    }
+
+    #[test]
+    fn test_checked_reads_stop_at_end() {
+        let data = vec![0x34, 0x12, 0x78, 0x56];
+        assert_eq!(read_i16(&data, 2), Some(0x5678));
+        assert_eq!(read_u32(&data, 0), Some(0x5678_1234));
+        assert_eq!(read_i16(&data, 3), None);
+        assert_eq!(read_u16(&data, usize::MAX), None);
     }
 
     #[test]
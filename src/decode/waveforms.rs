@@ -1,11 +1,14 @@
 //! Waveform data decoding
 
 use crate::constants::WaveformType;
+use crate::constants::special_values::WF_PACER_MARKER;
 use crate::protocol::DriHeader;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use log::{debug, warn};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
 
 use super::subrecords::*;
 
@@ -16,16 +19,49 @@ pub struct WaveformData {
     pub timestamp: DateTime<Utc>,
     /// Waveform type
     pub waveform_type: WaveformType,
+    /// Plug identifier of the sending monitor (from the record header)
+    #[serde(default)]
+    pub plug_id: u16,
     /// Sample values
     pub samples: Vec<i16>,
-    /// Sample rate (samples per second)
-    pub sample_rate: u16,
+    /// Sample rate from the waveform table (samples per second)
+    #[serde(alias = "sample_rate")]
+    pub nominal_rate: u16,
+    /// Sample rate measured from the received sample counts
+    ///
+    /// Set by [`Decoder`](super::Decoder) once a few seconds of contiguous
+    /// data have been seen; `None` before that and after a gap. It differs
+    /// from `nominal_rate` when the monitor is configured to downsample.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_rate: Option<f64>,
     /// Status flags
     pub status: WaveformStatus,
+    /// Indices of samples that carried a pacer spike marker (ECG only)
+    ///
+    /// See [`extract_pacer_markers`] for how the marked samples are filled.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pacer_indices: Vec<usize>,
 }
 
+/// Gap in sampling, any waveform
+const WF_STATUS_GAP: u16 = 0x0001;
+/// Pacer pulse detected, ECG channels only
+const WF_STATUS_PACER: u16 = 0x0004;
+/// Electrode lead off, ECG channels only
+const WF_STATUS_LEAD_OFF: u16 = 0x0008;
+const WF_STATUS_KNOWN: u16 = WF_STATUS_GAP | WF_STATUS_PACER | WF_STATUS_LEAD_OFF;
+
 /// Waveform status flags
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+///
+/// | Bit    | Field            | Applies to        |
+/// |--------|------------------|-------------------|
+/// | 0x0001 | `gap`            | all waveforms     |
+/// | 0x0004 | `pacer_detected` | ECG1, ECG2, ECG3  |
+/// | 0x0008 | `lead_off`       | ECG1, ECG2, ECG3  |
+///
+/// The remaining bits have no meaning we can rely on across monitor
+/// software versions; they are kept in `other_bits` rather than dropped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WaveformStatus {
     /// Gap in sampling (data lost)
     pub gap: bool,
@@ -33,16 +69,55 @@ pub struct WaveformStatus {
     pub pacer_detected: bool,
     /// Lead off (ECG only)
     pub lead_off: bool,
+    /// Fewer samples present than the subrecord header announced
+    ///
+    /// Set by the decoder, not part of the status word.
+    #[serde(default)]
+    pub truncated: bool,
+    /// Status bits not decoded into a flag above
+    #[serde(default)]
+    pub other_bits: u16,
 }
 
 impl WaveformStatus {
     /// Parse from status word
     pub fn from_u16(status: u16) -> Self {
         Self {
-            gap: (status & 0x0001) != 0,
-            pacer_detected: (status & 0x0004) != 0,
-            lead_off: (status & 0x0008) != 0,
+            gap: (status & WF_STATUS_GAP) != 0,
+            pacer_detected: (status & WF_STATUS_PACER) != 0,
+            lead_off: (status & WF_STATUS_LEAD_OFF) != 0,
+            truncated: false,
+            other_bits: status & !WF_STATUS_KNOWN,
+        }
+    }
+
+    /// Encode as a status word
+    pub fn to_u16(&self) -> u16 {
+        let mut status = self.other_bits & !WF_STATUS_KNOWN;
+        if self.gap {
+            status |= WF_STATUS_GAP;
+        }
+        if self.pacer_detected {
+            status |= WF_STATUS_PACER;
+        }
+        if self.lead_off {
+            status |= WF_STATUS_LEAD_OFF;
         }
+        status
+    }
+
+    /// Names of the flags that are set and meaningful for the given waveform
+    pub fn flag_names(&self, waveform_type: WaveformType) -> Vec<&'static str> {
+        let ecg = waveform_type.is_ecg();
+        [
+            (self.gap, "gap"),
+            (self.pacer_detected && ecg, "pacer"),
+            (self.lead_off && ecg, "lead_off"),
+            (self.truncated, "truncated"),
+        ]
+        .into_iter()
+        .filter_map(|(set, name)| set.then_some(name))
+        .collect()
     }
 }
 
@@ -55,13 +130,9 @@ struct WaveformHeader {
 
 impl WaveformHeader {
     fn parse(data: &[u8]) -> Option<Self> {
-        if data.len() < 6 {
-            return None;
-        }
-
-        let act_len = read_u16(&data[0..2]);
-        let status = read_u16(&data[2..4]);
-        let _reserved = read_u16(&data[4..6]);
+        let act_len = read_u16(data, 0)?;
+        let status = read_u16(data, 2)?;
+        let _reserved = read_u16(data, 4)?;
 
         Some(Self {
             act_len,
@@ -71,9 +142,85 @@ impl WaveformHeader {
     }
 }
 
+/// Waveform command subrecord echoed by the monitor
+///
+/// Same layout as a request: `req_type` (u16), a reserved word, then up to
+/// 8 waveform type codes ended by `0xFF`. The list is the set the monitor
+/// accepted, which may be shorter than the one requested (e.g. when the
+/// total exceeded 600 samples/s).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WaveformCommand {
+    pub req_type: u16,
+    /// Accepted waveforms, in subrecord order
+    pub waveforms: Vec<WaveformType>,
+}
+
+impl WaveformCommand {
+    /// Parse a command subrecord, skipping unknown type codes
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let req_type = read_u16(data, 0)?;
+        let codes = data.get(4..12)?;
+        let waveforms = codes
+            .iter()
+            .take_while(|&&code| code != 0xFF)
+            .filter_map(|&code| {
+                let wf = WaveformType::from_u8(code);
+                if wf.is_none() {
+                    warn!(code; "Unknown waveform type in command");
+                }
+                wf
+            })
+            .collect();
+
+        Some(Self {
+            req_type,
+            waveforms,
+        })
+    }
+
+    /// Total nominal sample rate of the accepted waveforms
+    pub fn total_rate(&self) -> u32 {
+        self.waveforms
+            .iter()
+            .map(|wf| wf.info().samples_per_second as u32)
+            .sum()
+    }
+
+    /// Requested waveforms that the monitor did not accept
+    pub fn dropped(&self, requested: &[WaveformType]) -> Vec<WaveformType> {
+        requested
+            .iter()
+            .filter(|wf| !self.waveforms.contains(wf))
+            .copied()
+            .collect()
+    }
+}
+
+/// Find and parse the command subrecord of a waveform frame, if any
+pub fn decode_waveform_command(header: &DriHeader, data: &[u8]) -> Option<WaveformCommand> {
+    let index = header
+        .subrecords
+        .iter()
+        .position(|sr| sr.sr_type == WaveformType::Cmd as u8)?;
+    let sub_data = header.get_subrecord_data(data, index).ok()?;
+    WaveformCommand::parse(sub_data)
+}
+
 /// Decode waveform data from a frame
 pub fn decode_waveforms(header: &DriHeader, data: &[u8]) -> Result<Vec<WaveformData>> {
+    decode_waveforms_detailed(header, data).map(|(waveforms, _)| waveforms)
+}
+
+/// Decode waveform data, also returning the type codes that were skipped
+///
+/// Subrecords with a type this version does not know are dropped from the
+/// waveforms; their raw `sr_type` codes are returned in order instead.
+pub fn decode_waveforms_detailed(
+    header: &DriHeader,
+    data: &[u8],
+) -> Result<(Vec<WaveformData>, Vec<u8>)> {
     let mut waveforms = Vec::new();
+    let mut unknown_types = Vec::new();
     let timestamp = header.timestamp();
 
     // Iterate through subrecords
@@ -82,12 +229,13 @@ pub fn decode_waveforms(header: &DriHeader, data: &[u8]) -> Result<Vec<WaveformD
         let waveform_type = match WaveformType::from_u8(subrecord.sr_type) {
             Some(wf) => wf,
             None => {
-                warn!("Unknown waveform type: {}", subrecord.sr_type);
+                warn!(sr_type = subrecord.sr_type; "Unknown waveform type");
+                unknown_types.push(subrecord.sr_type);
                 continue;
             }
         };
 
-        // Skip command subrecord
+        // Command subrecord, see decode_waveform_command
         if waveform_type == WaveformType::Cmd {
             continue;
         }
@@ -96,7 +244,7 @@ pub fn decode_waveforms(header: &DriHeader, data: &[u8]) -> Result<Vec<WaveformD
         let sub_data = match header.get_subrecord_data(data, i) {
             Ok(d) => d,
             Err(e) => {
-                warn!("Failed to get subrecord data: {}", e);
+                warn!(error:% = e; "Failed to get subrecord data");
                 continue;
             }
         };
@@ -110,53 +258,485 @@ pub fn decode_waveforms(header: &DriHeader, data: &[u8]) -> Result<Vec<WaveformD
             }
         };
 
-        // Parse samples (after 6-byte header)
-        let sample_count = wf_header.act_len as usize;
-        let mut samples = Vec::with_capacity(sample_count);
-
-        for sample_idx in 0..sample_count {
-            let offset = 6 + (sample_idx * 2); // Each sample is 2 bytes
-            if offset + 2 <= sub_data.len() {
-                let sample = read_i16(&sub_data[offset..offset + 2]);
-                samples.push(sample);
-            } else {
-                warn!(
-                    "Failed to read sample {} for {:?} (offset {} exceeds data length {})",
-                    sample_idx,
-                    waveform_type,
-                    offset,
-                    sub_data.len()
-                );
-                break;
-            }
+        // Parse samples (after 6-byte header), 2 bytes each
+        let expected_bytes = wf_header.act_len as usize * 2;
+        let available_bytes = sub_data.len().saturating_sub(6);
+        let truncated = expected_bytes > available_bytes;
+        if truncated {
+            warn!(
+                waveform:? = waveform_type,
+                expected_bytes,
+                available_bytes;
+                "Truncated waveform subrecord"
+            );
         }
 
-        let sample_rate = waveform_type.info().samples_per_second;
-        let status = WaveformStatus::from_u16(wf_header.status);
+        let sample_count = expected_bytes.min(available_bytes) / 2;
+        let mut samples: Vec<i16> = (0..sample_count)
+            .filter_map(|i| read_i16(sub_data, 6 + i * 2))
+            .collect();
+        let pacer_indices = if waveform_type.is_ecg() {
+            extract_pacer_markers(&mut samples)
+        } else {
+            Vec::new()
+        };
+
+        let nominal_rate = waveform_type.info().samples_per_second;
+        let status = WaveformStatus {
+            truncated,
+            ..WaveformStatus::from_u16(wf_header.status)
+        };
 
         debug!(
-            "Decoded waveform: type={:?}, samples={}, rate={}, gap={}",
-            waveform_type,
-            samples.len(),
-            sample_rate,
-            status.gap
+            waveform:? = waveform_type,
+            samples = samples.len(),
+            rate = nominal_rate,
+            gap = status.gap;
+            "Decoded waveform"
         );
 
         waveforms.push(WaveformData {
             timestamp,
             waveform_type,
+            plug_id: header.plug_id,
             samples,
-            sample_rate,
+            nominal_rate,
+            effective_rate: None,
             status,
+            pacer_indices,
         });
     }
 
-    Ok(waveforms)
+    Ok((waveforms, unknown_types))
+}
+
+/// Remove pacer spike markers from ECG samples, returning their indices
+///
+/// A pacer pulse may be signalled in-band by replacing one sample with
+/// [`WF_PACER_MARKER`] (`0x8000`). Each marker is overwritten with the
+/// previous sample (or 0 at the start of the block) so that the amplitude
+/// series and sample timing stay intact.
+pub fn extract_pacer_markers(samples: &mut [i16]) -> Vec<usize> {
+    let mut indices = Vec::new();
+    for i in 0..samples.len() {
+        if samples[i] == WF_PACER_MARKER {
+            indices.push(i);
+            samples[i] = if i > 0 { samples[i - 1] } else { 0 };
+        }
+    }
+    indices
+}
+
+impl WaveformData {
+    /// Effective sample rate if measured, otherwise the nominal one
+    pub fn sample_rate(&self) -> f64 {
+        self.effective_rate.unwrap_or(self.nominal_rate as f64)
+    }
+
+    /// Samples in physical units (see [`WaveformType::base_unit`])
+    pub fn scaled_samples(&self) -> Vec<f64> {
+        let factor = self.waveform_type.scale_factor();
+        self.samples
+            .iter()
+            .map(|&s| f64::from(s) / factor)
+            .collect()
+    }
+
+    /// Samples in physical units as `f32`, half the memory of [`scaled_samples`](Self::scaled_samples)
+    ///
+    /// Every 16-bit sample fits exactly in an `f32`; only the division by
+    /// the scale factor rounds, to a relative error below 1e-7, far finer
+    /// than the monitor's resolution. Prefer the `f64` variant when the
+    /// values feed long accumulations (sums, integrals) where rounding adds up.
+    pub fn scaled_samples_f32(&self) -> Vec<f32> {
+        let factor = self.waveform_type.scale_factor() as f32;
+        self.samples
+            .iter()
+            .map(|&s| f32::from(s) / factor)
+            .collect()
+    }
+}
+
+/// Completed seconds averaged into an effective rate
+const RATE_WINDOW: usize = 10;
+
+/// Completed seconds needed before an effective rate is reported
+const RATE_MIN_SECONDS: usize = 3;
+
+/// Measures effective sample rates from per-second sample counts
+///
+/// Frame timestamps have a 1-second resolution, so samples are counted per
+/// timestamp second and averaged over the last [`RATE_WINDOW`] seconds. The
+/// first (partial) second after a start or a gap is discarded.
+#[derive(Default)]
+pub(crate) struct RateEstimator {
+    channels: HashMap<WaveformType, RateChannel>,
+}
+
+struct RateChannel {
+    second: i64,
+    count: usize,
+    primed: bool,
+    counts: VecDeque<usize>,
+}
+
+impl RateChannel {
+    fn new(second: i64) -> Self {
+        Self {
+            second,
+            count: 0,
+            primed: false,
+            counts: VecDeque::with_capacity(RATE_WINDOW),
+        }
+    }
+}
+
+impl RateEstimator {
+    /// Account for a decoded chunk and return the current estimate
+    pub(crate) fn observe(&mut self, data: &WaveformData) -> Option<f64> {
+        let second = data.timestamp.timestamp();
+        let channel = self
+            .channels
+            .entry(data.waveform_type)
+            .or_insert_with(|| RateChannel::new(second));
+
+        if data.status.gap || second < channel.second || second > channel.second + 1 {
+            *channel = RateChannel::new(second);
+        } else if second == channel.second + 1 {
+            if channel.primed {
+                if channel.counts.len() == RATE_WINDOW {
+                    channel.counts.pop_front();
+                }
+                channel.counts.push_back(channel.count);
+            }
+            channel.primed = true;
+            channel.second = second;
+            channel.count = 0;
+        }
+        channel.count += data.samples.len();
+
+        (channel.counts.len() >= RATE_MIN_SECONDS)
+            .then(|| channel.counts.iter().sum::<usize>() as f64 / channel.counts.len() as f64)
+    }
+}
+
+/// Frame timestamps only have a 1-second resolution
+pub(crate) const TIMESTAMP_RESOLUTION: Duration = Duration::from_secs(1);
+
+/// EEG-related waveforms, grouped by [`WaveformBuffer::eeg_window`]
+pub const EEG_WAVEFORMS: &[WaveformType] = &[
+    WaveformType::Eeg1,
+    WaveformType::Eeg2,
+    WaveformType::Eeg3,
+    WaveformType::Eeg4,
+    WaveformType::Ent100,
+    WaveformType::EegBis,
+];
+
+/// Several waveforms sampled on a common time base
+#[derive(Debug, Clone)]
+pub struct AlignedWindow {
+    /// Time of the first sample
+    pub start: DateTime<Utc>,
+    /// Common sample rate (the highest among the buffered channels)
+    pub sample_rate: u16,
+    /// One row per requested waveform, in request order; `NaN` where a
+    /// channel has no signal (including channels never received)
+    pub channels: Vec<(WaveformType, Vec<f64>)>,
+}
+
+/// Reduces waveform data by an integer factor for overview storage
+///
+/// Every block of `2 * factor` samples is replaced by its minimum and
+/// maximum, in the order they occur. Naive subsampling keeps every n-th
+/// sample and easily misses short features of physiological signals (QRS
+/// complexes, pacer spikes, pressure peaks); the min/max pair keeps the
+/// signal envelope, so a plot of the reduced data still shows them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaveformDecimator {
+    factor: u16,
+}
+
+impl WaveformDecimator {
+    /// Create a decimator keeping one sample out of `factor` (at least 1)
+    pub fn new(factor: u16) -> Self {
+        Self {
+            factor: factor.max(1),
+        }
+    }
+
+    /// Reduction factor
+    pub fn factor(&self) -> u16 {
+        self.factor
+    }
+
+    /// Decimate one waveform chunk
+    ///
+    /// Chunks are processed independently; a trailing partial block still
+    /// yields its min/max, so short chunks may keep slightly more samples.
+    pub fn decimate(&self, data: &WaveformData) -> WaveformData {
+        if self.factor == 1 {
+            return data.clone();
+        }
+
+        let block = 2 * self.factor as usize;
+        let mut samples = Vec::with_capacity(data.samples.len() / self.factor as usize + 2);
+        let mut block_starts = Vec::new();
+        for chunk in data.samples.chunks(block) {
+            block_starts.push(samples.len());
+            let (min_idx, _) = chunk.iter().enumerate().min_by_key(|(_, v)| **v).unwrap();
+            let (max_idx, _) = chunk.iter().enumerate().max_by_key(|(_, v)| **v).unwrap();
+            match min_idx.cmp(&max_idx) {
+                std::cmp::Ordering::Less => samples.extend([chunk[min_idx], chunk[max_idx]]),
+                std::cmp::Ordering::Greater => samples.extend([chunk[max_idx], chunk[min_idx]]),
+                std::cmp::Ordering::Equal => samples.push(chunk[min_idx]),
+            }
+        }
+
+        // A pacer spike is placed at the start of its block
+        let mut pacer_indices: Vec<usize> = data
+            .pacer_indices
+            .iter()
+            .filter_map(|&i| block_starts.get(i / block).copied())
+            .collect();
+        pacer_indices.dedup();
+
+        WaveformData {
+            samples,
+            pacer_indices,
+            nominal_rate: (data.nominal_rate / self.factor).max(1),
+            effective_rate: data.effective_rate.map(|rate| rate / self.factor as f64),
+            ..data.clone()
+        }
+    }
+}
+
+/// Reassembles continuous signals from per-frame waveform chunks
+///
+/// Keeps the most recent `capacity` of samples per waveform type. Lost
+/// samples (frames flagged with `status.gap`, or a timestamp later than the
+/// end of the buffered signal by more than the timestamp resolution) are
+/// filled with `NaN` based on the elapsed time and the sample rate.
+pub struct WaveformBuffer {
+    capacity: Duration,
+    channels: HashMap<WaveformType, WaveformChannel>,
+}
+
+struct WaveformChannel {
+    sample_rate: u16,
+    samples: VecDeque<f64>,
+    /// Time just after the last buffered sample
+    end: DateTime<Utc>,
+}
+
+impl WaveformBuffer {
+    /// Create a buffer keeping up to `capacity` of signal per waveform
+    pub fn new(capacity: Duration) -> Self {
+        Self {
+            capacity,
+            channels: HashMap::new(),
+        }
+    }
+
+    /// Append a decoded waveform chunk
+    pub fn push(&mut self, data: &WaveformData) {
+        let rate = data.nominal_rate;
+        if rate == 0 {
+            return;
+        }
+        let max_len = samples_for(self.capacity, rate);
+
+        let channel = self
+            .channels
+            .entry(data.waveform_type)
+            .or_insert_with(|| WaveformChannel {
+                sample_rate: rate,
+                samples: VecDeque::with_capacity(max_len),
+                end: data.timestamp,
+            });
+        if channel.sample_rate != rate {
+            *channel = WaveformChannel {
+                sample_rate: rate,
+                samples: VecDeque::with_capacity(max_len),
+                end: data.timestamp,
+            };
+        }
+
+        if let Some(lag) = lost_since(channel.end, data) {
+            let missing = samples_for(lag, rate).min(max_len);
+            debug!(waveform:? = data.waveform_type, missing; "Filling missing samples");
+            channel
+                .samples
+                .extend(std::iter::repeat_n(f64::NAN, missing));
+            channel.end = data.timestamp;
+        }
+
+        channel
+            .samples
+            .extend(data.samples.iter().map(|&s| s as f64));
+        channel.end +=
+            chrono::Duration::microseconds(data.samples.len() as i64 * 1_000_000 / rate as i64);
+
+        let excess = channel.samples.len().saturating_sub(max_len);
+        channel.samples.drain(..excess);
+    }
+
+    /// The most recent `duration` of a waveform (oldest sample first)
+    ///
+    /// Shorter if less signal has been buffered; gaps are `NaN`.
+    pub fn window(&self, waveform_type: WaveformType, duration: Duration) -> Vec<f64> {
+        let Some(channel) = self.channels.get(&waveform_type) else {
+            return Vec::new();
+        };
+        let len = samples_for(duration, channel.sample_rate).min(channel.samples.len());
+        channel
+            .samples
+            .range(channel.samples.len() - len..)
+            .copied()
+            .collect()
+    }
+
+    /// The most recent `duration` of several waveforms, aligned in time
+    ///
+    /// The window ends where the least advanced buffered channel ends, so
+    /// channels delivered in different frames line up. Slower channels are
+    /// held at the common (highest) rate. Returns `None` if none of the
+    /// waveforms has been buffered.
+    pub fn aligned(
+        &self,
+        waveform_types: &[WaveformType],
+        duration: Duration,
+    ) -> Option<AlignedWindow> {
+        let present: Vec<&WaveformChannel> = waveform_types
+            .iter()
+            .filter_map(|wf| self.channels.get(wf))
+            .collect();
+        let end = present.iter().map(|c| c.end).min()?;
+        let sample_rate = present.iter().map(|c| c.sample_rate).max()?;
+
+        let len = samples_for(duration, sample_rate);
+        let step_us = 1_000_000.0 / sample_rate as f64;
+        let start = end - chrono::Duration::microseconds((len as f64 * step_us).round() as i64);
+
+        let channels = waveform_types
+            .iter()
+            .map(|&wf| {
+                let samples = match self.channels.get(&wf) {
+                    Some(channel) => (0..len)
+                        .map(|k| {
+                            let t = start
+                                + chrono::Duration::microseconds(
+                                    (k as f64 * step_us).round() as i64
+                                );
+                            channel.sample_at(t)
+                        })
+                        .collect(),
+                    None => vec![f64::NAN; len],
+                };
+                (wf, samples)
+            })
+            .collect();
+
+        Some(AlignedWindow {
+            start,
+            sample_rate,
+            channels,
+        })
+    }
+
+    /// The most recent `duration` of EEG1-4, ENT and BIS, aligned in time
+    pub fn eeg_window(&self, duration: Duration) -> Option<AlignedWindow> {
+        self.aligned(EEG_WAVEFORMS, duration)
+    }
+
+    /// Sample rate of a buffered waveform
+    pub fn sample_rate(&self, waveform_type: WaveformType) -> Option<u16> {
+        self.channels.get(&waveform_type).map(|c| c.sample_rate)
+    }
+
+    /// Drop all buffered samples
+    pub fn clear(&mut self) {
+        self.channels.clear();
+    }
+}
+
+impl WaveformChannel {
+    /// The sample covering time `t`, `NaN` outside the buffered signal
+    fn sample_at(&self, t: DateTime<Utc>) -> f64 {
+        let before_end = (self.end - t).num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6;
+        let back = (before_end * self.sample_rate as f64 - 1e-6).ceil();
+        if back < 1.0 || back > self.samples.len() as f64 {
+            return f64::NAN;
+        }
+        self.samples[self.samples.len() - back as usize]
+    }
+}
+
+/// Time lost between `end` (just after the previous chunk) and `data`
+///
+/// `None` if `data` continues the signal, allowing for the timestamp
+/// resolution unless the chunk is flagged with `status.gap`.
+pub(crate) fn lost_since(end: DateTime<Utc>, data: &WaveformData) -> Option<Duration> {
+    let lag = (data.timestamp - end).to_std().unwrap_or(Duration::ZERO);
+    let tolerance = if data.status.gap {
+        Duration::ZERO
+    } else {
+        TIMESTAMP_RESOLUTION
+    };
+    (lag > tolerance).then_some(lag)
+}
+
+pub(crate) fn samples_for(duration: Duration, rate: u16) -> usize {
+    (duration.as_secs_f64() * rate as f64).round() as usize
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::constants::dri_types::DriMainType;
+    use crate::decode::test_frame;
+    use chrono::TimeZone;
+
+    /// Waveform subrecord: sample count, status and reserved words, samples
+    fn wave_subrecord(samples: &[i16]) -> Vec<u8> {
+        let mut subrecord = (samples.len() as u16).to_le_bytes().to_vec();
+        subrecord.extend([0u8; 4]);
+        for sample in samples {
+            subrecord.extend(sample.to_le_bytes());
+        }
+        subrecord
+    }
+
+    fn chunk(secs: i64, first: i16, count: i16, gap: bool) -> WaveformData {
+        WaveformData {
+            timestamp: Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap(),
+            waveform_type: WaveformType::Ecg1,
+            plug_id: 0,
+            samples: (first..first + count).collect(),
+            nominal_rate: 100,
+            effective_rate: None,
+            status: WaveformStatus {
+                gap,
+                ..WaveformStatus::default()
+            },
+            pacer_indices: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_scaled_samples_f32_match_f64() {
+        let mut data = chunk(0, -3, 6, false);
+        data.waveform_type = WaveformType::Co2;
+        data.samples.push(i16::MAX);
+
+        let wide = data.scaled_samples();
+        let narrow = data.scaled_samples_f32();
+        assert_eq!(wide[0], -0.03);
+        assert_eq!(wide[6], 327.67);
+        for (w, n) in wide.iter().zip(&narrow) {
+            assert!((w - f64::from(*n)).abs() <= w.abs() * 1e-7);
+        }
+    }
 
     #[test]
     fn test_waveform_status() {
@@ -167,5 +747,186 @@ mod tests {
         let status = WaveformStatus::from_u16(0x0004);
         assert!(!status.gap);
         assert!(status.pacer_detected);
+
+        for word in [0x0000, 0x0001, 0x0004, 0x0008, 0x000D, 0x8012] {
+            assert_eq!(WaveformStatus::from_u16(word).to_u16(), word);
+        }
+
+        let status = WaveformStatus::from_u16(0x001D);
+        assert_eq!(status.other_bits, 0x0010);
+        assert_eq!(
+            status.flag_names(WaveformType::Ecg1),
+            vec!["gap", "pacer", "lead_off"]
+        );
+        assert_eq!(status.flag_names(WaveformType::Pleth), vec!["gap"]);
+    }
+
+    #[test]
+    fn test_truncated_subrecord_is_flagged() {
+        // Header claims 10 samples, only 3 follow
+        let mut subrecord = wave_subrecord(&[1, 2, 3]);
+        subrecord[0..2].copy_from_slice(&10u16.to_le_bytes());
+        let frame = test_frame(
+            DriMainType::Wave,
+            &[(WaveformType::Pleth as u8, &subrecord)],
+        );
+
+        let header = DriHeader::parse(&frame).unwrap();
+        let data = header.extract_data(&frame).unwrap();
+        let waveforms = decode_waveforms(&header, data).unwrap();
+        assert_eq!(waveforms[0].samples, vec![1, 2, 3]);
+        assert!(waveforms[0].status.truncated);
+        assert!(!waveforms[0].status.gap);
+    }
+
+    #[test]
+    fn test_pacer_markers_are_extracted() {
+        let subrecord = wave_subrecord(&[10, 20, WF_PACER_MARKER, 30]);
+        let frame = test_frame(DriMainType::Wave, &[(WaveformType::Ecg1 as u8, &subrecord)]);
+
+        let header = DriHeader::parse(&frame).unwrap();
+        let data = header.extract_data(&frame).unwrap();
+        let waveforms = decode_waveforms(&header, data).unwrap();
+        assert_eq!(waveforms[0].samples, vec![10, 20, 20, 30]);
+        assert_eq!(waveforms[0].pacer_indices, vec![2]);
+    }
+
+    #[test]
+    fn test_command_subrecord_lists_accepted_waveforms() {
+        let mut command = [0u8; 32];
+        command[4] = WaveformType::Ecg1 as u8;
+        command[5] = 0xFF;
+        let frame = test_frame(DriMainType::Wave, &[(WaveformType::Cmd as u8, &command)]);
+
+        let header = DriHeader::parse(&frame).unwrap();
+        let data = header.extract_data(&frame).unwrap();
+        let command = decode_waveform_command(&header, data).unwrap();
+        assert_eq!(command.waveforms, vec![WaveformType::Ecg1]);
+        assert_eq!(command.total_rate(), 300);
+        assert_eq!(
+            command.dropped(&[WaveformType::Ecg1, WaveformType::Pleth]),
+            vec![WaveformType::Pleth]
+        );
+        assert!(decode_waveforms(&header, data).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unknown_types_are_reported() {
+        // Unknown type 40, then PLETH
+        let subrecord = wave_subrecord(&[7]);
+        let frame = test_frame(
+            DriMainType::Wave,
+            &[(40, &subrecord), (WaveformType::Pleth as u8, &subrecord)],
+        );
+
+        let header = DriHeader::parse(&frame).unwrap();
+        let data = header.extract_data(&frame).unwrap();
+        let (waveforms, unknown) = decode_waveforms_detailed(&header, data).unwrap();
+        assert_eq!(unknown, vec![40]);
+        assert_eq!(waveforms.len(), 1);
+        assert_eq!(waveforms[0].waveform_type, WaveformType::Pleth);
+    }
+
+    #[test]
+    fn test_effective_rate_from_sample_counts() {
+        let mut estimator = RateEstimator::default();
+        let mut rates = Vec::new();
+        // Nominal 100/s but the monitor sends 50/s, in 4 frames per second
+        for secs in 0..6 {
+            for frame in 0..4 {
+                rates.push(estimator.observe(&chunk(secs, 0, 12 + frame % 2, false)));
+            }
+        }
+
+        // Second 0 is discarded, seconds 1-3 complete when second 4 starts
+        assert!(rates[..16].iter().all(Option::is_none));
+        assert_eq!(rates[16], Some(50.0));
+        assert_eq!(rates.last(), Some(&Some(50.0)));
+
+        // A gap restarts the measurement
+        assert_eq!(estimator.observe(&chunk(6, 0, 12, true)), None);
+    }
+
+    #[test]
+    fn test_eeg_channels_are_aligned() {
+        let eeg = |wf, secs: i64| WaveformData {
+            waveform_type: wf,
+            ..chunk(secs, secs as i16 * 100, 100, false)
+        };
+
+        let mut buffer = WaveformBuffer::new(Duration::from_secs(10));
+        assert!(buffer.eeg_window(Duration::from_secs(1)).is_none());
+        // EEG1 is one frame ahead of EEG2, EEG3/4 never arrive
+        for secs in 0..3 {
+            buffer.push(&eeg(WaveformType::Eeg1, secs));
+        }
+        for secs in 0..2 {
+            buffer.push(&eeg(WaveformType::Eeg2, secs));
+        }
+
+        let window = buffer.eeg_window(Duration::from_secs(1)).unwrap();
+        assert_eq!(window.sample_rate, 100);
+        assert_eq!(window.start, Utc.timestamp_opt(1_700_000_001, 0).unwrap());
+        assert_eq!(window.channels.len(), EEG_WAVEFORMS.len());
+
+        let expected: Vec<f64> = (100..200).map(f64::from).collect();
+        assert_eq!(window.channels[0], (WaveformType::Eeg1, expected.clone()));
+        assert_eq!(window.channels[1], (WaveformType::Eeg2, expected));
+        assert!(window.channels[2].1.iter().all(|s| s.is_nan()));
+        assert_eq!(window.channels[2].1.len(), 100);
+    }
+
+    #[test]
+    fn test_decimation_keeps_spikes() {
+        let mut data = chunk(0, 0, 300, false);
+        data.samples = vec![0; 300];
+        data.samples[7] = 1000; // single-sample spike
+        data.samples[8] = -200;
+
+        let decimated = WaveformDecimator::new(10).decimate(&data);
+        assert_eq!(decimated.nominal_rate, data.nominal_rate / 10);
+        assert_eq!(decimated.samples.len(), 30);
+        assert_eq!(&decimated.samples[..2], &[1000, -200]);
+        assert!(decimated.samples[2..].iter().all(|&s| s == 0));
+    }
+
+    #[test]
+    fn test_buffer_fills_gaps() {
+        let mut buffer = WaveformBuffer::new(Duration::from_secs(10));
+        buffer.push(&chunk(0, 0, 100, false));
+        // Contiguous within the timestamp resolution: no fill
+        buffer.push(&chunk(1, 100, 100, false));
+        // Two seconds of signal lost
+        buffer.push(&chunk(4, 200, 100, true));
+
+        let window = buffer.window(WaveformType::Ecg1, Duration::from_secs(10));
+        assert_eq!(window.len(), 500);
+        assert_eq!(window.iter().filter(|v| v.is_nan()).count(), 200);
+        assert_eq!(window[199], 199.0);
+        assert!(window[200].is_nan());
+        assert_eq!(window[400], 200.0);
+
+        assert!(
+            buffer
+                .window(WaveformType::Pleth, Duration::from_secs(1))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_buffer_keeps_most_recent_samples() {
+        let mut buffer = WaveformBuffer::new(Duration::from_secs(2));
+        for i in 0..5 {
+            buffer.push(&chunk(i, (i * 100) as i16, 100, false));
+        }
+
+        let window = buffer.window(WaveformType::Ecg1, Duration::from_secs(10));
+        assert_eq!(window.len(), 200);
+        assert_eq!(window[0], 300.0);
+        assert_eq!(window[199], 499.0);
+
+        let recent = buffer.window(WaveformType::Ecg1, Duration::from_millis(500));
+        assert_eq!(recent, (450..500).map(|v| v as f64).collect::<Vec<_>>());
+        assert_eq!(buffer.sample_rate(WaveformType::Ecg1), Some(100));
     }
 }
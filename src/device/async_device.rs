@@ -0,0 +1,280 @@
+//! Asynchronous device communication using tokio
+//!
+//! Requires the `async` feature. The protocol handling is the same as for
+//! the blocking [`Device`](super::Device): the sans-IO `FrameParser` and
+//! `Decoder` are reused, only the byte I/O is async.
+
+use super::SerialConfig;
+use super::serial_device::waveform_from_name;
+use crate::constants::WaveformType;
+use crate::constants::dri_types::PHDBCL_REQ_ALL;
+use crate::decode::{Decoder, DriRecord};
+use crate::protocol::framing::create_frame;
+use crate::protocol::header::{create_phdb_request, create_waveform_request};
+use crate::protocol::{ChecksumMode, DriFrame, FrameParser};
+use crate::{DriError, Result};
+use chrono::Utc;
+use futures_util::Stream;
+use futures_util::stream;
+use log::{debug, info, warn};
+use std::collections::VecDeque;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+
+/// Waveform request types
+const WF_REQ_CONT_START: u16 = 0;
+const WF_REQ_CONT_STOP: u16 = 1;
+
+/// Device connected to a GE monitor over an async byte stream
+///
+/// Unlike the blocking device, dropping does not stop transmission (there
+/// is no async drop); call [`AsyncDevice::stop_all`] before closing.
+pub struct AsyncDevice<S: AsyncRead + AsyncWrite + Unpin> {
+    stream: S,
+    parser: FrameParser,
+    pending: VecDeque<std::result::Result<DriFrame, DriError>>,
+}
+
+/// Async device connected to a GE monitor over RS-232
+pub type AsyncSerialDevice = AsyncDevice<SerialStream>;
+
+impl AsyncDevice<SerialStream> {
+    /// Open a serial port with the default GE monitor settings
+    pub fn open(port_name: &str) -> Result<Self> {
+        Self::open_with_config(port_name, &SerialConfig::default())
+    }
+
+    /// Open a serial port with custom serial settings
+    pub fn open_with_config(port_name: &str, config: &SerialConfig) -> Result<Self> {
+        info!("Opening async serial port: {} ({:?})", port_name, config);
+
+        let stream = tokio_serial::new(port_name, config.baud_rate)
+            .timeout(config.timeout)
+            .data_bits(config.data_bits)
+            .parity(config.parity)
+            .stop_bits(config.stop_bits)
+            .flow_control(config.flow_control)
+            .open_native_async()?;
+
+        Ok(Self::with_stream(stream).with_checksum_mode(config.checksum_mode))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncDevice<S> {
+    /// Create a device over an already opened stream
+    pub fn with_stream(stream: S) -> Self {
+        Self {
+            stream,
+            parser: FrameParser::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Set the checksum convention of received frames
+    pub fn with_checksum_mode(mut self, mode: ChecksumMode) -> Self {
+        self.parser = FrameParser::new().with_checksum_mode(mode);
+        self
+    }
+
+    /// Request displayed values (current physiological data)
+    ///
+    /// # Arguments
+    /// * `interval` - Update interval in seconds (minimum 5)
+    ///
+    /// Returns the interval actually requested.
+    pub async fn request_displayed_values(&mut self, interval: u16) -> Result<u16> {
+        let interval = interval.max(5);
+        info!("Requesting displayed values every {} seconds", interval);
+
+        let header = create_phdb_request(1, interval, PHDBCL_REQ_ALL);
+        self.write_frame(&create_frame(&header)).await?;
+        Ok(interval)
+    }
+
+    /// Request 10-second trended values
+    pub async fn request_trend_10s(&mut self) -> Result<()> {
+        info!("Requesting 10-second trend values");
+
+        let header = create_phdb_request(2, 1, PHDBCL_REQ_ALL);
+        self.write_frame(&create_frame(&header)).await
+    }
+
+    /// Request 60-second trended values
+    pub async fn request_trend_60s(&mut self) -> Result<()> {
+        info!("Requesting 60-second trend values");
+
+        let header = create_phdb_request(3, 1, PHDBCL_REQ_ALL);
+        self.write_frame(&create_frame(&header)).await
+    }
+
+    /// Request waveform data
+    ///
+    /// # Arguments
+    /// * `waveform_names` - Array of waveform names (e.g., ["ECG1", "PLETH"])
+    ///
+    /// Unknown names are skipped; returns the waveforms actually requested.
+    pub async fn request_waveforms(
+        &mut self,
+        waveform_names: &[&str],
+    ) -> Result<Vec<WaveformType>> {
+        let mut waveforms = Vec::new();
+        for name in waveform_names {
+            match waveform_from_name(name) {
+                Some(wf) => waveforms.push(wf),
+                None => warn!("Unknown waveform {:?} skipped", name),
+            }
+        }
+
+        if waveforms.is_empty() {
+            anyhow::bail!("No valid waveforms specified");
+        }
+
+        crate::constants::waveforms::validate_waveform_set(&waveforms)?;
+
+        info!("Requesting waveforms: {:?}", waveforms);
+
+        let waveform_types: Vec<u8> = waveforms.iter().map(|wf| *wf as u8).collect();
+        let header = create_waveform_request(&waveform_types, WF_REQ_CONT_START);
+        self.write_frame(&create_frame(&header)).await?;
+        Ok(waveforms)
+    }
+
+    /// Stop waveform transmission
+    pub async fn stop_waveforms(&mut self) -> Result<()> {
+        info!("Stopping waveform transmission");
+
+        let header = create_waveform_request(&[], WF_REQ_CONT_STOP);
+        self.write_frame(&create_frame(&header)).await
+    }
+
+    /// Stop all data transmission
+    pub async fn stop_all(&mut self) -> Result<()> {
+        info!("Stopping all data transmission");
+
+        self.write_frame(&create_frame(&create_phdb_request(1, 0, 0)))
+            .await?;
+        for subtype in [2, 3] {
+            self.write_frame(&create_frame(&create_phdb_request(subtype, 0, 0)))
+                .await?;
+        }
+        self.stop_waveforms().await
+    }
+
+    /// Read one complete frame from the device
+    ///
+    /// Fails with an IO error of kind `UnexpectedEof` if the stream closes.
+    pub async fn read_frame(&mut self) -> Result<DriFrame> {
+        let mut buffer = [0u8; 2048];
+
+        loop {
+            if let Some(frame) = self.pending.pop_front() {
+                return Ok(frame?);
+            }
+
+            let bytes_read = self.stream.read(&mut buffer).await?;
+            if bytes_read == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+
+            debug!(bytes_read; "Read from async stream");
+            self.pending
+                .extend(self.parser.process_bytes(&buffer[..bytes_read]));
+        }
+    }
+
+    /// Stream of decoded records
+    ///
+    /// Same semantics as [`Device::records`](super::Device::records): decode
+    /// errors are yielded and the stream continues, frames without
+    /// decodable data are skipped, and the stream ends after yielding a
+    /// transport error.
+    pub fn records(&mut self) -> impl Stream<Item = Result<DriRecord>> + '_ {
+        stream::unfold(
+            (self, Decoder::new(), VecDeque::new(), false),
+            |(device, mut decoder, mut pending, finished)| async move {
+                if finished {
+                    return None;
+                }
+
+                loop {
+                    if let Some(record) = pending.pop_front() {
+                        return Some((Ok(record), (device, decoder, pending, false)));
+                    }
+
+                    let frame = match device.read_frame().await {
+                        Ok(frame) => frame,
+                        Err(e) => {
+                            let finished = e.downcast_ref::<std::io::Error>().is_some();
+                            return Some((Err(e), (device, decoder, pending, finished)));
+                        }
+                    };
+
+                    match decoder.decode_bytes_all(&frame.data) {
+                        Ok(records) => {
+                            let received_at = Utc::now();
+                            pending.extend(records.into_iter().map(|mut record| {
+                                record.mark_received(received_at);
+                                record
+                            }));
+                        }
+                        Err(e) => return Some((Err(e), (device, decoder, pending, false))),
+                    }
+                }
+            },
+        )
+    }
+
+    /// Clear the parser buffer (useful after errors)
+    pub fn reset_parser(&mut self) {
+        self.parser.reset();
+        self.pending.clear();
+    }
+
+    async fn write_frame(&mut self, frame: &[u8]) -> Result<()> {
+        debug!(bytes = frame.len(); "Writing to async stream");
+        self.stream.write_all(frame).await?;
+        self.stream.flush().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn test_read_frames_from_one_chunk() {
+        let (client, mut monitor) = tokio::io::duplex(4096);
+        let mut device = AsyncDevice::with_stream(client);
+
+        let mut chunk = create_frame(&[0x01, 0x02]);
+        chunk.extend(create_frame(&[0x03, 0x04]));
+        monitor.write_all(&chunk).await.unwrap();
+
+        assert_eq!(device.read_frame().await.unwrap().data, vec![0x01, 0x02]);
+        assert_eq!(device.read_frame().await.unwrap().data, vec![0x03, 0x04]);
+
+        device.request_displayed_values(10).await.unwrap();
+        let mut written = [0u8; 1];
+        monitor.read_exact(&mut written).await.unwrap();
+        assert_eq!(written[0], 0x7E);
+    }
+
+    #[tokio::test]
+    async fn test_records_stream_ends_on_eof() {
+        let (client, mut monitor) = tokio::io::duplex(4096);
+        let mut device = AsyncDevice::with_stream(client);
+
+        // Too short to carry a DRI header
+        monitor
+            .write_all(&create_frame(&[0x01, 0x02, 0x03]))
+            .await
+            .unwrap();
+        drop(monitor);
+
+        let results: Vec<_> = device.records().collect().await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.is_err()));
+    }
+}
@@ -1,7 +1,21 @@
 //! Device communication module
 
+#[cfg(feature = "async")]
+pub mod async_device;
 pub mod port_selector;
+pub mod reconnect;
+pub mod serial_config;
 pub mod serial_device;
+pub mod transport;
 
-pub use port_selector::select_port;
-pub use serial_device::SerialDevice;
+#[cfg(feature = "async")]
+pub use async_device::{AsyncDevice, AsyncSerialDevice};
+pub use port_selector::{find_ge_port, select_port};
+pub use reconnect::ReconnectPolicy;
+pub use serial_config::SerialConfig;
+pub use serial_device::{
+    ActiveRequests, Capabilities, DEFAULT_KEEPALIVE_INTERVAL, DEFAULT_WAVEFORM_CONFIRMATION_WINDOW,
+    Device, FileDevice, ProbeOutcome, Records, SerialDevice, TcpDevice, WaveformConfirmation,
+    WaveformConfirmationCallback,
+};
+pub use transport::{FileTransport, SerialTransport, TcpTransport, Transport};
@@ -1,8 +1,52 @@
-//! Interactive serial port selection
+//! Serial port selection (USB auto-detection or interactive)
 
 use crate::Result;
 use dialoguer::Select;
-use serialport::SerialPortInfo;
+use log::info;
+use serialport::{SerialPortInfo, SerialPortType};
+
+/// USB-serial adapters known to be used with GE/Datex-Ohmeda monitors
+///
+/// (vendor ID, product ID, description). Add entries here for other cables.
+pub const KNOWN_ADAPTERS: &[(u16, u16, &str)] = &[
+    (0x0403, 0x6001, "FTDI FT232R (GE DRI cable)"),
+    (0x0403, 0x6015, "FTDI FT231X"),
+    (0x067B, 0x2303, "Prolific PL2303"),
+    (0x10C4, 0xEA60, "Silicon Labs CP210x"),
+];
+
+/// Find the first serial port attached through a known adapter
+///
+/// Returns `Ok(None)` if no such port is connected, so callers can fall
+/// back to [`select_port`].
+pub fn find_ge_port() -> Result<Option<String>> {
+    let ports = serialport::available_ports()?;
+    let found = match_ge_port(&ports);
+
+    if let Some(port_name) = &found {
+        info!("Auto-detected GE monitor adapter on {}", port_name);
+    }
+
+    Ok(found)
+}
+
+/// Pick the first USB port whose VID/PID is in [`KNOWN_ADAPTERS`]
+fn match_ge_port(ports: &[SerialPortInfo]) -> Option<String> {
+    ports
+        .iter()
+        .find(|port| match &port.port_type {
+            SerialPortType::UsbPort(usb) => is_known_adapter(usb.vid, usb.pid),
+            _ => false,
+        })
+        .map(|port| port.port_name.clone())
+}
+
+/// Check a USB vendor/product ID pair against [`KNOWN_ADAPTERS`]
+pub fn is_known_adapter(vid: u16, pid: u16) -> bool {
+    KNOWN_ADAPTERS
+        .iter()
+        .any(|&(known_vid, known_pid, _)| known_vid == vid && known_pid == pid)
+}
 
 /// Interactively select a serial port from available ports
 pub fn select_port() -> Result<String> {
@@ -71,6 +115,18 @@ pub fn list_ports() -> Result<Vec<SerialPortInfo>> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_known_adapters() {
+        assert!(is_known_adapter(0x0403, 0x6001));
+        assert!(!is_known_adapter(0x0403, 0x0000));
+
+        let ports = vec![SerialPortInfo {
+            port_name: "/dev/ttyS0".to_string(),
+            port_type: SerialPortType::PciPort,
+        }];
+        assert_eq!(match_ge_port(&ports), None);
+    }
+
     #[test]
     fn test_list_ports() {
         // This test just ensures the function doesn't panic
@@ -0,0 +1,161 @@
+//! Serial line settings for GE monitors
+
+use crate::protocol::ChecksumMode;
+use std::time::Duration;
+
+pub use serialport::{DataBits, FlowControl, Parity, StopBits};
+
+/// Default size of the buffer passed to each transport read
+///
+/// Larger than [`MAX_RECORD_SIZE`](crate::constants::MAX_RECORD_SIZE) so
+/// that a typical frame, with some byte stuffing, fits in one read.
+pub const DEFAULT_READ_BUFFER_SIZE: usize = 2048;
+
+/// Serial port configuration
+///
+/// The defaults match the documented GE monitor settings
+/// (19200 baud, 8 data bits, even parity, 1 stop bit, RTS/CTS).
+/// Some S/5 installations and RS-232-to-USB adapters need different
+/// settings, which can be changed with the builder methods.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialConfig {
+    /// Baud rate
+    pub baud_rate: u32,
+    /// Number of data bits
+    pub data_bits: DataBits,
+    /// Parity checking mode
+    pub parity: Parity,
+    /// Number of stop bits
+    pub stop_bits: StopBits,
+    /// Flow control mode
+    pub flow_control: FlowControl,
+    /// Read timeout used by blocking reads
+    pub timeout: Duration,
+    /// Bytes requested from the transport per read
+    ///
+    /// Frames larger than the buffer simply take several reads. A read that
+    /// fills the buffer completely means more data was already waiting,
+    /// see [`Device::full_reads`](super::Device::full_reads).
+    pub read_buffer_size: usize,
+    /// Checksum convention of received frames
+    pub checksum_mode: ChecksumMode,
+    /// Time a request write may block, e.g. while flow control holds it
+    pub write_timeout: Duration,
+    /// Further attempts after a request write timed out
+    pub write_retries: u32,
+}
+
+impl SerialConfig {
+    /// Create a configuration with the default GE monitor settings
+    pub fn new() -> Self {
+        Self {
+            baud_rate: 19200,
+            data_bits: DataBits::Eight,
+            parity: Parity::Even,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::Hardware,
+            timeout: Duration::from_millis(1000),
+            read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            checksum_mode: ChecksumMode::default(),
+            write_timeout: Duration::from_millis(1000),
+            write_retries: 3,
+        }
+    }
+
+    /// Set the baud rate
+    pub fn baud_rate(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    /// Set the number of data bits
+    pub fn data_bits(mut self, data_bits: DataBits) -> Self {
+        self.data_bits = data_bits;
+        self
+    }
+
+    /// Set the parity checking mode
+    pub fn parity(mut self, parity: Parity) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    /// Set the number of stop bits
+    pub fn stop_bits(mut self, stop_bits: StopBits) -> Self {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    /// Set the flow control mode
+    pub fn flow_control(mut self, flow_control: FlowControl) -> Self {
+        self.flow_control = flow_control;
+        self
+    }
+
+    /// Set the read timeout
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the read buffer size (at least one byte)
+    pub fn read_buffer_size(mut self, size: usize) -> Self {
+        self.read_buffer_size = size.max(1);
+        self
+    }
+
+    /// Set the checksum convention, e.g. [`ChecksumMode::Auto`] for an
+    /// unfamiliar device
+    pub fn checksum_mode(mut self, mode: ChecksumMode) -> Self {
+        self.checksum_mode = mode;
+        self
+    }
+
+    /// Set the time a request write may block before it is retried
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Set how often a timed-out request write is retried
+    pub fn write_retries(mut self, retries: u32) -> Self {
+        self.write_retries = retries;
+        self
+    }
+}
+
+impl Default for SerialConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_matches_ge_settings() {
+        let config = SerialConfig::default();
+        assert_eq!(config.baud_rate, 19200);
+        assert_eq!(config.data_bits, DataBits::Eight);
+        assert_eq!(config.parity, Parity::Even);
+        assert_eq!(config.stop_bits, StopBits::One);
+        assert_eq!(config.flow_control, FlowControl::Hardware);
+        assert_eq!(config.timeout, Duration::from_millis(1000));
+        assert_eq!(config.read_buffer_size, DEFAULT_READ_BUFFER_SIZE);
+    }
+
+    #[test]
+    fn test_builder_overrides() {
+        let config = SerialConfig::new()
+            .baud_rate(9600)
+            .flow_control(FlowControl::None)
+            .timeout(Duration::from_millis(250));
+
+        assert_eq!(config.baud_rate, 9600);
+        assert_eq!(config.flow_control, FlowControl::None);
+        assert_eq!(config.timeout, Duration::from_millis(250));
+        assert_eq!(config.parity, Parity::Even);
+    }
+}
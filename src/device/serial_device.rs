@@ -1,27 +1,209 @@
-//! Serial device communication with GE monitors
+//! Device communication with GE monitors
 
+use super::transport::{FileTransport, SerialTransport, TcpTransport, Transport, is_timeout};
+use super::{ReconnectPolicy, SerialConfig};
+use crate::DriError;
 use crate::Result;
+use crate::constants::MAX_SUBRECORDS;
 use crate::constants::WaveformType;
-use crate::constants::dri_types::PHDBCL_REQ_ALL;
-use crate::protocol::framing::create_frame;
-use crate::protocol::header::{create_phdb_request, create_waveform_request};
-use crate::protocol::{DriFrame, FrameParser};
+use crate::constants::dri_types::{
+    DriFeature, DriLevel, DriMainType, PHDBCL_REQ_ALL, PHDBCL_REQ_EXT2_MASK, PHDBCL_REQ_EXT3_MASK,
+    PhdbClass, PhdbSubrecordType,
+};
+use crate::constants::waveforms::{MAX_TOTAL_SAMPLE_RATE, calculate_total_sample_rate};
+use crate::decode::{Decoder, DriRecord};
+use crate::protocol::{ChecksumMode, DriFrame, DriHeader, FrameParser, RequestBuilder};
+use chrono::{DateTime, Utc};
 use log::{debug, info, warn};
-use serialport::SerialPort;
-use std::io::{Read, Write};
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::fmt;
+use std::net::ToSocketAddrs;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 
-/// Waveform request types
-const WF_REQ_CONT_START: u16 = 0;
-const WF_REQ_CONT_STOP: u16 = 1;
+/// Suggested keepalive interval for [`Device::with_keepalive`]
+pub const DEFAULT_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
 
-/// Serial device connected to a GE monitor
-pub struct SerialDevice {
-    port: Box<dyn SerialPort>,
+/// Delay before the first write retry, doubled on each further one
+const WRITE_RETRY_DELAY: Duration = Duration::from_millis(20);
+
+/// Time given to requested waveforms to start arriving
+pub const DEFAULT_WAVEFORM_CONFIRMATION_WINDOW: Duration = Duration::from_secs(5);
+
+/// Callback of [`Device::with_waveform_confirmation`]
+pub type WaveformConfirmationCallback = Box<dyn FnMut(&WaveformConfirmation) + Send>;
+
+/// Device connected to a GE monitor over some byte transport
+pub struct Device<T: Transport> {
+    transport: T,
     parser: FrameParser,
+    config: SerialConfig,
+    reconnect_policy: ReconnectPolicy,
+    active: ActiveRequests,
+    keepalive: Option<Duration>,
+    last_displayed_request: Instant,
+    stop_flag: Option<Arc<AtomicBool>>,
+    dri_level: Option<DriLevel>,
+    read_buffer: Vec<u8>,
+    /// Frames parsed from an earlier read, not returned yet
+    pending: VecDeque<std::result::Result<DriFrame, DriError>>,
+    full_reads: u64,
+    confirmation_window: Duration,
+    on_waveform_confirmation: Option<WaveformConfirmationCallback>,
+    waveform_watch: Option<WaveformWatch>,
+    /// When the transport was opened, or last reopened
+    connected: LinkTime,
+    /// When the last frame with a valid checksum arrived
+    last_frame: Option<LinkTime>,
+}
+
+/// Wall-clock time for display, with the monotonic instant for durations
+#[derive(Debug, Clone, Copy)]
+struct LinkTime {
+    at: DateTime<Utc>,
+    instant: Instant,
+}
+
+impl LinkTime {
+    fn now() -> Self {
+        Self {
+            at: Utc::now(),
+            instant: Instant::now(),
+        }
+    }
+}
+
+/// Which requested waveforms arrived after [`Device::request_waveforms`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WaveformConfirmation {
+    /// Requested waveforms seen in at least one frame
+    pub received: Vec<WaveformType>,
+    /// Requested waveforms that never arrived, e.g. rejected by the monitor
+    pub missing: Vec<WaveformType>,
+}
+
+/// Requested waveforms not yet seen, until the confirmation deadline
+struct WaveformWatch {
+    requested: Vec<WaveformType>,
+    seen: Vec<WaveformType>,
+    deadline: Instant,
+}
+
+/// Requests currently in effect on the monitor
+///
+/// Updated by the `request_*`/`stop_*` methods and replayed by
+/// [`Device::resend_active_requests`] (after a reconnect, or as a keepalive).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActiveRequests {
+    /// Displayed values update interval in seconds, if requested
+    pub displayed_interval: Option<u16>,
+    /// Class mask of the displayed values request
+    pub class_mask: u32,
+    /// 10-second trends requested
+    pub trend_10s: bool,
+    /// 60-second trends requested
+    pub trend_60s: bool,
+    /// Streaming waveforms
+    pub waveforms: Vec<WaveformType>,
+}
+
+/// Result of [`Device::probe`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProbeOutcome {
+    /// A frame with a valid checksum and a DRI header was received
+    Dri {
+        dri_level: DriLevel,
+        maintype: DriMainType,
+        /// Frames that failed their checksum before the valid one
+        checksum_errors: usize,
+    },
+    /// Frames were received, but none passed the checksum
+    ///
+    /// Points to a protocol mismatch (wrong serial settings or device).
+    BadChecksums { checksum_errors: usize },
+    /// No DRI frame arrived before the timeout
+    ///
+    /// Points to a wiring problem or a monitor with DRI output disabled.
+    NoTraffic {
+        /// Bytes received, none of them forming a frame
+        bytes: usize,
+    },
+}
+
+impl ProbeOutcome {
+    /// Whether a frame passed its checksum
+    pub fn checksum_valid(&self) -> bool {
+        matches!(self, ProbeOutcome::Dri { .. })
+    }
+}
+
+impl fmt::Display for ProbeOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProbeOutcome::Dri {
+                dri_level,
+                maintype,
+                checksum_errors,
+            } => write!(
+                f,
+                "DRI monitor found: level {:?} ({}), first record {:?}, {} checksum errors",
+                dri_level,
+                dri_level.year_str(),
+                maintype,
+                checksum_errors
+            ),
+            ProbeOutcome::BadChecksums { checksum_errors } => write!(
+                f,
+                "traffic but bad checksums ({} frames failed)",
+                checksum_errors
+            ),
+            ProbeOutcome::NoTraffic { bytes } => {
+                write!(f, "no DRI traffic ({} bytes received)", bytes)
+            }
+        }
+    }
+}
+
+/// What the monitor sent during [`Device::discover`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// DRI level from the record headers
+    pub dri_level: Option<DriLevel>,
+    /// Physiological classes received with the displayed values
+    pub classes: Vec<PhdbClass>,
+    /// Waveforms received, in request order
+    pub waveforms: Vec<WaveformType>,
 }
 
-impl SerialDevice {
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.dri_level {
+            Some(level) => write!(f, "DRI level {:?} ({})", level, level.year_str())?,
+            None => write!(f, "no DRI traffic")?,
+        }
+        let waveforms: Vec<&str> = self.waveforms.iter().map(|wf| wf.name()).collect();
+        write!(
+            f,
+            ", classes {:?}, waveforms [{}]",
+            self.classes,
+            waveforms.join(", ")
+        )
+    }
+}
+
+/// Device connected to a GE monitor over RS-232
+pub type SerialDevice = Device<SerialTransport>;
+
+/// Device connected to a GE monitor's network interface over TCP
+pub type TcpDevice = Device<TcpTransport>;
+
+/// Device replaying a captured serial stream, for tests and demos
+pub type FileDevice = Device<FileTransport>;
+
+impl Device<SerialTransport> {
     /// Open a serial port connection to a GE monitor
     ///
     /// # Arguments
@@ -34,42 +216,186 @@ impl SerialDevice {
     /// - Stop bits: 1
     /// - Flow control: RTS/CTS
     pub fn open(port_name: &str) -> Result<Self> {
-        info!("Opening serial port: {}", port_name);
+        Self::open_with_config(port_name, &SerialConfig::default())
+    }
+
+    /// Open a serial port connection with custom serial settings
+    ///
+    /// # Arguments
+    /// * `port_name` - Serial port name (e.g., "/dev/ttyUSB0" or "COM3")
+    /// * `config` - Serial line settings
+    pub fn open_with_config(port_name: &str, config: &SerialConfig) -> Result<Self> {
+        let transport = SerialTransport::open(port_name, config)?;
+        Ok(Self::with_transport(transport, config.clone()))
+    }
+}
+
+impl Device<TcpTransport> {
+    /// Connect to a monitor or gateway DRI network interface
+    ///
+    /// # Arguments
+    /// * `addr` - Socket address (e.g., "10.0.0.5:2000")
+    pub fn connect_tcp<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        Self::connect_tcp_with_config(addr, &SerialConfig::default())
+    }
+
+    /// Connect to a DRI network interface using the timeout from `config`
+    ///
+    /// Serial line settings in `config` are ignored for TCP.
+    pub fn connect_tcp_with_config<A: ToSocketAddrs>(
+        addr: A,
+        config: &SerialConfig,
+    ) -> Result<Self> {
+        let transport = TcpTransport::connect(addr, config.timeout)?;
+        Ok(Self::with_transport(transport, config.clone()))
+    }
+}
+
+impl Device<FileTransport> {
+    /// Replay a file of stuffed bytes captured from the serial line
+    ///
+    /// Requests are accepted and discarded; `read_frame` fails at the end
+    /// of the file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let transport = FileTransport::open(path)?;
+        Ok(Self::with_transport(transport, SerialConfig::default()))
+    }
+}
+
+impl<T: Transport> Device<T> {
+    /// Create a device over an already established transport
+    pub fn with_transport(transport: T, config: SerialConfig) -> Self {
+        Self {
+            transport,
+            parser: FrameParser::new().with_checksum_mode(config.checksum_mode),
+            read_buffer: vec![0u8; config.read_buffer_size.max(1)],
+            pending: VecDeque::new(),
+            full_reads: 0,
+            config,
+            reconnect_policy: ReconnectPolicy::default(),
+            active: ActiveRequests::default(),
+            keepalive: None,
+            last_displayed_request: Instant::now(),
+            stop_flag: None,
+            dri_level: None,
+            confirmation_window: DEFAULT_WAVEFORM_CONFIRMATION_WINDOW,
+            on_waveform_confirmation: None,
+            waveform_watch: None,
+            connected: LinkTime::now(),
+            last_frame: None,
+        }
+    }
 
-        let port = serialport::new(port_name, 19200)
-            .timeout(Duration::from_millis(1000))
-            .data_bits(serialport::DataBits::Eight)
-            .parity(serialport::Parity::Even)
-            .stop_bits(serialport::StopBits::One)
-            .flow_control(serialport::FlowControl::Hardware)
-            .open()?;
+    /// Periodically re-send the active displayed values request
+    ///
+    /// Some S/5 monitors silently stop sending displayed values unless the
+    /// request is refreshed. With a keepalive, `read_frame` re-sends the
+    /// displayed values request (same interval and class mask) once
+    /// `interval` has elapsed since it was last sent. Waveform and trend
+    /// requests are not re-sent, so streaming is not disturbed.
+    ///
+    /// A fresh request restarts the monitor's `tx_interval` cycle, so use a
+    /// keepalive interval that is a multiple of the requested update
+    /// interval (e.g. 30s for a 10s interval), otherwise records may come
+    /// early or be skipped. Disabled by default; see
+    /// [`DEFAULT_KEEPALIVE_INTERVAL`].
+    pub fn with_keepalive(mut self, interval: Duration) -> Self {
+        self.keepalive = Some(interval);
+        self
+    }
 
-        info!("Serial port opened successfully");
+    /// Abort blocking reads once `flag` is set (e.g. from a Ctrl+C handler)
+    ///
+    /// The flag is checked after every transport read, so a blocked
+    /// `read_frame` returns within the read timeout with an IO error of
+    /// kind `Interrupted`.
+    pub fn with_stop_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.stop_flag = Some(flag);
+        self
+    }
 
-        Ok(Self {
-            port,
-            parser: FrameParser::new(),
-        })
+    /// Report which requested waveforms actually started arriving
+    ///
+    /// After each [`Device::request_waveforms`], `callback` is called from
+    /// `read_frame` once every requested waveform has been received, or
+    /// when `window` has elapsed. Without a callback, missing waveforms are
+    /// logged as warnings after [`DEFAULT_WAVEFORM_CONFIRMATION_WINDOW`].
+    pub fn with_waveform_confirmation<F>(mut self, window: Duration, callback: F) -> Self
+    where
+        F: FnMut(&WaveformConfirmation) + Send + 'static,
+    {
+        self.confirmation_window = window;
+        self.on_waveform_confirmation = Some(Box::new(callback));
+        self
+    }
+
+    /// Set the retry schedule used by [`Device::read_frame_with_reconnect`]
+    pub fn with_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = policy;
+        self
     }
 
     /// Request displayed values (current physiological data)
     ///
     /// # Arguments
     /// * `interval` - Update interval in seconds (minimum 5)
-    pub fn request_displayed_values(&mut self, interval: u16) -> Result<()> {
+    ///
+    /// Returns the interval actually requested.
+    pub fn request_displayed_values(&mut self, interval: u16) -> Result<u16> {
+        self.request_displayed_values_with_classes(interval, PHDBCL_REQ_ALL)
+    }
+
+    /// Request displayed values for a subset of physiological classes
+    ///
+    /// # Arguments
+    /// * `interval` - Update interval in seconds (minimum 5)
+    /// * `class_mask` - `PHDBCL_*` mask, e.g. `PhdbClassSet::basic().with_ext1().mask()`
+    ///
+    /// Returns the interval actually requested.
+    pub fn request_displayed_values_with_classes(
+        &mut self,
+        interval: u16,
+        class_mask: u32,
+    ) -> Result<u16> {
+        let requested = interval;
         let interval = interval.max(5); // Minimum 5 seconds
+        if interval != requested {
+            warn!(
+                "Displayed values interval raised from {} to {} seconds",
+                requested, interval
+            );
+        }
 
-        info!("Requesting displayed values every {} seconds", interval);
+        // Missing classes are simply not sent, so only warn
+        if let Some(level) = self.dri_level {
+            for feature in unsupported_classes(level, class_mask) {
+                warn!("{:?} is not available at DRI level {:?}", feature, level);
+            }
+        }
 
-        let header = create_phdb_request(
-            1, // DRI_PH_DISPL
-            interval,
-            PHDBCL_REQ_ALL,
+        info!(
+            "Requesting displayed values every {} seconds (class mask {:#06x})",
+            interval, class_mask
         );
 
-        let frame = create_frame(&header);
+        let frame = RequestBuilder::new()
+            .with_class_mask(class_mask)
+            .displayed_values(interval);
         self.write_frame(&frame)?;
 
+        self.active.displayed_interval = Some(interval);
+        self.active.class_mask = class_mask;
+        self.last_displayed_request = Instant::now();
+        Ok(interval)
+    }
+
+    /// Request 10-second trended values
+    pub fn request_trend_10s(&mut self) -> Result<()> {
+        info!("Requesting 10-second trend values");
+
+        self.write_frame(&RequestBuilder::new().trend_10s())?;
+
+        self.active.trend_10s = true;
         Ok(())
     }
 
@@ -77,15 +403,9 @@ impl SerialDevice {
     pub fn request_trend_60s(&mut self) -> Result<()> {
         info!("Requesting 60-second trend values");
 
-        let header = create_phdb_request(
-            3, // DRI_PH_60S_TREND
-            1, // Interval (positive, but exact value doesn't matter for trends)
-            PHDBCL_REQ_ALL,
-        );
-
-        let frame = create_frame(&header);
-        self.write_frame(&frame)?;
+        self.write_frame(&RequestBuilder::new().trend_60s())?;
 
+        self.active.trend_60s = true;
         Ok(())
     }
 
@@ -96,12 +416,17 @@ impl SerialDevice {
     ///
     /// # Sample Rate Limit
     /// Total sample rate must not exceed 600 samples/second
-    pub fn request_waveforms(&mut self, waveform_names: &[&str]) -> Result<()> {
+    ///
+    /// Unknown names are skipped; returns the waveforms actually requested.
+    pub fn request_waveforms(&mut self, waveform_names: &[&str]) -> Result<Vec<WaveformType>> {
         // Convert names to WaveformType
-        let waveforms: Vec<WaveformType> = waveform_names
-            .iter()
-            .filter_map(|name| self.parse_waveform_name(name))
-            .collect();
+        let mut waveforms = Vec::new();
+        for name in waveform_names {
+            match self.parse_waveform_name(name) {
+                Some(wf) => waveforms.push(wf),
+                None => warn!("Unknown waveform {:?} skipped", name),
+            }
+        }
 
         if waveforms.is_empty() {
             anyhow::bail!("No valid waveforms specified");
@@ -110,26 +435,65 @@ impl SerialDevice {
         // Validate sample rate
         crate::constants::waveforms::validate_waveform_set(&waveforms)?;
 
-        info!("Requesting waveforms: {:?}", waveform_names);
+        if let Some(level) = self.dri_level {
+            let unsupported = unsupported_waveforms(level, &waveforms);
+            if !unsupported.is_empty() {
+                anyhow::bail!(
+                    "Waveforms {:?} need a newer DRI level than the monitor's {:?}",
+                    unsupported,
+                    level
+                );
+            }
+        }
 
-        // Convert to u8 values
-        let waveform_types: Vec<u8> = waveforms.iter().map(|wf| *wf as u8).collect();
+        info!("Requesting waveforms: {:?}", waveforms);
 
-        let header = create_waveform_request(&waveform_types, WF_REQ_CONT_START);
-        let frame = create_frame(&header);
-        self.write_frame(&frame)?;
+        self.write_frame(&RequestBuilder::new().waveforms(&waveforms))?;
 
-        Ok(())
+        self.active.waveforms = waveforms.clone();
+        self.waveform_watch = Some(WaveformWatch {
+            requested: waveforms.clone(),
+            seen: Vec::new(),
+            deadline: Instant::now() + self.confirmation_window,
+        });
+        Ok(waveforms)
     }
 
     /// Stop waveform transmission
     pub fn stop_waveforms(&mut self) -> Result<()> {
         info!("Stopping waveform transmission");
 
-        let header = create_waveform_request(&[], WF_REQ_CONT_STOP);
-        let frame = create_frame(&header);
+        self.write_frame(&RequestBuilder::new().stop_waveforms())?;
+
+        self.active.waveforms.clear();
+        Ok(())
+    }
+
+    /// Stop displayed values, leaving trends and waveforms running
+    ///
+    /// Keepalive and reconnects no longer re-send the request.
+    pub fn stop_displayed_values(&mut self) -> Result<()> {
+        info!("Stopping displayed values");
+
+        let frame = RequestBuilder::new().stop_physiological(PhdbSubrecordType::Displ);
         self.write_frame(&frame)?;
 
+        self.active.displayed_interval = None;
+        self.active.class_mask = 0;
+        Ok(())
+    }
+
+    /// Stop 10-second and 60-second trends
+    pub fn stop_trends(&mut self) -> Result<()> {
+        info!("Stopping trend values");
+
+        let requests = RequestBuilder::new();
+        for subtype in [PhdbSubrecordType::Trend10s, PhdbSubrecordType::Trend60s] {
+            self.write_frame(&requests.stop_physiological(subtype))?;
+        }
+
+        self.active.trend_10s = false;
+        self.active.trend_60s = false;
         Ok(())
     }
 
@@ -137,122 +501,599 @@ impl SerialDevice {
     pub fn stop_all(&mut self) -> Result<()> {
         info!("Stopping all data transmission");
 
-        // Stop displayed values
-        let header = create_phdb_request(1, 0, 0);
-        let frame = create_frame(&header);
-        self.write_frame(&frame)?;
-
-        // Stop trends
-        let header = create_phdb_request(3, 0, 0);
-        let frame = create_frame(&header);
-        self.write_frame(&frame)?;
-
-        // Stop waveforms
+        self.stop_displayed_values()?;
+        self.stop_trends()?;
         self.stop_waveforms()?;
 
+        self.active = ActiveRequests::default();
         Ok(())
     }
 
     /// Read one complete frame from the device
     ///
-    /// This will block until a complete frame is received or timeout occurs
+    /// This will block until a complete frame is received or timeout occurs.
+    /// When one read yields several frames, the others are returned by the
+    /// following calls before reading again. A frame with a bad checksum
+    /// is returned as an error in its place.
     pub fn read_frame(&mut self) -> Result<DriFrame> {
-        let mut buffer = [0u8; 2048];
-
         loop {
-            match self.port.read(&mut buffer) {
+            if let Some(frame) = self.poll_frame()? {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Read one complete frame, or `None` if the read timed out first
+    ///
+    /// Like [`read_frame`](Self::read_frame), but returns after at most one
+    /// read, so the caller can do periodic work while the monitor is silent.
+    pub fn poll_frame(&mut self) -> Result<Option<DriFrame>> {
+        if self.pending.is_empty() {
+            if self.stop_requested() {
+                return Err(
+                    std::io::Error::new(std::io::ErrorKind::Interrupted, "Stop requested").into(),
+                );
+            }
+
+            self.send_keepalive_if_due()?;
+
+            match self.read_chunk() {
+                Ok(0) => {}
                 Ok(bytes_read) => {
-                    if bytes_read == 0 {
-                        continue;
-                    }
+                    debug!(bytes_read; "Read from transport");
+                    let frames = self.parser.process_bytes(&self.read_buffer[..bytes_read]);
+                    self.pending.extend(frames);
+                }
+                // Timeout is normal
+                Err(ref e) if is_timeout(e) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
 
-                    debug!("Read {} bytes from serial port", bytes_read);
+        match self.pending.pop_front() {
+            Some(frame) => {
+                let frame = frame?;
+                self.frame_received(&frame);
+                self.check_waveform_watch(Some(&frame));
+                Ok(Some(frame))
+            }
+            None => {
+                self.check_waveform_watch(None);
+                Ok(None)
+            }
+        }
+    }
 
-                    let frames = self.parser.process_bytes(&buffer[..bytes_read])?;
+    /// Read from the transport into the read buffer, counting full reads
+    fn read_chunk(&mut self) -> std::io::Result<usize> {
+        let bytes_read = self.transport.read(&mut self.read_buffer)?;
+        if bytes_read == self.read_buffer.len() {
+            self.full_reads += 1;
+            debug!(
+                buffer_size = bytes_read,
+                full_reads = self.full_reads;
+                "Read filled the buffer"
+            );
+        }
+        Ok(bytes_read)
+    }
 
-                    if !frames.is_empty() {
-                        return Ok(frames[0].clone());
-                    }
+    /// Number of reads that filled the read buffer completely
+    ///
+    /// A steadily growing count means data arrives faster than it is read;
+    /// consider a larger [`SerialConfig::read_buffer_size`].
+    pub fn full_reads(&self) -> u64 {
+        self.full_reads
+    }
+
+    /// Requests currently in effect on the monitor
+    pub fn active_requests(&self) -> &ActiveRequests {
+        &self.active
+    }
+
+    /// DRI level of the monitor, known once a frame has been received
+    pub fn dri_level(&self) -> Option<DriLevel> {
+        self.dri_level
+    }
+
+    /// When the link was opened, or last reconnected
+    pub fn connected_at(&self) -> DateTime<Utc> {
+        self.connected.at
+    }
+
+    /// Time since the link was opened, or last reconnected
+    pub fn uptime(&self) -> Duration {
+        self.connected.instant.elapsed()
+    }
+
+    /// When the last frame with a valid checksum was received
+    pub fn last_frame_at(&self) -> Option<DateTime<Utc>> {
+        self.last_frame.map(|time| time.at)
+    }
+
+    /// Time since the last valid frame, or since connecting if none arrived
+    ///
+    /// A port can stay open while the monitor has stopped sending (cable
+    /// pulled on a USB adapter, monitor rebooted); a watchdog comparing
+    /// this to a few update intervals can call [`Device::reconnect`] long
+    /// before reads fail. Measured on the monotonic clock.
+    pub fn time_since_last_frame(&self) -> Duration {
+        self.last_frame.unwrap_or(self.connected).instant.elapsed()
+    }
+
+    /// Bookkeeping for every frame taken off the link
+    fn frame_received(&mut self, frame: &DriFrame) {
+        self.last_frame = Some(LinkTime::now());
+        self.observe_level(frame);
+    }
+
+    /// Capture the DRI level from the first received header
+    ///
+    /// Requests sent before the level was known are checked here and
+    /// reported as warnings, since they cannot be rejected any more.
+    fn observe_level(&mut self, frame: &DriFrame) {
+        if self.dri_level.is_some() {
+            return;
+        }
+        let Some(level) = frame.data.get(3).copied().and_then(DriLevel::from_u8) else {
+            return;
+        };
+
+        info!("Monitor DRI level: {:?} ({})", level, level.year_str());
+        self.dri_level = Some(level);
+
+        let mut unsupported = unsupported_waveforms(level, &self.active.waveforms);
+        if self.active.displayed_interval.is_some() {
+            unsupported.extend(unsupported_classes(level, self.active.class_mask));
+        }
+        for feature in unsupported {
+            warn!(
+                "Requested {:?} is not available at DRI level {:?}, expect no data",
+                feature, level
+            );
+        }
+    }
+
+    /// Track requested waveforms in `frame` and report once confirmed
+    fn check_waveform_watch(&mut self, frame: Option<&DriFrame>) {
+        let Some(watch) = &mut self.waveform_watch else {
+            return;
+        };
+
+        if let Some(header) = frame.and_then(|frame| DriHeader::parse(&frame.data).ok())
+            && header.r_maintype == DriMainType::Wave
+        {
+            for waveform in header
+                .subrecords
+                .iter()
+                .filter_map(|sr| WaveformType::from_u8(sr.sr_type))
+            {
+                if watch.requested.contains(&waveform) && !watch.seen.contains(&waveform) {
+                    watch.seen.push(waveform);
+                }
+            }
+        }
+
+        if watch.seen.len() < watch.requested.len() && Instant::now() < watch.deadline {
+            return;
+        }
+
+        let (received, missing) = watch
+            .requested
+            .iter()
+            .partition(|waveform| watch.seen.contains(waveform));
+        let confirmation = WaveformConfirmation { received, missing };
+        self.waveform_watch = None;
+
+        match &mut self.on_waveform_confirmation {
+            Some(callback) => callback(&confirmation),
+            None => {
+                for waveform in &confirmation.missing {
+                    warn!(waveform = waveform.name(); "Requested waveform not received");
+                }
+            }
+        }
+    }
+
+    /// Send the active requests again
+    ///
+    /// Used after reconnecting; also useful as a keepalive for monitors
+    /// that stop transmitting unless requests are refreshed.
+    pub fn resend_active_requests(&mut self) -> Result<()> {
+        let active = self.active.clone();
+
+        if let Some(interval) = active.displayed_interval {
+            self.request_displayed_values_with_classes(interval, active.class_mask)?;
+        }
+        if active.trend_10s {
+            self.request_trend_10s()?;
+        }
+        if active.trend_60s {
+            self.request_trend_60s()?;
+        }
+        if !active.waveforms.is_empty() {
+            let names: Vec<&str> = active.waveforms.iter().map(|wf| wf.name()).collect();
+            self.request_waveforms(&names)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read one complete frame, reconnecting on transport failures
+    ///
+    /// On a non-timeout IO error the transport is closed and reopened
+    /// following the device's [`ReconnectPolicy`], then the active requests
+    /// (displayed values, trends, waveforms) are sent again. Fails once all
+    /// attempts are exhausted or if the transport cannot be reopened.
+    pub fn read_frame_with_reconnect(&mut self) -> Result<DriFrame> {
+        loop {
+            if let Some(frame) = self.poll_frame_with_reconnect()? {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// [`poll_frame`](Self::poll_frame), reconnecting like
+    /// [`read_frame_with_reconnect`](Self::read_frame_with_reconnect)
+    pub fn poll_frame_with_reconnect(&mut self) -> Result<Option<DriFrame>> {
+        loop {
+            match self.poll_frame() {
+                Ok(frame) => return Ok(frame),
+                Err(e) if self.stop_requested() => return Err(e),
+                Err(e) if e.downcast_ref::<std::io::Error>().is_some() => {
+                    warn!(error:% = e; "Transport error, reconnecting");
+                    self.reconnect()?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn stop_requested(&self) -> bool {
+        self.stop_flag
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::SeqCst))
+    }
+
+    /// Reopen the transport and re-issue the active requests
+    pub fn reconnect(&mut self) -> Result<()> {
+        let policy = self.reconnect_policy.clone();
+
+        for attempt in 0..policy.max_retries {
+            thread::sleep(policy.delay(attempt));
+
+            match self.transport.reopen() {
+                Ok(()) => {
+                    info!(attempts = attempt + 1; "Reconnected");
+                    self.connected = LinkTime::now();
+                    self.parser.reset();
+                    self.transport.set_timeout(self.config.timeout)?;
+                    self.resend_active_requests()?;
+                    return Ok(());
                 }
-                Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {
-                    // Timeout is normal, just continue
-                    continue;
+                Err(e) if e.kind() == std::io::ErrorKind::Unsupported => {
+                    anyhow::bail!("Transport does not support reconnecting");
                 }
                 Err(e) => {
-                    return Err(e.into());
+                    warn!(attempt = attempt + 1, error:% = e; "Reconnect attempt failed");
                 }
             }
         }
+
+        anyhow::bail!("Failed to reconnect after {} attempts", policy.max_retries)
     }
 
     /// Try to read a frame without blocking (non-blocking read)
     pub fn try_read_frame(&mut self) -> Result<Option<DriFrame>> {
-        let mut buffer = [0u8; 2048];
+        if let Some(frame) = self.pending.pop_front() {
+            let frame = frame?;
+            self.frame_received(&frame);
+            return Ok(Some(frame));
+        }
 
         // Set a very short timeout for non-blocking behavior
-        self.port.set_timeout(Duration::from_millis(10))?;
+        self.transport.set_timeout(Duration::from_millis(10))?;
+        let result = self.read_chunk();
+
+        // Restore the configured timeout for subsequent blocking reads
+        self.transport.set_timeout(self.config.timeout)?;
 
-        match self.port.read(&mut buffer) {
+        match result {
             Ok(bytes_read) => {
                 if bytes_read == 0 {
                     return Ok(None);
                 }
 
-                let frames = self.parser.process_bytes(&buffer[..bytes_read])?;
+                let mut frames = self
+                    .parser
+                    .process_bytes(&self.read_buffer[..bytes_read])
+                    .into_iter();
+                let frame = frames.next().transpose();
+                self.pending.extend(frames);
+                let frame = frame?;
+                if let Some(frame) = &frame {
+                    self.frame_received(frame);
+                }
 
-                Ok(frames.into_iter().next())
+                Ok(frame)
             }
-            Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => Ok(None),
+            Err(ref e) if is_timeout(e) => Ok(None),
             Err(e) => Err(e.into()),
         }
     }
 
-    /// Write a frame to the device
-    fn write_frame(&mut self, frame: &[u8]) -> Result<()> {
-        debug!("Writing {} bytes to serial port", frame.len());
-        self.port.write_all(frame)?;
-        self.port.flush()?;
+    /// Check that a DRI monitor answers on this link
+    ///
+    /// Sends a displayed values request and waits up to `timeout` for the
+    /// first frame with a valid checksum and header. Displayed values are
+    /// then stopped if they were not requested before, and the active
+    /// requests are sent again, as the probe request replaced their interval.
+    pub fn probe(&mut self, timeout: Duration) -> Result<ProbeOutcome> {
+        info!(timeout:?; "Probing for DRI traffic");
+        self.write_frame(&RequestBuilder::new().displayed_values(5))?;
+
+        let deadline = Instant::now() + timeout;
+        let mut bytes = 0;
+        let mut checksum_errors = 0;
+        let mut outcome = None;
+
+        while outcome.is_none() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            self.transport
+                .set_timeout(remaining.min(self.config.timeout))?;
+
+            let bytes_read = match self.read_chunk() {
+                Ok(0) => continue,
+                Ok(n) => n,
+                Err(ref e) if is_timeout(e) => continue,
+                Err(e) => {
+                    self.transport.set_timeout(self.config.timeout)?;
+                    return Err(e.into());
+                }
+            };
+            bytes += bytes_read;
+
+            let mut results = self
+                .parser
+                .process_bytes(&self.read_buffer[..bytes_read])
+                .into_iter();
+            for result in results.by_ref() {
+                match result {
+                    Ok(frame) => {
+                        if let Ok(header) = DriHeader::parse(&frame.data) {
+                            self.frame_received(&frame);
+                            outcome = Some(ProbeOutcome::Dri {
+                                dri_level: header.dri_level,
+                                maintype: header.r_maintype,
+                                checksum_errors,
+                            });
+                            break;
+                        }
+                    }
+                    Err(DriError::ChecksumError) => checksum_errors += 1,
+                    Err(_) => {}
+                }
+            }
+            // Frames read along with the answer are left for read_frame
+            self.pending.extend(results);
+        }
+        self.transport.set_timeout(self.config.timeout)?;
+
+        if self.active.displayed_interval.is_none() {
+            self.write_frame(&RequestBuilder::new().stop_physiological(PhdbSubrecordType::Displ))?;
+        }
+        self.resend_active_requests()?;
+
+        let outcome = outcome.unwrap_or(if checksum_errors > 0 {
+            ProbeOutcome::BadChecksums { checksum_errors }
+        } else {
+            ProbeOutcome::NoTraffic { bytes }
+        });
+        info!(outcome:%; "Probe result");
+        Ok(outcome)
+    }
+
+    /// Find out which classes and waveforms the monitor actually sends
+    ///
+    /// Requests displayed values of every class, then every waveform the
+    /// DRI level allows, in batches within the sample rate limit, listening
+    /// for `step` after each request (so at least one displayed values
+    /// interval of 5 s). Takes `step` times the number of batches plus one.
+    /// The active requests are restored afterwards.
+    pub fn discover(&mut self, step: Duration) -> Result<Capabilities> {
+        let mut capabilities = Capabilities::default();
+        let mut decoder = Decoder::new();
+
+        info!(step:?; "Discovering monitor capabilities");
+        self.write_frame(
+            &RequestBuilder::new()
+                .with_class_mask(PHDBCL_REQ_ALL)
+                .displayed_values(5),
+        )?;
+        self.listen(step, &mut decoder, &mut capabilities)?;
+
+        let candidates: Vec<WaveformType> = WaveformType::ALL
+            .iter()
+            .copied()
+            .filter(|&wf| {
+                self.dri_level
+                    .is_none_or(|level| level.supports(DriFeature::Waveform(wf)))
+            })
+            .collect();
+        for batch in discovery_batches(&candidates) {
+            debug!(batch:?; "Discovery: requesting waveforms");
+            self.write_frame(&RequestBuilder::new().waveforms(&batch))?;
+            self.listen(step, &mut decoder, &mut capabilities)?;
+        }
+
+        let requests = RequestBuilder::new();
+        self.write_frame(&requests.stop_waveforms())?;
+        self.write_frame(&requests.stop_physiological(PhdbSubrecordType::Displ))?;
+        self.resend_active_requests()?;
+
+        capabilities.dri_level = self.dri_level;
+        capabilities.classes.sort_by_key(|&class| class as u8);
+        capabilities
+            .waveforms
+            .sort_by_key(|wf| candidates.iter().position(|c| c == wf));
+        info!(capabilities:%; "Discovery result");
+        Ok(capabilities)
+    }
+
+    /// Record what arrives during `duration` into `capabilities`
+    fn listen(
+        &mut self,
+        duration: Duration,
+        decoder: &mut Decoder,
+        capabilities: &mut Capabilities,
+    ) -> Result<()> {
+        let deadline = Instant::now() + duration;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || self.stop_requested() {
+                break;
+            }
+            self.transport
+                .set_timeout(remaining.min(self.config.timeout))?;
+            let bytes_read = match self.read_chunk() {
+                Ok(0) => continue,
+                Ok(n) => n,
+                Err(ref e) if is_timeout(e) => continue,
+                Err(e) => {
+                    self.transport.set_timeout(self.config.timeout)?;
+                    return Err(e.into());
+                }
+            };
+
+            let frames = self.parser.process_bytes(&self.read_buffer[..bytes_read]);
+            for frame in frames.into_iter().flatten() {
+                self.frame_received(&frame);
+                for record in decoder.decode_bytes_all(&frame.data).unwrap_or_default() {
+                    match record {
+                        DriRecord::Physiological(phys) => {
+                            if !capabilities.classes.contains(&phys.class) {
+                                capabilities.classes.push(phys.class);
+                            }
+                        }
+                        DriRecord::Waveform { waveforms } => {
+                            for wf in waveforms {
+                                if !capabilities.waveforms.contains(&wf.waveform_type) {
+                                    capabilities.waveforms.push(wf.waveform_type);
+                                }
+                            }
+                        }
+                        DriRecord::Aux(_) | DriRecord::Event(_) | DriRecord::Network(_) => {}
+                    }
+                }
+            }
+        }
+
+        self.transport.set_timeout(self.config.timeout)?;
         Ok(())
     }
 
-    /// Parse waveform name to WaveformType
-    fn parse_waveform_name(&self, name: &str) -> Option<WaveformType> {
-        match name.to_uppercase().as_str() {
-            "ECG1" => Some(WaveformType::Ecg1),
-            "ECG2" => Some(WaveformType::Ecg2),
-            "ECG3" => Some(WaveformType::Ecg3),
-            "PLETH" => Some(WaveformType::Pleth),
-            "PLETH2" => Some(WaveformType::Pleth2),
-            "CO2" => Some(WaveformType::Co2),
-            "O2" => Some(WaveformType::O2),
-            "N2O" => Some(WaveformType::N2o),
-            "AA" => Some(WaveformType::Aa),
-            "INVP1" => Some(WaveformType::Invp1),
-            "INVP2" => Some(WaveformType::Invp2),
-            "INVP3" => Some(WaveformType::Invp3),
-            "INVP4" => Some(WaveformType::Invp4),
-            "INVP5" => Some(WaveformType::Invp5),
-            "INVP6" => Some(WaveformType::Invp6),
-            "INVP7" => Some(WaveformType::Invp7),
-            "INVP8" => Some(WaveformType::Invp8),
-            "AWP" => Some(WaveformType::Awp),
-            "FLOW" => Some(WaveformType::Flow),
-            "RESP" => Some(WaveformType::Resp),
-            "EEG1" => Some(WaveformType::Eeg1),
-            "EEG2" => Some(WaveformType::Eeg2),
-            "EEG3" => Some(WaveformType::Eeg3),
-            "EEG4" => Some(WaveformType::Eeg4),
-            "ENT_100" | "ENT100" => Some(WaveformType::Ent100),
-            _ => {
-                warn!("Unknown waveform name: {}", name);
-                None
+    /// Iterate over decoded records
+    ///
+    /// Encapsulates the read → header parse → data extraction → decode
+    /// pipeline. Frames that fail to parse or decode are yielded as `Err`
+    /// items and iteration continues; frames carrying no decodable data are
+    /// skipped. Iteration ends after a transport error (e.g. the port was
+    /// disconnected), which is yielded as the last item.
+    pub fn records(&mut self) -> Records<'_, T> {
+        Records {
+            device: self,
+            decoder: Decoder::new(),
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    /// Re-send the displayed values request if the keepalive interval elapsed
+    fn send_keepalive_if_due(&mut self) -> Result<()> {
+        let (Some(keepalive), Some(interval)) = (self.keepalive, self.active.displayed_interval)
+        else {
+            return Ok(());
+        };
+
+        if self.last_displayed_request.elapsed() >= keepalive {
+            debug!("Keepalive: refreshing displayed values request");
+            self.request_displayed_values_with_classes(interval, self.active.class_mask)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a request, retrying while the transport times out
+    ///
+    /// A timeout means the port accepted no data in time, typically while
+    /// hardware flow control holds it off; the write is retried with
+    /// backoff and fails with [`DriError::WriteStalled`] once retries run
+    /// out. Any other error means the link is gone and is returned as the
+    /// IO error, which [`read_frame_with_reconnect`](Self::read_frame_with_reconnect)
+    /// reacts to. A frame cut short by a timeout is sent again in full;
+    /// the monitor resynchronizes on the next frame character.
+    fn write_frame(&mut self, frame: &[u8]) -> Result<()> {
+        debug!(bytes = frame.len(); "Writing to transport");
+        // Serial ports share one timeout between reads and writes
+        let swap_timeout = self.config.write_timeout != self.config.timeout;
+        if swap_timeout {
+            self.transport.set_timeout(self.config.write_timeout)?;
+        }
+        let result = self.write_with_retries(frame);
+        if swap_timeout {
+            self.transport.set_timeout(self.config.timeout)?;
+        }
+        result
+    }
+
+    fn write_with_retries(&mut self, frame: &[u8]) -> Result<()> {
+        let attempts = self.config.write_retries + 1;
+        for attempt in 0..attempts {
+            let result = self
+                .transport
+                .write_all(frame)
+                .and_then(|()| self.transport.flush());
+            match result {
+                Ok(()) => return Ok(()),
+                Err(ref e) if is_timeout(e) && attempt + 1 < attempts => {
+                    let delay = WRITE_RETRY_DELAY * 2u32.saturating_pow(attempt);
+                    warn!(attempt = attempt + 1, delay:?; "Write timed out, retrying");
+                    thread::sleep(delay);
+                }
+                Err(ref e) if is_timeout(e) => break,
+                Err(e) => return Err(e.into()),
             }
         }
+        Err(DriError::WriteStalled(attempts).into())
+    }
+
+    /// Parse waveform name to WaveformType
+    fn parse_waveform_name(&self, name: &str) -> Option<WaveformType> {
+        waveform_from_name(name)
     }
 
-    /// Get port name
+    /// Get port name (or socket address for network transports)
     pub fn port_name(&self) -> Result<String> {
-        Ok(self.port.name().unwrap_or_else(|| "Unknown".to_string()))
+        Ok(self
+            .transport
+            .name()
+            .unwrap_or_else(|| "Unknown".to_string()))
+    }
+
+    /// Get the settings this device was opened with
+    pub fn config(&self) -> &SerialConfig {
+        &self.config
+    }
+
+    /// Checksum convention in use, detected if configured as `Auto`
+    ///
+    /// See [`FrameParser::checksum_mode`].
+    pub fn checksum_mode(&self) -> ChecksumMode {
+        self.parser.checksum_mode()
     }
 
     /// Clear the parser buffer (useful after errors)
@@ -261,7 +1102,127 @@ impl SerialDevice {
     }
 }
 
-impl Drop for SerialDevice {
+/// Iterator over decoded records, created by [`Device::records`]
+pub struct Records<'a, T: Transport> {
+    device: &'a mut Device<T>,
+    decoder: Decoder,
+    pending: VecDeque<DriRecord>,
+    finished: bool,
+}
+
+impl<T: Transport> Iterator for Records<'_, T> {
+    type Item = Result<DriRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            if let Some(record) = self.pending.pop_front() {
+                return Some(Ok(record));
+            }
+
+            let frame = match self.device.read_frame() {
+                Ok(frame) => frame,
+                Err(e) => {
+                    // Transport failures are not recoverable by reading on
+                    if e.downcast_ref::<std::io::Error>().is_some() {
+                        self.finished = true;
+                    }
+                    return Some(Err(e));
+                }
+            };
+
+            match self.decoder.decode_bytes_all(&frame.data) {
+                Ok(records) => {
+                    let received_at = Utc::now();
+                    self.pending.extend(records.into_iter().map(|mut record| {
+                        record.mark_received(received_at);
+                        record
+                    }));
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        None
+    }
+}
+
+/// Split waveforms into requests of at most 8 within the sample rate limit
+fn discovery_batches(waveforms: &[WaveformType]) -> Vec<Vec<WaveformType>> {
+    let mut batches: Vec<Vec<WaveformType>> = Vec::new();
+    for &waveform in waveforms {
+        match batches.last_mut() {
+            Some(batch)
+                if batch.len() < MAX_SUBRECORDS
+                    && calculate_total_sample_rate(batch)
+                        + calculate_total_sample_rate(&[waveform])
+                        <= MAX_TOTAL_SAMPLE_RATE =>
+            {
+                batch.push(waveform)
+            }
+            _ => batches.push(vec![waveform]),
+        }
+    }
+    batches
+}
+
+/// Parse a waveform name (e.g. "ECG1", case-insensitive) to WaveformType
+pub(crate) fn waveform_from_name(name: &str) -> Option<WaveformType> {
+    match name.to_uppercase().as_str() {
+        "ECG1" => Some(WaveformType::Ecg1),
+        "ECG2" => Some(WaveformType::Ecg2),
+        "ECG3" => Some(WaveformType::Ecg3),
+        "PLETH" => Some(WaveformType::Pleth),
+        "PLETH2" => Some(WaveformType::Pleth2),
+        "CO2" => Some(WaveformType::Co2),
+        "O2" => Some(WaveformType::O2),
+        "N2O" => Some(WaveformType::N2o),
+        "AA" => Some(WaveformType::Aa),
+        "INVP1" => Some(WaveformType::Invp1),
+        "INVP2" => Some(WaveformType::Invp2),
+        "INVP3" => Some(WaveformType::Invp3),
+        "INVP4" => Some(WaveformType::Invp4),
+        "INVP5" => Some(WaveformType::Invp5),
+        "INVP6" => Some(WaveformType::Invp6),
+        "INVP7" => Some(WaveformType::Invp7),
+        "INVP8" => Some(WaveformType::Invp8),
+        "AWP" => Some(WaveformType::Awp),
+        "FLOW" => Some(WaveformType::Flow),
+        "RESP" => Some(WaveformType::Resp),
+        "EEG1" => Some(WaveformType::Eeg1),
+        "EEG2" => Some(WaveformType::Eeg2),
+        "EEG3" => Some(WaveformType::Eeg3),
+        "EEG4" => Some(WaveformType::Eeg4),
+        "ENT_100" | "ENT100" => Some(WaveformType::Ent100),
+        _ => {
+            warn!("Unknown waveform name: {}", name);
+            None
+        }
+    }
+}
+
+/// Requested waveforms the monitor's DRI level cannot provide
+fn unsupported_waveforms(level: DriLevel, waveforms: &[WaveformType]) -> Vec<DriFeature> {
+    waveforms
+        .iter()
+        .map(|&wf| DriFeature::Waveform(wf))
+        .filter(|&feature| !level.supports(feature))
+        .collect()
+}
+
+/// Classes in a `PHDBCL_*` request mask the monitor's DRI level cannot provide
+fn unsupported_classes(level: DriLevel, class_mask: u32) -> Vec<DriFeature> {
+    [
+        (PHDBCL_REQ_EXT2_MASK, PhdbClass::Ext2),
+        (PHDBCL_REQ_EXT3_MASK, PhdbClass::Ext3),
+    ]
+    .into_iter()
+    .filter(|&(mask, _)| class_mask & mask != 0)
+    .map(|(_, class)| DriFeature::Class(class))
+    .filter(|&feature| !level.supports(feature))
+    .collect()
+}
+
+impl<T: Transport> Drop for Device<T> {
     fn drop(&mut self) {
         info!("Closing serial device");
         let _ = self.stop_all();
@@ -271,16 +1232,83 @@ impl Drop for SerialDevice {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::protocol::framing::create_frame;
+    use crate::protocol::header::{create_phdb_request, create_waveform_request};
+    use crate::protocol::request::WF_REQ_CONT_START;
+    use std::io::{self, Read, Write};
+
+    /// In-memory transport: reads from a queue of chunks, records writes.
+    /// Once the queue is drained, reads fail as if the link was lost.
+    #[derive(Default)]
+    struct MockTransport {
+        incoming: Vec<Vec<u8>>,
+        written: Vec<u8>,
+        /// Writes that time out before the next one succeeds
+        write_stalls: u32,
+        /// Chunks available after the next reopen
+        after_reopen: Vec<Vec<u8>>,
+        reopen_count: u32,
+    }
+
+    impl Read for MockTransport {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.incoming.is_empty() {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+            // Chunks larger than the buffer are delivered over several reads
+            let mut chunk = self.incoming.remove(0);
+            if chunk.len() > buf.len() {
+                self.incoming.insert(0, chunk.split_off(buf.len()));
+            }
+            buf[..chunk.len()].copy_from_slice(&chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    impl Write for MockTransport {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.write_stalls > 0 {
+                self.write_stalls -= 1;
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Transport for MockTransport {
+        fn set_timeout(&mut self, _timeout: Duration) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn name(&self) -> Option<String> {
+            Some("mock".to_string())
+        }
+
+        fn reopen(&mut self) -> io::Result<()> {
+            self.reopen_count += 1;
+            self.incoming = std::mem::take(&mut self.after_reopen);
+            self.written.clear();
+            Ok(())
+        }
+    }
+
+    /// Request frames written to the transport
+    fn written_frames(written: &[u8]) -> Vec<DriFrame> {
+        FrameParser::new()
+            .process_bytes(written)
+            .into_iter()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap()
+    }
 
     #[test]
     fn test_parse_waveform_names() {
-        let device = SerialDevice {
-            port: serialport::new("dummy", 19200)
-                .timeout(Duration::from_millis(1))
-                .open()
-                .unwrap(),
-            parser: FrameParser::new(),
-        };
+        let device = Device::with_transport(MockTransport::default(), SerialConfig::default());
 
         assert_eq!(device.parse_waveform_name("ECG1"), Some(WaveformType::Ecg1));
         assert_eq!(device.parse_waveform_name("ecg1"), Some(WaveformType::Ecg1));
@@ -290,4 +1318,476 @@ mod tests {
         );
         assert_eq!(device.parse_waveform_name("INVALID"), None);
     }
+
+    #[test]
+    fn test_read_frame_over_transport() {
+        let mut transport = MockTransport::default();
+        transport.incoming.push(create_frame(&[0x01, 0x02, 0x03]));
+        let mut device = Device::with_transport(transport, SerialConfig::default());
+
+        let frame = device.read_frame().unwrap();
+        assert_eq!(frame.data, vec![0x01, 0x02, 0x03]);
+
+        device.request_displayed_values(10).unwrap();
+        assert_eq!(device.transport.written.first(), Some(&0x7E));
+    }
+
+    #[test]
+    fn test_frames_from_one_read_are_all_returned() {
+        let mut transport = MockTransport::default();
+        let mut chunk = create_frame(&[0x01, 0x02]);
+        chunk.extend(create_frame(&[0x03, 0x04]));
+        transport.incoming.push(chunk);
+        let mut device = Device::with_transport(transport, SerialConfig::default());
+
+        assert_eq!(device.read_frame().unwrap().data, vec![0x01, 0x02]);
+        assert_eq!(device.read_frame().unwrap().data, vec![0x03, 0x04]);
+        assert!(device.read_frame().is_err());
+    }
+
+    #[test]
+    fn test_bad_checksum_does_not_drop_the_rest_of_the_read() {
+        let mut transport = MockTransport::default();
+        let mut chunk = create_frame(&[0x01, 0x02]);
+        chunk.extend([0x7E, 0x03, 0x04, 0xFF, 0x7E]); // Wrong checksum
+        chunk.extend(create_frame(&[0x05, 0x06]));
+        transport.incoming.push(chunk);
+        let mut device = Device::with_transport(transport, SerialConfig::default());
+
+        assert_eq!(device.read_frame().unwrap().data, vec![0x01, 0x02]);
+        let err = device.read_frame().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DriError>(),
+            Some(DriError::ChecksumError)
+        ));
+        assert_eq!(device.read_frame().unwrap().data, vec![0x05, 0x06]);
+    }
+
+    #[test]
+    fn test_frames_and_reconnects_are_timestamped() {
+        let mut transport = MockTransport::default();
+        transport.incoming.push(create_frame(&[0x01, 0x02]));
+        transport.after_reopen.push(create_frame(&[0x03, 0x04]));
+        let policy = ReconnectPolicy::new()
+            .max_retries(1)
+            .initial_delay(Duration::ZERO);
+        let mut device = Device::with_transport(transport, SerialConfig::default())
+            .with_reconnect_policy(policy);
+        let opened_at = device.connected_at();
+        assert_eq!(device.last_frame_at(), None);
+        // No frame yet: measured from connecting (uptime is read first)
+        assert!(device.uptime() <= device.time_since_last_frame());
+
+        device.read_frame().unwrap();
+        let first_frame_at = device.last_frame_at().unwrap();
+        assert!(first_frame_at >= opened_at);
+        thread::sleep(Duration::from_millis(5));
+        assert!(device.time_since_last_frame() >= Duration::from_millis(5));
+
+        // The empty queue fails the read and triggers a reopen
+        device.read_frame_with_reconnect().unwrap();
+        assert!(device.connected_at() > opened_at);
+        assert!(device.last_frame_at().unwrap() >= device.connected_at());
+        assert!(device.time_since_last_frame() < Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_stalled_writes_are_retried() {
+        let transport = MockTransport {
+            write_stalls: 2,
+            ..MockTransport::default()
+        };
+        let mut device = Device::with_transport(transport, SerialConfig::default());
+        device.request_displayed_values(10).unwrap();
+        assert_eq!(device.transport.written.first(), Some(&0x7E));
+
+        device.transport.write_stalls = 4;
+        let err = device.request_displayed_values(10).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DriError>(),
+            Some(DriError::WriteStalled(4))
+        ));
+    }
+
+    #[test]
+    fn test_poll_frame_returns_after_a_timeout() {
+        let mut transport = MockTransport::default();
+        // An empty read stands for a timeout
+        transport.incoming.push(Vec::new());
+        transport.incoming.push(create_frame(&[0x01, 0x02]));
+        let mut device = Device::with_transport(transport, SerialConfig::default());
+
+        assert!(device.poll_frame_with_reconnect().unwrap().is_none());
+        let frame = device.poll_frame_with_reconnect().unwrap().unwrap();
+        assert_eq!(frame.data, vec![0x01, 0x02]);
+    }
+
+    #[test]
+    fn test_tcp_peer_that_never_reads_stalls_writes() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut transport =
+            TcpTransport::connect(listener.local_addr().unwrap(), Duration::from_secs(1)).unwrap();
+        let (_peer, _) = listener.accept().unwrap();
+
+        // Fill the socket buffers, as the peer never reads
+        transport.set_timeout(Duration::from_millis(50)).unwrap();
+        let chunk = [0u8; 64 * 1024];
+        while transport.write(&chunk).is_ok() {}
+
+        let config = SerialConfig::new()
+            .write_timeout(Duration::from_millis(50))
+            .write_retries(1);
+        let mut device = Device::with_transport(transport, config);
+        let err = device.request_displayed_values(10).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DriError>(),
+            Some(DriError::WriteStalled(2))
+        ));
+    }
+
+    #[test]
+    fn test_small_read_buffer_counts_full_reads() {
+        let mut transport = MockTransport::default();
+        transport.incoming.push(create_frame(&[0x01; 20]));
+        let config = SerialConfig::new().read_buffer_size(8);
+        let mut device = Device::with_transport(transport, config);
+
+        let frame = device.read_frame().unwrap();
+        assert_eq!(frame.data, vec![0x01; 20]);
+        // 23 framed bytes: two full reads, then 7 bytes
+        assert_eq!(device.full_reads(), 2);
+
+        // Non-blocking reads use the same buffer: 9 framed bytes
+        device.transport.incoming.push(create_frame(&[0x02; 6]));
+        assert!(device.try_read_frame().unwrap().is_none());
+        assert_eq!(device.full_reads(), 3);
+        assert_eq!(
+            device.try_read_frame().unwrap().unwrap().data,
+            vec![0x02; 6]
+        );
+    }
+
+    #[test]
+    fn test_probe_reports_level_after_bad_checksums() {
+        let mut header = vec![0u8; crate::constants::HEADER_SIZE];
+        header[3] = 8; // Level02
+        header[16..18].copy_from_slice(&(DriMainType::Wave as u16).to_le_bytes());
+        header[20] = 0xFF;
+
+        let mut transport = MockTransport::default();
+        transport.incoming.push(vec![0x7E, 0x01, 0x02, 0x00, 0x7E]);
+        transport.incoming.push(create_frame(&header));
+        let mut device = Device::with_transport(transport, SerialConfig::default());
+
+        let outcome = device.probe(Duration::from_secs(1)).unwrap();
+        assert_eq!(
+            outcome,
+            ProbeOutcome::Dri {
+                dri_level: DriLevel::Level02,
+                maintype: DriMainType::Wave,
+                checksum_errors: 1,
+            }
+        );
+        assert!(outcome.checksum_valid());
+        // Request, then stop since nothing else was requested
+        let frames = written_frames(&device.transport.written);
+        assert_eq!(frames.len(), 2);
+    }
+
+    #[test]
+    fn test_probe_restores_active_interval() {
+        let mut header = vec![0u8; crate::constants::HEADER_SIZE];
+        header[3] = 8;
+        header[20] = 0xFF;
+
+        let mut transport = MockTransport::default();
+        transport.incoming.push(create_frame(&header));
+        let mut device = Device::with_transport(transport, SerialConfig::default());
+        device.request_displayed_values(10).unwrap();
+
+        device.probe(Duration::from_secs(1)).unwrap();
+        // Interval request, probe request, then the interval again, no stop
+        let frames = written_frames(&device.transport.written);
+        assert_eq!(frames.len(), 3);
+        assert_ne!(frames[1].data, frames[0].data);
+        assert_eq!(frames[2].data, frames[0].data);
+    }
+
+    #[test]
+    fn test_waveform_confirmation_reports_missing_waveforms() {
+        let mut header = vec![0u8; crate::constants::HEADER_SIZE];
+        header[3] = 8;
+        header[16..18].copy_from_slice(&(DriMainType::Wave as u16).to_le_bytes());
+        header[20] = WaveformType::Ecg1 as u8;
+        header[23] = 0xFF;
+
+        let mut transport = MockTransport::default();
+        transport.incoming.push(create_frame(&header));
+        let confirmations = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = confirmations.clone();
+        let mut device = Device::with_transport(transport, SerialConfig::default())
+            .with_waveform_confirmation(Duration::ZERO, move |confirmation| {
+                seen.lock().unwrap().push(confirmation.clone())
+            });
+
+        device.request_waveforms(&["ECG1", "PLETH2"]).unwrap();
+        device.read_frame().unwrap();
+        assert_eq!(
+            *confirmations.lock().unwrap(),
+            [WaveformConfirmation {
+                received: vec![WaveformType::Ecg1],
+                missing: vec![WaveformType::Pleth2],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_discovery_batches_respect_request_limits() {
+        let batches = discovery_batches(WaveformType::ALL);
+        assert_eq!(batches.concat(), WaveformType::ALL);
+        for batch in &batches {
+            assert!(batch.len() <= MAX_SUBRECORDS);
+            assert!(crate::constants::waveforms::validate_waveform_set(batch).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_discovery_records_received_waveforms() {
+        let mut header = vec![0u8; crate::constants::HEADER_SIZE];
+        header[3] = 8;
+        header[16..18].copy_from_slice(&(DriMainType::Wave as u16).to_le_bytes());
+        header[20] = WaveformType::Pleth as u8;
+        header[23] = 0xFF;
+        let mut frame = header;
+        // Pleth subrecord: 2 samples
+        frame.extend(2u16.to_le_bytes());
+        frame.extend([0u8; 4]);
+        frame.extend([0u8; 4]);
+
+        let mut transport = MockTransport::default();
+        transport.incoming.push(create_frame(&frame));
+        let mut device = Device::with_transport(transport, SerialConfig::default());
+        let mut capabilities = Capabilities::default();
+        // The mock fails once its frames have been read
+        let _ = device.listen(
+            Duration::from_secs(1),
+            &mut Decoder::new(),
+            &mut capabilities,
+        );
+        assert_eq!(capabilities.waveforms, [WaveformType::Pleth]);
+    }
+
+    #[test]
+    fn test_dri_level_gates_waveform_requests() {
+        let mut transport = MockTransport::default();
+        // Level95 header byte
+        transport
+            .incoming
+            .push(create_frame(&[0x00, 0x00, 0x01, 0x02]));
+        let mut device = Device::with_transport(transport, SerialConfig::default());
+        assert_eq!(device.dri_level(), None);
+
+        // Unknown level: sent as requested
+        device.request_waveforms(&["EEG1"]).unwrap();
+        device.read_frame().unwrap();
+        assert_eq!(device.dri_level(), Some(DriLevel::Level95));
+
+        assert!(device.request_waveforms(&["EEG1"]).is_err());
+        device.request_waveforms(&["ECG1"]).unwrap();
+        assert_eq!(
+            unsupported_classes(DriLevel::Level95, PHDBCL_REQ_ALL),
+            vec![
+                DriFeature::Class(PhdbClass::Ext2),
+                DriFeature::Class(PhdbClass::Ext3)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_requests_return_effective_parameters() {
+        let mut device = Device::with_transport(MockTransport::default(), SerialConfig::default());
+
+        assert_eq!(device.request_displayed_values(1).unwrap(), 5);
+        assert_eq!(
+            device
+                .request_waveforms(&["ECG1", "BOGUS", "pleth"])
+                .unwrap(),
+            vec![WaveformType::Ecg1, WaveformType::Pleth]
+        );
+    }
+
+    #[test]
+    fn test_request_displayed_values_with_classes() {
+        use crate::constants::HEADER_SIZE;
+        use crate::constants::dri_types::PhdbClassSet;
+
+        let mut device = Device::with_transport(MockTransport::default(), SerialConfig::default());
+        device
+            .request_displayed_values_with_classes(10, PhdbClassSet::basic().with_ext1().mask())
+            .unwrap();
+
+        let frames = written_frames(&device.transport.written);
+        let request = &frames[0].data[HEADER_SIZE..];
+        assert_eq!(request[0], 1);
+        assert_eq!(&request[3..7], &0x0002u32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_active_requests_track_requests() {
+        use crate::constants::dri_types::PhdbClassSet;
+
+        let mut device = Device::with_transport(MockTransport::default(), SerialConfig::default());
+        assert_eq!(device.active_requests(), &ActiveRequests::default());
+
+        let mask = PhdbClassSet::basic().with_ext2().mask();
+        device
+            .request_displayed_values_with_classes(2, mask)
+            .unwrap();
+        device.request_trend_60s().unwrap();
+        device.request_waveforms(&["ECG1", "pleth"]).unwrap();
+
+        let active = device.active_requests();
+        assert_eq!(active.displayed_interval, Some(5)); // clamped to the minimum
+        assert_eq!(active.class_mask, mask);
+        assert!(!active.trend_10s);
+        assert!(active.trend_60s);
+        assert_eq!(
+            active.waveforms,
+            vec![WaveformType::Ecg1, WaveformType::Pleth]
+        );
+
+        device.transport.written.clear();
+        device.resend_active_requests().unwrap();
+        let frames = written_frames(&device.transport.written);
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].data, create_phdb_request(1, 5, mask));
+
+        device.stop_waveforms().unwrap();
+        assert!(device.active_requests().waveforms.is_empty());
+        device.stop_all().unwrap();
+        assert_eq!(device.active_requests(), &ActiveRequests::default());
+    }
+
+    #[test]
+    fn test_stopped_displayed_values_are_not_resent() {
+        let mut device = Device::with_transport(MockTransport::default(), SerialConfig::default());
+        device.request_displayed_values(10).unwrap();
+        device.request_trend_10s().unwrap();
+
+        device.stop_displayed_values().unwrap();
+        assert_eq!(device.active_requests().displayed_interval, None);
+
+        device.transport.written.clear();
+        device.resend_active_requests().unwrap();
+        let frames = written_frames(&device.transport.written);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].data, create_phdb_request(2, 1, PHDBCL_REQ_ALL));
+
+        device.stop_trends().unwrap();
+        assert_eq!(device.active_requests(), &ActiveRequests::default());
+    }
+
+    #[test]
+    fn test_keepalive_resends_displayed_values_only() {
+        let mut transport = MockTransport::default();
+        transport.incoming.push(create_frame(&[0x01]));
+        transport.incoming.push(create_frame(&[0x02]));
+
+        let mut device = Device::with_transport(transport, SerialConfig::default());
+        device.request_displayed_values(10).unwrap();
+        device.request_waveforms(&["ECG1"]).unwrap();
+
+        // Disabled by default
+        device.transport.written.clear();
+        device.read_frame().unwrap();
+        assert!(device.transport.written.is_empty());
+
+        device = device.with_keepalive(Duration::ZERO);
+        device.read_frame().unwrap();
+        let frames = written_frames(&device.transport.written);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].data, create_phdb_request(1, 10, PHDBCL_REQ_ALL));
+    }
+
+    #[test]
+    fn test_read_frame_with_reconnect_replays_requests() {
+        let mut transport = MockTransport::default();
+        transport.after_reopen.push(create_frame(&[0x01, 0x02]));
+        let policy = ReconnectPolicy::new()
+            .max_retries(2)
+            .initial_delay(Duration::ZERO);
+        let mut device = Device::with_transport(transport, SerialConfig::default())
+            .with_reconnect_policy(policy);
+        device.request_displayed_values(10).unwrap();
+        device.request_waveforms(&["ECG1"]).unwrap();
+
+        // The queue is empty: the first read fails and triggers a reopen
+        let frame = device.read_frame_with_reconnect().unwrap();
+        assert_eq!(frame.data, vec![0x01, 0x02]);
+        assert_eq!(device.transport.reopen_count, 1);
+
+        let frames = written_frames(&device.transport.written);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].data, create_phdb_request(1, 10, PHDBCL_REQ_ALL));
+        assert_eq!(
+            frames[1].data,
+            create_waveform_request(&[1], WF_REQ_CONT_START)
+        );
+    }
+
+    #[test]
+    fn test_stop_flag_interrupts_read_without_reconnect() {
+        let mut transport = MockTransport::default();
+        transport.incoming.push(create_frame(&[0x01, 0x02]));
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut device =
+            Device::with_transport(transport, SerialConfig::default()).with_stop_flag(stop.clone());
+
+        assert!(device.read_frame_with_reconnect().is_ok());
+
+        stop.store(true, Ordering::SeqCst);
+        let err = device.read_frame_with_reconnect().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<io::Error>().unwrap().kind(),
+            io::ErrorKind::Interrupted
+        );
+        assert_eq!(device.transport.reopen_count, 0);
+    }
+
+    #[test]
+    fn test_records_yields_errors_and_continues() {
+        let mut transport = MockTransport::default();
+        // Too short to carry a DRI header
+        transport.incoming.push(create_frame(&[0x01, 0x02, 0x03]));
+        // Valid header: empty waveform record, decodes to nothing
+        let mut header = vec![0u8; crate::constants::HEADER_SIZE];
+        header[3] = 8;
+        header[16] = 1;
+        header[20] = 0xFF;
+        transport.incoming.push(create_frame(&header));
+        let mut device = Device::with_transport(transport, SerialConfig::default());
+
+        let mut records = device.records();
+        assert!(records.next().unwrap().is_err());
+        // The empty record is skipped, then the lost link ends iteration
+        assert!(records.next().unwrap().is_err());
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn test_file_device_replays_captured_stream() {
+        let path = std::env::temp_dir().join(format!("dri_capture_{}.bin", std::process::id()));
+        // 0x7E in the data is stuffed on the line
+        let data = vec![0x01, 0x7E, 0x02];
+        std::fs::write(&path, create_frame(&data)).unwrap();
+
+        let mut device = FileDevice::open(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        device.request_displayed_values(10).unwrap();
+        assert_eq!(device.read_frame().unwrap().data, data);
+
+        let err = device.read_frame().unwrap_err();
+        let io_err = err.downcast_ref::<io::Error>().unwrap();
+        assert_eq!(io_err.kind(), io::ErrorKind::UnexpectedEof);
+    }
 }
@@ -0,0 +1,282 @@
+//! Byte transports carrying the DRI protocol (RS-232 or network interface)
+
+use super::SerialConfig;
+use crate::Result;
+use crate::storage::output::open_input;
+use log::info;
+use serialport::SerialPort;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::Duration;
+
+/// A bidirectional byte stream to a GE monitor
+///
+/// Framing, requests and decoding are transport-agnostic; a transport
+/// only has to move bytes and honor a read timeout. Reads that time out
+/// must fail with `ErrorKind::TimedOut` or `ErrorKind::WouldBlock`.
+pub trait Transport: Read + Write + Send {
+    /// Set the timeout applied to subsequent reads, and to writes where the
+    /// transport keeps a separate write timeout
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()>;
+
+    /// Human-readable name of the endpoint (port name or socket address)
+    fn name(&self) -> Option<String>;
+
+    /// Close and reopen the underlying connection after a failure
+    ///
+    /// Transports that cannot be reopened return `ErrorKind::Unsupported`.
+    fn reopen(&mut self) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// RS-232 transport backed by a serial port
+pub struct SerialTransport {
+    /// `None` while closed during a reopen
+    port: Option<Box<dyn SerialPort>>,
+    /// Port name and settings, if opened by name (needed to reopen)
+    settings: Option<(String, SerialConfig)>,
+}
+
+impl SerialTransport {
+    /// Open a serial port with the given settings
+    pub fn open(port_name: &str, config: &SerialConfig) -> Result<Self> {
+        let port = open_port(port_name, config)?;
+        Ok(Self {
+            port: Some(port),
+            settings: Some((port_name.to_string(), config.clone())),
+        })
+    }
+
+    /// Wrap an already opened serial port
+    pub fn from_port(port: Box<dyn SerialPort>) -> Self {
+        Self {
+            port: Some(port),
+            settings: None,
+        }
+    }
+
+    fn port(&mut self) -> io::Result<&mut Box<dyn SerialPort>> {
+        self.port
+            .as_mut()
+            .ok_or_else(|| io::ErrorKind::NotConnected.into())
+    }
+}
+
+fn open_port(port_name: &str, config: &SerialConfig) -> serialport::Result<Box<dyn SerialPort>> {
+    info!("Opening serial port: {} ({:?})", port_name, config);
+
+    let port = serialport::new(port_name, config.baud_rate)
+        .timeout(config.timeout)
+        .data_bits(config.data_bits)
+        .parity(config.parity)
+        .stop_bits(config.stop_bits)
+        .flow_control(config.flow_control)
+        .open()?;
+
+    info!("Serial port opened successfully");
+
+    Ok(port)
+}
+
+impl Read for SerialTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.port()?.read(buf)
+    }
+}
+
+impl Write for SerialTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.port()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port()?.flush()
+    }
+}
+
+impl Transport for SerialTransport {
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.port()?.set_timeout(timeout).map_err(io::Error::from)
+    }
+
+    fn name(&self) -> Option<String> {
+        match (&self.port, &self.settings) {
+            (Some(port), _) => port.name(),
+            (None, Some((port_name, _))) => Some(port_name.clone()),
+            (None, None) => None,
+        }
+    }
+
+    fn reopen(&mut self) -> io::Result<()> {
+        let Some((port_name, config)) = &self.settings else {
+            return Err(io::ErrorKind::Unsupported.into());
+        };
+
+        // Close first: the old handle may hold an exclusive lock on the port
+        self.port = None;
+        self.port = Some(open_port(port_name, config)?);
+        Ok(())
+    }
+}
+
+/// Network interface transport (e.g. a CARESCAPE gateway) over TCP
+pub struct TcpTransport {
+    stream: TcpStream,
+    peer: String,
+    addr: Option<SocketAddr>,
+    timeout: Duration,
+}
+
+impl TcpTransport {
+    /// Connect to a DRI network interface
+    pub fn connect<A: ToSocketAddrs>(addr: A, timeout: Duration) -> Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        let addr = stream.peer_addr().ok();
+        let peer = addr
+            .map(|a| a.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        info!("Connected to DRI network interface at {}", peer);
+
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        stream.set_nodelay(true)?;
+
+        Ok(Self {
+            stream,
+            peer,
+            addr,
+            timeout,
+        })
+    }
+}
+
+impl Read for TcpTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.read(buf)
+    }
+}
+
+impl Write for TcpTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl Transport for TcpTransport {
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.stream.set_read_timeout(Some(timeout))?;
+        self.stream.set_write_timeout(Some(timeout))
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.peer.clone())
+    }
+
+    fn reopen(&mut self) -> io::Result<()> {
+        let addr = self
+            .addr
+            .ok_or_else(|| io::Error::from(io::ErrorKind::Unsupported))?;
+
+        let stream = TcpStream::connect_timeout(&addr, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        stream.set_nodelay(true)?;
+        self.stream = stream;
+
+        info!("Reconnected to DRI network interface at {}", self.peer);
+        Ok(())
+    }
+}
+
+/// Captured serial stream replayed from a file
+///
+/// The file holds the bytes exactly as they came off the line (stuffed
+/// frames), not a [`RawWriter`](crate::storage::RawWriter) capture.
+/// Writes are discarded, so requests are no-ops. Reading past the end
+/// fails with `ErrorKind::UnexpectedEof`.
+pub struct FileTransport {
+    reader: Box<dyn Read + Send>,
+    path: String,
+}
+
+impl FileTransport {
+    /// Open a capture, decompressing it if the path ends in `.gz`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let reader = open_input(&path)?;
+        Ok(Self {
+            reader,
+            path: path.as_ref().display().to_string(),
+        })
+    }
+}
+
+impl Read for FileTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.reader.read(buf)? {
+            0 if !buf.is_empty() => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "End of capture",
+            )),
+            n => Ok(n),
+        }
+    }
+}
+
+impl Write for FileTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for FileTransport {
+    fn set_timeout(&mut self, _timeout: Duration) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.path.clone())
+    }
+}
+
+/// Check whether an IO error is a read timeout rather than a real failure
+pub fn is_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_tcp_transport_roundtrip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut client = TcpTransport::connect(addr, Duration::from_millis(200)).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+
+        client.write_all(&[0x7E, 0x01, 0x7E]).unwrap();
+        let mut buf = [0u8; 3];
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0x7E, 0x01, 0x7E]);
+
+        // Nothing to read: must report a timeout, not a hard error
+        let err = client.read(&mut buf).unwrap_err();
+        assert!(is_timeout(&err));
+    }
+}
@@ -8,13 +8,15 @@ pub mod constants;
 pub mod decode;
 pub mod device;
 pub mod protocol;
+#[cfg(feature = "sim")]
+pub mod sim;
 pub mod storage;
 pub mod ui;
 
 // Re-export commonly used types
 pub use constants::{DriLevel, DriMainType, SpecialValue};
 pub use decode::{PhysiologicalData, WaveformData};
-pub use device::SerialDevice;
+pub use device::{Device, SerialConfig, SerialDevice};
 pub use protocol::{DriFrame, DriHeader};
 
 /// Result type alias for this crate
@@ -29,8 +31,14 @@ pub enum DriError {
     #[error("Invalid frame: incomplete data")]
     IncompleteFrame,
 
-    #[error("Invalid frame: bad framing")]
-    FramingError,
+    /// Frame boundaries or length are wrong; carries the observed length
+    #[error("Invalid frame: bad framing ({0} bytes)")]
+    FramingError(usize),
+
+    /// Writes kept timing out, e.g. hardware flow control held off; the
+    /// link itself may still be up
+    #[error("Write stalled after {0} attempts (flow control?)")]
+    WriteStalled(u32),
 
     #[error("Unsupported DRI level: {0}")]
     UnsupportedDriLevel(u8),
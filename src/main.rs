@@ -1,50 +1,315 @@
 //! GE DRI Protocol Parser - Main Application
 
 use anyhow::Result;
-use chrono::Local;
-use ge_dri_prototype::decode::Decoder;
-use ge_dri_prototype::device::SerialDevice;
-use ge_dri_prototype::storage::{CsvWriter, JsonWriter, RawWriter};
+use chrono::{DateTime, Local, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use ge_dri_prototype::DriError;
+use ge_dri_prototype::constants::DriMainType;
+use ge_dri_prototype::decode::{
+    Decoder, DecoderOptions, DriRecord, InvalidPolicy, WaveformDecimator,
+};
+use ge_dri_prototype::device::{DEFAULT_WAVEFORM_CONFIRMATION_WINDOW, SerialConfig, SerialDevice};
+use ge_dri_prototype::protocol::ChecksumMode;
+use ge_dri_prototype::storage::{
+    CsvWriter, EventFormat, EventWriter, JsonFormat, JsonWriter, MultiSink, RawFormat, RawReader,
+    RawWriter, RecordSink, RotationPolicy, SessionReport, SessionStats,
+};
+#[cfg(feature = "mqtt")]
+use ge_dri_prototype::storage::{MqttPublisher, MqttQos};
 use ge_dri_prototype::ui;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Default displayed values interval (seconds)
+const DEFAULT_INTERVAL: u16 = 10;
+
+/// Default waveforms to collect
+const DEFAULT_WAVEFORMS: &[&str] = &["ECG1", "PLETH"];
+
+/// How long `--probe` waits for a valid frame (displayed values come every 5 s)
+const PROBE_TIMEOUT: Duration = Duration::from_secs(12);
+
+/// How long `--discover` listens after each request
+const DISCOVERY_STEP: Duration = Duration::from_secs(6);
+
+#[derive(Parser)]
+#[command(name = "GE DRI Collector")]
+#[command(about = "Collects physiological data and waveforms from a GE monitor")]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Serial port connected to the monitor (skips port selection)
+    #[arg(short, long)]
+    port: Option<String>,
+
+    /// Displayed values update interval in seconds (5-3600)
+    #[arg(short, long, value_parser = clap::value_parser!(u16).range(5..=3600))]
+    interval: Option<u16>,
+
+    /// Waveforms to collect, comma-separated (e.g. ECG1,PLETH,CO2)
+    #[arg(short, long, value_delimiter = ',')]
+    waveforms: Option<Vec<String>>,
+
+    /// Prefix for the output files [default: output_<timestamp>]
+    #[arg(short, long)]
+    output_prefix: Option<String>,
+
+    /// Append to existing output files instead of overwriting them
+    /// (JSON is then written one record per line)
+    #[arg(long, requires = "output_prefix")]
+    append: bool,
+
+    /// Write JSON vitals as `{"value": .., "unit": ..}` objects
+    #[arg(long, global = true)]
+    json_units: bool,
+
+    /// Start a new CSV/raw file segment once the current one reaches this size (MiB)
+    #[arg(long)]
+    rotate_mb: Option<u64>,
+
+    /// Start a new CSV/raw file segment every hour
+    #[arg(long)]
+    rotate_hourly: bool,
+
+    /// Store a receive timestamp with every frame of the raw capture
+    #[arg(long)]
+    raw_timestamps: bool,
+
+    /// How invalid vitals are written: empty (none), NaN, or the raw sentinel code
+    #[arg(long, global = true, value_enum, default_value_t = InvalidValues::None)]
+    invalid_values: InvalidValues,
+
+    /// Checksum convention of the monitor's frames; `auto` detects it
+    #[arg(long, global = true, value_enum, default_value_t = Checksum::Unstuffed)]
+    checksum: Checksum,
+
+    /// Keep only one waveform sample in N in the CSV output (min/max preserving)
+    #[arg(long, global = true, value_parser = clap::value_parser!(u16).range(1..))]
+    decimate: Option<u16>,
+
+    /// Also publish every record to this MQTT broker (host:port)
+    #[cfg(feature = "mqtt")]
+    #[arg(long)]
+    mqtt: Option<String>,
+
+    /// Wait for the broker to acknowledge each MQTT message (QoS 1)
+    #[cfg(feature = "mqtt")]
+    #[arg(long, requires = "mqtt")]
+    mqtt_ack: bool,
+
+    /// MQTT keep-alive interval in seconds (0 disables it)
+    #[cfg(feature = "mqtt")]
+    #[arg(long, requires = "mqtt", default_value_t = 0)]
+    mqtt_keep_alive: u16,
+
+    /// Never prompt: auto-detect the port and use defaults for missing options
+    #[arg(long)]
+    non_interactive: bool,
+
+    /// Check that a DRI monitor answers on the port, then exit
+    #[arg(long)]
+    probe: bool,
+
+    /// List the classes and waveforms the monitor sends, then exit
+    #[arg(long)]
+    discover: bool,
+}
+
+/// Command-line names for [`InvalidPolicy`]
+#[derive(Clone, Copy, ValueEnum)]
+enum InvalidValues {
+    None,
+    Nan,
+    Sentinel,
+}
+
+impl InvalidValues {
+    fn decoder(self) -> Decoder {
+        let policy = match self {
+            InvalidValues::None => InvalidPolicy::None,
+            InvalidValues::Nan => InvalidPolicy::NaN,
+            InvalidValues::Sentinel => InvalidPolicy::Sentinel,
+        };
+        Decoder::new_with_options(DecoderOptions::new().invalid_policy(policy))
+    }
+}
+
+/// Command-line names for [`ChecksumMode`]
+#[derive(Clone, Copy, ValueEnum)]
+enum Checksum {
+    Unstuffed,
+    Stuffed,
+    Auto,
+}
+
+impl From<Checksum> for ChecksumMode {
+    fn from(checksum: Checksum) -> Self {
+        match checksum {
+            Checksum::Unstuffed => ChecksumMode::Unstuffed,
+            Checksum::Stuffed => ChecksumMode::Stuffed,
+            Checksum::Auto => ChecksumMode::Auto,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Decode a raw capture offline into the same CSV/JSON as a live session
+    Decode {
+        /// Raw capture written by a live session (`.raw` or `.raw.gz`)
+        input: PathBuf,
+
+        /// Prefix for the output files
+        output_prefix: String,
+
+        /// Replay frames at the pace they were captured (timestamped captures only)
+        #[arg(long)]
+        realtime: bool,
+    },
+}
+
+/// Record destinations shared by live and offline sessions
+struct Outputs {
+    sinks: MultiSink,
+    stats: SessionStats,
+    record_count: usize,
+}
+
+impl Outputs {
+    fn new(sinks: MultiSink) -> Self {
+        Self {
+            sinks,
+            stats: SessionStats::new(),
+            record_count: 0,
+        }
+    }
+
+    fn write(&mut self, record: &DriRecord) -> Result<()> {
+        // Aux and network records are kept in the raw capture only
+        self.sinks.write_record(record)?;
+        match record {
+            DriRecord::Physiological(phys) => {
+                self.stats.update(phys);
+                self.record_count += 1;
+            }
+            DriRecord::Waveform { waveforms } => {
+                for wf in waveforms {
+                    self.stats.update_waveform(wf);
+                    self.record_count += 1;
+                }
+            }
+            DriRecord::Aux(_) | DriRecord::Network(_) => {}
+            DriRecord::Event(_) => self.record_count += 1,
+        }
+        Ok(())
+    }
+
+    /// Close all outputs, returning the number of records written
+    fn finish(self) -> Result<(usize, SessionReport)> {
+        Box::new(self.sinks).finish()?;
+        Ok((self.record_count, self.stats.report()))
+    }
+}
 
 fn main() -> Result<()> {
+    let args = Args::parse();
+
     // Initialize logger
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
+    if let Some(Command::Decode {
+        input,
+        output_prefix,
+        realtime,
+    }) = &args.command
+    {
+        return decode_file(&args, input, output_prefix, *realtime);
+    }
+
     // Display banner
     ui::display_banner();
 
-    // Select serial port
-    let port_name = ge_dri_prototype::device::select_port()?;
+    // Select serial port (argument, auto-detect, then interactive)
+    let port_name = match args.port {
+        Some(port_name) => port_name,
+        None => match ge_dri_prototype::device::find_ge_port()? {
+            Some(port_name) => port_name,
+            None if args.non_interactive => {
+                anyhow::bail!("No GE monitor adapter detected; specify one with --port")
+            }
+            None => ge_dri_prototype::device::select_port()?,
+        },
+    };
     ui::success(&format!("Selected port: {}", port_name));
 
     // Connect to device
     ui::info("Connecting to monitor...");
-    let mut device = SerialDevice::open(&port_name)?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let config = SerialConfig::new().checksum_mode(args.checksum.into());
+    let mut device = SerialDevice::open_with_config(&port_name, &config)?
+        .with_stop_flag(stop.clone())
+        .with_waveform_confirmation(DEFAULT_WAVEFORM_CONFIRMATION_WINDOW, |confirmation| {
+            for waveform in &confirmation.missing {
+                ui::error(&format!("{} requested but not received", waveform.name()));
+            }
+        });
     ui::success("Connected successfully!");
 
+    if args.probe {
+        ui::info("Probing for DRI traffic...");
+        let outcome = device.probe(PROBE_TIMEOUT)?;
+        if outcome.checksum_valid() {
+            ui::success(&outcome.to_string());
+        } else {
+            ui::error(&outcome.to_string());
+        }
+        return Ok(());
+    }
+
+    if args.discover {
+        ui::info("Discovering monitor capabilities (about a minute)...");
+        let capabilities = device.discover(DISCOVERY_STEP)?;
+        if capabilities.dri_level.is_some() {
+            ui::success(&capabilities.to_string());
+        } else {
+            ui::error(&capabilities.to_string());
+        }
+        return Ok(());
+    }
+
     // Configure data collection
     println!();
     ui::info("=== Data Collection Configuration ===");
 
-    let interval = loop {
-        let input = ui::get_input("Update interval in seconds (5-3600)", "10")?;
-        if input.is_empty() {
-            break 10;
-        }
-        match input.parse::<u16>() {
-            Ok(val) if val >= 5 && val <= 3600 => break val,
-            _ => ui::error("Invalid interval. Must be between 5 and 3600 seconds."),
-        }
+    let interval = match args.interval {
+        Some(interval) => interval,
+        None if args.non_interactive => DEFAULT_INTERVAL,
+        None => loop {
+            let input = ui::get_input("Update interval in seconds (5-3600)", "10")?;
+            if input.is_empty() {
+                break DEFAULT_INTERVAL;
+            }
+            match input.parse::<u16>() {
+                Ok(val) if val >= 5 && val <= 3600 => break val,
+                _ => ui::error("Invalid interval. Must be between 5 and 3600 seconds."),
+            }
+        },
     };
 
-    let waveforms_input = ui::get_input(
-        "Waveforms to collect (comma-separated, e.g., ECG1,PLETH,CO2)",
-        "ECG1,PLETH",
-    )?;
+    let waveforms_input = match args.waveforms {
+        Some(waveforms) => waveforms.join(","),
+        None if args.non_interactive => String::new(),
+        None => ui::get_input(
+            "Waveforms to collect (comma-separated, e.g., ECG1,PLETH,CO2)",
+            "ECG1,PLETH",
+        )?,
+    };
 
     let waveforms: Vec<String> = if waveforms_input.is_empty() {
-        vec!["ECG1".to_string(), "PLETH".to_string()]
+        DEFAULT_WAVEFORMS.iter().map(|s| s.to_string()).collect()
     } else {
         waveforms_input
             .split(',')
@@ -54,33 +319,89 @@ fn main() -> Result<()> {
 
     // Request data from monitor
     ui::info("Requesting data from monitor...");
-    device.request_displayed_values(interval)?;
+    let interval = device.request_displayed_values(interval)?;
 
     // Convert String to &str for request_waveforms
     let waveform_refs: Vec<&str> = waveforms.iter().map(|s| s.as_str()).collect();
-    device.request_waveforms(&waveform_refs)?;
+    let requested = device.request_waveforms(&waveform_refs)?;
+    if requested.len() < waveforms.len() {
+        ui::error("Some waveform names were not recognised and were skipped");
+    }
 
+    let requested: Vec<&str> = requested.iter().map(|wf| wf.name()).collect();
     ui::success(&format!(
         "Requested displayed values ({}s interval) and waveforms: {}",
         interval,
-        waveforms.join(", ")
+        requested.join(", ")
     ));
 
     // Initialize storage
-    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-    let base_filename = format!("output_{}", timestamp);
+    let base_filename = args.output_prefix.unwrap_or_else(|| {
+        let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+        format!("output_{}", timestamp)
+    });
+
+    let mut rotation = RotationPolicy::new().hourly(args.rotate_hourly);
+    if let Some(mb) = args.rotate_mb {
+        rotation = rotation.max_bytes(mb * 1024 * 1024);
+    }
 
-    let mut csv_writer = CsvWriter::new(format!("{}.csv", base_filename))?;
-    let mut json_writer = JsonWriter::new(format!("{}.json", base_filename))?;
-    let mut raw_writer = RawWriter::new(format!("{}.raw", base_filename))?;
+    let mut csv_writer = CsvWriter::new(format!("{}.csv", base_filename))?
+        .with_append(args.append)
+        .with_rotation(rotation.clone());
+    if let Some(factor) = args.decimate {
+        csv_writer = csv_writer.with_decimation(WaveformDecimator::new(factor));
+    }
+    let events_path = format!("{}.events.csv", base_filename);
+    let (json_writer, mut raw_writer, event_writer) = if args.append {
+        (
+            JsonWriter::with_format(format!("{}.json", base_filename), JsonFormat::Lines)?,
+            RawWriter::append(format!("{}.raw", base_filename))?.with_rotation(rotation),
+            EventWriter::append(events_path, EventFormat::Csv)?,
+        )
+    } else {
+        (
+            JsonWriter::new(format!("{}.json", base_filename))?,
+            RawWriter::new(format!("{}.raw", base_filename))?.with_rotation(rotation),
+            EventWriter::new(events_path, EventFormat::Csv)?,
+        )
+    };
+    let json_writer = json_writer.with_units(args.json_units);
+    if args.raw_timestamps {
+        raw_writer = raw_writer.with_format(RawFormat::Timestamped);
+    }
 
     ui::success(&format!(
-        "Created output files: {}.{{csv,json,raw}}",
+        "Created output files: {}.{{csv,json,raw,events.csv}}",
         base_filename
     ));
 
+    #[cfg_attr(not(feature = "mqtt"), allow(unused_mut))]
+    let mut sinks = MultiSink::new()
+        .with_sink(csv_writer)
+        .with_sink(json_writer)
+        .with_sink(event_writer);
+
+    #[cfg(feature = "mqtt")]
+    if let Some(broker) = &args.mqtt {
+        let qos = if args.mqtt_ack {
+            MqttQos::AtLeastOnce
+        } else {
+            MqttQos::AtMostOnce
+        };
+        let publisher = MqttPublisher::connect(
+            broker.as_str(),
+            "ge-dri-prototype",
+            Duration::from_secs(args.mqtt_keep_alive.into()),
+        )?;
+        ui::success(&format!("Publishing to MQTT broker {}", broker));
+        sinks.push(Box::new(publisher.with_qos(qos)));
+    }
+
+    let mut outputs = Outputs::new(sinks);
+
     // Initialize decoder
-    let decoder = Decoder::new();
+    let mut decoder = args.invalid_values.decoder();
 
     // Main collection loop
     println!();
@@ -88,17 +409,30 @@ fn main() -> Result<()> {
     ui::info("Press Ctrl+C to stop");
     println!();
 
+    {
+        let stop = stop.clone();
+        ctrlc::set_handler(move || stop.store(true, Ordering::SeqCst))?;
+    }
+
     let mut frame_count = 0;
 
-    loop {
-        match device.read_frame() {
-            Ok(frame) => {
+    while !stop.load(Ordering::SeqCst) {
+        let result = device.poll_frame_with_reconnect();
+        // E.g. MQTT pings, also while the monitor is silent
+        outputs.sinks.keep_alive()?;
+        match result {
+            Ok(None) => {}
+            Ok(Some(frame)) => {
                 // Write raw frame
                 raw_writer.write_frame(&frame)?;
 
                 // Parse header from frame data
                 let header = match ge_dri_prototype::protocol::DriHeader::parse(&frame.data) {
                     Ok(h) => h,
+                    Err(DriError::FramingError(len)) => {
+                        log::debug!(len; "Ignoring frame too short for a header");
+                        continue;
+                    }
                     Err(e) => {
                         ui::error(&format!("Failed to parse header: {}", e));
                         continue;
@@ -115,16 +449,16 @@ fn main() -> Result<()> {
                 };
 
                 // Decode frame with header and data
-                match decoder.decode_frame(&header, data) {
-                    Ok(Some(record)) => {
+                match decoder.decode_frame_all(&header, data) {
+                    Ok(records) if !records.is_empty() => {
                         frame_count += 1;
 
-                        // Write to storage
-                        match &record {
-                            ge_dri_prototype::decode::DriRecord::Physiological(phys) => {
-                                csv_writer.write_physiological(phys)?;
-                                json_writer.write_physiological(phys)?;
+                        for mut record in records {
+                            record.mark_received(Utc::now());
 
+                            outputs.write(&record)?;
+
+                            if let DriRecord::Physiological(phys) = &record {
                                 // Display live vitals
                                 print!("\r");
 
@@ -183,12 +517,6 @@ fn main() -> Result<()> {
                                 use std::io::{self, Write};
                                 io::stdout().flush()?;
                             }
-                            ge_dri_prototype::decode::DriRecord::Waveform { waveforms } => {
-                                for wf in waveforms {
-                                    csv_writer.write_waveform(wf)?;
-                                    json_writer.write_waveform(wf)?;
-                                }
-                            }
                         }
 
                         // Show statistics every 100 frames
@@ -198,7 +526,7 @@ fn main() -> Result<()> {
                             print!("Current vitals: ");
                         }
                     }
-                    Ok(None) => {
+                    Ok(_) => {
                         // No data in frame (e.g., unsupported record type)
                     }
                     Err(e) => {
@@ -206,33 +534,12 @@ fn main() -> Result<()> {
                     }
                 }
             }
+            Err(_) if stop.load(Ordering::SeqCst) => break,
             Err(e) => {
+                // Reconnection (with backoff) already failed
                 println!();
-                ui::error(&format!("Read error: {}", e));
-
-                // Ask user if they want to reconnect
-                if ui::confirm("Connection lost. Try to reconnect?")? {
-                    ui::info("Attempting to reconnect...");
-                    match SerialDevice::open(&port_name) {
-                        Ok(new_device) => {
-                            device = new_device;
-                            device.request_displayed_values(interval)?;
-
-                            // Convert String to &str again
-                            let waveform_refs: Vec<&str> =
-                                waveforms.iter().map(|s| s.as_str()).collect();
-                            device.request_waveforms(&waveform_refs)?;
-
-                            ui::success("Reconnected successfully!");
-                        }
-                        Err(e) => {
-                            ui::error(&format!("Reconnection failed: {}", e));
-                            break;
-                        }
-                    }
-                } else {
-                    break;
-                }
+                ui::error(&format!("Connection lost: {}", e));
+                break;
             }
         }
     }
@@ -240,11 +547,143 @@ fn main() -> Result<()> {
     // Cleanup
     println!();
     ui::info("Stopping data collection...");
-    device.stop_all()?;
+    if let Err(e) = device.stop_all() {
+        ui::error(&format!("Failed to stop transmission: {}", e));
+    }
+
+    let (record_count, report) = outputs.finish()?;
+    raw_writer.finish()?;
+
+    ui::success(&format!(
+        "Collection stopped. Total frames: {}, records written: {}",
+        frame_count, record_count
+    ));
+    println!();
+    ui::info("=== Session Summary ===");
+    print!("{}", report);
+
+    Ok(())
+}
+
+/// Decode a raw capture into CSV and JSON, as a live session would
+fn decode_file(args: &Args, input: &Path, output_prefix: &str, realtime: bool) -> Result<()> {
+    let mut csv = CsvWriter::new(format!("{}.csv", output_prefix))?;
+    if let Some(factor) = args.decimate {
+        csv = csv.with_decimation(WaveformDecimator::new(factor));
+    }
+    let json = JsonWriter::new(format!("{}.json", output_prefix))?.with_units(args.json_units);
+    let events = EventWriter::new(format!("{}.events.csv", output_prefix), EventFormat::Csv)?;
+    let mut outputs = Outputs::new(
+        MultiSink::new()
+            .with_sink(csv)
+            .with_sink(json)
+            .with_sink(events),
+    );
+
+    ui::info(&format!("Decoding {}...", input.display()));
+
+    let mut reader = RawReader::open(input)?.with_checksum_mode(args.checksum.into());
+    if realtime && reader.format() != RawFormat::Timestamped {
+        ui::error("Capture has no timestamps, --realtime ignored");
+    }
+    let mut previous_at: Option<DateTime<Utc>> = None;
+
+    let mut decoder = args.invalid_values.decoder();
+    let mut decoded = 0;
+    let mut checksum_errors = 0;
+    let mut framing_errors = 0;
+    let mut decode_errors = 0;
+    let mut short_frames = 0;
+    let mut by_type: Vec<(DriMainType, usize)> = Vec::new();
+
+    while let Some(frame) = reader.next() {
+        let received_at = reader.received_at();
+        if realtime {
+            if let (Some(previous), Some(current)) = (previous_at, received_at)
+                && let Ok(delay) = (current - previous).to_std()
+            {
+                std::thread::sleep(delay);
+            }
+            previous_at = received_at;
+        }
+
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(DriError::ChecksumError) => {
+                checksum_errors += 1;
+                continue;
+            }
+            Err(DriError::IoError(e)) => return Err(e.into()),
+            Err(_) => {
+                framing_errors += 1;
+                continue;
+            }
+        };
+
+        let decoded_frame = match decoder.decode_with_meta(&frame.data) {
+            Ok(decoded_frame) => decoded_frame,
+            Err(e) if matches!(e.downcast_ref(), Some(DriError::FramingError(_))) => {
+                short_frames += 1;
+                continue;
+            }
+            Err(e) => {
+                ui::error(&format!("Failed to parse header: {}", e));
+                decode_errors += 1;
+                continue;
+            }
+        };
+
+        let maintype = decoded_frame.meta.maintype;
+        match by_type.iter_mut().find(|(t, _)| *t == maintype) {
+            Some((_, count)) => *count += 1,
+            None => by_type.push((maintype, 1)),
+        }
+
+        match decoded_frame.records {
+            Ok(mut records) => {
+                decoded += 1;
+                for record in &mut records {
+                    if let Some(received_at) = received_at {
+                        record.mark_received(received_at);
+                    }
+                    outputs.write(record)?;
+                }
+            }
+            Err(e) => {
+                ui::error(&format!("Decode error: {}", e));
+                decode_errors += 1;
+            }
+        }
+    }
+
+    let (record_count, report) = outputs.finish()?;
+
     ui::success(&format!(
-        "Collection stopped. Total frames: {}",
-        frame_count
+        "Decoded {} frames into {}.{{csv,json,events.csv}} ({} records)",
+        decoded, output_prefix, record_count
+    ));
+    ui::info(&format!(
+        "Checksum errors: {}, other framing errors: {}, decode errors: {}",
+        checksum_errors, framing_errors, decode_errors
     ));
+    if short_frames > 0 {
+        ui::info(&format!(
+            "Skipped {} frames too short to be data records",
+            short_frames
+        ));
+    }
+    if matches!(args.checksum, Checksum::Auto) {
+        ui::info(&format!(
+            "Checksum convention: {:?}",
+            reader.checksum_mode()
+        ));
+    }
+    for (maintype, count) in &by_type {
+        println!("  {:?}: {} frames", maintype, count);
+    }
+    println!();
+    ui::info("=== Session Summary ===");
+    print!("{}", report);
 
     Ok(())
 }
@@ -1,4 +1,36 @@
 //! Checksum calculation and validation for DRI frames
+//!
+//! The checksum is documented as the sum of the frame bytes, but devices
+//! differ on whether the escape bytes added by byte stuffing are included.
+//! [`ChecksumMode`] selects the convention, or detects it.
+
+use crate::constants::{BIT5, CTRL_CHAR, FRAME_CHAR};
+
+/// Which bytes the frame checksum is summed over
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChecksumMode {
+    /// The frame data before stuffing, as in this crate's requests
+    #[default]
+    Unstuffed,
+    /// The frame data as transmitted, escape sequences included
+    Stuffed,
+    /// Accept either until a frame validates with only one of them, then
+    /// keep that one
+    Auto,
+}
+
+impl ChecksumMode {
+    /// Whether `checksum` is valid for `data` (unstuffed) in this mode
+    pub fn validates(self, data: &[u8], checksum: u8) -> bool {
+        match self {
+            ChecksumMode::Unstuffed => calculate_checksum(data) == checksum,
+            ChecksumMode::Stuffed => calculate_stuffed_checksum(data) == checksum,
+            ChecksumMode::Auto => {
+                calculate_checksum(data) == checksum || calculate_stuffed_checksum(data) == checksum
+            }
+        }
+    }
+}
 
 /// Calculate checksum for a byte slice
 ///
@@ -7,6 +39,19 @@ pub fn calculate_checksum(data: &[u8]) -> u8 {
     data.iter().fold(0u8, |acc, &byte| acc.wrapping_add(byte))
 }
 
+/// Calculate the checksum over the stuffed form of `data`
+///
+/// Each `0x7E`/`0x7D` byte counts as its two-byte escape sequence.
+pub fn calculate_stuffed_checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |acc, &byte| {
+        if byte == FRAME_CHAR || byte == CTRL_CHAR {
+            acc.wrapping_add(CTRL_CHAR).wrapping_add(byte & !BIT5)
+        } else {
+            acc.wrapping_add(byte)
+        }
+    })
+}
+
 /// Validate that data has correct checksum
 ///
 /// The last byte should be the checksum of all preceding bytes
@@ -48,6 +93,16 @@ mod tests {
         assert!(validate_checksum(&data));
     }
 
+    #[test]
+    fn test_stuffed_checksum_counts_escape_bytes() {
+        let data = [0x01, 0x7E];
+        assert_eq!(calculate_checksum(&data), 0x7F);
+        // 0x01 + 0x7D + 0x5E
+        assert_eq!(calculate_stuffed_checksum(&data), 0xDC);
+        assert!(ChecksumMode::Auto.validates(&data, 0xDC));
+        assert!(!ChecksumMode::Unstuffed.validates(&data, 0xDC));
+    }
+
     #[test]
     fn test_validate_checksum_invalid() {
         let data = vec![0x01, 0x02, 0x03, 0x04, 0xFF]; // Wrong checksum
@@ -1,8 +1,9 @@
 //! Frame parsing and byte stuffing/unstuffing for DRI protocol
 
+use super::checksum::{ChecksumMode, calculate_checksum, calculate_stuffed_checksum};
 use crate::DriError;
 use crate::constants::{BIT5, CTRL_CHAR, FRAME_CHAR};
-use log::{debug, trace};
+use log::{debug, info, trace};
 
 /// A complete DRI frame with unstuffed data
 #[derive(Debug, Clone)]
@@ -30,6 +31,11 @@ impl DriFrame {
     pub fn validate(&self) -> bool {
         super::checksum::validate_checksum(&self.complete_data())
     }
+
+    /// Validate the frame checksum with the given convention
+    pub fn validate_with(&self, mode: ChecksumMode) -> bool {
+        mode.validates(&self.data, self.checksum)
+    }
 }
 
 /// State machine for parsing DRI frames from a byte stream
@@ -37,6 +43,7 @@ impl DriFrame {
 pub struct FrameParser {
     state: ParserState,
     buffer: Vec<u8>,
+    checksum_mode: ChecksumMode,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -55,10 +62,38 @@ impl FrameParser {
         Self {
             state: ParserState::WaitingForStart,
             buffer: Vec::with_capacity(2048),
+            checksum_mode: ChecksumMode::default(),
         }
     }
 
+    /// Set the checksum convention, see [`ChecksumMode`]
+    pub fn with_checksum_mode(mut self, mode: ChecksumMode) -> Self {
+        self.checksum_mode = mode;
+        self
+    }
+
+    /// Checksum convention in use
+    ///
+    /// With [`ChecksumMode::Auto`], stays `Auto` until a frame has told the
+    /// conventions apart, then reports the detected one. Frames without
+    /// `0x7E`/`0x7D` bytes validate the same either way.
+    pub fn checksum_mode(&self) -> ChecksumMode {
+        self.checksum_mode
+    }
+
+    /// Check a frame framed elsewhere, locking an `Auto` convention as
+    /// frames parsed here do
+    pub fn validate(&mut self, frame: &DriFrame) -> bool {
+        if !frame.validate_with(self.checksum_mode) {
+            return false;
+        }
+        self.detect_checksum_mode(frame);
+        true
+    }
+
     /// Reset the parser state
+    ///
+    /// A detected checksum convention is kept.
     pub fn reset(&mut self) {
         self.state = ParserState::WaitingForStart;
         self.buffer.clear();
@@ -71,7 +106,7 @@ impl FrameParser {
     /// - Ok(None) if more data is needed
     /// - Err if an error occurred
     pub fn process_byte(&mut self, byte: u8) -> Result<Option<DriFrame>, DriError> {
-        trace!("Parser state: {:?}, byte: 0x{:02X}", self.state, byte);
+        trace!(state:? = self.state, byte; "Parser byte");
 
         match self.state {
             ParserState::WaitingForStart => {
@@ -86,7 +121,7 @@ impl FrameParser {
             ParserState::InFrame => {
                 if byte == FRAME_CHAR {
                     // End of frame
-                    debug!("Frame end detected, buffer size: {}", self.buffer.len());
+                    debug!(buffer_size = self.buffer.len(); "Frame end detected");
                     return self.finalize_frame();
                 } else if byte == CTRL_CHAR {
                     // Next byte needs unstuffing
@@ -102,7 +137,7 @@ impl FrameParser {
             ParserState::NeedUnstuff => {
                 // Unstuff the byte by ORing with BIT5
                 let unstuffed = byte | BIT5;
-                trace!("Unstuffing: 0x{:02X} -> 0x{:02X}", byte, unstuffed);
+                trace!(byte, unstuffed; "Unstuffing");
                 self.buffer.push(unstuffed);
                 self.state = ParserState::InFrame;
                 Ok(None)
@@ -111,16 +146,14 @@ impl FrameParser {
     }
 
     /// Process multiple bytes
-    pub fn process_bytes(&mut self, bytes: &[u8]) -> Result<Vec<DriFrame>, DriError> {
-        let mut frames = Vec::new();
-
-        for &byte in bytes {
-            if let Some(frame) = self.process_byte(byte)? {
-                frames.push(frame);
-            }
-        }
-
-        Ok(frames)
+    ///
+    /// Frames failing the checksum or too short are returned as `Err` in
+    /// place, and parsing continues with the next frame.
+    pub fn process_bytes(&mut self, bytes: &[u8]) -> Vec<Result<DriFrame, DriError>> {
+        bytes
+            .iter()
+            .filter_map(|&byte| self.process_byte(byte).transpose())
+            .collect()
     }
 
     /// Finalize the current frame
@@ -132,7 +165,7 @@ impl FrameParser {
         }
 
         if self.buffer.len() < 2 {
-            debug!("Frame too short ({}), ignoring", self.buffer.len());
+            debug!(buffer_size = self.buffer.len(); "Frame too short, ignoring");
             self.state = ParserState::WaitingForStart;
             return Err(DriError::IncompleteFrame);
         }
@@ -144,17 +177,42 @@ impl FrameParser {
         let frame = DriFrame::new(data, checksum);
 
         // Validate checksum
-        if !frame.validate() {
-            debug!("Checksum validation failed");
+        if !frame.validate_with(self.checksum_mode) {
+            debug!(
+                buffer_size = frame.data.len(),
+                expected = frame.checksum,
+                actual = calculate_checksum(&frame.data),
+                actual_stuffed = calculate_stuffed_checksum(&frame.data);
+                "Checksum validation failed"
+            );
             self.state = ParserState::WaitingForStart;
             return Err(DriError::ChecksumError);
         }
 
-        debug!("Valid frame parsed, size: {}", frame.data.len());
+        self.detect_checksum_mode(&frame);
+
+        debug!(buffer_size = frame.data.len(); "Valid frame parsed");
         self.state = ParserState::WaitingForStart;
         Ok(Some(frame))
     }
 
+    /// Lock an `Auto` convention onto the only one `frame` validates with
+    fn detect_checksum_mode(&mut self, frame: &DriFrame) {
+        if self.checksum_mode != ChecksumMode::Auto {
+            return;
+        }
+        let unstuffed = frame.validate_with(ChecksumMode::Unstuffed);
+        let stuffed = frame.validate_with(ChecksumMode::Stuffed);
+        if unstuffed != stuffed {
+            self.checksum_mode = if stuffed {
+                ChecksumMode::Stuffed
+            } else {
+                ChecksumMode::Unstuffed
+            };
+            info!(mode:? = self.checksum_mode; "Detected checksum convention");
+        }
+    }
+
     /// Get the current buffer size (for debugging)
     pub fn buffer_size(&self) -> usize {
         self.buffer.len()
@@ -204,6 +262,15 @@ pub fn create_frame(data: &[u8]) -> Vec<u8> {
 mod tests {
     use super::*;
 
+    /// Frames of `bytes`, failing on any invalid one
+    fn parse_all(parser: &mut FrameParser, bytes: &[u8]) -> Vec<DriFrame> {
+        parser
+            .process_bytes(bytes)
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
     #[test]
     fn test_simple_frame() {
         let mut parser = FrameParser::new();
@@ -212,7 +279,7 @@ mod tests {
         // Checksum = 0x01 + 0x02 + 0x03 = 0x06
         let bytes = vec![0x7E, 0x01, 0x02, 0x03, 0x06, 0x7E];
 
-        let frames = parser.process_bytes(&bytes).unwrap();
+        let frames = parse_all(&mut parser, &bytes);
         assert_eq!(frames.len(), 1);
         assert_eq!(frames[0].data, vec![0x01, 0x02, 0x03]);
         assert_eq!(frames[0].checksum, 0x06);
@@ -225,14 +292,42 @@ mod tests {
 
         // Frame with 0x7E in data: 0x7E 0x7D 0x5E 0x01 <checksum> 0x7E
         // 0x7D 0x5E unstuffs to 0x7E
-        let data = vec![0x7E];
-        let checksum = super::super::checksum::calculate_checksum(&data);
+        let data = vec![0x7E, 0x01];
+        let checksum = calculate_checksum(&data);
 
-        let bytes = vec![0x7E, 0x7D, 0x5E, checksum, 0x7E];
+        let bytes = vec![0x7E, 0x7D, 0x5E, 0x01, checksum, 0x7E];
 
-        let frames = parser.process_bytes(&bytes).unwrap();
+        let frames = parse_all(&mut parser, &bytes);
         assert_eq!(frames.len(), 1);
-        assert_eq!(frames[0].data, vec![0x7E]);
+        assert_eq!(frames[0].data, data);
+    }
+
+    #[test]
+    fn test_auto_checksum_locks_onto_stuffed_convention() {
+        let mut parser = FrameParser::new().with_checksum_mode(ChecksumMode::Auto);
+
+        // No stuffed bytes: both conventions agree
+        assert_eq!(parse_all(&mut parser, &create_frame(&[0x01])).len(), 1);
+        assert_eq!(parser.checksum_mode(), ChecksumMode::Auto);
+
+        let data = [0x7E, 0x01];
+        let mut stuffed = vec![
+            0x7E,
+            0x7D,
+            0x5E,
+            0x01,
+            calculate_stuffed_checksum(&data),
+            0x7E,
+        ];
+        assert_eq!(parse_all(&mut parser, &stuffed)[0].data, data);
+        assert_eq!(parser.checksum_mode(), ChecksumMode::Stuffed);
+
+        // The other convention is now rejected
+        stuffed[4] = calculate_checksum(&data);
+        assert!(matches!(
+            parser.process_bytes(&stuffed)[..],
+            [Err(DriError::ChecksumError)]
+        ));
     }
 
     #[test]
@@ -242,9 +337,23 @@ mod tests {
         // Frame with wrong checksum
         let bytes = vec![0x7E, 0x01, 0x02, 0x03, 0xFF, 0x7E];
 
-        let result = parser.process_bytes(&bytes);
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), DriError::ChecksumError));
+        let results = parser.process_bytes(&bytes);
+        assert!(matches!(results[..], [Err(DriError::ChecksumError)]));
+    }
+
+    #[test]
+    fn test_bad_frame_does_not_hide_the_next_ones() {
+        let mut parser = FrameParser::new();
+
+        let mut bytes = create_frame(&[0x01]);
+        bytes.extend([0x7E, 0x02, 0x03, 0xFF, 0x7E]); // Wrong checksum
+        bytes.extend(create_frame(&[0x04]));
+
+        let results = parser.process_bytes(&bytes);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().data, vec![0x01]);
+        assert!(matches!(results[1], Err(DriError::ChecksumError)));
+        assert_eq!(results[2].as_ref().unwrap().data, vec![0x04]);
     }
 
     #[test]
@@ -257,7 +366,7 @@ mod tests {
             0x7E, 0x02, 0x02, 0x7E, // Second frame
         ];
 
-        let frames = parser.process_bytes(&bytes).unwrap();
+        let frames = parse_all(&mut parser, &bytes);
         assert_eq!(frames.len(), 2);
         assert_eq!(frames[0].data, vec![0x01]);
         assert_eq!(frames[1].data, vec![0x02]);
@@ -274,7 +383,7 @@ mod tests {
 
         // Parse it back
         let mut parser = FrameParser::new();
-        let parsed = parser.process_bytes(&frame).unwrap();
+        let parsed = parse_all(&mut parser, &frame);
         assert_eq!(parsed.len(), 1);
         assert_eq!(parsed[0].data, data);
     }
@@ -1,12 +1,13 @@
 //! DRI record header parsing
 
 use crate::DriError;
-use crate::constants::{DriLevel, DriMainType, HEADER_SIZE, MAX_SUBRECORDS};
+use crate::constants::{DriLevel, DriMainType, HEADER_SIZE, MAX_RECORD_SIZE, MAX_SUBRECORDS};
+use crate::decode::subrecords::{read_u16, read_u32};
 use chrono::{DateTime, Utc};
-use log::debug;
+use log::{debug, warn};
 
 /// DRI record header (40 bytes)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DriHeader {
     /// Total length of record (including header)
     pub r_len: u16,
@@ -15,6 +16,11 @@ pub struct DriHeader {
     /// DRI level the monitor supports
     pub dri_level: DriLevel,
     /// Plug identifier
+    ///
+    /// Identifies the monitor/interface that sent the record; the only way
+    /// to attribute records when several monitors share a gateway. Values
+    /// are assigned by the sender and carry no meaning beyond that, so
+    /// outputs keep them as plain numbers to compare for equality.
     pub plug_id: u16,
     /// Timestamp (Unix time - seconds since 1970-01-01)
     pub r_time: u32,
@@ -25,7 +31,7 @@ pub struct DriHeader {
 }
 
 /// Subrecord descriptor
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SubrecordDescriptor {
     /// Offset from start of data area
     pub offset: u16,
@@ -36,22 +42,25 @@ pub struct SubrecordDescriptor {
 impl DriHeader {
     /// Parse a header from raw bytes
     ///
-    /// The header is 40 bytes in little-endian format
+    /// The header is 40 bytes in little-endian format. Shorter data is not
+    /// a data record (some monitors send short request acknowledgments)
+    /// and fails with [`DriError::FramingError`] and the observed length,
+    /// so callers can tell it from a corrupt header and skip it.
     pub fn parse(data: &[u8]) -> Result<Self, DriError> {
         if data.len() < HEADER_SIZE {
-            return Err(DriError::IncompleteFrame);
+            return Err(DriError::FramingError(data.len()));
         }
 
         // Parse fields (all little-endian)
-        let r_len = u16::from_le_bytes([data[0], data[1]]);
+        let r_len = read_u16(data, 0).ok_or(DriError::IncompleteFrame)?;
         let r_nbr = data[2];
         let dri_level_byte = data[3];
-        let plug_id = u16::from_le_bytes([data[4], data[5]]);
-        let r_time = u32::from_le_bytes([data[6], data[7], data[8], data[9]]);
+        let plug_id = read_u16(data, 4).ok_or(DriError::IncompleteFrame)?;
+        let r_time = read_u32(data, 6).ok_or(DriError::IncompleteFrame)?;
 
         // Reserved bytes at 10-15
 
-        let r_maintype_raw = u16::from_le_bytes([data[16], data[17]]);
+        let r_maintype_raw = read_u16(data, 16).ok_or(DriError::IncompleteFrame)?;
 
         // Parse DRI level
         let dri_level = DriLevel::from_u8(dri_level_byte)
@@ -64,9 +73,12 @@ impl DriHeader {
         // Parse subrecord descriptors (8 descriptors, 3 bytes each)
         let mut subrecords = Vec::new();
         for i in 0..MAX_SUBRECORDS {
+            // The last descriptor runs past the 40-byte header
             let base = 18 + (i * 3);
-            let offset = u16::from_le_bytes([data[base], data[base + 1]]);
-            let sr_type = data[base + 2];
+            let Some(&[lo, hi, sr_type]) = data.get(base..base + 3) else {
+                break;
+            };
+            let offset = u16::from_le_bytes([lo, hi]);
 
             // 0xFF marks end of subrecord list
             if sr_type == 0xFF {
@@ -77,14 +89,15 @@ impl DriHeader {
         }
 
         debug!(
-            "Parsed header: len={}, type={:?}, level={:?}, subrecords={}",
             r_len,
-            r_maintype,
-            dri_level,
-            subrecords.len()
+            r_nbr,
+            maintype:? = r_maintype,
+            level:? = dri_level,
+            subrecords = subrecords.len();
+            "Parsed header"
         );
 
-        Ok(DriHeader {
+        let header = DriHeader {
             r_len,
             r_nbr,
             dri_level,
@@ -92,7 +105,62 @@ impl DriHeader {
             r_time,
             r_maintype,
             subrecords,
-        })
+        };
+        if let Some(problem) = header.length_problem() {
+            warn!(r_len, r_nbr, maintype:? = r_maintype; "{}", problem);
+        }
+        Ok(header)
+    }
+
+    /// Serialize back to the 40-byte wire form
+    ///
+    /// Descriptors are followed by the `0xFF` terminator when it fits. The
+    /// header has room for one descriptor less than [`MAX_SUBRECORDS`]; any
+    /// beyond are dropped. Reserved bytes are not kept by [`DriHeader::parse`] and
+    /// are written as zeros, so `parse(&h.to_bytes())` equals `h`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![0u8; HEADER_SIZE];
+        data[0..2].copy_from_slice(&self.r_len.to_le_bytes());
+        data[2] = self.r_nbr;
+        data[3] = self.dri_level as u8;
+        data[4..6].copy_from_slice(&self.plug_id.to_le_bytes());
+        data[6..10].copy_from_slice(&self.r_time.to_le_bytes());
+        data[16..18].copy_from_slice(&(self.r_maintype as u16).to_le_bytes());
+
+        let terminator = SubrecordDescriptor {
+            offset: 0,
+            sr_type: 0xFF,
+        };
+        let descriptors = self.subrecords.iter().chain(std::iter::once(&terminator));
+        for (chunk, descriptor) in data[18..].chunks_exact_mut(3).zip(descriptors) {
+            chunk[0..2].copy_from_slice(&descriptor.offset.to_le_bytes());
+            chunk[2] = descriptor.sr_type;
+        }
+        data
+    }
+
+    /// Describe why `r_len` is implausible, if it is
+    ///
+    /// A length over [`MAX_RECORD_SIZE`] usually means the link is not
+    /// speaking little-endian DRI, or that frames are cut in the wrong place.
+    pub fn length_problem(&self) -> Option<String> {
+        if self.r_len as usize <= MAX_RECORD_SIZE {
+            return None;
+        }
+
+        let swapped = self.r_len.swap_bytes();
+        let hint = if (HEADER_SIZE..=MAX_RECORD_SIZE).contains(&(swapped as usize)) {
+            format!(
+                "read big-endian it would be {}, check the byte order of the link",
+                swapped
+            )
+        } else {
+            "check the framing and the serial settings".to_string()
+        };
+        Some(format!(
+            "Implausible record length {} (max {}): {}",
+            self.r_len, MAX_RECORD_SIZE, hint
+        ))
     }
 
     /// Get timestamp as DateTime
@@ -100,6 +168,19 @@ impl DriHeader {
         DateTime::from_timestamp(self.r_time as i64, 0).unwrap_or_else(|| Utc::now())
     }
 
+    /// Monitor clock minus host clock, measured now
+    ///
+    /// Positive when the monitor's clock is ahead. `r_time` has a 1-second
+    /// resolution, so small values are expected.
+    pub fn clock_skew(&self) -> chrono::Duration {
+        self.clock_skew_at(Utc::now())
+    }
+
+    /// Monitor clock minus the given host time
+    pub fn clock_skew_at(&self, host_time: DateTime<Utc>) -> chrono::Duration {
+        self.timestamp() - host_time
+    }
+
     /// Get the data portion (everything after the header)
     pub fn extract_data<'a>(&self, frame_data: &'a [u8]) -> Result<&'a [u8], DriError> {
         if frame_data.len() < HEADER_SIZE {
@@ -259,4 +340,89 @@ mod tests {
         assert_eq!(header.dri_level, DriLevel::Level02);
         assert_eq!(header.r_maintype, DriMainType::Phdb);
     }
+
+    #[test]
+    fn test_header_round_trips_through_bytes() {
+        let header = DriHeader {
+            r_len: 1128,
+            r_nbr: 42,
+            dri_level: DriLevel::Level02,
+            plug_id: 7,
+            r_time: 1_700_000_000,
+            r_maintype: DriMainType::Wave,
+            subrecords: vec![
+                SubrecordDescriptor {
+                    offset: 0,
+                    sr_type: 1,
+                },
+                SubrecordDescriptor {
+                    offset: 406,
+                    sr_type: 4,
+                },
+            ],
+        };
+        let bytes = header.to_bytes();
+        assert_eq!(bytes.len(), HEADER_SIZE);
+        assert_eq!(bytes[26], 0xFF);
+        assert_eq!(DriHeader::parse(&bytes).unwrap(), header);
+
+        // A full descriptor list leaves no room for the terminator
+        let full = DriHeader {
+            subrecords: vec![
+                SubrecordDescriptor {
+                    offset: 10,
+                    sr_type: 2,
+                };
+                MAX_SUBRECORDS - 1
+            ],
+            ..header
+        };
+        assert_eq!(DriHeader::parse(&full.to_bytes()).unwrap(), full);
+    }
+
+    #[test]
+    fn test_short_frame_reports_its_length() {
+        let err = DriHeader::parse(&[0u8; 6]).unwrap_err();
+        assert!(matches!(err, DriError::FramingError(6)));
+        assert!(err.to_string().contains("6 bytes"));
+    }
+
+    #[test]
+    fn test_descriptors_without_terminator() {
+        let mut data = vec![0u8; HEADER_SIZE];
+        data[3] = 8;
+        // Type byte of every descriptor that fits in the header
+        for sr_type in data[20..].iter_mut().step_by(3) {
+            *sr_type = 1;
+        }
+
+        let header = DriHeader::parse(&data).unwrap();
+        assert_eq!(header.subrecords.len(), MAX_SUBRECORDS - 1);
+    }
+
+    #[test]
+    fn test_byte_swapped_length_is_flagged() {
+        let mut data = vec![0u8; HEADER_SIZE];
+        data[3] = 8;
+        data[20] = 0xFF;
+
+        data[0..2].copy_from_slice(&1128u16.to_le_bytes());
+        assert_eq!(DriHeader::parse(&data).unwrap().length_problem(), None);
+
+        data[0..2].copy_from_slice(&1128u16.to_be_bytes());
+        let problem = DriHeader::parse(&data).unwrap().length_problem().unwrap();
+        assert!(problem.contains("byte order"), "{}", problem);
+    }
+
+    #[test]
+    fn test_clock_skew() {
+        let mut data = vec![0u8; HEADER_SIZE];
+        data[3] = 8;
+        data[6..10].copy_from_slice(&1_700_000_090u32.to_le_bytes());
+        data[20] = 0xFF;
+
+        let header = DriHeader::parse(&data).unwrap();
+        let host_time = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        assert_eq!(header.clock_skew_at(host_time).num_seconds(), 90);
+    }
 }
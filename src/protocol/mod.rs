@@ -3,7 +3,11 @@
 pub mod checksum;
 pub mod framing;
 pub mod header;
+pub mod request;
+pub mod sequence;
 
-pub use checksum::validate_checksum;
+pub use checksum::{ChecksumMode, validate_checksum};
 pub use framing::{DriFrame, FrameParser};
 pub use header::DriHeader;
+pub use request::RequestBuilder;
+pub use sequence::SequenceMonitor;
@@ -0,0 +1,108 @@
+//! Request frame construction
+//!
+//! [`RequestBuilder`] produces complete, stuffed frames that can be written
+//! to any transport, or inspected in tests.
+
+use super::framing::create_frame;
+use super::header::{create_phdb_request, create_waveform_request};
+use crate::constants::WaveformType;
+use crate::constants::dri_types::{PHDBCL_REQ_ALL, PhdbSubrecordType};
+
+/// Start continuous waveform transmission
+pub const WF_REQ_CONT_START: u16 = 0;
+/// Stop continuous waveform transmission
+pub const WF_REQ_CONT_STOP: u16 = 1;
+
+/// Builds request frames ready to send to the monitor
+#[derive(Debug, Clone, Copy)]
+pub struct RequestBuilder {
+    class_mask: u32,
+}
+
+impl Default for RequestBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestBuilder {
+    /// Create a builder requesting all physiological classes
+    pub fn new() -> Self {
+        Self {
+            class_mask: PHDBCL_REQ_ALL,
+        }
+    }
+
+    /// Set the `PHDBCL_*` class mask used for displayed values
+    pub fn with_class_mask(mut self, class_mask: u32) -> Self {
+        self.class_mask = class_mask;
+        self
+    }
+
+    /// Displayed values every `interval` seconds
+    ///
+    /// The monitor's 5 second minimum is not enforced here.
+    pub fn displayed_values(&self, interval: u16) -> Vec<u8> {
+        create_frame(&create_phdb_request(
+            PhdbSubrecordType::Displ as u8,
+            interval,
+            self.class_mask,
+        ))
+    }
+
+    /// 10-second trended values (all classes)
+    pub fn trend_10s(&self) -> Vec<u8> {
+        // Interval must be positive, its value is ignored for trends
+        create_frame(&create_phdb_request(
+            PhdbSubrecordType::Trend10s as u8,
+            1,
+            PHDBCL_REQ_ALL,
+        ))
+    }
+
+    /// 60-second trended values (all classes)
+    pub fn trend_60s(&self) -> Vec<u8> {
+        create_frame(&create_phdb_request(
+            PhdbSubrecordType::Trend60s as u8,
+            1,
+            PHDBCL_REQ_ALL,
+        ))
+    }
+
+    /// Stop one kind of physiological transmission
+    pub fn stop_physiological(&self, subtype: PhdbSubrecordType) -> Vec<u8> {
+        create_frame(&create_phdb_request(subtype as u8, 0, 0))
+    }
+
+    /// Continuous waveforms (at most 8 are sent)
+    pub fn waveforms(&self, waveforms: &[WaveformType]) -> Vec<u8> {
+        let types: Vec<u8> = waveforms.iter().map(|&wf| wf as u8).collect();
+        create_frame(&create_waveform_request(&types, WF_REQ_CONT_START))
+    }
+
+    /// Stop waveform transmission
+    pub fn stop_waveforms(&self) -> Vec<u8> {
+        create_frame(&create_waveform_request(&[], WF_REQ_CONT_STOP))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::dri_types::PHDBCL_REQ_EXT1_MASK;
+    use crate::protocol::FrameParser;
+
+    #[test]
+    fn test_displayed_values_frame() {
+        let frame = RequestBuilder::new()
+            .with_class_mask(PHDBCL_REQ_EXT1_MASK)
+            .displayed_values(10);
+
+        let frames = FrameParser::new().process_bytes(&frame);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(
+            frames[0].as_ref().unwrap().data,
+            create_phdb_request(1, 10, PHDBCL_REQ_EXT1_MASK)
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Record sequence tracking
+//!
+//! The monitor numbers every record it sends in `DriHeader::r_nbr` (a `u8`
+//! that wraps around). A jump in that counter means records were lost on
+//! the line.
+
+use log::warn;
+
+/// Detects missed records from consecutive `r_nbr` values
+#[derive(Debug, Clone, Default)]
+pub struct SequenceMonitor {
+    last: Option<u8>,
+    missed: u64,
+    gaps: u64,
+}
+
+impl SequenceMonitor {
+    /// Create a monitor with no record seen yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the next `r_nbr` and return how many records were missed before it
+    pub fn observe(&mut self, r_nbr: u8) -> u8 {
+        let missed = match self.last {
+            Some(last) => r_nbr.wrapping_sub(last).wrapping_sub(1),
+            None => 0,
+        };
+        self.last = Some(r_nbr);
+
+        // A repeated number wraps to 255; treat it as a duplicate, not a gap
+        if missed == u8::MAX {
+            warn!(r_nbr; "Duplicate record number");
+            return 0;
+        }
+
+        if missed > 0 {
+            warn!(missed, r_nbr; "Missed records");
+            self.missed += missed as u64;
+            self.gaps += 1;
+        }
+        missed
+    }
+
+    /// Total number of records missed so far
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    /// Number of discontinuities detected so far
+    pub fn gaps(&self) -> u64 {
+        self.gaps
+    }
+
+    /// Forget the last record number (e.g. after a reconnect)
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_gaps_and_wraparound() {
+        let mut monitor = SequenceMonitor::new();
+        assert_eq!(monitor.observe(10), 0);
+        assert_eq!(monitor.observe(11), 0);
+        assert_eq!(monitor.observe(14), 2);
+        assert_eq!(monitor.observe(254), 239);
+        assert_eq!(monitor.observe(255), 0);
+        assert_eq!(monitor.observe(0), 0);
+        assert_eq!(monitor.observe(2), 1);
+        assert_eq!(monitor.observe(2), 0);
+
+        assert_eq!(monitor.missed(), 242);
+        assert_eq!(monitor.gaps(), 3);
+
+        monitor.reset();
+        assert_eq!(monitor.observe(100), 0);
+    }
+}
@@ -0,0 +1,477 @@
+//! DRI frame construction for the simulator
+
+use super::SimVitals;
+use super::signals::{generate_co2_sample, generate_ecg_sample, generate_pleth_sample};
+use crate::Result;
+use crate::constants::special_values::DATA_INVALID;
+use crate::constants::{BIT5, CTRL_CHAR, FRAME_CHAR, HEADER_SIZE, WaveformType};
+use crate::decode::waveforms::WaveformStatus;
+use chrono::{DateTime, Utc};
+use std::io::Write;
+use std::time::Duration;
+
+// Main types
+const DRI_MT_PHDB: u16 = 0;
+const DRI_MT_WAVE: u16 = 1;
+
+// Physiological subrecord type
+const DRI_PH_DISPL: u8 = 1;
+
+// Physiological class (Basic)
+const DRI_PHDBCL_BASIC: u8 = 0;
+
+/// Request decoded from a client frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    /// Displayed values every `interval` seconds
+    PhdbRequest { interval: u16 },
+    /// Continuous waveforms (waveform type codes)
+    WaveformRequest { waveforms: Vec<u8> },
+    /// Stop all transmission
+    StopAll,
+}
+
+/// Parse a (stuffed) request frame sent by a client
+pub fn parse_request(data: &[u8]) -> Option<Request> {
+    // Very simplified parser - just look for unstuffed frames
+    let mut unstuffed = Vec::new();
+    let mut i = 0;
+    let mut in_frame = false;
+
+    while i < data.len() {
+        if data[i] == FRAME_CHAR {
+            if in_frame && !unstuffed.is_empty() {
+                // End of frame
+                break;
+            }
+            in_frame = true;
+            i += 1;
+            continue;
+        }
+
+        if !in_frame {
+            i += 1;
+            continue;
+        }
+
+        if data[i] == CTRL_CHAR && i + 1 < data.len() {
+            unstuffed.push(data[i + 1] | BIT5);
+            i += 2;
+        } else {
+            unstuffed.push(data[i]);
+            i += 1;
+        }
+    }
+
+    if unstuffed.len() < HEADER_SIZE {
+        return None;
+    }
+
+    // Parse main type
+    let main_type = u16::from_le_bytes([unstuffed[16], unstuffed[17]]);
+
+    match main_type {
+        DRI_MT_PHDB => {
+            if unstuffed.len() >= HEADER_SIZE + 3 {
+                let interval =
+                    u16::from_le_bytes([unstuffed[HEADER_SIZE + 1], unstuffed[HEADER_SIZE + 2]]);
+                if interval == 0 {
+                    Some(Request::StopAll)
+                } else {
+                    Some(Request::PhdbRequest { interval })
+                }
+            } else {
+                None
+            }
+        }
+        DRI_MT_WAVE => {
+            if unstuffed.len() >= HEADER_SIZE + 12 {
+                let req_type =
+                    u16::from_le_bytes([unstuffed[HEADER_SIZE], unstuffed[HEADER_SIZE + 1]]);
+                if req_type == 1 {
+                    // Stop waveforms
+                    Some(Request::StopAll)
+                } else {
+                    let mut waveforms = Vec::new();
+                    for i in 0..8 {
+                        let wf_type = unstuffed[HEADER_SIZE + 4 + i];
+                        if wf_type == 0xFF {
+                            break;
+                        }
+                        if wf_type != 0 {
+                            waveforms.push(wf_type);
+                        }
+                    }
+                    Some(Request::WaveformRequest { waveforms })
+                }
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Build an unstuffed displayed values record (basic class)
+///
+/// Group offsets match `decode_basic_class`, so the record decodes back to
+/// `vitals` (rounded to the resolution of each field).
+pub fn create_phdb_frame(frame_nbr: u8, vitals: &SimVitals) -> Vec<u8> {
+    create_phdb_frame_at(frame_nbr, vitals, Utc::now())
+}
+
+/// [`create_phdb_frame`] stamped with `time` instead of the current time
+pub fn create_phdb_frame_at(frame_nbr: u8, vitals: &SimVitals, time: DateTime<Utc>) -> Vec<u8> {
+    let mut data = vec![0u8; HEADER_SIZE + 1088]; // Header + physiological data subrecord
+
+    let timestamp = time.timestamp() as u32;
+
+    // Header
+    data[0..2].copy_from_slice(&((HEADER_SIZE + 1088) as u16).to_le_bytes());
+    data[2] = frame_nbr;
+    data[3] = 8; // DRI_LEVEL_02
+    data[6..10].copy_from_slice(&timestamp.to_le_bytes());
+    data[16..18].copy_from_slice(&DRI_MT_PHDB.to_le_bytes());
+
+    // Subrecord descriptor
+    data[18..20].copy_from_slice(&0u16.to_le_bytes()); // offset 0
+    data[20] = DRI_PH_DISPL; // subrecord type
+    data[21..23].copy_from_slice(&0u16.to_le_bytes());
+    data[23] = 0xFF; // end marker
+
+    // Physiological data (1088 bytes)
+    let phys_start = HEADER_SIZE;
+
+    // Timestamp (4 bytes)
+    data[phys_start..phys_start + 4].copy_from_slice(&timestamp.to_le_bytes());
+
+    // Basic class data starts after the timestamp
+    let basic = &mut data[phys_start + 4..];
+    let SimVitals {
+        hr,
+        spo2,
+        nibp_sys,
+        nibp_dia,
+        temp,
+        etco2,
+        rr,
+        peep,
+        ppeak,
+        tv,
+        asystole,
+        apnea,
+    } = *vitals;
+
+    // ECG (offset 0, 16 bytes)
+    // exists + active (+ asystole, bit 2)
+    write_group_header(&mut basic[0..], 0x0003 | (asystole as u32) << 2);
+    write_value(&mut basic[6..], hr, 1.0); // HR
+    write_value(&mut basic[8..], 0.0, 100.0); // ST1
+    write_value(&mut basic[10..], 0.0, 100.0); // ST2
+    write_value(&mut basic[12..], 0.0, 100.0); // ST3
+    write_value(&mut basic[14..], rr, 1.0); // Impedance RR
+
+    // INVP1-4 (offsets 16, 30, 44, 58, 14 bytes each): not connected
+    for offset in [16, 30, 44, 58] {
+        write_invalid_values(&mut basic[offset + 6..offset + 14]);
+    }
+
+    // NIBP (offset 76, 14 bytes)
+    // exists + active + auto mode (bit 3)
+    write_group_header(&mut basic[76..], 0x0003 | 1 << 3);
+    write_value(&mut basic[82..], nibp_sys, 100.0);
+    write_value(&mut basic[84..], nibp_dia, 100.0);
+    write_value(&mut basic[86..], (nibp_sys + 2.0 * nibp_dia) / 3.0, 100.0);
+    write_value(&mut basic[88..], hr, 1.0);
+
+    // TEMP1 (offset 90, 8 bytes)
+    write_group_header(&mut basic[90..], 0x0003);
+    write_value(&mut basic[96..], temp, 100.0);
+
+    // TEMP2-4 (offsets 98, 106, 114): not connected
+    for offset in [98, 106, 114] {
+        write_invalid_values(&mut basic[offset + 6..offset + 8]);
+    }
+
+    // SpO2 (offset 122, 14 bytes)
+    write_group_header(&mut basic[122..], 0x0003);
+    write_value(&mut basic[128..], spo2, 100.0); // SpO2
+    write_value(&mut basic[130..], hr, 1.0); // PR
+    write_value(&mut basic[132..], 15.0, 10.0); // IR amplitude
+
+    // CO2 (offset 136, 14 bytes)
+    // exists + active (+ apnea, bit 2)
+    write_group_header(&mut basic[136..], 0x0003 | (apnea as u32) << 2);
+    write_value(&mut basic[142..], etco2, 100.0); // EtCO2
+    write_value(&mut basic[144..], 0.4, 100.0); // FiCO2
+    write_value(&mut basic[146..], rr, 1.0); // RR
+    write_value(&mut basic[148..], 760.0, 10.0); // Ambient pressure (mmHg)
+
+    // O2 (offset 150, 10 bytes)
+    write_group_header(&mut basic[150..], 0x0003);
+    write_value(&mut basic[156..], 21.0, 100.0); // EtO2
+    write_value(&mut basic[158..], 21.0, 100.0); // FiO2
+
+    // N2O (offset 160, 10 bytes)
+    write_group_header(&mut basic[160..], 0x0001); // exists but not active
+    write_invalid_values(&mut basic[166..170]);
+
+    // AA (offset 170, 12 bytes)
+    write_group_header(&mut basic[170..], 0x0001);
+    write_invalid_values(&mut basic[176..182]);
+
+    // Flow & Volume (offset 182, 22 bytes)
+    write_group_header(&mut basic[182..], 0x0003); // active
+    write_value(&mut basic[188..], rr, 1.0); // RR
+    write_value(&mut basic[190..], ppeak, 100.0); // Ppeak
+    write_value(&mut basic[192..], peep, 100.0); // PEEP
+    write_value(&mut basic[194..], 0.0, 100.0); // Pplat
+    write_value(&mut basic[196..], tv, 10.0); // TV insp
+    write_value(&mut basic[198..], tv, 10.0); // TV exp
+    write_value(&mut basic[200..], 50.0, 100.0); // Compliance
+    write_value(&mut basic[202..], rr * tv / 1000.0, 100.0); // MV (l/min, TV in ml)
+
+    // Cardiac output (offset 204, 14 bytes): not connected
+    write_invalid_values(&mut basic[210..218]);
+
+    // NMT (offset 218, 12 bytes): not connected
+    write_invalid_values(&mut basic[224..230]);
+
+    // SvO2 (offset 236, 10 bytes): not connected
+    write_invalid_values(&mut basic[242..246]);
+
+    // Class marker at end (bytes 1086-1087)
+    let class_offset = phys_start + 1086;
+    let cl_drilvl_subt = (DRI_PHDBCL_BASIC as u16) << 8 | DRI_PH_DISPL as u16;
+    data[class_offset..class_offset + 2].copy_from_slice(&cl_drilvl_subt.to_le_bytes());
+
+    data
+}
+
+/// Build an unstuffed waveform record from `(waveform type, samples)` pairs
+///
+/// `status` is written to every subrecord; the pacer and lead-off flags
+/// only apply to ECG waveforms and are cleared for the others.
+pub fn create_waveform_frame(
+    frame_nbr: u8,
+    subrecords: &[(u8, Vec<i16>)],
+    status: WaveformStatus,
+) -> Vec<u8> {
+    create_waveform_frame_at(frame_nbr, subrecords, status, Utc::now())
+}
+
+/// [`create_waveform_frame`] stamped with `time` instead of the current time
+pub fn create_waveform_frame_at(
+    frame_nbr: u8,
+    subrecords: &[(u8, Vec<i16>)],
+    status: WaveformStatus,
+    time: DateTime<Utc>,
+) -> Vec<u8> {
+    let timestamp = time.timestamp() as u32;
+
+    // Calculate data size (header per waveform + samples)
+    let total_size = HEADER_SIZE
+        + subrecords
+            .iter()
+            .map(|(_, samples)| 6 + samples.len() * 2)
+            .sum::<usize>();
+
+    let mut data = vec![0u8; total_size];
+
+    // Header
+    data[0..2].copy_from_slice(&(total_size as u16).to_le_bytes());
+    data[2] = frame_nbr;
+    data[3] = 8; // DRI_LEVEL_02
+    data[6..10].copy_from_slice(&timestamp.to_le_bytes());
+    data[16..18].copy_from_slice(&DRI_MT_WAVE.to_le_bytes());
+
+    // Subrecords
+    let mut offset = 0u16;
+    for (i, (wf_type, samples)) in subrecords.iter().enumerate() {
+        data[18 + i * 3..18 + i * 3 + 2].copy_from_slice(&offset.to_le_bytes());
+        data[18 + i * 3 + 2] = *wf_type;
+        offset += (6 + samples.len() * 2) as u16;
+    }
+    data[18 + subrecords.len() * 3 + 2] = 0xFF; // end marker
+
+    // Waveform data
+    let mut data_offset = HEADER_SIZE;
+    for (wf_type, samples) in subrecords {
+        let status = if is_ecg(*wf_type) {
+            status
+        } else {
+            WaveformStatus {
+                gap: status.gap,
+                ..WaveformStatus::default()
+            }
+        };
+
+        // Waveform header (6 bytes)
+        data[data_offset..data_offset + 2].copy_from_slice(&(samples.len() as u16).to_le_bytes());
+        data[data_offset + 2..data_offset + 4].copy_from_slice(&status.to_u16().to_le_bytes());
+        data_offset += 6;
+
+        for sample in samples {
+            data[data_offset..data_offset + 2].copy_from_slice(&sample.to_le_bytes());
+            data_offset += 2;
+        }
+    }
+
+    data
+}
+
+/// Continuous waveform source emitting every sample exactly once
+///
+/// Each call to [`WaveformStream::next_frame`] carries the samples due
+/// since the previous call at each waveform's declared
+/// `samples_per_second`, so frames can be sent at any cadence.
+#[derive(Debug, Clone)]
+pub struct WaveformStream {
+    channels: Vec<Channel>,
+    epoch: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+struct Channel {
+    wf_type: u8,
+    sample_rate: u16,
+    emitted: u64,
+}
+
+impl WaveformStream {
+    /// Start a stream for the given waveform type codes
+    pub fn new(waveforms: &[u8]) -> Self {
+        let channels = waveforms
+            .iter()
+            .map(|&wf_type| Channel {
+                wf_type,
+                sample_rate: WaveformType::from_u8(wf_type)
+                    .map_or(0, |wf| wf.info().samples_per_second),
+                emitted: 0,
+            })
+            .collect();
+        Self {
+            channels,
+            epoch: None,
+        }
+    }
+
+    /// Stamp frames with `epoch` plus the elapsed time instead of the
+    /// current time, so the frames only depend on their inputs
+    pub fn with_epoch(mut self, epoch: DateTime<Utc>) -> Self {
+        self.epoch = Some(epoch);
+        self
+    }
+
+    /// Waveform type codes in this stream
+    pub fn waveforms(&self) -> Vec<u8> {
+        self.channels.iter().map(|c| c.wf_type).collect()
+    }
+
+    /// Build a frame with all samples due up to `elapsed` since the stream started
+    pub fn next_frame(
+        &mut self,
+        frame_nbr: u8,
+        elapsed: Duration,
+        hr: f64,
+        status: WaveformStatus,
+    ) -> Vec<u8> {
+        let subrecords: Vec<(u8, Vec<i16>)> = self
+            .channels
+            .iter_mut()
+            .map(|channel| {
+                let due = (elapsed.as_secs_f64() * channel.sample_rate as f64).floor() as u64;
+                let samples = (channel.emitted..due.max(channel.emitted))
+                    .map(|n| {
+                        let t = n as f64 / channel.sample_rate as f64;
+                        generate_sample(channel.wf_type, &t, hr)
+                    })
+                    .collect();
+                channel.emitted = due.max(channel.emitted);
+                (channel.wf_type, samples)
+            })
+            .collect();
+
+        let time = match self.epoch {
+            Some(epoch) => epoch + elapsed,
+            None => Utc::now(),
+        };
+        create_waveform_frame_at(frame_nbr, &subrecords, status, time)
+    }
+}
+
+/// Sample of a waveform type at time `t` (seconds)
+fn generate_sample(wf_type: u8, t: &f64, hr: f64) -> i16 {
+    match WaveformType::from_u8(wf_type) {
+        Some(WaveformType::Ecg1 | WaveformType::Ecg2 | WaveformType::Ecg3) => {
+            generate_ecg_sample(t, hr)
+        }
+        Some(WaveformType::Pleth | WaveformType::Pleth2) => generate_pleth_sample(t, hr),
+        Some(WaveformType::Co2) => generate_co2_sample(t, hr),
+        _ => 0,
+    }
+}
+
+fn is_ecg(wf_type: u8) -> bool {
+    WaveformType::from_u8(wf_type).is_some_and(|wf| wf.is_ecg())
+}
+
+fn write_group_header(data: &mut [u8], status: u32) {
+    data[0..4].copy_from_slice(&status.to_le_bytes());
+    data[4..6].copy_from_slice(&0u16.to_le_bytes()); // label
+}
+
+/// Mark every value in a group as invalid (no data)
+fn write_invalid_values(values: &mut [u8]) {
+    for raw in values.chunks_exact_mut(2) {
+        raw.copy_from_slice(&DATA_INVALID.to_le_bytes());
+    }
+}
+
+/// Write `value` in units of `1/factor` (e.g. factor 100 for 1/100 mmHg)
+fn write_value(data: &mut [u8], value: f64, factor: f64) {
+    let raw = (value * factor).round() as i16;
+    data[0..2].copy_from_slice(&raw.to_le_bytes());
+}
+
+/// Frame, stuff and checksum a record for transmission
+pub fn encode_frame(data: &[u8]) -> Vec<u8> {
+    // Calculate checksum on STUFFED data as per GE DRI protocol
+    let mut checksum = 0u8;
+    let mut stuffed = Vec::new();
+    stuffed.push(FRAME_CHAR);
+
+    // Stuff data and calculate checksum on stuffed bytes
+    for &byte in data {
+        if byte == FRAME_CHAR || byte == CTRL_CHAR {
+            stuffed.push(CTRL_CHAR);
+            stuffed.push(byte & !BIT5);
+            // Add BOTH stuffed bytes to checksum
+            checksum = checksum.wrapping_add(CTRL_CHAR);
+            checksum = checksum.wrapping_add(byte & !BIT5);
+        } else {
+            stuffed.push(byte);
+            checksum = checksum.wrapping_add(byte);
+        }
+    }
+
+    // Now stuff and add the checksum itself
+    if checksum == FRAME_CHAR || checksum == CTRL_CHAR {
+        stuffed.push(CTRL_CHAR);
+        stuffed.push(checksum & !BIT5);
+    } else {
+        stuffed.push(checksum);
+    }
+
+    stuffed.push(FRAME_CHAR);
+
+    stuffed
+}
+
+/// Encode a record and write it to a port
+pub fn send_frame<W: Write + ?Sized>(port: &mut W, data: &[u8]) -> Result<()> {
+    port.write_all(&encode_frame(data))?;
+    port.flush()?;
+
+    Ok(())
+}
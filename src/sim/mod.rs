@@ -0,0 +1,84 @@
+//! Simulated GE monitor (frame and signal generation)
+//!
+//! Requires the `sim` feature. Used by the `faker` binary and by tests
+//! that need synthetic DRI frames with known values.
+
+pub mod frames;
+pub mod scenario;
+pub mod signals;
+
+pub use frames::{
+    Request, WaveformStream, create_phdb_frame, create_phdb_frame_at, create_waveform_frame,
+    create_waveform_frame_at, encode_frame, parse_request, send_frame,
+};
+pub use scenario::{Keyframe, Scenario};
+pub use signals::{generate_co2_sample, generate_ecg_sample, generate_pleth_sample, vary_value};
+
+use rand::Rng;
+
+/// Simulated vital signs written into physiological frames
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimVitals {
+    /// Heart rate (bpm)
+    pub hr: f64,
+    /// SpO2 (%)
+    pub spo2: f64,
+    /// NIBP systolic (mmHg)
+    pub nibp_sys: f64,
+    /// NIBP diastolic (mmHg)
+    pub nibp_dia: f64,
+    /// Temperature (°C)
+    pub temp: f64,
+    /// End-tidal CO2 (%)
+    pub etco2: f64,
+    /// Respiration rate (breaths/min)
+    pub rr: f64,
+    /// PEEP (cmH2O)
+    pub peep: f64,
+    /// Peak pressure (cmH2O)
+    pub ppeak: f64,
+    /// Tidal volume (ml)
+    pub tv: f64,
+    /// ECG asystole alarm
+    pub asystole: bool,
+    /// CO2 apnea alarm
+    pub apnea: bool,
+}
+
+impl SimVitals {
+    /// Drift every value around its baseline with noise drawn from `rng`
+    ///
+    /// A seeded `rng` gives the same sequence of vitals on every run.
+    pub fn vary<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let baseline = Self::default();
+        self.hr = vary_value(rng, self.hr, baseline.hr, 5.0);
+        self.spo2 = vary_value(rng, self.spo2, baseline.spo2, 2.0);
+        self.nibp_sys = vary_value(rng, self.nibp_sys, baseline.nibp_sys, 10.0);
+        self.nibp_dia = vary_value(rng, self.nibp_dia, baseline.nibp_dia, 5.0);
+        self.temp = vary_value(rng, self.temp, baseline.temp, 0.3);
+        self.etco2 = vary_value(rng, self.etco2, baseline.etco2, 0.5);
+        self.rr = vary_value(rng, self.rr, baseline.rr, 2.0);
+        self.peep = vary_value(rng, self.peep, baseline.peep, 0.5);
+        self.ppeak = vary_value(rng, self.ppeak, baseline.ppeak, 2.0);
+        self.tv = vary_value(rng, self.tv, baseline.tv, 50.0);
+    }
+}
+
+impl Default for SimVitals {
+    fn default() -> Self {
+        Self {
+            hr: 75.0,
+            spo2: 98.0,
+            nibp_sys: 120.0,
+            nibp_dia: 80.0,
+            temp: 37.0,
+            etco2: 5.2,
+            rr: 16.0,
+            peep: 5.0,
+            ppeak: 20.0,
+            tv: 500.0,
+            asystole: false,
+            apnea: false,
+        }
+    }
+}
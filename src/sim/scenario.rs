@@ -0,0 +1,266 @@
+//! Scripted clinical scenarios for the simulator
+//!
+//! A scenario is a timeline of keyframes. Each keyframe sets some of the
+//! simulated values at a given time (seconds from the start); values are
+//! interpolated linearly between the keyframes that set them and held
+//! after the last one. Alarm flags switch at their keyframe.
+//!
+//! Scenario files are JSON:
+//!
+//! ```json
+//! {
+//!   "name": "desaturation",
+//!   "keyframes": [
+//!     { "at": 0, "spo2": 98, "hr": 75 },
+//!     { "at": 60, "spo2": 84, "hr": 96 },
+//!     { "at": 180, "spo2": 97, "hr": 76 }
+//!   ]
+//! }
+//! ```
+//!
+//! Keyframe fields: `at` (required), `hr`, `spo2`, `rr`, `etco2`,
+//! `asystole` and `apnea`. Built-in presets: `desaturation`,
+//! `bradycardia`, `apnea`.
+
+use super::SimVitals;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Names of the built-in scenarios
+pub const PRESETS: &[&str] = &["desaturation", "bradycardia", "apnea"];
+
+/// A scripted timeline of vital signs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    /// Scenario name
+    pub name: String,
+    /// Keyframes, sorted by time
+    pub keyframes: Vec<Keyframe>,
+}
+
+/// Values set at a point of the timeline
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// Seconds from the start of the scenario
+    pub at: f64,
+    /// Heart rate (bpm)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hr: Option<f64>,
+    /// SpO2 (%)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spo2: Option<f64>,
+    /// Respiration rate (breaths/min)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rr: Option<f64>,
+    /// End-tidal CO2 (%)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etco2: Option<f64>,
+    /// ECG asystole alarm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asystole: Option<bool>,
+    /// CO2 apnea alarm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub apnea: Option<bool>,
+}
+
+impl Scenario {
+    /// Load a scenario from a JSON file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::from_json(&contents)
+    }
+
+    /// Parse a scenario from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut scenario: Scenario = serde_json::from_str(json)?;
+        scenario.keyframes.sort_by(|a, b| a.at.total_cmp(&b.at));
+        Ok(scenario)
+    }
+
+    /// Built-in scenario by name (see [`PRESETS`])
+    pub fn preset(name: &str) -> Option<Self> {
+        let keyframes = match name {
+            // SpO2 falls to the mid 80s with a compensatory tachycardia
+            "desaturation" => vec![
+                key(0.0).hr(75.0).spo2(98.0),
+                key(60.0).hr(96.0).spo2(84.0),
+                key(120.0).hr(98.0).spo2(83.0),
+                key(180.0).hr(76.0).spo2(97.0),
+            ],
+            // Slowing heart rate ending in a short asystole
+            "bradycardia" => vec![
+                key(0.0).hr(75.0),
+                key(60.0).hr(38.0),
+                key(120.0).hr(35.0),
+                Keyframe {
+                    asystole: Some(true),
+                    ..key(150.0).hr(0.0)
+                },
+                Keyframe {
+                    asystole: Some(false),
+                    ..key(165.0).hr(40.0)
+                },
+                key(225.0).hr(72.0),
+            ],
+            // Breathing stops for a minute, SpO2 drifts down
+            "apnea" => vec![
+                key(0.0).rr(16.0).etco2(5.2).spo2(98.0),
+                key(30.0).rr(16.0).etco2(5.2),
+                Keyframe {
+                    apnea: Some(true),
+                    ..key(35.0).rr(0.0).etco2(0.0).spo2(98.0)
+                },
+                Keyframe {
+                    apnea: Some(false),
+                    ..key(95.0).rr(0.0).etco2(0.0).spo2(90.0)
+                },
+                key(100.0).rr(16.0).etco2(5.2),
+                key(150.0).spo2(98.0),
+            ],
+            _ => return None,
+        };
+
+        Some(Self {
+            name: name.to_string(),
+            keyframes,
+        })
+    }
+
+    /// Overwrite the scripted values of `vitals` at `elapsed` seconds
+    ///
+    /// Values the scenario never sets are left untouched.
+    pub fn apply(&self, elapsed: f64, vitals: &mut SimVitals) {
+        if let Some(hr) = self.interpolate(elapsed, |k| k.hr) {
+            vitals.hr = hr;
+        }
+        if let Some(spo2) = self.interpolate(elapsed, |k| k.spo2) {
+            vitals.spo2 = spo2;
+        }
+        if let Some(rr) = self.interpolate(elapsed, |k| k.rr) {
+            vitals.rr = rr;
+        }
+        if let Some(etco2) = self.interpolate(elapsed, |k| k.etco2) {
+            vitals.etco2 = etco2;
+        }
+        if let Some(asystole) = self.step(elapsed, |k| k.asystole) {
+            vitals.asystole = asystole;
+        }
+        if let Some(apnea) = self.step(elapsed, |k| k.apnea) {
+            vitals.apnea = apnea;
+        }
+    }
+
+    /// Time of the last keyframe
+    pub fn duration(&self) -> f64 {
+        self.keyframes.last().map_or(0.0, |k| k.at)
+    }
+
+    fn interpolate(&self, t: f64, field: impl Fn(&Keyframe) -> Option<f64>) -> Option<f64> {
+        let points = self
+            .keyframes
+            .iter()
+            .filter_map(|k| field(k).map(|v| (k.at, v)));
+
+        let mut previous: Option<(f64, f64)> = None;
+        for (at, value) in points {
+            if at >= t {
+                return Some(match previous {
+                    Some((prev_at, prev_value)) if at > prev_at => {
+                        prev_value + (value - prev_value) * (t - prev_at) / (at - prev_at)
+                    }
+                    Some((_, prev_value)) => prev_value,
+                    None => value,
+                });
+            }
+            previous = Some((at, value));
+        }
+        previous.map(|(_, value)| value)
+    }
+
+    fn step(&self, t: f64, field: impl Fn(&Keyframe) -> Option<bool>) -> Option<bool> {
+        self.keyframes
+            .iter()
+            .take_while(|k| k.at <= t)
+            .filter_map(field)
+            .last()
+    }
+}
+
+fn key(at: f64) -> Keyframe {
+    Keyframe {
+        at,
+        ..Keyframe::default()
+    }
+}
+
+impl Keyframe {
+    fn hr(mut self, hr: f64) -> Self {
+        self.hr = Some(hr);
+        self
+    }
+
+    fn spo2(mut self, spo2: f64) -> Self {
+        self.spo2 = Some(spo2);
+        self
+    }
+
+    fn rr(mut self, rr: f64) -> Self {
+        self.rr = Some(rr);
+        self
+    }
+
+    fn etco2(mut self, etco2: f64) -> Self {
+        self.etco2 = Some(etco2);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolation_and_flags() {
+        let scenario = Scenario::from_json(
+            r#"{
+                "name": "test",
+                "keyframes": [
+                    { "at": 10, "spo2": 90, "apnea": true },
+                    { "at": 0, "spo2": 98, "hr": 60 },
+                    { "at": 20, "spo2": 80, "apnea": false }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let mut vitals = SimVitals::default();
+        scenario.apply(5.0, &mut vitals);
+        assert_eq!(vitals.spo2, 94.0);
+        assert_eq!(vitals.hr, 60.0);
+        assert!(!vitals.apnea);
+
+        scenario.apply(15.0, &mut vitals);
+        assert_eq!(vitals.spo2, 85.0);
+        assert!(vitals.apnea);
+
+        scenario.apply(100.0, &mut vitals);
+        assert_eq!(vitals.spo2, 80.0);
+        assert!(!vitals.apnea);
+        assert_eq!(vitals.rr, SimVitals::default().rr);
+    }
+
+    #[test]
+    fn test_presets() {
+        for name in PRESETS {
+            let scenario = Scenario::preset(name).unwrap();
+            assert!(scenario.duration() > 0.0);
+        }
+        assert!(Scenario::preset("unknown").is_none());
+
+        let bradycardia = Scenario::preset("bradycardia").unwrap();
+        let mut vitals = SimVitals::default();
+        bradycardia.apply(155.0, &mut vitals);
+        assert!(vitals.asystole);
+    }
+}
@@ -0,0 +1,70 @@
+//! Synthetic waveform samples and vital sign drift
+
+use rand::Rng;
+
+/// ECG sample (P-QRS-T shape) at time `phase` (seconds)
+pub fn generate_ecg_sample(phase: &f64, hr: f64) -> i16 {
+    let freq = hr / 60.0; // Hz
+    let t = phase * freq;
+    let t_mod = t - t.floor();
+
+    // Simplified ECG shape
+    let value = if t_mod < 0.1 {
+        // P wave
+        (t_mod * 10.0).sin() * 200.0
+    } else if t_mod < 0.15 {
+        0.0
+    } else if t_mod < 0.2 {
+        // Q wave
+        -300.0
+    } else if t_mod < 0.25 {
+        // R wave
+        1500.0
+    } else if t_mod < 0.3 {
+        // S wave
+        -500.0
+    } else if t_mod < 0.5 {
+        // ST segment
+        0.0
+    } else if t_mod < 0.65 {
+        // T wave
+        ((t_mod - 0.5) * 6.67).sin() * 400.0
+    } else {
+        0.0
+    };
+
+    value as i16
+}
+
+/// Plethysmograph sample at time `phase` (seconds)
+pub fn generate_pleth_sample(phase: &f64, hr: f64) -> i16 {
+    let freq = hr / 60.0;
+    let t = phase * freq;
+
+    // Plethysmograph wave (0-100%)
+    let value = 50.0 + 30.0 * (t * 2.0 * std::f64::consts::PI).sin();
+    (value * 10.0) as i16 // Scale to 1/10%
+}
+
+/// CO2 sample at time `phase` (seconds)
+pub fn generate_co2_sample(phase: &f64, hr: f64) -> i16 {
+    let freq = hr / 60.0 / 4.0; // Slower than HR
+    let t = phase * freq;
+    let t_mod = t - t.floor();
+
+    // Square-ish wave for CO2: inspiration, then the expiratory plateau
+    let value = if t_mod < 0.3 {
+        0.4 // FiCO2 (%)
+    } else {
+        5.2 // EtCO2 (%)
+    };
+
+    (value * 100.0) as i16 // Scale to 1/100%
+}
+
+/// Move `current` towards `target` with some noise drawn from `rng`
+pub fn vary_value<R: Rng + ?Sized>(rng: &mut R, current: f64, target: f64, max_change: f64) -> f64 {
+    let diff = target - current;
+    let change = (diff / 10.0).clamp(-max_change, max_change);
+    current + change + (rng.r#gen::<f64>() - 0.5) * max_change * 0.3
+}
@@ -0,0 +1,138 @@
+//! Reader for files written by [`BinaryWriter`](super::BinaryWriter)
+
+use super::binary_writer::BINARY_MAGIC;
+use super::output::open_input;
+use super::raw_reader::read_full;
+use crate::decode::DriRecord;
+use anyhow::{Result, anyhow, bail};
+use std::io::Read;
+use std::path::Path;
+
+/// Largest record accepted, to stop early on a corrupt length
+const MAX_BINARY_RECORD: usize = 16 * 1024 * 1024;
+
+/// Iterates over the records of a binary file
+///
+/// Iteration stops at the end of the input or after the first error, since
+/// record boundaries cannot be trusted past it.
+pub struct BinaryReader<R: Read = Box<dyn Read + Send>> {
+    reader: R,
+    done: bool,
+}
+
+impl BinaryReader {
+    /// Open a binary file, decompressing it if the path ends in `.gz`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(open_input(path)?)
+    }
+}
+
+impl<R: Read> BinaryReader<R> {
+    /// Read records from any byte source; fails if it is not a binary file
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; BINARY_MAGIC.len()];
+        if read_full(&mut reader, &mut magic)? != magic.len() || &magic != BINARY_MAGIC {
+            bail!("Not a binary DRI record file");
+        }
+        Ok(Self {
+            reader,
+            done: false,
+        })
+    }
+
+    fn read_record(&mut self) -> Option<Result<DriRecord>> {
+        let mut len = [0u8; 4];
+        match read_full(&mut self.reader, &mut len) {
+            Ok(0) => return None,
+            Ok(4) => {}
+            Ok(_) => return Some(Err(anyhow!("Truncated binary record length"))),
+            Err(e) => return Some(Err(e.into())),
+        }
+
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_BINARY_RECORD {
+            return Some(Err(anyhow!("Binary record too large: {} bytes", len)));
+        }
+        let mut bytes = vec![0u8; len];
+        match read_full(&mut self.reader, &mut bytes) {
+            Ok(n) if n == len => Some(DriRecord::from_bytes(&bytes)),
+            Ok(_) => Some(Err(anyhow!("Truncated binary record"))),
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
+
+impl<R: Read> Iterator for BinaryReader<R> {
+    type Item = Result<DriRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let item = self.read_record();
+        if !matches!(item, Some(Ok(_))) {
+            self.done = true;
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::WaveformType;
+    use crate::constants::dri_types::{PhdbClass, PhdbSubrecordType};
+    use crate::decode::physiological::PhysiologicalData;
+    use crate::decode::waveforms::{WaveformData, WaveformStatus};
+    use crate::storage::BinaryWriter;
+    use crate::storage::output::SharedBuffer;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_records_round_trip() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let mut phys =
+            PhysiologicalData::empty(timestamp, PhdbClass::Basic, PhdbSubrecordType::Displ);
+        phys.spo2 = Some(97.5);
+        phys.received_at = Some(timestamp);
+        let waveform = WaveformData {
+            timestamp,
+            waveform_type: WaveformType::Ecg1,
+            plug_id: 0,
+            samples: vec![-3, 0, 12],
+            nominal_rate: 300,
+            effective_rate: None,
+            status: WaveformStatus::default(),
+            pacer_indices: vec![1],
+        };
+
+        let bytes = DriRecord::Physiological(phys.clone()).to_bytes().unwrap();
+        let Ok(DriRecord::Physiological(decoded)) = DriRecord::from_bytes(&bytes) else {
+            panic!("physiological record expected");
+        };
+        assert_eq!(decoded.spo2, Some(97.5));
+        assert_eq!(decoded.received_at, Some(timestamp));
+        assert_eq!(decoded.spo2_pr, None);
+
+        let mut future = bytes.clone();
+        future[0] += 1;
+        assert!(DriRecord::from_bytes(&future).is_err());
+
+        let buffer = SharedBuffer::default();
+        let mut writer = BinaryWriter::from_writer(buffer.clone()).unwrap();
+        writer.write_physiological(&phys).unwrap();
+        writer.write_waveform(&waveform).unwrap();
+        writer.finish().unwrap();
+
+        let records: Vec<_> = BinaryReader::new(buffer.bytes().as_slice())
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        let DriRecord::Waveform { waveforms } = &records[1] else {
+            panic!("waveform record expected");
+        };
+        assert_eq!(waveforms[0].samples, waveform.samples);
+        assert_eq!(waveforms[0].pacer_indices, [1]);
+    }
+}
@@ -0,0 +1,118 @@
+//! Compact binary writer for decoded records
+//!
+//! A file starts with [`BINARY_MAGIC`], followed by one entry per record:
+//! its length (u32 LE) and the bytes of [`DriRecord::to_bytes`], which
+//! begin with the format version. Output is gzip-compressed when the path
+//! ends in `.gz`. Files are read back with [`BinaryReader`](super::BinaryReader).
+
+use super::OutputFile;
+use super::sink::RecordSink;
+use crate::decode::DriRecord;
+use crate::decode::physiological::PhysiologicalData;
+use crate::decode::waveforms::WaveformData;
+use anyhow::Result;
+use std::io::Write;
+use std::path::Path;
+
+/// First bytes of a binary record file
+pub const BINARY_MAGIC: &[u8; 6] = b"DRIBIN";
+
+pub struct BinaryWriter {
+    file: OutputFile,
+    record_count: usize,
+    closed: bool,
+}
+
+impl BinaryWriter {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_output(OutputFile::create(path)?)
+    }
+
+    /// Create a writer over any target, such as a socket
+    pub fn from_writer<W: Write + Send + 'static>(writer: W) -> Result<Self> {
+        Self::from_output(OutputFile::from_writer(writer))
+    }
+
+    fn from_output(mut file: OutputFile) -> Result<Self> {
+        file.write_all(BINARY_MAGIC)?;
+        Ok(Self {
+            file,
+            record_count: 0,
+            closed: false,
+        })
+    }
+
+    /// Write a decoded record
+    ///
+    /// Records are buffered; call [`RecordSink::flush`] to push them out.
+    pub fn write_record(&mut self, record: &DriRecord) -> Result<()> {
+        let bytes = record.to_bytes()?;
+        self.file.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        self.file.write_all(&bytes)?;
+        self.record_count += 1;
+        Ok(())
+    }
+
+    /// Write physiological data as a record of its own
+    pub fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        self.write_record(&DriRecord::Physiological(data.clone()))
+    }
+
+    /// Write one waveform as a record of its own
+    pub fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
+        self.write_record(&DriRecord::Waveform {
+            waveforms: vec![data.clone()],
+        })
+    }
+
+    /// Number of records written so far
+    pub fn record_count(&self) -> usize {
+        self.record_count
+    }
+
+    /// Flush the file and, for gzip, write the trailer
+    pub fn finish(mut self) -> Result<()> {
+        self.close()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.closed = true;
+        self.file.finish()?;
+        Ok(())
+    }
+}
+
+impl RecordSink for BinaryWriter {
+    fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        BinaryWriter::write_physiological(self, data)
+    }
+
+    fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
+        BinaryWriter::write_waveform(self, data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.file.flush()?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        BinaryWriter::finish(*self)
+    }
+
+    /// Keep the record whole, including aux records
+    fn write_record(&mut self, record: &DriRecord) -> Result<()> {
+        BinaryWriter::write_record(self, record)
+    }
+}
+
+impl Drop for BinaryWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            log::error!("Failed to finish binary file: {}", e);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Typed physiological column table shared by the typed storage backends
+
+use crate::decode::physiological::PhysiologicalData;
+use chrono::{DateTime, Utc};
+
+/// How a physiological column is extracted and typed
+pub(crate) enum Column {
+    Timestamp(fn(&PhysiologicalData) -> DateTime<Utc>),
+    Flag(fn(&PhysiologicalData) -> bool),
+    Value(fn(&PhysiologicalData) -> Option<f64>),
+    Label(fn(&PhysiologicalData) -> Option<String>),
+}
+
+/// Physiological columns, in output order
+///
+/// Single source of truth for the CSV header and rows and for the typed
+/// backends' schemas.
+pub(crate) const PHYS_COLUMNS: &[(&str, Column)] = &[
+    ("timestamp", Column::Timestamp(|d| d.timestamp)),
+    ("class", Column::Label(|d| Some(format!("{:?}", d.class)))),
+    (
+        "subtype",
+        Column::Label(|d| Some(format!("{:?}", d.subtype))),
+    ),
+    ("plug_id", Column::Label(|d| Some(d.plug_id.to_string()))),
+    ("ecg_exists", Column::Flag(|d| d.ecg_status.exists)),
+    ("ecg_active", Column::Flag(|d| d.ecg_status.active)),
+    ("ecg_asystole", Column::Flag(|d| d.ecg_status.asystole)),
+    ("ecg_noise", Column::Flag(|d| d.ecg_status.noise)),
+    ("ecg_artifact", Column::Flag(|d| d.ecg_status.artifact)),
+    ("ecg_learning", Column::Flag(|d| d.ecg_status.learning)),
+    ("ecg_pacer_on", Column::Flag(|d| d.ecg_status.pacer_on)),
+    ("ecg_ch1_off", Column::Flag(|d| d.ecg_status.channel1_off)),
+    ("ecg_ch2_off", Column::Flag(|d| d.ecg_status.channel2_off)),
+    ("ecg_ch3_off", Column::Flag(|d| d.ecg_status.channel3_off)),
+    ("ecg_hr", Column::Value(|d| d.ecg_hr)),
+    ("ecg_st1_mm", Column::Value(|d| d.ecg_st1)),
+    ("ecg_st2_mm", Column::Value(|d| d.ecg_st2)),
+    ("ecg_st3_mm", Column::Value(|d| d.ecg_st3)),
+    ("ecg_rr", Column::Value(|d| d.ecg_rr)),
+    (
+        "ecg_hr_source",
+        Column::Label(|d| debug_label(&d.ecg_hr_source)),
+    ),
+    ("ecg_lead1", Column::Label(|d| debug_label(&d.ecg_lead1))),
+    ("ecg_lead2", Column::Label(|d| debug_label(&d.ecg_lead2))),
+    ("ecg_lead3", Column::Label(|d| debug_label(&d.ecg_lead3))),
+    ("nibp_exists", Column::Flag(|d| d.nibp_status.exists)),
+    ("nibp_active", Column::Flag(|d| d.nibp_status.active)),
+    ("nibp_auto_mode", Column::Flag(|d| d.nibp_status.auto_mode)),
+    ("nibp_stat_mode", Column::Flag(|d| d.nibp_status.stat_mode)),
+    ("nibp_measuring", Column::Flag(|d| d.nibp_status.measuring)),
+    ("nibp_stasis", Column::Flag(|d| d.nibp_status.stasis_on)),
+    (
+        "nibp_calibrating",
+        Column::Flag(|d| d.nibp_status.calibrating),
+    ),
+    (
+        "nibp_old_data",
+        Column::Flag(|d| d.nibp_status.data_older_than_60s),
+    ),
+    ("nibp_sys_mmhg", Column::Value(|d| d.nibp_sys)),
+    ("nibp_dia_mmhg", Column::Value(|d| d.nibp_dia)),
+    ("nibp_mean_mmhg", Column::Value(|d| d.nibp_mean)),
+    ("nibp_hr", Column::Value(|d| d.nibp_hr)),
+    ("invp1_exists", Column::Flag(|d| d.invp1_status.exists)),
+    ("invp1_active", Column::Flag(|d| d.invp1_status.active)),
+    (
+        "invp1_label",
+        Column::Label(|d| debug_label(&d.invp1_label)),
+    ),
+    ("invp1_sys_mmhg", Column::Value(|d| d.invp1_sys)),
+    ("invp1_dia_mmhg", Column::Value(|d| d.invp1_dia)),
+    ("invp1_mean_mmhg", Column::Value(|d| d.invp1_mean)),
+    ("invp1_hr", Column::Value(|d| d.invp1_hr)),
+    ("spo2_exists", Column::Flag(|d| d.spo2_status.exists)),
+    ("spo2_active", Column::Flag(|d| d.spo2_status.active)),
+    ("spo2_percent", Column::Value(|d| d.spo2)),
+    ("spo2_pr", Column::Value(|d| d.spo2_pr)),
+    ("spo2_ir_amp_percent", Column::Value(|d| d.spo2_ir_amp)),
+    ("temp1_exists", Column::Flag(|d| d.temp1_status.exists)),
+    ("temp1_active", Column::Flag(|d| d.temp1_status.active)),
+    (
+        "temp1_label",
+        Column::Label(|d| debug_label(&d.temp1_label)),
+    ),
+    ("temp1_celsius", Column::Value(|d| d.temp1)),
+    ("temp2_exists", Column::Flag(|d| d.temp2_status.exists)),
+    ("temp2_active", Column::Flag(|d| d.temp2_status.active)),
+    (
+        "temp2_label",
+        Column::Label(|d| debug_label(&d.temp2_label)),
+    ),
+    ("temp2_celsius", Column::Value(|d| d.temp2)),
+    ("co2_exists", Column::Flag(|d| d.co2_status.exists)),
+    ("co2_active", Column::Flag(|d| d.co2_status.active)),
+    ("co2_apnea", Column::Flag(|d| d.co2_status.apnea_co2)),
+    (
+        "co2_calibrating",
+        Column::Flag(|d| d.co2_status.calibrating_sensor),
+    ),
+    ("co2_zeroing", Column::Flag(|d| d.co2_status.zeroing_sensor)),
+    ("co2_occlusion", Column::Flag(|d| d.co2_status.occlusion)),
+    ("co2_air_leak", Column::Flag(|d| d.co2_status.air_leak)),
+    (
+        "co2_apnea_resp",
+        Column::Flag(|d| d.co2_status.apnea_from_resp),
+    ),
+    (
+        "co2_apnea_deactivated",
+        Column::Flag(|d| d.co2_status.apnea_deactivated),
+    ),
+    ("co2_wet", Column::Flag(|d| d.co2_status.wet_condition)),
+    ("co2_et_percent", Column::Value(|d| d.co2_et)),
+    ("co2_fi_percent", Column::Value(|d| d.co2_fi)),
+    ("co2_rr", Column::Value(|d| d.co2_rr)),
+    ("co2_amb_press_mmhg", Column::Value(|d| d.co2_amb_press)),
+    ("o2_exists", Column::Flag(|d| d.o2_status.exists)),
+    ("o2_active", Column::Flag(|d| d.o2_status.active)),
+    ("o2_calibrating", Column::Flag(|d| d.o2_status.calibrating)),
+    ("o2_meas_off", Column::Flag(|d| d.o2_status.measurement_off)),
+    ("o2_et_percent", Column::Value(|d| d.o2_et)),
+    ("o2_fi_percent", Column::Value(|d| d.o2_fi)),
+    ("n2o_exists", Column::Flag(|d| d.n2o_status.exists)),
+    ("n2o_active", Column::Flag(|d| d.n2o_status.active)),
+    (
+        "n2o_calibrating",
+        Column::Flag(|d| d.n2o_status.calibrating),
+    ),
+    (
+        "n2o_meas_off",
+        Column::Flag(|d| d.n2o_status.measurement_off),
+    ),
+    ("n2o_et_percent", Column::Value(|d| d.n2o_et)),
+    ("n2o_fi_percent", Column::Value(|d| d.n2o_fi)),
+    ("aa_exists", Column::Flag(|d| d.aa_status.exists)),
+    ("aa_active", Column::Flag(|d| d.aa_status.active)),
+    ("aa_calibrating", Column::Flag(|d| d.aa_status.calibrating)),
+    ("aa_meas_off", Column::Flag(|d| d.aa_status.measurement_off)),
+    ("aa_agent", Column::Label(|d| debug_label(&d.aa_agent))),
+    ("aa_et_percent", Column::Value(|d| d.aa_et)),
+    ("aa_fi_percent", Column::Value(|d| d.aa_fi)),
+    ("aa_mac", Column::Value(|d| d.aa_mac)),
+    ("flow_exists", Column::Flag(|d| d.flow_status.exists)),
+    ("flow_active", Column::Flag(|d| d.flow_status.active)),
+    (
+        "flow_disconnection",
+        Column::Flag(|d| d.flow_status.disconnection),
+    ),
+    (
+        "flow_calibrating",
+        Column::Flag(|d| d.flow_status.calibrating),
+    ),
+    ("flow_zeroing", Column::Flag(|d| d.flow_status.zeroing)),
+    (
+        "flow_obstruction",
+        Column::Flag(|d| d.flow_status.obstruction),
+    ),
+    ("flow_leak", Column::Flag(|d| d.flow_status.leak)),
+    (
+        "flow_meas_off",
+        Column::Flag(|d| d.flow_status.measurement_off),
+    ),
+    (
+        "flow_tv_base",
+        Column::Label(|d| Some(format!("{:?}", d.flow_status.tv_base))),
+    ),
+    ("flow_rr", Column::Value(|d| d.flow_rr)),
+    ("flow_ppeak_cmh2o", Column::Value(|d| d.flow_ppeak)),
+    ("flow_peep_cmh2o", Column::Value(|d| d.flow_peep)),
+    ("flow_pplat_cmh2o", Column::Value(|d| d.flow_pplat)),
+    ("flow_tv_insp_ml", Column::Value(|d| d.flow_tv_insp)),
+    ("flow_tv_exp_ml", Column::Value(|d| d.flow_tv_exp)),
+    (
+        "flow_compliance_ml_per_cmh2o",
+        Column::Value(|d| d.flow_compliance),
+    ),
+    ("flow_mv_exp_l_per_min", Column::Value(|d| d.flow_mv_exp)),
+    ("co_exists", Column::Flag(|d| d.co_status.exists)),
+    ("co_active", Column::Flag(|d| d.co_status.active)),
+    ("co_l_per_min", Column::Value(|d| d.co)),
+    ("co_blood_temp_celsius", Column::Value(|d| d.co_blood_temp)),
+    ("co_ref_percent", Column::Value(|d| d.co_ref)),
+    ("co_pcwp_mmhg", Column::Value(|d| d.co_pcwp)),
+    ("svo2_exists", Column::Flag(|d| d.svo2_status.exists)),
+    ("svo2_active", Column::Flag(|d| d.svo2_status.active)),
+    ("svo2_percent", Column::Value(|d| d.svo2)),
+    ("nmt_exists", Column::Flag(|d| d.nmt_status.exists)),
+    ("nmt_active", Column::Flag(|d| d.nmt_status.active)),
+    ("nmt_t1_percent", Column::Value(|d| d.nmt_t1)),
+    ("nmt_tof_ratio_percent", Column::Value(|d| d.nmt_tof_ratio)),
+    ("nmt_ptc", Column::Value(|d| d.nmt_ptc)),
+    (
+        "discontinuities",
+        Column::Label(|d| (!d.discontinuities.is_empty()).then(|| d.discontinuities.join(";"))),
+    ),
+];
+
+/// Format an optional enum as its debug name
+fn debug_label<T: std::fmt::Debug>(opt: &Option<T>) -> Option<String> {
+    opt.as_ref().map(|v| format!("{:?}", v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::dri_types::{PhdbClass, PhdbSubrecordType};
+
+    #[test]
+    fn test_value_columns_follow_iter_vitals() {
+        // Give every vital a distinct value through its serialized name
+        let empty =
+            PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+        let mut json = serde_json::to_value(&empty).unwrap();
+        for (i, (name, _, _)) in empty.iter_vitals().enumerate() {
+            assert!(json.get(name).is_some(), "{} is not a field", name);
+            json[name] = serde_json::json!(i as f64);
+        }
+        let data: PhysiologicalData = serde_json::from_value(json).unwrap();
+
+        let columns: Vec<Option<f64>> = PHYS_COLUMNS
+            .iter()
+            .filter_map(|(_, column)| match column {
+                Column::Value(get) => Some(get(&data)),
+                _ => None,
+            })
+            .collect();
+        let vitals: Vec<Option<f64>> = data.iter_vitals().map(|(_, value, _)| value).collect();
+        assert_eq!(columns, vitals);
+    }
+}
@@ -1,357 +1,737 @@
 //! CSV file writer for DRI data
+//!
+//! Output is gzip-compressed when the path ends in `.gz`. Waveforms go to a
+//! separate `.waveforms.csv` file, laid out per [`WaveformCsvLayout`].
 
+use super::OutputFile;
+use super::columns::{Column, PHYS_COLUMNS};
+use super::output::{is_gzip_path, read_first_line};
+use super::rotation::{RotationPolicy, segment_path};
+use super::sink::RecordSink;
+use crate::constants::WaveformType;
 use crate::decode::physiological::PhysiologicalData;
-use crate::decode::waveforms::WaveformData;
-use anyhow::Result;
+use crate::decode::waveforms::{TIMESTAMP_RESOLUTION, WaveformData, WaveformDecimator};
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, SecondsFormat, Utc};
 use csv::Writer;
-use std::fs::File;
+use std::collections::HashMap;
+use std::io::Write;
 use std::path::Path;
 
+/// Layout of the waveform CSV file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WaveformCsvLayout {
+    /// One row per frame, samples as a JSON array in `samples_json`
+    #[default]
+    Json,
+    /// One row per sample with its absolute time
+    ///
+    /// Uses the effective sample rate once the decoder has measured it.
+    Long,
+}
+
+const JSON_WAVEFORM_HEADER: &[&str] = &[
+    "timestamp",
+    "waveform_type",
+    "plug_id",
+    "sample_rate",
+    "effective_rate",
+    "first_sample_time",
+    "sample_period_ms",
+    "sample_count",
+    "gap",
+    "pacer_detected",
+    "lead_off",
+    "samples_json",
+];
+
+const LONG_WAVEFORM_HEADER: &[&str] = &[
+    "timestamp",
+    "frame_timestamp",
+    "waveform_type",
+    "plug_id",
+    "sample_index",
+    "offset_ms",
+    "sample_value",
+];
+
+/// Physiological columns written to the main CSV file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum CsvColumnSet {
+    /// Every column, including module status flags
+    #[default]
+    All,
+    /// Timestamp and the main vital sign values only
+    Vitals,
+    /// The named columns, in the given order
+    Custom(Vec<String>),
+}
+
+/// Columns selected by [`CsvColumnSet::Vitals`]
+const VITALS_COLUMNS: &[&str] = &[
+    "timestamp",
+    "ecg_hr",
+    "ecg_rr",
+    "nibp_sys_mmhg",
+    "nibp_dia_mmhg",
+    "nibp_mean_mmhg",
+    "invp1_sys_mmhg",
+    "invp1_dia_mmhg",
+    "invp1_mean_mmhg",
+    "spo2_percent",
+    "spo2_pr",
+    "temp1_celsius",
+    "temp2_celsius",
+    "co2_et_percent",
+    "co2_fi_percent",
+    "co2_rr",
+];
+
+impl CsvColumnSet {
+    /// Names of every available physiological column, in output order
+    pub fn available() -> impl Iterator<Item = &'static str> {
+        PHYS_COLUMNS.iter().map(|(name, _)| *name)
+    }
+
+    /// Resolve the set to indices into the column table
+    fn resolve(&self) -> Result<Vec<usize>> {
+        let index_of = |name: &str| {
+            PHYS_COLUMNS
+                .iter()
+                .position(|(column, _)| *column == name)
+                .ok_or_else(|| anyhow!("Unknown CSV column: {}", name))
+        };
+
+        match self {
+            Self::All => Ok((0..PHYS_COLUMNS.len()).collect()),
+            Self::Vitals => VITALS_COLUMNS.iter().map(|name| index_of(name)).collect(),
+            Self::Custom(names) => {
+                if names.is_empty() {
+                    bail!("No CSV columns selected");
+                }
+                names.iter().map(|name| index_of(name)).collect()
+            }
+        }
+    }
+}
+
+/// One CSV output, opened and given its header on first write
+struct CsvOutput {
+    path: Option<String>,
+    writer: Option<Writer<OutputFile>>,
+    header_written: bool,
+    append: bool,
+    rotation: Option<RotationPolicy>,
+    segment: u32,
+    opened_at: DateTime<Utc>,
+}
+
+impl CsvOutput {
+    fn file(path: String) -> Self {
+        Self {
+            path: Some(path),
+            writer: None,
+            header_written: false,
+            append: false,
+            rotation: None,
+            segment: 0,
+            opened_at: Utc::now(),
+        }
+    }
+
+    fn stream(output: OutputFile) -> Self {
+        Self {
+            writer: Some(Writer::from_writer(output)),
+            path: None,
+            ..Self::file(String::new())
+        }
+    }
+
+    fn open<H>(&mut self, header: H) -> Result<&mut Writer<OutputFile>>
+    where
+        H: Fn() -> Vec<&'static str>,
+    {
+        self.rotate_if_due()?;
+
+        if self.writer.is_none() {
+            let base = self
+                .path
+                .as_ref()
+                .ok_or_else(|| anyhow!("CSV output already closed"))?;
+            let path = segment_path(base, self.segment);
+
+            let output = if self.append {
+                if let Some(existing) = read_first_line(&path)? {
+                    if existing != header().join(",") {
+                        bail!(
+                            "Cannot append to {}: existing header does not match the selected columns",
+                            path
+                        );
+                    }
+                    self.header_written = true;
+                }
+                OutputFile::append(&path)?
+            } else {
+                OutputFile::create(&path)?
+            };
+            self.writer = Some(Writer::from_writer(output));
+            self.opened_at = Utc::now();
+        }
+        let writer = self.writer.as_mut().expect("CSV output was just opened");
+
+        if !self.header_written {
+            writer.write_record(header())?;
+            self.header_written = true;
+        }
+        Ok(writer)
+    }
+
+    /// Finish the current segment if the rotation policy says so
+    fn rotate_if_due(&mut self) -> Result<()> {
+        let (Some(rotation), Some(base), Some(_)) = (&self.rotation, &self.path, &self.writer)
+        else {
+            return Ok(());
+        };
+
+        // Rows are flushed as they are written, so the size on disk is current
+        let size = std::fs::metadata(segment_path(base, self.segment))?.len();
+        if rotation.should_rotate(size, self.opened_at, Utc::now()) {
+            self.finish_segment()?;
+            self.segment += 1;
+            self.header_written = false;
+        }
+        Ok(())
+    }
+
+    fn finish_segment(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Flush and, for gzip, write the trailer; a no-op if never opened
+    fn close(&mut self) -> Result<()> {
+        self.path = None;
+        self.finish_segment()
+    }
+}
+
 pub struct CsvWriter {
-    main_writer: Option<Writer<File>>,
-    waveform_writer: Option<Writer<File>>,
-    main_path: String,
-    waveform_path: String,
+    main: CsvOutput,
+    waveforms: Option<CsvOutput>,
+    waveform_layout: WaveformCsvLayout,
+    decimator: Option<WaveformDecimator>,
+    columns: Vec<usize>,
+    /// Time just after the last written sample, per waveform and plug
+    sample_clock: HashMap<(WaveformType, u16), DateTime<Utc>>,
 }
 
 impl CsvWriter {
     pub fn new<P: AsRef<Path>>(base_path: P) -> Result<Self> {
+        Self::with_columns(base_path, CsvColumnSet::All)
+    }
+
+    /// Create a writer emitting only the selected physiological columns
+    ///
+    /// Fails if a custom set names an unknown column.
+    pub fn with_columns<P: AsRef<Path>>(base_path: P, columns: CsvColumnSet) -> Result<Self> {
         let base_path_str = base_path.as_ref().to_string_lossy().to_string();
-        let waveform_path = if base_path_str.ends_with(".csv") {
-            base_path_str.replace(".csv", ".waveforms.csv")
+        let gzip = is_gzip_path(&base_path_str);
+        let stem = base_path_str.strip_suffix(".gz").unwrap_or(&base_path_str);
+        let mut waveform_path = if stem.ends_with(".csv") {
+            stem.replace(".csv", ".waveforms.csv")
         } else {
-            format!("{}.waveforms.csv", base_path_str)
+            format!("{}.waveforms.csv", stem)
         };
+        if gzip {
+            waveform_path.push_str(".gz");
+        }
+
+        Self::from_outputs(
+            CsvOutput::file(base_path_str),
+            Some(CsvOutput::file(waveform_path)),
+            columns,
+        )
+    }
+
+    /// Write physiological rows to any target, e.g. `std::io::stdout()`
+    ///
+    /// Waveforms are rejected unless a target is added with
+    /// [`CsvWriter::with_waveform_writer`].
+    pub fn from_writer<W: Write + Send + 'static>(writer: W) -> Result<Self> {
+        Self::from_writer_with_columns(writer, CsvColumnSet::All)
+    }
 
+    /// Write the selected physiological columns to any target
+    pub fn from_writer_with_columns<W: Write + Send + 'static>(
+        writer: W,
+        columns: CsvColumnSet,
+    ) -> Result<Self> {
+        Self::from_outputs(
+            CsvOutput::stream(OutputFile::from_writer(writer)),
+            None,
+            columns,
+        )
+    }
+
+    fn from_outputs(
+        main: CsvOutput,
+        waveforms: Option<CsvOutput>,
+        columns: CsvColumnSet,
+    ) -> Result<Self> {
         Ok(Self {
-            main_writer: None,
-            waveform_writer: None,
-            main_path: base_path_str,
-            waveform_path,
+            main,
+            waveforms,
+            waveform_layout: WaveformCsvLayout::default(),
+            decimator: None,
+            columns: columns.resolve()?,
+            sample_clock: HashMap::new(),
         })
     }
 
-    /// Write physiological data
-    pub fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
-        // Initialize writer on first call
-        if self.main_writer.is_none() {
-            let file = File::create(&self.main_path)?;
-            let mut writer = Writer::from_writer(file);
-
-            // Write header with all fields including status flags
-            writer.write_record(&[
-                "timestamp",
-                "class",
-                "subtype",
-                // ECG
-                "ecg_exists",
-                "ecg_active",
-                "ecg_asystole",
-                "ecg_noise",
-                "ecg_artifact",
-                "ecg_learning",
-                "ecg_pacer_on",
-                "ecg_ch1_off",
-                "ecg_ch2_off",
-                "ecg_ch3_off",
-                "ecg_hr",
-                "ecg_st1_mm",
-                "ecg_st2_mm",
-                "ecg_st3_mm",
-                "ecg_rr",
-                "ecg_hr_source",
-                "ecg_lead1",
-                "ecg_lead2",
-                "ecg_lead3",
-                // NIBP
-                "nibp_exists",
-                "nibp_active",
-                "nibp_auto_mode",
-                "nibp_stat_mode",
-                "nibp_measuring",
-                "nibp_stasis",
-                "nibp_calibrating",
-                "nibp_old_data",
-                "nibp_sys_mmhg",
-                "nibp_dia_mmhg",
-                "nibp_mean_mmhg",
-                "nibp_hr",
-                // INVP1
-                "invp1_exists",
-                "invp1_active",
-                "invp1_label",
-                "invp1_sys_mmhg",
-                "invp1_dia_mmhg",
-                "invp1_mean_mmhg",
-                "invp1_hr",
-                // SpO2
-                "spo2_exists",
-                "spo2_active",
-                "spo2_percent",
-                "spo2_pr",
-                "spo2_ir_amp_percent",
-                // Temperature 1
-                "temp1_exists",
-                "temp1_active",
-                "temp1_label",
-                "temp1_celsius",
-                // Temperature 2
-                "temp2_exists",
-                "temp2_active",
-                "temp2_label",
-                "temp2_celsius",
-                // CO2
-                "co2_exists",
-                "co2_active",
-                "co2_apnea",
-                "co2_calibrating",
-                "co2_zeroing",
-                "co2_occlusion",
-                "co2_air_leak",
-                "co2_apnea_resp",
-                "co2_apnea_deactivated",
-                "co2_wet",
-                "co2_et_percent",
-                "co2_fi_percent",
-                "co2_rr",
-                // O2
-                "o2_exists",
-                "o2_active",
-                "o2_calibrating",
-                "o2_meas_off",
-                "o2_et_percent",
-                "o2_fi_percent",
-                // N2O
-                "n2o_exists",
-                "n2o_active",
-                "n2o_calibrating",
-                "n2o_meas_off",
-                "n2o_et_percent",
-                "n2o_fi_percent",
-                // AA
-                "aa_exists",
-                "aa_active",
-                "aa_calibrating",
-                "aa_meas_off",
-                "aa_agent",
-                "aa_et_percent",
-                "aa_fi_percent",
-                "aa_mac",
-                // Flow/Volume (Ventilator)
-                "flow_exists",
-                "flow_active",
-                "flow_disconnection",
-                "flow_calibrating",
-                "flow_zeroing",
-                "flow_obstruction",
-                "flow_leak",
-                "flow_meas_off",
-                "flow_tv_base",
-                "flow_rr",
-                "flow_ppeak_cmh2o",
-                "flow_peep_cmh2o",
-                "flow_pplat_cmh2o",
-                "flow_tv_insp_ml",
-                "flow_tv_exp_ml",
-                "flow_compliance_ml_per_cmh2o",
-                "flow_mv_exp_l_per_min",
-            ])?;
-
-            self.main_writer = Some(writer);
+    /// Append to existing files instead of overwriting them
+    ///
+    /// The header is only written to new or empty files; appending fails on
+    /// first write if an existing header differs from the selected columns.
+    pub fn with_append(mut self, append: bool) -> Self {
+        self.main.append = append;
+        if let Some(waveforms) = &mut self.waveforms {
+            waveforms.append = append;
         }
+        self
+    }
 
-        // Write data row
-        if let Some(writer) = &mut self.main_writer {
-            writer.write_record(&[
-                data.timestamp.to_rfc3339(),
-                format!("{:?}", data.class),
-                format!("{:?}", data.subtype),
-                // ECG status
-                data.ecg_status.exists.to_string(),
-                data.ecg_status.active.to_string(),
-                data.ecg_status.asystole.to_string(),
-                data.ecg_status.noise.to_string(),
-                data.ecg_status.artifact.to_string(),
-                data.ecg_status.learning.to_string(),
-                data.ecg_status.pacer_on.to_string(),
-                data.ecg_status.channel1_off.to_string(),
-                data.ecg_status.channel2_off.to_string(),
-                data.ecg_status.channel3_off.to_string(),
-                // ECG values
-                format_option_f64(data.ecg_hr),
-                format_option_f64(data.ecg_st1),
-                format_option_f64(data.ecg_st2),
-                format_option_f64(data.ecg_st3),
-                format_option_f64(data.ecg_rr),
-                format_option_debug(&data.ecg_hr_source),
-                format_option_debug(&data.ecg_lead1),
-                format_option_debug(&data.ecg_lead2),
-                format_option_debug(&data.ecg_lead3),
-                // NIBP status
-                data.nibp_status.exists.to_string(),
-                data.nibp_status.active.to_string(),
-                data.nibp_status.auto_mode.to_string(),
-                data.nibp_status.stat_mode.to_string(),
-                data.nibp_status.measuring.to_string(),
-                data.nibp_status.stasis_on.to_string(),
-                data.nibp_status.calibrating.to_string(),
-                data.nibp_status.data_older_than_60s.to_string(),
-                // NIBP values
-                format_option_f64(data.nibp_sys),
-                format_option_f64(data.nibp_dia),
-                format_option_f64(data.nibp_mean),
-                format_option_f64(data.nibp_hr),
-                // INVP1 status
-                data.invp1_status.exists.to_string(),
-                data.invp1_status.active.to_string(),
-                // INVP1 values
-                format_option_debug(&data.invp1_label),
-                format_option_f64(data.invp1_sys),
-                format_option_f64(data.invp1_dia),
-                format_option_f64(data.invp1_mean),
-                format_option_f64(data.invp1_hr),
-                // SpO2 status
-                data.spo2_status.exists.to_string(),
-                data.spo2_status.active.to_string(),
-                // SpO2 values
-                format_option_f64(data.spo2),
-                format_option_f64(data.spo2_pr),
-                format_option_f64(data.spo2_ir_amp),
-                // Temp1 status
-                data.temp1_status.exists.to_string(),
-                data.temp1_status.active.to_string(),
-                // Temp1 values
-                format_option_debug(&data.temp1_label),
-                format_option_f64(data.temp1),
-                // Temp2 status
-                data.temp2_status.exists.to_string(),
-                data.temp2_status.active.to_string(),
-                // Temp2 values
-                format_option_debug(&data.temp2_label),
-                format_option_f64(data.temp2),
-                // CO2 status
-                data.co2_status.exists.to_string(),
-                data.co2_status.active.to_string(),
-                data.co2_status.apnea_co2.to_string(),
-                data.co2_status.calibrating_sensor.to_string(),
-                data.co2_status.zeroing_sensor.to_string(),
-                data.co2_status.occlusion.to_string(),
-                data.co2_status.air_leak.to_string(),
-                data.co2_status.apnea_from_resp.to_string(),
-                data.co2_status.apnea_deactivated.to_string(),
-                data.co2_status.wet_condition.to_string(),
-                // CO2 values
-                format_option_f64(data.co2_et),
-                format_option_f64(data.co2_fi),
-                format_option_f64(data.co2_rr),
-                // O2 status
-                data.o2_status.exists.to_string(),
-                data.o2_status.active.to_string(),
-                data.o2_status.calibrating.to_string(),
-                data.o2_status.measurement_off.to_string(),
-                // O2 values
-                format_option_f64(data.o2_et),
-                format_option_f64(data.o2_fi),
-                // N2O status
-                data.n2o_status.exists.to_string(),
-                data.n2o_status.active.to_string(),
-                data.n2o_status.calibrating.to_string(),
-                data.n2o_status.measurement_off.to_string(),
-                // N2O values
-                format_option_f64(data.n2o_et),
-                format_option_f64(data.n2o_fi),
-                // AA status
-                data.aa_status.exists.to_string(),
-                data.aa_status.active.to_string(),
-                data.aa_status.calibrating.to_string(),
-                data.aa_status.measurement_off.to_string(),
-                // AA values
-                format_option_debug(&data.aa_agent),
-                format_option_f64(data.aa_et),
-                format_option_f64(data.aa_fi),
-                format_option_f64(data.aa_mac),
-                // Flow status
-                data.flow_status.exists.to_string(),
-                data.flow_status.active.to_string(),
-                data.flow_status.disconnection.to_string(),
-                data.flow_status.calibrating.to_string(),
-                data.flow_status.zeroing.to_string(),
-                data.flow_status.obstruction.to_string(),
-                data.flow_status.leak.to_string(),
-                data.flow_status.measurement_off.to_string(),
-                format!("{:?}", data.flow_status.tv_base),
-                // Flow values
-                format_option_f64(data.flow_rr),
-                format_option_f64(data.flow_ppeak),
-                format_option_f64(data.flow_peep),
-                format_option_f64(data.flow_pplat),
-                format_option_f64(data.flow_tv_insp),
-                format_option_f64(data.flow_tv_exp),
-                format_option_f64(data.flow_compliance),
-                format_option_f64(data.flow_mv_exp),
-            ])?;
-
-            writer.flush()?;
+    /// Roll the physiological and waveform files over to new segments
+    ///
+    /// Each file rotates on its own; every segment starts with the header.
+    /// Has no effect on outputs created with [`CsvWriter::from_writer`].
+    pub fn with_rotation(mut self, rotation: RotationPolicy) -> Self {
+        self.main.rotation = Some(rotation.clone());
+        if let Some(waveforms) = &mut self.waveforms {
+            waveforms.rotation = Some(rotation);
         }
+        self
+    }
+
+    /// Send waveform rows to the given target
+    pub fn with_waveform_writer<W: Write + Send + 'static>(mut self, writer: W) -> Self {
+        self.waveforms = Some(CsvOutput::stream(OutputFile::from_writer(writer)));
+        self
+    }
+
+    /// Set the layout of the waveform file
+    pub fn with_waveform_layout(mut self, layout: WaveformCsvLayout) -> Self {
+        self.waveform_layout = layout;
+        self
+    }
+
+    /// Decimate waveforms before writing them, see [`WaveformDecimator`]
+    pub fn with_decimation(mut self, decimator: WaveformDecimator) -> Self {
+        self.decimator = Some(decimator);
+        self
+    }
+
+    /// Write physiological data
+    pub fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        let columns = &self.columns;
+        let writer = self
+            .main
+            .open(|| columns.iter().map(|&i| PHYS_COLUMNS[i].0).collect())?;
 
+        writer.write_record(
+            columns
+                .iter()
+                .map(|&i| format_column(&PHYS_COLUMNS[i].1, data)),
+        )?;
+        writer.flush()?;
         Ok(())
     }
 
     /// Write waveform data
     pub fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
-        // Initialize writer on first call
-        if self.waveform_writer.is_none() {
-            let file = File::create(&self.waveform_path)?;
-            let mut writer = Writer::from_writer(file);
-
-            writer.write_record(&[
-                "timestamp",
-                "waveform_type",
-                "sample_rate",
-                "sample_count",
-                "gap",
-                "pacer_detected",
-                "lead_off",
-                "samples_json",
-            ])?;
-
-            self.waveform_writer = Some(writer);
-        }
+        let decimated;
+        let data = match self.decimator {
+            Some(decimator) => {
+                decimated = decimator.decimate(data);
+                &decimated
+            }
+            None => data,
+        };
+        let rate = data.sample_rate().max(1.0);
+        let first_sample = self.first_sample_time(data, rate);
+        let layout = self.waveform_layout;
+        let writer = self
+            .waveforms
+            .as_mut()
+            .ok_or_else(|| anyhow!("No waveform output configured for this CSV writer"))?
+            .open(|| match layout {
+                WaveformCsvLayout::Json => JSON_WAVEFORM_HEADER.to_vec(),
+                WaveformCsvLayout::Long => LONG_WAVEFORM_HEADER.to_vec(),
+            })?;
 
-        // Write data row
-        if let Some(writer) = &mut self.waveform_writer {
-            let samples_json = serde_json::to_string(&data.samples)?;
-
-            writer.write_record(&[
-                data.timestamp.to_rfc3339(),
-                format!("{:?}", data.waveform_type),
-                data.sample_rate.to_string(),
-                data.samples.len().to_string(),
-                data.status.gap.to_string(),
-                data.status.pacer_detected.to_string(),
-                data.status.lead_off.to_string(),
-                samples_json,
-            ])?;
-
-            writer.flush()?;
+        match self.waveform_layout {
+            WaveformCsvLayout::Json => {
+                let samples_json = serde_json::to_string(&data.samples)?;
+
+                writer.write_record(&[
+                    data.timestamp.to_rfc3339(),
+                    data.waveform_type.name().to_string(),
+                    data.plug_id.to_string(),
+                    data.nominal_rate.to_string(),
+                    data.effective_rate
+                        .map_or_else(String::new, |rate| format!("{:.2}", rate)),
+                    first_sample.to_rfc3339_opts(SecondsFormat::Micros, false),
+                    format!("{:.3}", 1000.0 / rate),
+                    data.samples.len().to_string(),
+                    data.status.gap.to_string(),
+                    data.status.pacer_detected.to_string(),
+                    data.status.lead_off.to_string(),
+                    samples_json,
+                ])?;
+            }
+            WaveformCsvLayout::Long => {
+                let frame_timestamp = data.timestamp.to_rfc3339();
+                let waveform_type = data.waveform_type.name().to_string();
+
+                for (index, sample) in data.samples.iter().enumerate() {
+                    let offset_us = (index as f64 * 1_000_000.0 / rate) as i64;
+                    let timestamp = first_sample + chrono::Duration::microseconds(offset_us);
+
+                    writer.write_record(&[
+                        timestamp.to_rfc3339_opts(SecondsFormat::Micros, false),
+                        frame_timestamp.clone(),
+                        waveform_type.clone(),
+                        data.plug_id.to_string(),
+                        index.to_string(),
+                        format!("{:.3}", offset_us as f64 / 1000.0),
+                        sample.to_string(),
+                    ])?;
+                }
+            }
         }
 
+        writer.flush()?;
         Ok(())
     }
+
+    /// Time of the first sample of a frame
+    ///
+    /// Frame timestamps only have a one-second resolution, so a frame that
+    /// continues the previous one of the same waveform starts right after
+    /// its last sample; this keeps sample times from overlapping. After a
+    /// gap flag, or a timestamp too far from the expected one, the frame
+    /// timestamp is used again.
+    fn first_sample_time(&mut self, data: &WaveformData, rate: f64) -> DateTime<Utc> {
+        let key = (data.waveform_type, data.plug_id);
+        let resolution = chrono::Duration::from_std(TIMESTAMP_RESOLUTION).unwrap();
+        let first = match self.sample_clock.get(&key) {
+            Some(&end)
+                if !data.status.gap
+                    && data.timestamp - end <= resolution
+                    && end - data.timestamp <= resolution * 2 =>
+            {
+                end
+            }
+            _ => data.timestamp,
+        };
+        let duration_us = (data.samples.len() as f64 * 1_000_000.0 / rate) as i64;
+        self.sample_clock
+            .insert(key, first + chrono::Duration::microseconds(duration_us));
+        first
+    }
+
+    /// Flush both CSV files and, for gzip, write the trailers
+    pub fn finish(mut self) -> Result<()> {
+        self.close()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        let main = self.main.close();
+        let waveforms = self.waveforms.as_mut().map_or(Ok(()), CsvOutput::close);
+        main.and(waveforms)
+    }
 }
 
-/// Format Option<f64> for CSV
-fn format_option_f64(opt: Option<f64>) -> String {
-    match opt {
-        Some(val) => format!("{:.2}", val),
-        None => String::new(),
+impl RecordSink for CsvWriter {
+    fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        CsvWriter::write_physiological(self, data)
+    }
+
+    fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
+        CsvWriter::write_waveform(self, data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        // Rows are flushed as they are written
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        CsvWriter::finish(*self)
     }
 }
 
-/// Format Option<Debug> for CSV
-fn format_option_debug<T: std::fmt::Debug>(opt: &Option<T>) -> String {
-    match opt {
-        Some(val) => format!("{:?}", val),
-        None => String::new(),
+impl Drop for CsvWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            log::error!("Failed to finish CSV file: {}", e);
+        }
+    }
+}
+
+/// Format one physiological column for CSV
+fn format_column(column: &Column, data: &PhysiologicalData) -> String {
+    match column {
+        Column::Timestamp(get) => get(data).to_rfc3339(),
+        Column::Flag(get) => get(data).to_string(),
+        Column::Value(get) => get(data).map_or_else(String::new, |v| format!("{:.2}", v)),
+        Column::Label(get) => get(data).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::dri_types::{PhdbClass, PhdbSubrecordType};
+    use crate::storage::output::SharedBuffer;
+    use chrono::Utc;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_gzip_csv_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("dri_csv_gz_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("capture.csv.gz");
+
+        {
+            let mut writer = CsvWriter::new(&path).unwrap();
+            for hr in [60.0, 61.0, 62.0] {
+                let mut phys = PhysiologicalData::empty(
+                    Utc::now(),
+                    PhdbClass::Basic,
+                    PhdbSubrecordType::Displ,
+                );
+                phys.ecg_hr = Some(hr);
+                writer.write_physiological(&phys).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+
+        let mut contents = String::new();
+        GzDecoder::new(std::fs::File::open(&path).unwrap())
+            .read_to_string(&mut contents)
+            .unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("timestamp,class,subtype"));
+        assert!(lines[3].contains(",62.00,"));
+    }
+
+    #[test]
+    fn test_column_selection() {
+        let dir = std::env::temp_dir().join(format!("dri_csv_cols_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("capture.csv");
+
+        let columns = CsvColumnSet::Custom(vec!["spo2_percent".into(), "ecg_hr".into()]);
+        let mut writer = CsvWriter::with_columns(&path, columns).unwrap();
+        let mut phys =
+            PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+        phys.ecg_hr = Some(72.0);
+        writer.write_physiological(&phys).unwrap();
+        writer.finish().unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(contents, "spo2_percent,ecg_hr\n,72.00\n");
+
+        let unknown = CsvColumnSet::Custom(vec!["ecg_bpm".into()]);
+        assert!(CsvWriter::with_columns(&path, unknown).is_err());
+        assert!(CsvWriter::with_columns(&path, CsvColumnSet::Vitals).is_ok());
+        assert_eq!(CsvColumnSet::available().count(), PHYS_COLUMNS.len());
+    }
+
+    #[test]
+    fn test_from_writer() {
+        use crate::storage::output::SharedBuffer;
+
+        let buffer = SharedBuffer::default();
+        let columns = CsvColumnSet::Custom(vec!["ecg_hr".into()]);
+        let mut writer = CsvWriter::from_writer_with_columns(buffer.clone(), columns).unwrap();
+        let mut phys =
+            PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+        phys.ecg_hr = Some(60.0);
+        writer.write_physiological(&phys).unwrap();
+
+        // Rows are flushed as they are written
+        assert_eq!(buffer.contents(), "ecg_hr\n60.00\n");
+        assert!(writer.write_waveform(&sample_waveform()).is_err());
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_append_reuses_header() {
+        let dir = std::env::temp_dir().join(format!("dri_csv_append_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("capture.csv.gz");
+        let vitals = || CsvColumnSet::Custom(vec!["ecg_hr".into()]);
+
+        for hr in [60.0, 61.0] {
+            let mut writer = CsvWriter::with_columns(&path, vitals())
+                .unwrap()
+                .with_append(true);
+            let mut phys =
+                PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+            phys.ecg_hr = Some(hr);
+            writer.write_physiological(&phys).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let mut contents = String::new();
+        flate2::read::MultiGzDecoder::new(std::fs::File::open(&path).unwrap())
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "ecg_hr\n60.00\n61.00\n");
+
+        // A different schema is refused rather than mixed into the file
+        let mut writer = CsvWriter::with_columns(&path, CsvColumnSet::Vitals)
+            .unwrap()
+            .with_append(true);
+        let phys = PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+        assert!(writer.write_physiological(&phys).is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_size_rotation_repeats_header() {
+        let dir = std::env::temp_dir().join(format!("dri_csv_rotate_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("capture.csv");
+
+        let columns = CsvColumnSet::Custom(vec!["ecg_hr".into()]);
+        let mut writer = CsvWriter::with_columns(&path, columns)
+            .unwrap()
+            .with_rotation(RotationPolicy::new().max_bytes(19));
+        for hr in [60.0, 61.0, 62.0, 63.0] {
+            let mut phys =
+                PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+            phys.ecg_hr = Some(hr);
+            writer.write_physiological(&phys).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("capture.csv"), "ecg_hr\n60.00\n61.00\n");
+        assert_eq!(read("capture.1.csv"), "ecg_hr\n62.00\n63.00\n");
+        assert!(!dir.join("capture.waveforms.csv").exists());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_gzip_waveform_path() {
+        let waveform_path = |base| {
+            CsvWriter::new(base)
+                .unwrap()
+                .waveforms
+                .as_ref()
+                .unwrap()
+                .path
+                .clone()
+        };
+        assert_eq!(
+            waveform_path("capture.csv.gz").as_deref(),
+            Some("capture.waveforms.csv.gz")
+        );
+        assert_eq!(
+            waveform_path("capture.csv").as_deref(),
+            Some("capture.waveforms.csv")
+        );
+    }
+
+    fn sample_waveform() -> WaveformData {
+        use crate::constants::WaveformType;
+        use crate::decode::waveforms::WaveformStatus;
+        use chrono::TimeZone;
+
+        WaveformData {
+            timestamp: Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(),
+            waveform_type: WaveformType::Pleth,
+            plug_id: 0,
+            samples: vec![10, 20, 30],
+            nominal_rate: 100,
+            effective_rate: None,
+            status: WaveformStatus::default(),
+            pacer_indices: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_long_waveform_layout() {
+        let dir = std::env::temp_dir().join(format!("dri_csv_long_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("capture.csv");
+
+        let mut writer = CsvWriter::new(&path)
+            .unwrap()
+            .with_waveform_layout(WaveformCsvLayout::Long);
+        writer.write_waveform(&sample_waveform()).unwrap();
+        writer.finish().unwrap();
+
+        let contents = std::fs::read_to_string(dir.join("capture.waveforms.csv")).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[3],
+            "2024-05-01T12:00:00.020000+00:00,2024-05-01T12:00:00+00:00,PLETH,0,2,20.000,30"
+        );
+    }
+
+    #[test]
+    fn test_consecutive_frames_continue_sample_times() {
+        let buffer = SharedBuffer::default();
+        let mut writer = CsvWriter::from_writer(std::io::sink())
+            .unwrap()
+            .with_waveform_writer(buffer.clone());
+
+        // Both frames carry the same one-second timestamp
+        let first = sample_waveform();
+        writer.write_waveform(&first).unwrap();
+        writer.write_waveform(&first).unwrap();
+        // A flagged gap restarts from the frame timestamp
+        let mut after_gap = sample_waveform();
+        after_gap.timestamp += chrono::Duration::seconds(5);
+        after_gap.status.gap = true;
+        writer.write_waveform(&after_gap).unwrap();
+        writer.finish().unwrap();
+
+        let first_samples: Vec<String> = buffer
+            .contents()
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').nth(5).unwrap().to_string())
+            .collect();
+        assert_eq!(
+            first_samples,
+            [
+                "2024-05-01T12:00:00.000000+00:00",
+                "2024-05-01T12:00:00.030000+00:00",
+                "2024-05-01T12:00:05.000000+00:00",
+            ]
+        );
+        assert!(
+            buffer
+                .contents()
+                .lines()
+                .nth(1)
+                .unwrap()
+                .contains(",10.000,")
+        );
     }
 }
@@ -0,0 +1,422 @@
+//! EDF+ file writer for DRI waveforms
+//!
+//! Waveforms are accumulated in memory for the whole session and written
+//! as a single EDF+C file by [`EdfWriter::finish`] (or on drop).
+//!
+//! - One signal per `WaveformType`, labelled with its DRI name. Samples are
+//!   stored unchanged as 16-bit integers; the physical range is derived from
+//!   the resolution in `WaveformInfo` (e.g. INVP: -327.68..327.67 mmHg).
+//! - A data record lasts `1 / gcd(sample rates)` seconds, the shortest
+//!   interval holding a whole number of samples of every signal (0.04 s for
+//!   ECG at 300 Hz with CO2 at 25 Hz).
+//! - Samples lost by the monitor (`status.gap`, or a timestamp past the end
+//!   of the signal by more than the timestamp resolution) are replaced by
+//!   zeros, as many as the sample rate gives for the elapsed time, so all
+//!   signals stay aligned on the file start and the file is continuous. A
+//!   `Gap` annotation is written in the `EDF Annotations` signal at the
+//!   onset of the first sample received after the gap. A signal starting
+//!   late is padded with zeros at the start, shorter ones at the end.
+
+use crate::constants::WaveformType;
+use crate::decode::physiological::PhysiologicalData;
+use crate::decode::waveforms::{WaveformData, lost_since, samples_for};
+use crate::storage::sink::RecordSink;
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const DIGITAL_MIN: i32 = i16::MIN as i32;
+const DIGITAL_MAX: i32 = i16::MAX as i32;
+
+/// Samples and gap positions of one waveform
+struct Signal {
+    waveform_type: WaveformType,
+    sample_rate: u32,
+    samples: Vec<i16>,
+    /// Sample indices following a gap
+    gaps: Vec<usize>,
+    /// Time just after the last sample
+    end: DateTime<Utc>,
+}
+
+pub struct EdfWriter {
+    path: PathBuf,
+    start: Option<DateTime<Utc>>,
+    signals: BTreeMap<u8, Signal>,
+    finished: bool,
+}
+
+impl EdfWriter {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            start: None,
+            signals: BTreeMap::new(),
+            finished: false,
+        })
+    }
+
+    /// Accumulate waveform data
+    pub fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
+        if data.nominal_rate == 0 || data.samples.is_empty() {
+            return Ok(());
+        }
+
+        let start = *self.start.get_or_insert(data.timestamp);
+        let rate = data.nominal_rate;
+
+        let signal = self
+            .signals
+            .entry(data.waveform_type as u8)
+            .or_insert_with(|| Signal {
+                waveform_type: data.waveform_type,
+                sample_rate: rate as u32,
+                samples: Vec::new(),
+                gaps: Vec::new(),
+                end: start,
+            });
+
+        // Padding before the first chunk aligns a late signal, it is no gap
+        let mut lost = false;
+        if let Some(lag) = lost_since(signal.end, data) {
+            let missing = samples_for(lag, rate);
+            lost = !signal.samples.is_empty();
+            signal.samples.resize(signal.samples.len() + missing, 0);
+            signal.end = data.timestamp;
+        }
+        if data.status.gap || lost {
+            signal.gaps.push(signal.samples.len());
+        }
+        signal.samples.extend_from_slice(&data.samples);
+        signal.end +=
+            chrono::Duration::microseconds(data.samples.len() as i64 * 1_000_000 / rate as i64);
+        Ok(())
+    }
+
+    /// Write the EDF+ file
+    pub fn finish(mut self) -> Result<()> {
+        self.write_file()
+    }
+
+    fn write_file(&mut self) -> Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+
+        let Some(start) = self.start else {
+            return Ok(());
+        };
+        let signals: Vec<&Signal> = self.signals.values().collect();
+
+        let rate_gcd = signals.iter().fold(0, |acc, s| gcd(acc, s.sample_rate));
+        if rate_gcd == 0 {
+            bail!("No waveform with a valid sample rate");
+        }
+        let record_duration = 1.0 / rate_gcd as f64;
+        let samples_per_record: Vec<usize> = signals
+            .iter()
+            .map(|s| (s.sample_rate / rate_gcd) as usize)
+            .collect();
+        let num_records = signals
+            .iter()
+            .zip(&samples_per_record)
+            .map(|(s, &spr)| s.samples.len().div_ceil(spr))
+            .max()
+            .unwrap_or(0);
+
+        // Time-keeping TAL for each record, followed by its gap annotations
+        let mut annotations: Vec<Vec<u8>> = (0..num_records)
+            .map(|r| {
+                format!("+{}\x14\x14\0", format_seconds(r as f64 * record_duration)).into_bytes()
+            })
+            .collect();
+        for (signal, &spr) in signals.iter().zip(&samples_per_record) {
+            for &index in &signal.gaps {
+                let onset = index as f64 / signal.sample_rate as f64;
+                annotations[index / spr].extend_from_slice(
+                    format!(
+                        "+{}\x14Gap {}\x14\0",
+                        format_seconds(onset),
+                        signal.waveform_type.name()
+                    )
+                    .as_bytes(),
+                );
+            }
+        }
+        let annotation_samples = annotations
+            .iter()
+            .map(|a| a.len().div_ceil(2))
+            .max()
+            .unwrap_or(1);
+
+        let mut out = BufWriter::new(File::create(&self.path)?);
+
+        // Main header
+        let ns = signals.len() + 1;
+        out.write_all(&field("0", 8))?;
+        out.write_all(&field("X X X X", 80))?;
+        out.write_all(&field(
+            &format!(
+                "Startdate {} X X X",
+                start.format("%d-%b-%Y").to_string().to_uppercase()
+            ),
+            80,
+        ))?;
+        out.write_all(&field(&start.format("%d.%m.%y").to_string(), 8))?;
+        out.write_all(&field(&start.format("%H.%M.%S").to_string(), 8))?;
+        out.write_all(&field(&(256 * (ns + 1)).to_string(), 8))?;
+        out.write_all(&field("EDF+C", 44))?;
+        out.write_all(&field(&num_records.to_string(), 8))?;
+        out.write_all(&field(&format_seconds(record_duration), 8))?;
+        out.write_all(&field(&ns.to_string(), 4))?;
+
+        // Signal headers, one field at a time for all signals
+        let mut labels: Vec<String> = signals
+            .iter()
+            .map(|s| s.waveform_type.name().to_string())
+            .collect();
+        labels.push("EDF Annotations".to_string());
+        write_fields(&mut out, &labels, 16)?;
+        write_fields(&mut out, &vec![String::new(); ns], 80)?;
+
+        let mut dimensions: Vec<String> = signals
+            .iter()
+            .map(|s| ascii_unit(s.waveform_type.info().base_unit()))
+            .collect();
+        dimensions.push(String::new());
+        write_fields(&mut out, &dimensions, 8)?;
+
+        let scales: Vec<f64> = signals
+            .iter()
+            .map(|s| s.waveform_type.info().scale())
+            .collect();
+        let mut physical_min: Vec<String> = scales
+            .iter()
+            .map(|scale| format_number(DIGITAL_MIN as f64 * scale))
+            .collect();
+        physical_min.push("-1".to_string());
+        write_fields(&mut out, &physical_min, 8)?;
+        let mut physical_max: Vec<String> = scales
+            .iter()
+            .map(|scale| format_number(DIGITAL_MAX as f64 * scale))
+            .collect();
+        physical_max.push("1".to_string());
+        write_fields(&mut out, &physical_max, 8)?;
+
+        write_fields(&mut out, &vec![DIGITAL_MIN.to_string(); ns], 8)?;
+        write_fields(&mut out, &vec![DIGITAL_MAX.to_string(); ns], 8)?;
+        write_fields(&mut out, &vec![String::new(); ns], 80)?;
+
+        let mut record_samples: Vec<String> =
+            samples_per_record.iter().map(|n| n.to_string()).collect();
+        record_samples.push(annotation_samples.to_string());
+        write_fields(&mut out, &record_samples, 8)?;
+        write_fields(&mut out, &vec![String::new(); ns], 32)?;
+
+        // Data records
+        for (r, annotation) in annotations.iter().enumerate() {
+            for (signal, &spr) in signals.iter().zip(&samples_per_record) {
+                for i in r * spr..(r + 1) * spr {
+                    let sample = signal.samples.get(i).copied().unwrap_or(0);
+                    out.write_all(&sample.to_le_bytes())?;
+                }
+            }
+            let mut padded = annotation.clone();
+            padded.resize(annotation_samples * 2, 0);
+            out.write_all(&padded)?;
+        }
+
+        out.flush()?;
+        Ok(())
+    }
+}
+
+impl RecordSink for EdfWriter {
+    fn write_physiological(&mut self, _data: &PhysiologicalData) -> Result<()> {
+        // EDF+ holds signals only
+        Ok(())
+    }
+
+    fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
+        EdfWriter::write_waveform(self, data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        // The file is written by finish
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        EdfWriter::finish(*self)
+    }
+}
+
+impl Drop for EdfWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.write_file() {
+            log::error!("Failed to write EDF file: {}", e);
+        }
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Left-aligned, space-padded ASCII header field
+fn field(value: &str, width: usize) -> Vec<u8> {
+    let mut bytes: Vec<u8> = value.bytes().filter(u8::is_ascii).take(width).collect();
+    bytes.resize(width, b' ');
+    bytes
+}
+
+fn write_fields<W: Write>(out: &mut W, values: &[String], width: usize) -> Result<()> {
+    for value in values {
+        out.write_all(&field(value, width))?;
+    }
+    Ok(())
+}
+
+/// Format a number so that it fits an 8-character header field
+fn format_number(value: f64) -> String {
+    let mut text = format!("{}", value);
+    text.truncate(8);
+    text
+}
+
+fn format_seconds(seconds: f64) -> String {
+    format!("{}", (seconds * 1e6).round() / 1e6)
+}
+
+/// EDF headers are ASCII-only
+fn ascii_unit(unit: &str) -> String {
+    unit.replace('μ', "u").replace('Ω', "Ohm")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::waveforms::WaveformStatus;
+    use chrono::TimeZone;
+
+    fn waveform(waveform_type: WaveformType, rate: u16, count: usize, gap: bool) -> WaveformData {
+        WaveformData {
+            timestamp: Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(),
+            waveform_type,
+            plug_id: 0,
+            samples: (0..count as i16).collect(),
+            nominal_rate: rate,
+            effective_rate: None,
+            status: WaveformStatus {
+                gap,
+                ..WaveformStatus::default()
+            },
+            pacer_indices: Vec::new(),
+        }
+    }
+
+    fn header_field(bytes: &[u8], offset: usize, width: usize) -> String {
+        String::from_utf8_lossy(&bytes[offset..offset + width])
+            .trim()
+            .to_string()
+    }
+
+    #[test]
+    fn test_edf_layout() {
+        let path = std::env::temp_dir().join(format!("dri_edf_{}.edf", std::process::id()));
+
+        let mut writer = EdfWriter::new(&path).unwrap();
+        writer
+            .write_waveform(&waveform(WaveformType::Ecg1, 300, 300, false))
+            .unwrap();
+        writer
+            .write_waveform(&waveform(WaveformType::Co2, 25, 25, false))
+            .unwrap();
+        writer
+            .write_waveform(&waveform(WaveformType::Co2, 25, 25, true))
+            .unwrap();
+        writer.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        // ECG1 (300 Hz) + CO2 (25 Hz) + annotations
+        assert_eq!(header_field(&bytes, 0, 8), "0");
+        assert_eq!(header_field(&bytes, 168, 8), "01.05.24");
+        assert_eq!(header_field(&bytes, 184, 8), "1024");
+        assert_eq!(header_field(&bytes, 192, 44), "EDF+C");
+        assert_eq!(header_field(&bytes, 236, 8), "50");
+        assert_eq!(header_field(&bytes, 244, 8), "0.04");
+        assert_eq!(header_field(&bytes, 252, 4), "3");
+
+        let ns = 3;
+        assert_eq!(header_field(&bytes, 256, 16), "ECG1");
+        assert_eq!(header_field(&bytes, 256 + 16, 16), "CO2");
+        assert_eq!(header_field(&bytes, 256 + 32, 16), "EDF Annotations");
+
+        let phys_dim = 256 + ns * (16 + 80);
+        assert_eq!(header_field(&bytes, phys_dim + 8, 8), "%");
+        let phys_min = phys_dim + ns * 8;
+        assert_eq!(header_field(&bytes, phys_min + 8, 8), "-327.68");
+
+        let spr = 256 + ns * (16 + 80 + 8 * 5 + 80);
+        assert_eq!(header_field(&bytes, spr, 8), "12");
+        assert_eq!(header_field(&bytes, spr + 8, 8), "1");
+        let annotation_samples: usize = header_field(&bytes, spr + 16, 8).parse().unwrap();
+
+        let record_size = (12 + 1 + annotation_samples) * 2;
+        assert_eq!(bytes.len(), 256 * 4 + 50 * record_size);
+
+        // The gap starts at CO2 sample 25, i.e. 1 second into the recording
+        let record = &bytes[256 * 4 + 25 * record_size..][..record_size];
+        let tal = String::from_utf8_lossy(&record[26..]);
+        assert!(tal.starts_with("+1\x14\x14\0+1\x14Gap CO2\x14\0"));
+    }
+
+    #[test]
+    fn test_gap_in_one_signal_keeps_records_aligned() {
+        let path = std::env::temp_dir().join(format!("dri_edf_gap_{}.edf", std::process::id()));
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+
+        let mut writer = EdfWriter::new(&path).unwrap();
+        for second in 0..4 {
+            let timestamp = start + chrono::Duration::seconds(second);
+            let mut ecg = waveform(WaveformType::Ecg1, 300, 300, false);
+            ecg.timestamp = timestamp;
+            writer.write_waveform(&ecg).unwrap();
+            // The CO2 chunk of second 2 is lost
+            if second != 2 {
+                let mut co2 = waveform(WaveformType::Co2, 25, 25, second == 3);
+                co2.timestamp = timestamp;
+                co2.samples = (100 * second as i16..).take(25).collect();
+                writer.write_waveform(&co2).unwrap();
+            }
+        }
+        writer.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        // 4 seconds of 0.04 s records, 12 ECG1 and 1 CO2 sample each
+        assert_eq!(header_field(&bytes, 236, 8), "100");
+        let spr = 256 + 3 * (16 + 80 + 8 * 5 + 80);
+        let annotation_samples: usize = header_field(&bytes, spr + 16, 8).parse().unwrap();
+        let record_size = (12 + 1 + annotation_samples) * 2;
+        assert_eq!(bytes.len(), 256 * 4 + 100 * record_size);
+
+        let co2 = |record: usize| {
+            let offset = 256 * 4 + record * record_size + 24;
+            i16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+        };
+        assert_eq!(co2(25), 100);
+        assert_eq!(co2(74), 0);
+        assert_eq!(co2(75), 300);
+        let record = &bytes[256 * 4 + 75 * record_size..][..record_size];
+        let tal = String::from_utf8_lossy(&record[26..]);
+        assert!(tal.starts_with("+3\x14\x14\0+3\x14Gap CO2\x14\0"));
+    }
+}
@@ -0,0 +1,153 @@
+//! Writer for anesthesia record keeping events
+//!
+//! Events go to their own stream, apart from the vitals: as CSV
+//! (`timestamp,plug_id,subrecord_type,data`, the raw subrecord in hex) or
+//! as one JSON object per line. Other records are ignored, so the writer can sit in a
+//! [`MultiSink`](super::MultiSink) next to the CSV and JSON writers.
+
+use super::OutputFile;
+use super::sink::RecordSink;
+use crate::decode::events::EventData;
+use crate::decode::physiological::PhysiologicalData;
+use crate::decode::waveforms::WaveformData;
+use anyhow::Result;
+use std::io::Write;
+use std::path::Path;
+
+/// CSV header of the event stream
+const EVENT_HEADER: [&str; 4] = ["timestamp", "plug_id", "subrecord_type", "data"];
+
+/// Layout of the event stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventFormat {
+    Csv,
+    /// Newline-delimited JSON objects
+    JsonLines,
+}
+
+enum Target {
+    Csv(Box<csv::Writer<OutputFile>>),
+    Json(OutputFile),
+}
+
+pub struct EventWriter {
+    target: Target,
+    event_count: usize,
+}
+
+impl EventWriter {
+    /// Create the event file, compressed if the path ends in `.gz`
+    pub fn new<P: AsRef<Path>>(path: P, format: EventFormat) -> Result<Self> {
+        Self::from_output(OutputFile::create(path)?, format, true)
+    }
+
+    /// Append to an existing event file, writing the CSV header only if it is new or empty
+    pub fn append<P: AsRef<Path>>(path: P, format: EventFormat) -> Result<Self> {
+        let is_new = std::fs::metadata(&path).map_or(true, |m| m.len() == 0);
+        Self::from_output(OutputFile::append(path)?, format, is_new)
+    }
+
+    /// Write events to any target, such as `std::io::stdout()`
+    pub fn from_writer<W: Write + Send + 'static>(writer: W, format: EventFormat) -> Result<Self> {
+        Self::from_output(OutputFile::from_writer(writer), format, true)
+    }
+
+    fn from_output(output: OutputFile, format: EventFormat, header: bool) -> Result<Self> {
+        let target = match format {
+            EventFormat::Csv => {
+                let mut csv = csv::Writer::from_writer(output);
+                if header {
+                    csv.write_record(EVENT_HEADER)?;
+                }
+                Target::Csv(Box::new(csv))
+            }
+            EventFormat::JsonLines => Target::Json(output),
+        };
+        Ok(Self {
+            target,
+            event_count: 0,
+        })
+    }
+
+    /// Write one event
+    pub fn write_event(&mut self, event: &EventData) -> Result<()> {
+        match &mut self.target {
+            Target::Csv(csv) => csv.write_record([
+                event.timestamp.to_rfc3339(),
+                event.plug_id.to_string(),
+                event.subrecord_type.to_string(),
+                event.data.iter().map(|b| format!("{:02x}", b)).collect(),
+            ])?,
+            Target::Json(output) => writeln!(output, "{}", serde_json::to_string(event)?)?,
+        }
+        self.event_count += 1;
+        Ok(())
+    }
+
+    /// Number of events written so far
+    pub fn event_count(&self) -> usize {
+        self.event_count
+    }
+}
+
+impl RecordSink for EventWriter {
+    fn write_physiological(&mut self, _data: &PhysiologicalData) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_waveform(&mut self, _data: &WaveformData) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_event(&mut self, event: &EventData) -> Result<()> {
+        EventWriter::write_event(self, event)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match &mut self.target {
+            Target::Csv(csv) => csv.flush()?,
+            Target::Json(output) => output.flush()?,
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        let mut output = match self.target {
+            Target::Csv(csv) => csv
+                .into_inner()
+                .map_err(|e| anyhow::anyhow!("Failed to flush events: {}", e.error()))?,
+            Target::Json(output) => output,
+        };
+        output.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::DriRecord;
+    use crate::storage::output::SharedBuffer;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_events_are_written_as_csv() {
+        let buffer = SharedBuffer::default();
+        let mut sink: Box<dyn RecordSink> =
+            Box::new(EventWriter::from_writer(buffer.clone(), EventFormat::Csv).unwrap());
+        let event = EventData {
+            timestamp: Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+            plug_id: 3,
+            subrecord_type: 0,
+            data: vec![0x0C, 0x00, 0x7E],
+        };
+        sink.write_record(&DriRecord::Event(event)).unwrap();
+        sink.finish().unwrap();
+
+        assert_eq!(
+            buffer.contents(),
+            "timestamp,plug_id,subrecord_type,data\n\
+             2023-11-14T22:13:20+00:00,3,0,0c007e\n"
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! HL7 FHIR export of physiological data
+//!
+//! Each present vital sign becomes one FHIR R4 `Observation` with a LOINC
+//! code and a UCUM unit. Vitals without a well-established LOINC code are
+//! not exported.
+
+use crate::decode::physiological::{PhysiologicalData, is_measured};
+use serde_json::{Value, json};
+
+const LOINC_SYSTEM: &str = "http://loinc.org";
+const UCUM_SYSTEM: &str = "http://unitsofmeasure.org";
+const CATEGORY_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/observation-category";
+
+/// Mapping of a vital to its LOINC code and UCUM unit
+struct VitalCode {
+    /// Name from [`PhysiologicalData::iter_vitals`]
+    field: &'static str,
+    loinc: &'static str,
+    display: &'static str,
+    ucum: &'static str,
+    unit: &'static str,
+}
+
+const VITALS: &[VitalCode] = &[
+    VitalCode {
+        field: "ecg_hr",
+        loinc: "8867-4",
+        display: "Heart rate",
+        ucum: "/min",
+        unit: "beats/min",
+    },
+    VitalCode {
+        field: "spo2",
+        loinc: "2708-6",
+        display: "Oxygen saturation in Arterial blood",
+        ucum: "%",
+        unit: "%",
+    },
+    VitalCode {
+        field: "spo2_pr",
+        loinc: "8889-8",
+        display: "Heart rate by Pulse oximetry",
+        ucum: "/min",
+        unit: "beats/min",
+    },
+    VitalCode {
+        field: "nibp_sys",
+        loinc: "8480-6",
+        display: "Systolic blood pressure",
+        ucum: "mm[Hg]",
+        unit: "mmHg",
+    },
+    VitalCode {
+        field: "nibp_dia",
+        loinc: "8462-4",
+        display: "Diastolic blood pressure",
+        ucum: "mm[Hg]",
+        unit: "mmHg",
+    },
+    VitalCode {
+        field: "nibp_mean",
+        loinc: "8478-0",
+        display: "Mean blood pressure",
+        ucum: "mm[Hg]",
+        unit: "mmHg",
+    },
+    VitalCode {
+        field: "ecg_rr",
+        loinc: "9279-1",
+        display: "Respiratory rate",
+        ucum: "/min",
+        unit: "breaths/min",
+    },
+    VitalCode {
+        field: "co2_rr",
+        loinc: "9279-1",
+        display: "Respiratory rate",
+        ucum: "/min",
+        unit: "breaths/min",
+    },
+    VitalCode {
+        field: "temp1",
+        loinc: "8310-5",
+        display: "Body temperature",
+        ucum: "Cel",
+        unit: "°C",
+    },
+    VitalCode {
+        field: "temp2",
+        loinc: "8310-5",
+        display: "Body temperature",
+        ucum: "Cel",
+        unit: "°C",
+    },
+    VitalCode {
+        field: "o2_fi",
+        loinc: "3150-0",
+        display: "Inhaled oxygen concentration",
+        ucum: "%",
+        unit: "%",
+    },
+];
+
+/// Convert a physiological record to FHIR `Observation` resources
+///
+/// Returns one Observation per present vital; missing and invalid values
+/// are skipped.
+pub fn to_observations(data: &PhysiologicalData) -> Vec<Value> {
+    let effective = data.timestamp.to_rfc3339();
+    let values: Vec<_> = data.iter_vitals().collect();
+
+    VITALS
+        .iter()
+        .filter_map(|vital| {
+            let (_, value, _) = values.iter().find(|(name, _, _)| *name == vital.field)?;
+            let value = value.filter(|&v| is_measured(v))?;
+            Some(json!({
+                "resourceType": "Observation",
+                "status": "final",
+                "category": [{
+                    "coding": [{
+                        "system": CATEGORY_SYSTEM,
+                        "code": "vital-signs",
+                        "display": "Vital Signs"
+                    }]
+                }],
+                "code": {
+                    "coding": [{
+                        "system": LOINC_SYSTEM,
+                        "code": vital.loinc,
+                        "display": vital.display
+                    }],
+                    "text": vital.display
+                },
+                "effectiveDateTime": effective,
+                "valueQuantity": {
+                    "value": value,
+                    "unit": vital.unit,
+                    "system": UCUM_SYSTEM,
+                    "code": vital.ucum
+                }
+            }))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::dri_types::{PhdbClass, PhdbSubrecordType};
+    use crate::constants::special_values::DATA_INVALID;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_to_observations() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let mut phys =
+            PhysiologicalData::empty(timestamp, PhdbClass::Basic, PhdbSubrecordType::Displ);
+        phys.ecg_hr = Some(72.0);
+        phys.spo2 = Some(98.5);
+
+        let observations = to_observations(&phys);
+        assert_eq!(observations.len(), 2);
+
+        let hr = &observations[0];
+        assert_eq!(hr["resourceType"], "Observation");
+        assert_eq!(hr["code"]["coding"][0]["code"], "8867-4");
+        assert_eq!(hr["effectiveDateTime"], "2024-05-01T12:00:00+00:00");
+        assert_eq!(hr["valueQuantity"]["value"], 72.0);
+        assert_eq!(hr["valueQuantity"]["code"], "/min");
+
+        let spo2 = &observations[1];
+        assert_eq!(spo2["code"]["coding"][0]["code"], "2708-6");
+        assert_eq!(spo2["valueQuantity"]["code"], "%");
+    }
+
+    #[test]
+    fn test_missing_vitals_are_skipped() {
+        let phys = PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+        assert!(to_observations(&phys).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_vitals_are_skipped() {
+        let mut phys =
+            PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+        // As kept by InvalidPolicy::NaN and InvalidPolicy::Sentinel
+        phys.ecg_hr = Some(f64::NAN);
+        phys.spo2 = Some(f64::from(DATA_INVALID));
+        assert!(to_observations(&phys).is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+//! InfluxDB line protocol writer for DRI data
+//!
+//! Each physiological record becomes one `vitals` point tagged with its
+//! class, subtype and plug id. Present numeric vitals are float fields, status
+//! flags are boolean fields, enum values (labels, sources) are string
+//! fields, and absent or invalid values are omitted.
+
+use super::columns::{Column, PHYS_COLUMNS};
+use super::sink::RecordSink;
+use crate::decode::physiological::{PhysiologicalData, is_measured};
+use crate::decode::waveforms::WaveformData;
+use anyhow::Result;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Measurement name used for physiological points
+pub const MEASUREMENT: &str = "vitals";
+
+/// Default number of lines buffered before writing
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// Format a physiological record as a line protocol point
+pub fn to_line_protocol(data: &PhysiologicalData) -> String {
+    let mut fields = Vec::new();
+    let mut nanos = 0;
+
+    for (name, column) in PHYS_COLUMNS {
+        match column {
+            // DRI timestamps have one second resolution
+            Column::Timestamp(get) => nanos = get(data).timestamp_millis() * 1_000_000,
+            Column::Flag(get) => fields.push(format!("{}={}", name, get(data))),
+            Column::Value(get) => {
+                if let Some(v) = get(data).filter(|&v| is_measured(v)) {
+                    fields.push(format!("{}={}", name, v));
+                }
+            }
+            Column::Label(_) if matches!(*name, "class" | "subtype" | "plug_id") => {}
+            Column::Label(get) => {
+                if let Some(v) = get(data) {
+                    fields.push(format!("{}=\"{}\"", name, v));
+                }
+            }
+        }
+    }
+
+    format!(
+        "{},class={:?},subtype={:?},plug_id={} {} {}",
+        MEASUREMENT,
+        data.class,
+        data.subtype,
+        data.plug_id,
+        fields.join(","),
+        nanos
+    )
+}
+
+pub struct InfluxLineWriter<W: Write> {
+    writer: W,
+    lines: Vec<String>,
+    batch_size: usize,
+}
+
+impl InfluxLineWriter<File> {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::from_writer(File::create(path)?))
+    }
+}
+
+impl<W: Write> InfluxLineWriter<W> {
+    /// Write lines to any output (file, socket, stdout)
+    pub fn from_writer(writer: W) -> Self {
+        Self {
+            writer,
+            lines: Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+
+    /// Set how many lines are buffered before writing
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Write physiological data
+    pub fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        self.lines.push(to_line_protocol(data));
+        if self.lines.len() >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write all buffered lines
+    pub fn flush(&mut self) -> Result<()> {
+        for line in self.lines.drain(..) {
+            writeln!(self.writer, "{}", line)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> RecordSink for InfluxLineWriter<W> {
+    fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        InfluxLineWriter::write_physiological(self, data)
+    }
+
+    fn write_waveform(&mut self, _data: &WaveformData) -> Result<()> {
+        // Line protocol output covers vitals only
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        InfluxLineWriter::flush(self)
+    }
+}
+
+impl<W: Write> Drop for InfluxLineWriter<W> {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            log::error!("Failed to write line protocol batch: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::dri_types::{PhdbClass, PhdbSubrecordType};
+    use crate::constants::special_values::DATA_INVALID;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_to_line_protocol() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let mut phys =
+            PhysiologicalData::empty(timestamp, PhdbClass::Basic, PhdbSubrecordType::Displ);
+        phys.ecg_hr = Some(72.0);
+        phys.spo2 = Some(98.5);
+        phys.ecg_status.active = true;
+
+        let line = to_line_protocol(&phys);
+        assert!(line.starts_with("vitals,class=Basic,subtype=Displ,plug_id=0 "));
+        assert!(line.ends_with(" 1714564800000000000"));
+        assert!(line.contains("ecg_hr=72"));
+        assert!(line.contains("spo2_percent=98.5"));
+        assert!(line.contains("ecg_active=true"));
+        assert!(line.contains("ecg_asystole=false"));
+        assert!(!line.contains("nibp_sys"));
+        assert!(!line.contains("ecg_hr_source"));
+    }
+
+    #[test]
+    fn test_invalid_values_are_omitted() {
+        let mut phys =
+            PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+        // As kept by InvalidPolicy::NaN and InvalidPolicy::Sentinel
+        phys.ecg_hr = Some(f64::NAN);
+        phys.spo2 = Some(f64::from(DATA_INVALID));
+        phys.spo2_pr = Some(72.0);
+
+        let line = to_line_protocol(&phys);
+        assert!(!line.contains("ecg_hr="));
+        assert!(!line.contains("spo2_percent="));
+        assert!(line.contains("spo2_pr=72"));
+    }
+
+    #[test]
+    fn test_batching() {
+        let phys = PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+        let mut output = Vec::new();
+        {
+            let mut writer = InfluxLineWriter::from_writer(&mut output).with_batch_size(2);
+            writer.write_physiological(&phys).unwrap();
+            writer.write_physiological(&phys).unwrap();
+            writer.write_physiological(&phys).unwrap();
+            assert_eq!(writer.lines.len(), 1);
+        }
+        let text = String::from_utf8(output).unwrap();
+        assert_eq!(text.lines().count(), 3);
+    }
+}
@@ -1,37 +1,244 @@
 //! JSON file writer for DRI data
+//!
+//! By default records are written as a single JSON array, closed by
+//! [`JsonWriter::finish`] or on drop. [`JsonFormat::Lines`] writes one
+//! object per line (NDJSON) instead, which suits streaming consumers and
+//! stays readable if the process is killed.
+//!
+//! [`JsonWriter::from_writer`] sends the output to any target, e.g. stdout.
+//!
+//! Vitals are plain numbers by default. [`JsonWriter::with_units`] writes
+//! each present vital as `{ "value": 37.0, "unit": "C" }` instead, using
+//! the units of [`PhysiologicalData::iter_vitals`].
 
+use super::OutputFile;
+use super::sink::RecordSink;
 use crate::decode::physiological::PhysiologicalData;
 use crate::decode::waveforms::WaveformData;
 use anyhow::Result;
-use serde_json;
-use std::fs::OpenOptions;
+use serde::Serialize;
+use serde_json::{self, json};
+use std::fs::{File, OpenOptions};
 use std::io::Write;
 use std::path::Path;
 
+/// Layout of the JSON output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonFormat {
+    /// A single `[ ... ]` array
+    Array,
+    /// Newline-delimited objects, appended to an existing file
+    Lines,
+}
+
 pub struct JsonWriter {
-    file: std::fs::File,
+    file: OutputFile,
+    format: JsonFormat,
+    units: bool,
+    record_count: usize,
+    closed: bool,
 }
 
 impl JsonWriter {
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Self::with_format(path, JsonFormat::Array)
+    }
+
+    /// Create a writer with the given output layout
+    pub fn with_format<P: AsRef<Path>>(path: P, format: JsonFormat) -> Result<Self> {
+        let file = match format {
+            JsonFormat::Array => File::create(path)?,
+            JsonFormat::Lines => OpenOptions::new().create(true).append(true).open(path)?,
+        };
+
+        Ok(Self::from_writer(file, format))
+    }
+
+    /// Create a writer over any target, such as `std::io::stdout()`
+    pub fn from_writer<W: Write + Send + 'static>(writer: W, format: JsonFormat) -> Self {
+        Self {
+            file: OutputFile::from_writer(writer),
+            format,
+            units: false,
+            record_count: 0,
+            closed: false,
+        }
+    }
 
-        Ok(Self { file })
+    /// Annotate vitals with their unit (`false` keeps the compact form)
+    pub fn with_units(mut self, units: bool) -> Self {
+        self.units = units;
+        self
     }
 
-    /// Write physiological data as JSON line
+    /// Write physiological data
     pub fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        if !self.units {
+            return self.write_record(data);
+        }
+
+        let mut value = serde_json::to_value(data)?;
+        for (field, _, unit) in data.iter_vitals().filter(|(_, _, unit)| !unit.is_empty()) {
+            if let Some(v) = value.get_mut(field).filter(|v| v.is_number()) {
+                *v = json!({ "value": v.take(), "unit": unit });
+            }
+        }
+        self.write_record(&value)
+    }
+
+    /// Write waveform data
+    pub fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
+        self.write_record(data)
+    }
+
+    /// Number of records written so far
+    pub fn record_count(&self) -> usize {
+        self.record_count
+    }
+
+    /// Close the array (if any) and flush the file
+    pub fn finish(mut self) -> Result<()> {
+        self.close()
+    }
+
+    fn write_record<T: Serialize>(&mut self, data: &T) -> Result<()> {
         let json = serde_json::to_string(data)?;
-        writeln!(self.file, "{}", json)?;
+        match self.format {
+            JsonFormat::Array => {
+                let separator = if self.record_count == 0 { "[\n" } else { ",\n" };
+                write!(self.file, "{}{}", separator, json)?;
+            }
+            JsonFormat::Lines => writeln!(self.file, "{}", json)?,
+        }
+        self.record_count += 1;
         self.file.flush()?;
         Ok(())
     }
 
-    /// Write waveform data as JSON line
-    pub fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
-        let json = serde_json::to_string(data)?;
-        writeln!(self.file, "{}", json)?;
+    fn close(&mut self) -> Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.closed = true;
+
+        if self.format == JsonFormat::Array {
+            if self.record_count == 0 {
+                writeln!(self.file, "[]")?;
+            } else {
+                writeln!(self.file, "\n]")?;
+            }
+        }
+        self.file.finish()?;
+        Ok(())
+    }
+}
+
+impl RecordSink for JsonWriter {
+    fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        JsonWriter::write_physiological(self, data)
+    }
+
+    fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
+        JsonWriter::write_waveform(self, data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
         self.file.flush()?;
         Ok(())
     }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        JsonWriter::finish(*self)
+    }
+}
+
+impl Drop for JsonWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            log::error!("Failed to finish JSON file: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::dri_types::{PhdbClass, PhdbSubrecordType};
+    use crate::storage::output::SharedBuffer;
+    use chrono::Utc;
+
+    fn write_records(path: &Path, format: JsonFormat, count: usize) -> usize {
+        let mut writer = JsonWriter::with_format(path, format).unwrap();
+        for _ in 0..count {
+            let phys =
+                PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+            writer.write_physiological(&phys).unwrap();
+        }
+        let written = writer.record_count();
+        writer.finish().unwrap();
+        written
+    }
+
+    #[test]
+    fn test_array_format_is_valid_json() {
+        let path = std::env::temp_dir().join(format!("dri_json_{}.json", std::process::id()));
+
+        assert_eq!(write_records(&path, JsonFormat::Array, 3), 3);
+        let value: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(value.as_array().unwrap().len(), 3);
+
+        write_records(&path, JsonFormat::Array, 0);
+        let value: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(value.as_array().unwrap().is_empty());
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_lines_format() {
+        let path = std::env::temp_dir().join(format!("dri_ndjson_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(write_records(&path, JsonFormat::Lines, 2), 2);
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(contents.lines().count(), 2);
+        for line in contents.lines() {
+            serde_json::from_str::<serde_json::Value>(line).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_unit_annotations() {
+        let mut phys =
+            PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+        phys.temp1 = Some(37.0);
+
+        let buffer = SharedBuffer::default();
+        let mut writer =
+            JsonWriter::from_writer(buffer.clone(), JsonFormat::Lines).with_units(true);
+        writer.write_physiological(&phys).unwrap();
+        writer.finish().unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(buffer.contents().trim()).unwrap();
+        assert_eq!(value["temp1"], json!({ "value": 37.0, "unit": "C" }));
+        assert!(value["temp2"].is_null());
+        assert_eq!(value["plug_id"], json!(0));
+    }
+
+    #[test]
+    fn test_from_writer() {
+        let buffer = SharedBuffer::default();
+        let mut writer = JsonWriter::from_writer(buffer.clone(), JsonFormat::Lines);
+        let phys = PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+        writer.write_physiological(&phys).unwrap();
+        writer.finish().unwrap();
+
+        let contents = buffer.contents();
+        assert_eq!(contents.lines().count(), 1);
+        serde_json::from_str::<serde_json::Value>(contents.trim()).unwrap();
+    }
 }
@@ -1,9 +1,47 @@
 //! Data storage module
 
+#[cfg(feature = "binary")]
+pub mod binary_reader;
+#[cfg(feature = "binary")]
+pub mod binary_writer;
+mod columns;
 pub mod csv_writer;
+pub mod edf_writer;
+pub mod event_writer;
+pub mod fhir;
+pub mod influx_writer;
 pub mod json_writer;
+#[cfg(feature = "mqtt")]
+pub mod mqtt_publisher;
+pub mod output;
+#[cfg(feature = "parquet")]
+pub mod parquet_writer;
+pub mod raw_reader;
 pub mod raw_writer;
+pub mod rotation;
+pub mod sink;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_writer;
+pub mod stats;
 
-pub use csv_writer::CsvWriter;
-pub use json_writer::JsonWriter;
-pub use raw_writer::RawWriter;
+#[cfg(feature = "binary")]
+pub use binary_reader::BinaryReader;
+#[cfg(feature = "binary")]
+pub use binary_writer::BinaryWriter;
+pub use csv_writer::{CsvColumnSet, CsvWriter, WaveformCsvLayout};
+pub use edf_writer::EdfWriter;
+pub use event_writer::{EventFormat, EventWriter};
+pub use influx_writer::InfluxLineWriter;
+pub use json_writer::{JsonFormat, JsonWriter};
+#[cfg(feature = "mqtt")]
+pub use mqtt_publisher::{MqttPublisher, MqttQos};
+pub use output::OutputFile;
+#[cfg(feature = "parquet")]
+pub use parquet_writer::ParquetWriter;
+pub use raw_reader::RawReader;
+pub use raw_writer::{RawFormat, RawWriter};
+pub use rotation::RotationPolicy;
+pub use sink::{MultiSink, RecordSink};
+#[cfg(feature = "sqlite")]
+pub use sqlite_writer::SqliteWriter;
+pub use stats::{SessionReport, SessionStats};
@@ -0,0 +1,389 @@
+//! MQTT publisher for DRI data
+//!
+//! Requires the `mqtt` feature. Physiological records are published as JSON
+//! to `<prefix>/<plug_id>/phys` and waveform chunks to
+//! `<prefix>/<plug_id>/wave/<type>`. A minimal MQTT 3.1.1 client is built
+//! in: clean session, optional keep-alive, QoS 0 and 1.
+
+use super::sink::RecordSink;
+use crate::decode::physiological::PhysiologicalData;
+use crate::decode::waveforms::WaveformData;
+use anyhow::{Result, anyhow, bail};
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// Default topic prefix
+pub const DEFAULT_TOPIC_PREFIX: &str = "dri";
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const PUBACK: u8 = 0x40;
+const PINGREQ: u8 = 0xC0;
+const PINGRESP: u8 = 0xD0;
+const DISCONNECT: u8 = 0xE0;
+
+/// Largest body a packet's variable-length size can describe
+const MAX_PACKET_BODY: usize = 268_435_455;
+
+/// Longest wait for a CONNACK or PUBACK from the broker
+const ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delivery guarantee for published messages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MqttQos {
+    /// Fire and forget
+    #[default]
+    AtMostOnce,
+    /// Wait for the broker's acknowledgement of each message
+    AtLeastOnce,
+}
+
+/// Publishes decoded records to an MQTT broker
+pub struct MqttPublisher<S: Read + Write = TcpStream> {
+    stream: S,
+    qos: MqttQos,
+    topic_prefix: String,
+    next_packet_id: u16,
+    published: usize,
+    keep_alive: Duration,
+    last_sent: Instant,
+}
+
+impl MqttPublisher<TcpStream> {
+    /// Connect to a broker, e.g. `"localhost:1883"`
+    ///
+    /// See [`with_stream`](Self::with_stream) for `keep_alive`. Waiting for
+    /// an acknowledgement fails after 10 seconds instead of hanging.
+    pub fn connect<A: ToSocketAddrs>(
+        addr: A,
+        client_id: &str,
+        keep_alive: Duration,
+    ) -> Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(ACK_TIMEOUT))?;
+        Self::with_stream(stream, client_id, keep_alive)
+    }
+}
+
+impl<S: Read + Write> MqttPublisher<S> {
+    /// Open an MQTT session over an already connected stream
+    ///
+    /// The broker drops the session if nothing is sent for 1.5 times
+    /// `keep_alive` (whole seconds, at most 65535); zero disables this.
+    /// Publishing counts as activity, see [`ping_if_due`](Self::ping_if_due)
+    /// for idle periods.
+    pub fn with_stream(mut stream: S, client_id: &str, keep_alive: Duration) -> Result<Self> {
+        let keep_alive_secs = u16::try_from(keep_alive.as_secs())
+            .map_err(|_| anyhow!("MQTT keep-alive too long: {:?}", keep_alive))?;
+
+        let mut body = Vec::new();
+        put_string(&mut body, "MQTT")?;
+        body.push(4); // protocol level 3.1.1
+        body.push(0x02); // clean session
+        body.extend(keep_alive_secs.to_be_bytes());
+        put_string(&mut body, client_id)?;
+        write_packet(&mut stream, CONNECT, &body)?;
+
+        let (kind, ack) = read_packet(&mut stream)?;
+        if kind != CONNACK || ack.len() != 2 {
+            bail!(
+                "Expected CONNACK from MQTT broker, got packet 0x{:02X}",
+                kind
+            );
+        }
+        if ack[1] != 0 {
+            bail!("MQTT broker refused the connection (code {})", ack[1]);
+        }
+
+        Ok(Self {
+            stream,
+            qos: MqttQos::default(),
+            topic_prefix: DEFAULT_TOPIC_PREFIX.to_string(),
+            next_packet_id: 1,
+            published: 0,
+            keep_alive: Duration::from_secs(keep_alive_secs.into()),
+            last_sent: Instant::now(),
+        })
+    }
+
+    /// Set the delivery guarantee
+    pub fn with_qos(mut self, qos: MqttQos) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Set the first topic level (default `dri`)
+    pub fn with_topic_prefix(mut self, prefix: &str) -> Self {
+        self.topic_prefix = prefix.to_string();
+        self
+    }
+
+    /// Publish physiological data
+    pub fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        let topic = format!("{}/{}/phys", self.topic_prefix, data.plug_id);
+        self.publish_json(&topic, data)
+    }
+
+    /// Publish waveform data
+    pub fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
+        let topic = format!(
+            "{}/{}/wave/{}",
+            self.topic_prefix,
+            data.plug_id,
+            data.waveform_type.name()
+        );
+        self.publish_json(&topic, data)
+    }
+
+    /// Number of messages published so far
+    pub fn published(&self) -> usize {
+        self.published
+    }
+
+    /// Send a PINGREQ if nothing was sent for the keep-alive interval
+    ///
+    /// Call it while no records arrive to keep the session open;
+    /// [`RecordSink::keep_alive`] does.
+    pub fn ping_if_due(&mut self) -> Result<()> {
+        if !self.keep_alive.is_zero() && self.last_sent.elapsed() >= self.keep_alive {
+            self.send(PINGREQ, &[])?;
+        }
+        Ok(())
+    }
+
+    /// Disconnect cleanly from the broker
+    pub fn finish(mut self) -> Result<()> {
+        self.send(DISCONNECT, &[])
+    }
+
+    fn send(&mut self, kind: u8, body: &[u8]) -> Result<()> {
+        write_packet(&mut self.stream, kind, body)?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+
+    fn publish_json<T: Serialize>(&mut self, topic: &str, data: &T) -> Result<()> {
+        let mut body = Vec::new();
+        put_string(&mut body, topic)?;
+
+        let packet_id = match self.qos {
+            MqttQos::AtMostOnce => None,
+            MqttQos::AtLeastOnce => {
+                let id = self.next_packet_id;
+                self.next_packet_id = self.next_packet_id.checked_add(1).unwrap_or(1);
+                body.extend(id.to_be_bytes());
+                Some(id)
+            }
+        };
+        serde_json::to_writer(&mut body, data)?;
+
+        let flags = if packet_id.is_some() { 0x02 } else { 0x00 };
+        self.send(PUBLISH | flags, &body)?;
+
+        if let Some(id) = packet_id {
+            // Answers to earlier pings may come first
+            let (kind, ack) = loop {
+                match read_packet(&mut self.stream)? {
+                    (PINGRESP, _) => continue,
+                    packet => break packet,
+                }
+            };
+            if kind != PUBACK || ack != id.to_be_bytes() {
+                bail!(
+                    "Expected PUBACK for message {}, got packet 0x{:02X}",
+                    id,
+                    kind
+                );
+            }
+        }
+
+        self.published += 1;
+        Ok(())
+    }
+}
+
+impl<S: Read + Write> RecordSink for MqttPublisher<S> {
+    fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        MqttPublisher::write_physiological(self, data)
+    }
+
+    fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
+        MqttPublisher::write_waveform(self, data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    fn keep_alive(&mut self) -> Result<()> {
+        self.ping_if_due()
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        MqttPublisher::finish(*self)
+    }
+}
+
+/// Append a length-prefixed UTF-8 string, failing past 65535 bytes
+fn put_string(buf: &mut Vec<u8>, s: &str) -> Result<()> {
+    let len =
+        u16::try_from(s.len()).map_err(|_| anyhow!("MQTT string too long: {} bytes", s.len()))?;
+    buf.extend(len.to_be_bytes());
+    buf.extend(s.as_bytes());
+    Ok(())
+}
+
+/// Write a packet with its fixed header and variable-length size
+fn write_packet<W: Write>(stream: &mut W, kind: u8, body: &[u8]) -> Result<()> {
+    check_body_len(body.len())?;
+    let mut packet = vec![kind];
+    let mut len = body.len();
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+    packet.extend(body);
+    stream.write_all(&packet)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Fail if a packet body of `len` bytes cannot be sent
+fn check_body_len(len: usize) -> Result<()> {
+    if len > MAX_PACKET_BODY {
+        bail!("MQTT packet too large: {} bytes", len);
+    }
+    Ok(())
+}
+
+/// Read one packet, returning its type (upper nibble) and body
+fn read_packet<R: Read>(stream: &mut R) -> Result<(u8, Vec<u8>)> {
+    let mut byte = [0u8; 1];
+    stream.read_exact(&mut byte)?;
+    let kind = byte[0] & 0xF0;
+
+    let mut len = 0usize;
+    for shift in (0..4).map(|i| 7 * i) {
+        stream.read_exact(&mut byte)?;
+        len |= ((byte[0] & 0x7F) as usize) << shift;
+        if byte[0] & 0x80 == 0 {
+            let mut body = vec![0u8; len];
+            stream.read_exact(&mut body)?;
+            return Ok((kind, body));
+        }
+    }
+    Err(anyhow!("Malformed MQTT packet length"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::dri_types::{PhdbClass, PhdbSubrecordType};
+    use chrono::Utc;
+    use std::io::Cursor;
+
+    /// Replays broker packets and records what the client sends
+    struct FakeBroker {
+        incoming: Cursor<Vec<u8>>,
+        sent: Vec<u8>,
+    }
+
+    impl Read for FakeBroker {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.incoming.read(buf)
+        }
+    }
+
+    impl Write for FakeBroker {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.sent.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_publish_with_ack() {
+        let broker = FakeBroker {
+            incoming: Cursor::new(vec![CONNACK, 2, 0, 0, PINGRESP, 0, PUBACK, 2, 0, 1]),
+            sent: Vec::new(),
+        };
+        let mut publisher = MqttPublisher::with_stream(broker, "test", Duration::ZERO)
+            .unwrap()
+            .with_qos(MqttQos::AtLeastOnce);
+
+        let mut data =
+            PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+        data.plug_id = 3;
+        publisher.write_physiological(&data).unwrap();
+        assert_eq!(publisher.published(), 1);
+
+        let sent = &publisher.stream.sent;
+        let connect_len = 2 + sent[1] as usize;
+        let publish = &sent[connect_len..];
+        assert_eq!(publish[0], PUBLISH | 0x02);
+        let topic = b"dri/3/phys";
+        // Two length bytes: the JSON body is over 127 bytes
+        assert_eq!(&publish[3..5], &(topic.len() as u16).to_be_bytes());
+        assert_eq!(&publish[5..5 + topic.len()], topic);
+        assert_eq!(&publish[5 + topic.len()..7 + topic.len()], &[0, 1]);
+
+        // A second message gets no PUBACK
+        assert!(publisher.write_physiological(&data).is_err());
+    }
+
+    #[test]
+    fn test_keep_alive_pings_when_idle() {
+        let broker = FakeBroker {
+            incoming: Cursor::new(vec![CONNACK, 2, 0, 0]),
+            sent: Vec::new(),
+        };
+        let mut publisher =
+            MqttPublisher::with_stream(broker, "test", Duration::from_secs(1)).unwrap();
+        let connect_len = publisher.stream.sent.len();
+
+        RecordSink::keep_alive(&mut publisher).unwrap();
+        assert_eq!(publisher.stream.sent.len(), connect_len);
+        publisher.last_sent -= Duration::from_secs(1);
+        RecordSink::keep_alive(&mut publisher).unwrap();
+        assert_eq!(&publisher.stream.sent[connect_len..], &[PINGREQ, 0]);
+    }
+
+    #[test]
+    fn test_oversized_fields_are_rejected() {
+        let broker = FakeBroker {
+            incoming: Cursor::new(vec![CONNACK, 2, 0, 0]),
+            sent: Vec::new(),
+        };
+        let publisher =
+            MqttPublisher::with_stream(broker, "test", Duration::from_secs(30)).unwrap();
+        // Keep-alive in seconds, after protocol name, level and flags
+        assert_eq!(&publisher.stream.sent[10..12], &30u16.to_be_bytes());
+
+        let long = "x".repeat(usize::from(u16::MAX) + 1);
+        assert!(put_string(&mut Vec::new(), &long).is_err());
+        let data = PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+        assert!(
+            publisher
+                .with_topic_prefix(&long)
+                .write_physiological(&data)
+                .is_err()
+        );
+
+        assert!(check_body_len(MAX_PACKET_BODY).is_ok());
+        assert!(check_body_len(MAX_PACKET_BODY + 1).is_err());
+    }
+}
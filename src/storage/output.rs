@@ -0,0 +1,136 @@
+//! Output files with optional gzip compression, or any other write target
+
+use flate2::Compression;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+/// A plain or gzip-compressed output file, or an arbitrary stream
+///
+/// Paths ending in `.gz` are compressed. Call [`OutputFile::finish`] before
+/// dropping so that the gzip trailer is written and errors are reported.
+pub enum OutputFile {
+    Plain(File),
+    Gzip(GzEncoder<File>),
+    Stream(Box<dyn Write + Send>),
+}
+
+impl OutputFile {
+    /// Create a file, compressed if the path ends in `.gz`
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::create(&path)?;
+        if is_gzip_path(&path) {
+            Ok(Self::Gzip(GzEncoder::new(file, Compression::default())))
+        } else {
+            Ok(Self::Plain(file))
+        }
+    }
+
+    /// Open a file for appending, creating it if needed
+    ///
+    /// Appending to a `.gz` path adds a new gzip member, which standard
+    /// tools decompress as one stream.
+    pub fn append<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        if is_gzip_path(&path) {
+            Ok(Self::Gzip(GzEncoder::new(file, Compression::default())))
+        } else {
+            Ok(Self::Plain(file))
+        }
+    }
+
+    /// Wrap any write target, such as `std::io::stdout()`
+    pub fn from_writer<W: Write + Send + 'static>(writer: W) -> Self {
+        Self::Stream(Box::new(writer))
+    }
+
+    /// Flush and, for gzip, write the trailer
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(file) => file.flush(),
+            Self::Gzip(encoder) => encoder.try_finish(),
+            Self::Stream(stream) => stream.flush(),
+        }
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(file) => file.write(buf),
+            Self::Gzip(encoder) => encoder.write(buf),
+            Self::Stream(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(file) => file.flush(),
+            Self::Gzip(encoder) => encoder.flush(),
+            Self::Stream(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Check whether a path selects gzip compression
+pub fn is_gzip_path<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+}
+
+/// Open a file for reading, decompressing it if the path ends in `.gz`
+pub fn open_input<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn Read + Send>> {
+    let file = File::open(&path)?;
+    if is_gzip_path(&path) {
+        Ok(Box::new(MultiGzDecoder::new(file)))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+/// Read the first line of an existing, possibly gzip-compressed file
+///
+/// Returns `None` if the file does not exist or is empty.
+pub fn read_first_line<P: AsRef<Path>>(path: P) -> io::Result<Option<String>> {
+    let reader = match open_input(&path) {
+        Ok(reader) => reader,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let mut line = String::new();
+    BufReader::new(reader).read_line(&mut line)?;
+    let line = line.trim_end_matches(['\r', '\n']);
+    Ok((!line.is_empty()).then(|| line.to_string()))
+}
+
+/// In-memory write target shared between a writer and a test
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl SharedBuffer {
+    pub(crate) fn contents(&self) -> String {
+        String::from_utf8(self.bytes()).unwrap()
+    }
+
+    pub(crate) fn bytes(&self) -> Vec<u8> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
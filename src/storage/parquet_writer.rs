@@ -0,0 +1,428 @@
+//! Parquet file writer for DRI data
+//!
+//! Rows are buffered in memory and written out as one Parquet row group
+//! every `row_group_size` records (see [`DEFAULT_ROW_GROUP_SIZE`]). Larger
+//! row groups compress better and load faster in pandas/polars, but keep
+//! more rows in memory and lose more data if the process is killed before
+//! the next flush. Call [`ParquetWriter::finish`] (or drop the writer) to
+//! write the remaining rows and the file footer; a file without a footer
+//! cannot be read.
+//!
+//! The physiological columns match the CSV schema, with typed columns:
+//! status flags are `BOOLEAN`, scaled values are optional `DOUBLE`, enums
+//! are optional UTF-8 strings and the timestamp is a UTC millisecond
+//! `TIMESTAMP`.
+
+use super::columns::{Column, PHYS_COLUMNS};
+use super::sink::RecordSink;
+use crate::decode::physiological::PhysiologicalData;
+use crate::decode::waveforms::{WaveformData, WaveformDecimator};
+use anyhow::{Result, anyhow};
+use parquet::basic::Compression;
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int32Type, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Default number of records buffered before a row group is written
+pub const DEFAULT_ROW_GROUP_SIZE: usize = 1000;
+
+const WAVEFORM_SCHEMA: &str = "
+    message waveform {
+        REQUIRED INT64 timestamp (TIMESTAMP(MILLIS,true));
+        REQUIRED BYTE_ARRAY waveform_type (UTF8);
+        REQUIRED INT32 plug_id;
+        REQUIRED INT32 sample_rate;
+        REQUIRED INT32 sample_count;
+        REQUIRED BOOLEAN gap;
+        REQUIRED BOOLEAN pacer_detected;
+        REQUIRED BOOLEAN lead_off;
+        REPEATED INT32 samples (INT_16);
+    }
+";
+
+pub struct ParquetWriter {
+    main_writer: Option<SerializedFileWriter<File>>,
+    waveform_writer: Option<SerializedFileWriter<File>>,
+    main_path: String,
+    waveform_path: String,
+    main_rows: Vec<PhysiologicalData>,
+    waveform_rows: Vec<WaveformData>,
+    row_group_size: usize,
+    decimator: Option<WaveformDecimator>,
+}
+
+impl ParquetWriter {
+    pub fn new<P: AsRef<Path>>(base_path: P) -> Result<Self> {
+        let base_path_str = base_path.as_ref().to_string_lossy().to_string();
+        let waveform_path = if base_path_str.ends_with(".parquet") {
+            base_path_str.replace(".parquet", ".waveforms.parquet")
+        } else {
+            format!("{}.waveforms.parquet", base_path_str)
+        };
+
+        Ok(Self {
+            main_writer: None,
+            waveform_writer: None,
+            main_path: base_path_str,
+            waveform_path,
+            main_rows: Vec::new(),
+            waveform_rows: Vec::new(),
+            row_group_size: DEFAULT_ROW_GROUP_SIZE,
+            decimator: None,
+        })
+    }
+
+    /// Set how many records are buffered before a row group is written
+    pub fn with_row_group_size(mut self, row_group_size: usize) -> Self {
+        self.row_group_size = row_group_size.max(1);
+        self
+    }
+
+    /// Decimate waveforms before writing them, see [`WaveformDecimator`]
+    pub fn with_decimation(mut self, decimator: WaveformDecimator) -> Self {
+        self.decimator = Some(decimator);
+        self
+    }
+
+    /// Write physiological data
+    pub fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        self.main_rows.push(data.clone());
+        if self.main_rows.len() >= self.row_group_size {
+            self.flush_physiological()?;
+        }
+        Ok(())
+    }
+
+    /// Write waveform data
+    pub fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
+        self.waveform_rows.push(match self.decimator {
+            Some(decimator) => decimator.decimate(data),
+            None => data.clone(),
+        });
+        if self.waveform_rows.len() >= self.row_group_size {
+            self.flush_waveforms()?;
+        }
+        Ok(())
+    }
+
+    /// Write all buffered records as row groups
+    pub fn flush(&mut self) -> Result<()> {
+        self.flush_physiological()?;
+        self.flush_waveforms()
+    }
+
+    /// Flush remaining records and write the file footers
+    pub fn finish(mut self) -> Result<()> {
+        self.close()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.flush()?;
+        if let Some(writer) = self.main_writer.take() {
+            writer.close()?;
+        }
+        if let Some(writer) = self.waveform_writer.take() {
+            writer.close()?;
+        }
+        Ok(())
+    }
+
+    fn flush_physiological(&mut self) -> Result<()> {
+        if self.main_rows.is_empty() {
+            return Ok(());
+        }
+
+        if self.main_writer.is_none() {
+            self.main_writer = Some(create_file_writer(
+                &self.main_path,
+                &physiological_schema(),
+            )?);
+        }
+
+        if let Some(writer) = &mut self.main_writer {
+            write_physiological_group(writer, &self.main_rows)?;
+            writer.flush()?;
+        }
+
+        self.main_rows.clear();
+        Ok(())
+    }
+
+    fn flush_waveforms(&mut self) -> Result<()> {
+        if self.waveform_rows.is_empty() {
+            return Ok(());
+        }
+
+        if self.waveform_writer.is_none() {
+            self.waveform_writer = Some(create_file_writer(&self.waveform_path, WAVEFORM_SCHEMA)?);
+        }
+
+        if let Some(writer) = &mut self.waveform_writer {
+            write_waveform_group(writer, &self.waveform_rows)?;
+            writer.flush()?;
+        }
+
+        self.waveform_rows.clear();
+        Ok(())
+    }
+}
+
+impl RecordSink for ParquetWriter {
+    fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        ParquetWriter::write_physiological(self, data)
+    }
+
+    fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
+        ParquetWriter::write_waveform(self, data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        ParquetWriter::flush(self)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        ParquetWriter::finish(*self)
+    }
+}
+
+impl Drop for ParquetWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            log::error!("Failed to finish Parquet files: {}", e);
+        }
+    }
+}
+
+/// Build the physiological message type from the column table
+fn physiological_schema() -> String {
+    let mut schema = String::from("message physiological {\n");
+    for (name, column) in PHYS_COLUMNS {
+        let field = match column {
+            Column::Timestamp(_) => format!("REQUIRED INT64 {} (TIMESTAMP(MILLIS,true));", name),
+            Column::Flag(_) => format!("REQUIRED BOOLEAN {};", name),
+            Column::Value(_) => format!("OPTIONAL DOUBLE {};", name),
+            Column::Label(_) => format!("OPTIONAL BYTE_ARRAY {} (UTF8);", name),
+        };
+        schema.push_str(&field);
+        schema.push('\n');
+    }
+    schema.push('}');
+    schema
+}
+
+fn create_file_writer(path: &str, message_type: &str) -> Result<SerializedFileWriter<File>> {
+    let schema = Arc::new(parse_message_type(message_type)?);
+    let props = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+    let file = File::create(path)?;
+    Ok(SerializedFileWriter::new(file, schema, props)?)
+}
+
+fn write_physiological_group(
+    writer: &mut SerializedFileWriter<File>,
+    rows: &[PhysiologicalData],
+) -> Result<()> {
+    let mut group = writer.next_row_group()?;
+
+    for (name, column) in PHYS_COLUMNS {
+        let mut col = group
+            .next_column()?
+            .ok_or_else(|| anyhow!("Parquet schema is missing column {}", name))?;
+
+        match column {
+            Column::Timestamp(get) => {
+                let values: Vec<i64> = rows.iter().map(|d| get(d).timestamp_millis()).collect();
+                col.typed::<Int64Type>().write_batch(&values, None, None)?;
+            }
+            Column::Flag(get) => {
+                let values: Vec<bool> = rows.iter().map(get).collect();
+                col.typed::<BoolType>().write_batch(&values, None, None)?;
+            }
+            Column::Value(get) => {
+                let (values, levels) = split_optional(rows.iter().map(get));
+                col.typed::<DoubleType>()
+                    .write_batch(&values, Some(&levels), None)?;
+            }
+            Column::Label(get) => {
+                let (values, levels) = split_optional(
+                    rows.iter()
+                        .map(|row| get(row).map(|s| ByteArray::from(s.into_bytes()))),
+                );
+                col.typed::<ByteArrayType>()
+                    .write_batch(&values, Some(&levels), None)?;
+            }
+        }
+
+        col.close()?;
+    }
+
+    group.close()?;
+    Ok(())
+}
+
+fn write_waveform_group(
+    writer: &mut SerializedFileWriter<File>,
+    rows: &[WaveformData],
+) -> Result<()> {
+    let mut group = writer.next_row_group()?;
+
+    let timestamps: Vec<i64> = rows
+        .iter()
+        .map(|w| w.timestamp.timestamp_millis())
+        .collect();
+    let types: Vec<ByteArray> = rows
+        .iter()
+        .map(|w| ByteArray::from(w.waveform_type.name()))
+        .collect();
+    let plug_ids: Vec<i32> = rows.iter().map(|w| i32::from(w.plug_id)).collect();
+    let rates: Vec<i32> = rows.iter().map(|w| w.nominal_rate as i32).collect();
+    let counts: Vec<i32> = rows.iter().map(|w| w.samples.len() as i32).collect();
+    let gaps: Vec<bool> = rows.iter().map(|w| w.status.gap).collect();
+    let pacers: Vec<bool> = rows.iter().map(|w| w.status.pacer_detected).collect();
+    let lead_offs: Vec<bool> = rows.iter().map(|w| w.status.lead_off).collect();
+
+    // Repeated samples: definition level 0 marks an empty list,
+    // repetition level 0 starts a new row
+    let mut samples = Vec::new();
+    let mut def_levels = Vec::new();
+    let mut rep_levels = Vec::new();
+    for row in rows {
+        if row.samples.is_empty() {
+            def_levels.push(0);
+            rep_levels.push(0);
+        }
+        for (i, &sample) in row.samples.iter().enumerate() {
+            samples.push(sample as i32);
+            def_levels.push(1);
+            rep_levels.push(if i == 0 { 0 } else { 1 });
+        }
+    }
+
+    let mut index = 0;
+    while let Some(mut col) = group.next_column()? {
+        match index {
+            0 => col
+                .typed::<Int64Type>()
+                .write_batch(&timestamps, None, None)?,
+            1 => col
+                .typed::<ByteArrayType>()
+                .write_batch(&types, None, None)?,
+            2 => col
+                .typed::<Int32Type>()
+                .write_batch(&plug_ids, None, None)?,
+            3 => col.typed::<Int32Type>().write_batch(&rates, None, None)?,
+            4 => col.typed::<Int32Type>().write_batch(&counts, None, None)?,
+            5 => col.typed::<BoolType>().write_batch(&gaps, None, None)?,
+            6 => col.typed::<BoolType>().write_batch(&pacers, None, None)?,
+            7 => col
+                .typed::<BoolType>()
+                .write_batch(&lead_offs, None, None)?,
+            _ => col.typed::<Int32Type>().write_batch(
+                &samples,
+                Some(&def_levels),
+                Some(&rep_levels),
+            )?,
+        };
+        col.close()?;
+        index += 1;
+    }
+
+    group.close()?;
+    Ok(())
+}
+
+/// Split optional values into present values and definition levels
+fn split_optional<T>(values: impl Iterator<Item = Option<T>>) -> (Vec<T>, Vec<i16>) {
+    let mut present = Vec::new();
+    let mut levels = Vec::new();
+    for value in values {
+        match value {
+            Some(v) => {
+                present.push(v);
+                levels.push(1);
+            }
+            None => levels.push(0),
+        }
+    }
+    (present, levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::WaveformType;
+    use crate::constants::dri_types::{PhdbClass, PhdbSubrecordType};
+    use crate::decode::waveforms::WaveformStatus;
+    use chrono::Utc;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+
+    #[test]
+    fn test_parquet_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("dri_parquet_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("capture.parquet");
+
+        let mut writer = ParquetWriter::new(&path).unwrap().with_row_group_size(2);
+        for hr in [60.0, 61.0, 62.0] {
+            let mut phys =
+                PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+            phys.ecg_hr = Some(hr);
+            phys.ecg_status.active = true;
+            writer.write_physiological(&phys).unwrap();
+        }
+        writer
+            .write_waveform(&WaveformData {
+                timestamp: Utc::now(),
+                waveform_type: WaveformType::Ecg1,
+                plug_id: 5,
+                samples: vec![1, -2, 3],
+                nominal_rate: 300,
+                effective_rate: None,
+                status: WaveformStatus::from_u16(0),
+                pacer_indices: Vec::new(),
+            })
+            .unwrap();
+        writer.finish().unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+        let hr_index = PHYS_COLUMNS
+            .iter()
+            .position(|(n, _)| *n == "ecg_hr")
+            .unwrap();
+        let spo2_index = PHYS_COLUMNS
+            .iter()
+            .position(|(n, _)| *n == "spo2_percent")
+            .unwrap();
+        let active_index = PHYS_COLUMNS
+            .iter()
+            .position(|(n, _)| *n == "ecg_active")
+            .unwrap();
+        let rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[2].get_double(hr_index).unwrap(), 62.0);
+        assert!(rows[0].get_bool(active_index).unwrap());
+        assert!(rows[0].get_double(spo2_index).is_err()); // null
+
+        let wave_path = dir.join("capture.waveforms.parquet");
+        let reader = SerializedFileReader::new(File::open(&wave_path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 1);
+        let row = reader.get_row_iter(None).unwrap().next().unwrap().unwrap();
+        assert_eq!(row.get_string(1).unwrap(), "ECG1");
+        assert_eq!(row.get_int(2).unwrap(), 5);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
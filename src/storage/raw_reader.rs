@@ -0,0 +1,322 @@
+//! Replay of raw captures written by [`RawWriter`](super::RawWriter)
+//!
+//! Both [`RawFormat`]s are read; the format is detected from the first
+//! bytes of the file. Plain captures store frame data without byte
+//! stuffing, so frames are split at the record length from their header
+//! rather than at the next `0x7E`. Frames too short for a header, or whose
+//! length does not end on a frame character, are split at the next `0x7E`.
+
+use super::output::open_input;
+use super::raw_writer::{RawFormat, TIMESTAMPED_MAGIC};
+use crate::DriError;
+use crate::constants::{FRAME_CHAR, HEADER_SIZE, MAX_RECORD_SIZE};
+use crate::protocol::{ChecksumMode, DriFrame, FrameParser};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Iterates over the frames of a raw capture
+///
+/// Each item is a frame or the framing error (e.g. checksum mismatch) that
+/// replaced it. Iteration stops at the end of the input, on an I/O error or
+/// on a corrupt timestamped record.
+pub struct RawReader<R: Read = Box<dyn Read + Send>> {
+    reader: BufReader<R>,
+    /// Bytes read past the end of a plain frame
+    lookahead: VecDeque<u8>,
+    parser: FrameParser,
+    format: RawFormat,
+    received_at: Option<DateTime<Utc>>,
+    done: bool,
+}
+
+impl RawReader {
+    /// Open a raw capture, decompressing it if the path ends in `.gz`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(open_input(path)?)?)
+    }
+}
+
+impl<R: Read> RawReader<R> {
+    /// Read frames from any byte source
+    pub fn new(reader: R) -> io::Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let format = if reader.fill_buf()?.starts_with(TIMESTAMPED_MAGIC) {
+            reader.consume(TIMESTAMPED_MAGIC.len());
+            RawFormat::Timestamped
+        } else {
+            RawFormat::Plain
+        };
+
+        Ok(Self {
+            reader,
+            lookahead: VecDeque::new(),
+            parser: FrameParser::new(),
+            format,
+            received_at: None,
+            done: false,
+        })
+    }
+
+    /// Set the checksum convention of the captured frames
+    pub fn with_checksum_mode(mut self, mode: ChecksumMode) -> Self {
+        self.parser = FrameParser::new().with_checksum_mode(mode);
+        self
+    }
+
+    /// Checksum convention in use, detected if set to `Auto`
+    pub fn checksum_mode(&self) -> ChecksumMode {
+        self.parser.checksum_mode()
+    }
+
+    /// Layout detected for this capture
+    pub fn format(&self) -> RawFormat {
+        self.format
+    }
+
+    /// Host receive time of the last frame returned
+    ///
+    /// Only timestamped captures record it.
+    pub fn received_at(&self) -> Option<DateTime<Utc>> {
+        self.received_at
+    }
+
+    fn next_plain(&mut self) -> Option<Result<DriFrame, DriError>> {
+        self.read_plain().unwrap_or_else(|e| Some(Err(e.into())))
+    }
+
+    fn read_plain(&mut self) -> io::Result<Option<Result<DriFrame, DriError>>> {
+        let mut body = 'frame: loop {
+            // The start character; the first data byte may be 0x7E too
+            loop {
+                match self.read_byte()? {
+                    None => return Ok(None),
+                    Some(FRAME_CHAR) => break,
+                    Some(_) => {}
+                }
+            }
+            let Some(first) = self.read_byte()? else {
+                return Ok(None);
+            };
+
+            // Record length, then the rest of the record, its checksum and
+            // the end character
+            let mut body = vec![first];
+            self.read_up_to(&mut body, 2)?;
+            if body.len() == 2 {
+                let r_len = usize::from(u16::from_le_bytes([body[0], body[1]]));
+                if (HEADER_SIZE..=MAX_RECORD_SIZE).contains(&r_len) {
+                    self.read_up_to(&mut body, r_len + 2)?;
+                    if body.get(r_len + 1) == Some(&FRAME_CHAR) {
+                        let frame = DriFrame::new(body[..r_len].to_vec(), body[r_len]);
+                        if self.parser.validate(&frame) {
+                            return Ok(Some(Ok(frame)));
+                        }
+                    }
+                }
+            }
+
+            // Otherwise the frame ends at the next frame character
+            match body.iter().position(|&byte| byte == FRAME_CHAR) {
+                // Empty frame: that character starts the next one
+                Some(0) => self.unread(&body),
+                Some(end) => {
+                    self.unread(&body[end + 1..]);
+                    body.truncate(end);
+                    break 'frame body;
+                }
+                None => loop {
+                    match self.read_byte()? {
+                        None => return Ok(None),
+                        Some(FRAME_CHAR) => break 'frame body,
+                        Some(byte) => body.push(byte),
+                    }
+                },
+            }
+        };
+
+        if body.len() < 2 {
+            return Ok(Some(Err(DriError::IncompleteFrame)));
+        }
+        let checksum = body.pop().unwrap();
+        let frame = DriFrame::new(body, checksum);
+        if self.parser.validate(&frame) {
+            Ok(Some(Ok(frame)))
+        } else {
+            Ok(Some(Err(DriError::ChecksumError)))
+        }
+    }
+
+    /// Return bytes to be read again before the rest of the input
+    fn unread(&mut self, bytes: &[u8]) {
+        for &byte in bytes.iter().rev() {
+            self.lookahead.push_front(byte);
+        }
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        if let Some(byte) = self.lookahead.pop_front() {
+            return Ok(Some(byte));
+        }
+        let mut byte = [0u8; 1];
+        match read_full(&mut self.reader, &mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+
+    /// Append bytes to `buf` until it holds `len` or the input ends
+    fn read_up_to(&mut self, buf: &mut Vec<u8>, len: usize) -> io::Result<()> {
+        while buf.len() < len {
+            match self.read_byte()? {
+                Some(byte) => buf.push(byte),
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn next_timestamped(&mut self) -> Option<Result<DriFrame, DriError>> {
+        let mut header = [0u8; 12];
+        match read_full(&mut self.reader, &mut header) {
+            Ok(0) => return None,
+            Ok(12) => {}
+            Ok(_) => return Some(Err(DriError::IncompleteFrame)),
+            Err(e) => return Some(Err(e.into())),
+        }
+
+        let micros = i64::from_le_bytes(header[0..8].try_into().unwrap());
+        let len = u32::from_le_bytes(header[8..12].try_into().unwrap()) as usize;
+        if len == 0 || len > MAX_RECORD_SIZE + 1 {
+            // Record boundaries are lost from here on
+            self.done = true;
+            return Some(Err(DriError::FramingError(len)));
+        }
+
+        let mut payload = vec![0u8; len];
+        match read_full(&mut self.reader, &mut payload) {
+            Ok(n) if n == len => {}
+            Ok(_) => return Some(Err(DriError::IncompleteFrame)),
+            Err(e) => return Some(Err(e.into())),
+        }
+
+        self.received_at = DateTime::from_timestamp_micros(micros);
+        let checksum = payload.pop().unwrap();
+        let frame = DriFrame::new(payload, checksum);
+        if self.parser.validate(&frame) {
+            Some(Ok(frame))
+        } else {
+            Some(Err(DriError::ChecksumError))
+        }
+    }
+}
+
+/// Read until `buf` is full or the input ends, returning the bytes read
+pub(super) fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+impl<R: Read> Iterator for RawReader<R> {
+    type Item = Result<DriFrame, DriError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let item = match self.format {
+            RawFormat::Plain => self.next_plain(),
+            RawFormat::Timestamped => self.next_timestamped(),
+        };
+        if matches!(item, None | Some(Err(DriError::IoError(_)))) {
+            self.done = true;
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::checksum::calculate_checksum;
+    use crate::storage::RawWriter;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_checksum_errors_are_reported_in_place() {
+        let data = vec![0x01, 0x02, 0x03];
+        let checksum = calculate_checksum(&data);
+        let mut raw = Vec::new();
+        for bad in [false, true, false] {
+            raw.push(0x7E);
+            raw.extend(&data);
+            raw.push(if bad { checksum ^ 0x01 } else { checksum });
+            raw.push(0x7E);
+        }
+
+        let items: Vec<_> = RawReader::new(Cursor::new(raw)).unwrap().collect();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_ref().unwrap().data, data);
+        assert!(matches!(items[1], Err(DriError::ChecksumError)));
+        assert!(items[2].is_ok());
+    }
+
+    #[test]
+    fn test_plain_frames_are_split_by_record_length() {
+        // A record holding both frame characters, between two short frames;
+        // its length, 126, is 0x7E as well
+        let mut payload = [0u8; 86];
+        payload[..4].copy_from_slice(&[0x01, 0x7E, 0x7D, 0x02]);
+        let mut record =
+            crate::decode::test_frame(crate::constants::DriMainType::Network, &[(0, &payload)]);
+        record[10] = 0x7E;
+        let short = vec![0x01, 0x02, 0x03];
+        let path = std::env::temp_dir().join(format!("dri_raw_plain_{}.raw", std::process::id()));
+        {
+            let mut writer = RawWriter::new(&path).unwrap();
+            for data in [&short, &record, &short] {
+                let frame = DriFrame::new(data.clone(), calculate_checksum(data));
+                writer.write_frame(&frame).unwrap();
+            }
+        }
+
+        let reader = RawReader::open(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let frames: Vec<_> = reader.map(|item| item.unwrap().data).collect();
+        assert_eq!(frames, [short.clone(), record, short]);
+    }
+
+    #[test]
+    fn test_timestamped_frames_survive_frame_characters() {
+        let path = std::env::temp_dir().join(format!("dri_raw_ts_{}.raw", std::process::id()));
+        // 0x7E inside the data would split a plain capture
+        let data = vec![0x01, 0x7E, 0x7D, 0x02];
+        let frame = DriFrame::new(data.clone(), calculate_checksum(&data));
+        {
+            let mut writer = RawWriter::new(&path)
+                .unwrap()
+                .with_format(RawFormat::Timestamped);
+            writer.write_frame(&frame).unwrap();
+            writer.write_frame(&frame).unwrap();
+        }
+
+        let mut reader = RawReader::open(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(reader.format(), RawFormat::Timestamped);
+        assert_eq!(reader.next().unwrap().unwrap().data, data);
+        let first = reader.received_at().unwrap();
+        assert_eq!(reader.next().unwrap().unwrap().data, data);
+        assert!(reader.received_at().unwrap() >= first);
+        assert!(reader.next().is_none());
+    }
+}
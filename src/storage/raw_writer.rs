@@ -1,36 +1,193 @@
 //! Raw binary writer for DRI frames
+//!
+//! Output is gzip-compressed when the path ends in `.gz`, and can be split
+//! into segments with a [`RotationPolicy`].
+//!
+//! With [`RawFormat::Timestamped`] a file starts with [`TIMESTAMPED_MAGIC`]
+//! and each frame is stored as the host receive time (i64 LE, microseconds
+//! since the Unix epoch), the payload length (u32 LE) and the payload
+//! (frame data followed by its checksum).
 
+use super::OutputFile;
+use super::rotation::{RotationPolicy, segment_path};
 use crate::protocol::DriFrame;
 use anyhow::Result;
-use std::fs::File;
+use chrono::{DateTime, Utc};
 use std::io::Write;
 use std::path::Path;
 
+/// First bytes of a file in the timestamped format
+pub const TIMESTAMPED_MAGIC: &[u8; 8] = b"DRIRAWT1";
+
+/// Layout of the frames in a raw file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RawFormat {
+    /// `0x7E`, frame data, checksum, `0x7E`; frames are found again from
+    /// the record length in their header, as the data is not byte-stuffed
+    #[default]
+    Plain,
+    /// Length-prefixed records with the host receive time
+    Timestamped,
+}
+
 pub struct RawWriter {
-    file: File,
+    file: OutputFile,
+    path: String,
+    append: bool,
+    format: RawFormat,
+    rotation: Option<RotationPolicy>,
+    segment: u32,
+    segment_empty: bool,
+    opened_at: DateTime<Utc>,
 }
 
 impl RawWriter {
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let file = File::create(path)?;
-        Ok(Self { file })
+        Self::open(path, false)
+    }
+
+    /// Append frames to an existing raw file, creating it if needed
+    pub fn append<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open(path, true)
+    }
+
+    fn open<P: AsRef<Path>>(path: P, append: bool) -> Result<Self> {
+        let path = path.as_ref().to_string_lossy().to_string();
+        Ok(Self {
+            file: open_segment(&path, append)?,
+            segment_empty: is_empty(&path),
+            path,
+            append,
+            format: RawFormat::default(),
+            rotation: None,
+            segment: 0,
+            opened_at: Utc::now(),
+        })
+    }
+
+    /// Choose the frame layout
+    ///
+    /// When appending, use the format of the existing file.
+    pub fn with_format(mut self, format: RawFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Roll over to a new segment file according to the policy
+    pub fn with_rotation(mut self, rotation: RotationPolicy) -> Self {
+        self.rotation = Some(rotation);
+        self
     }
 
     /// Write a complete DRI frame to the raw file
     pub fn write_frame(&mut self, frame: &DriFrame) -> Result<()> {
-        // Write frame start character
-        self.file.write_all(&[0x7E])?;
+        self.rotate_if_due()?;
 
-        // Write the frame data
-        self.file.write_all(&frame.data)?;
+        match self.format {
+            RawFormat::Plain => {
+                // Write frame start character
+                self.file.write_all(&[0x7E])?;
 
-        // Write the checksum
-        self.file.write_all(&[frame.checksum])?;
+                // Write the frame data
+                self.file.write_all(&frame.data)?;
 
-        // Write frame end character
-        self.file.write_all(&[0x7E])?;
+                // Write the checksum
+                self.file.write_all(&[frame.checksum])?;
+
+                // Write frame end character
+                self.file.write_all(&[0x7E])?;
+            }
+            RawFormat::Timestamped => {
+                if self.segment_empty {
+                    self.file.write_all(TIMESTAMPED_MAGIC)?;
+                }
+                let received_at = Utc::now().timestamp_micros();
+                self.file.write_all(&received_at.to_le_bytes())?;
+                self.file
+                    .write_all(&(frame.data.len() as u32 + 1).to_le_bytes())?;
+                self.file.write_all(&frame.data)?;
+                self.file.write_all(&[frame.checksum])?;
+            }
+        }
+        self.segment_empty = false;
 
         self.file.flush()?;
         Ok(())
     }
+
+    /// Flush the file and, for gzip, write the trailer
+    pub fn finish(mut self) -> Result<()> {
+        self.file.finish()?;
+        Ok(())
+    }
+
+    fn rotate_if_due(&mut self) -> Result<()> {
+        let Some(rotation) = &self.rotation else {
+            return Ok(());
+        };
+
+        let size = std::fs::metadata(segment_path(&self.path, self.segment))?.len();
+        if rotation.should_rotate(size, self.opened_at, Utc::now()) {
+            self.file.finish()?;
+            self.segment += 1;
+            let path = segment_path(&self.path, self.segment);
+            self.file = open_segment(&path, self.append)?;
+            self.segment_empty = is_empty(&path);
+            self.opened_at = Utc::now();
+        }
+        Ok(())
+    }
+}
+
+/// Check whether nothing has been written to `path` yet
+fn is_empty(path: &str) -> bool {
+    std::fs::metadata(path).map_or(true, |meta| meta.len() == 0)
+}
+
+fn open_segment(path: &str, append: bool) -> std::io::Result<OutputFile> {
+    if append {
+        OutputFile::append(path)
+    } else {
+        OutputFile::create(path)
+    }
+}
+
+impl Drop for RawWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.file.finish() {
+            log::error!("Failed to finish raw file: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_gzip_raw_roundtrip() {
+        let path = std::env::temp_dir().join(format!("dri_raw_{}.raw.gz", std::process::id()));
+
+        {
+            let mut writer = RawWriter::new(&path).unwrap();
+            for i in 0..3u8 {
+                let frame = DriFrame {
+                    data: vec![i, 0x01, 0x02],
+                    checksum: i.wrapping_add(3),
+                };
+                writer.write_frame(&frame).unwrap();
+            }
+        }
+
+        let mut contents = Vec::new();
+        GzDecoder::new(std::fs::File::open(&path).unwrap())
+            .read_to_end(&mut contents)
+            .unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(contents.len(), 3 * 6);
+        assert_eq!(&contents[6..12], &[0x7E, 0x01, 0x01, 0x02, 0x04, 0x7E]);
+    }
 }
@@ -0,0 +1,97 @@
+//! Output file rotation for long captures
+//!
+//! A rotated output is split into segments: the first keeps the configured
+//! path, later ones get an incrementing suffix before the extension
+//! (`capture.csv`, `capture.1.csv`, `capture.2.csv`, ...).
+
+use chrono::{DateTime, Utc};
+
+/// When to roll an output file over to a new segment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RotationPolicy {
+    /// Roll over once the file on disk reaches this size
+    pub max_bytes: Option<u64>,
+    /// Roll over when the wall clock crosses an hour boundary
+    pub hourly: bool,
+}
+
+impl RotationPolicy {
+    /// Create a policy that never rotates
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the size threshold in bytes
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Enable rotation on hour boundaries
+    pub fn hourly(mut self, hourly: bool) -> Self {
+        self.hourly = hourly;
+        self
+    }
+
+    /// Check whether a segment of `size` bytes opened at `opened_at` is due
+    pub fn should_rotate(&self, size: u64, opened_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        let too_big = self.max_bytes.is_some_and(|max| size >= max);
+        let new_hour = self.hourly && now.timestamp() / 3600 != opened_at.timestamp() / 3600;
+        size > 0 && (too_big || new_hour)
+    }
+}
+
+/// Path of the given segment of a rotated output
+///
+/// The suffix goes before the last extension, ignoring a trailing `.gz`.
+pub fn segment_path(path: &str, segment: u32) -> String {
+    if segment == 0 {
+        return path.to_string();
+    }
+
+    let (stem, gz) = match path.strip_suffix(".gz") {
+        Some(stem) => (stem, ".gz"),
+        None => (path, ""),
+    };
+    let name_start = stem.rfind(['/', '\\']).map_or(0, |i| i + 1);
+    match stem[name_start..].rfind('.') {
+        Some(dot) if dot > 0 => {
+            let dot = name_start + dot;
+            format!("{}.{}{}{}", &stem[..dot], segment, &stem[dot..], gz)
+        }
+        _ => format!("{}.{}{}", stem, segment, gz),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_segment_path() {
+        assert_eq!(segment_path("capture.csv", 0), "capture.csv");
+        assert_eq!(segment_path("capture.csv", 2), "capture.2.csv");
+        assert_eq!(segment_path("capture.csv.gz", 1), "capture.1.csv.gz");
+        assert_eq!(
+            segment_path("out/capture.waveforms.csv", 3),
+            "out/capture.waveforms.3.csv"
+        );
+        assert_eq!(segment_path("out.d/capture", 1), "out.d/capture.1");
+    }
+
+    #[test]
+    fn test_should_rotate() {
+        let opened = Utc.with_ymd_and_hms(2024, 5, 1, 12, 59, 0).unwrap();
+        let same_hour = Utc.with_ymd_and_hms(2024, 5, 1, 12, 59, 59).unwrap();
+        let next_hour = Utc.with_ymd_and_hms(2024, 5, 1, 13, 0, 0).unwrap();
+
+        let policy = RotationPolicy::new().max_bytes(100).hourly(true);
+        assert!(!policy.should_rotate(99, opened, same_hour));
+        assert!(policy.should_rotate(100, opened, same_hour));
+        assert!(policy.should_rotate(1, opened, next_hour));
+        // Empty segments are never rotated
+        assert!(!policy.should_rotate(0, opened, next_hour));
+        assert!(!RotationPolicy::new().should_rotate(1 << 40, opened, next_hour));
+    }
+}
@@ -0,0 +1,149 @@
+//! Common interface of record destinations
+//!
+//! Every writer in this module implements [`RecordSink`], so a session can
+//! hold any mix of them, including sinks defined outside this crate, and
+//! feed them through a [`MultiSink`].
+
+use crate::decode::DriRecord;
+use crate::decode::events::EventData;
+use crate::decode::physiological::PhysiologicalData;
+use crate::decode::waveforms::WaveformData;
+use anyhow::Result;
+
+/// Destination for decoded records
+pub trait RecordSink {
+    /// Write physiological data
+    fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()>;
+
+    /// Write waveform data
+    fn write_waveform(&mut self, data: &WaveformData) -> Result<()>;
+
+    /// Write an anesthesia record keeping event; ignored unless overridden
+    fn write_event(&mut self, _event: &EventData) -> Result<()> {
+        Ok(())
+    }
+
+    /// Push buffered data to the underlying file or connection
+    fn flush(&mut self) -> Result<()>;
+
+    /// Keep a connection open while no records arrive; does nothing unless
+    /// overridden
+    ///
+    /// Capture loops call it after every read, including reads that timed out.
+    fn keep_alive(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Flush and close the sink; dropping it also closes it, but hides errors
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.flush()
+    }
+
+    /// Write every part of a decoded record (aux and network records are skipped)
+    fn write_record(&mut self, record: &DriRecord) -> Result<()> {
+        match record {
+            DriRecord::Physiological(phys) => self.write_physiological(phys),
+            DriRecord::Waveform { waveforms } => {
+                waveforms.iter().try_for_each(|wf| self.write_waveform(wf))
+            }
+            DriRecord::Aux(_) => Ok(()),
+            DriRecord::Event(event) => self.write_event(event),
+            DriRecord::Network(_) => Ok(()),
+        }
+    }
+}
+
+/// Sends every record to several sinks, in the order they were added
+#[derive(Default)]
+pub struct MultiSink {
+    sinks: Vec<Box<dyn RecordSink>>,
+}
+
+impl MultiSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sink
+    pub fn with_sink<S: RecordSink + 'static>(mut self, sink: S) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Add an already boxed sink
+    pub fn push(&mut self, sink: Box<dyn RecordSink>) {
+        self.sinks.push(sink);
+    }
+
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+}
+
+impl RecordSink for MultiSink {
+    fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        self.sinks
+            .iter_mut()
+            .try_for_each(|sink| sink.write_physiological(data))
+    }
+
+    fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
+        self.sinks
+            .iter_mut()
+            .try_for_each(|sink| sink.write_waveform(data))
+    }
+
+    fn write_event(&mut self, event: &EventData) -> Result<()> {
+        self.sinks
+            .iter_mut()
+            .try_for_each(|sink| sink.write_event(event))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.sinks.iter_mut().try_for_each(|sink| sink.flush())
+    }
+
+    fn keep_alive(&mut self) -> Result<()> {
+        self.sinks.iter_mut().try_for_each(|sink| sink.keep_alive())
+    }
+
+    /// Finish every sink, even after a failure, and report the first error
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.sinks
+            .into_iter()
+            .map(|sink| sink.finish())
+            .fold(Ok(()), Result::and)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::dri_types::{PhdbClass, PhdbSubrecordType};
+    use crate::storage::output::SharedBuffer;
+    use crate::storage::{JsonFormat, JsonWriter};
+    use chrono::Utc;
+
+    #[test]
+    fn test_multi_sink_fans_out() {
+        let first = SharedBuffer::default();
+        let second = SharedBuffer::default();
+        let sink = MultiSink::new()
+            .with_sink(JsonWriter::from_writer(first.clone(), JsonFormat::Lines))
+            .with_sink(JsonWriter::from_writer(second.clone(), JsonFormat::Array));
+        assert_eq!(sink.len(), 2);
+
+        let mut sink: Box<dyn RecordSink> = Box::new(sink);
+        let phys = PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+        sink.write_record(&DriRecord::Physiological(phys)).unwrap();
+        sink.finish().unwrap();
+
+        assert_eq!(first.contents().lines().count(), 1);
+        let array: Vec<serde_json::Value> = serde_json::from_str(&second.contents()).unwrap();
+        assert_eq!(array.len(), 1);
+    }
+}
@@ -0,0 +1,270 @@
+//! SQLite database writer for DRI data
+//!
+//! Creates two tables:
+//! - `physiological`: one row per record, same columns as the CSV output
+//! - `waveform_samples`: one row per sample (`timestamp`, `waveform_type`,
+//!   `sample_index`, `value`)
+//!
+//! Timestamps are stored as UTC milliseconds since the Unix epoch and both
+//! tables are indexed on them. Inserts go through prepared statements inside
+//! a transaction that is committed every `batch_size` records (see
+//! [`DEFAULT_BATCH_SIZE`]); call [`SqliteWriter::finish`] or drop the writer
+//! to commit the last batch.
+
+use super::columns::{Column, PHYS_COLUMNS};
+use super::sink::RecordSink;
+use crate::decode::physiological::PhysiologicalData;
+use crate::decode::waveforms::WaveformData;
+use anyhow::Result;
+use rusqlite::types::Value;
+use rusqlite::{Connection, params, params_from_iter};
+use std::path::Path;
+
+/// Default number of records written per transaction
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+const INSERT_SAMPLE: &str = "INSERT INTO waveform_samples \
+    (timestamp, waveform_type, plug_id, sample_index, value) VALUES (?1, ?2, ?3, ?4, ?5)";
+
+pub struct SqliteWriter {
+    conn: Connection,
+    insert_physiological: String,
+    batch_size: usize,
+    pending: usize,
+}
+
+impl SqliteWriter {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        Self::with_connection(conn)
+    }
+
+    /// Use an already opened connection (e.g. an in-memory database)
+    pub fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(&create_tables_sql())?;
+
+        let placeholders = (1..=PHYS_COLUMNS.len())
+            .map(|i| format!("?{}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let insert_physiological = format!(
+            "INSERT INTO physiological ({}) VALUES ({})",
+            column_names().join(", "),
+            placeholders
+        );
+
+        Ok(Self {
+            conn,
+            insert_physiological,
+            batch_size: DEFAULT_BATCH_SIZE,
+            pending: 0,
+        })
+    }
+
+    /// Set how many records are written per transaction
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Write physiological data
+    pub fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        self.begin()?;
+
+        let values = PHYS_COLUMNS.iter().map(|(_, column)| match column {
+            Column::Timestamp(get) => Value::Integer(get(data).timestamp_millis()),
+            Column::Flag(get) => Value::Integer(get(data) as i64),
+            Column::Value(get) => get(data).map_or(Value::Null, Value::Real),
+            Column::Label(get) => get(data).map_or(Value::Null, Value::Text),
+        });
+
+        self.conn
+            .prepare_cached(&self.insert_physiological)?
+            .execute(params_from_iter(values))?;
+
+        self.record_written()
+    }
+
+    /// Write waveform data
+    pub fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
+        self.begin()?;
+
+        let timestamp = data.timestamp.timestamp_millis();
+        let waveform_type = data.waveform_type.name();
+        let mut stmt = self.conn.prepare_cached(INSERT_SAMPLE)?;
+        for (index, sample) in data.samples.iter().enumerate() {
+            stmt.execute(params![
+                timestamp,
+                waveform_type,
+                data.plug_id,
+                index as i64,
+                *sample
+            ])?;
+        }
+        drop(stmt);
+
+        self.record_written()
+    }
+
+    /// Commit the current batch
+    pub fn flush(&mut self) -> Result<()> {
+        if !self.conn.is_autocommit() {
+            self.conn.execute_batch("COMMIT")?;
+        }
+        self.pending = 0;
+        Ok(())
+    }
+
+    /// Commit the last batch and close the database
+    pub fn finish(mut self) -> Result<()> {
+        self.flush()
+    }
+
+    fn begin(&mut self) -> Result<()> {
+        if self.conn.is_autocommit() {
+            self.conn.execute_batch("BEGIN")?;
+        }
+        Ok(())
+    }
+
+    fn record_written(&mut self) -> Result<()> {
+        self.pending += 1;
+        if self.pending >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl RecordSink for SqliteWriter {
+    fn write_physiological(&mut self, data: &PhysiologicalData) -> Result<()> {
+        SqliteWriter::write_physiological(self, data)
+    }
+
+    fn write_waveform(&mut self, data: &WaveformData) -> Result<()> {
+        SqliteWriter::write_waveform(self, data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        SqliteWriter::flush(self)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        SqliteWriter::finish(*self)
+    }
+}
+
+impl Drop for SqliteWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            log::error!("Failed to commit SQLite batch: {}", e);
+        }
+    }
+}
+
+fn column_names() -> Vec<&'static str> {
+    PHYS_COLUMNS.iter().map(|(name, _)| *name).collect()
+}
+
+fn create_tables_sql() -> String {
+    let columns = PHYS_COLUMNS
+        .iter()
+        .map(|(name, column)| {
+            let sql_type = match column {
+                Column::Timestamp(_) => "INTEGER NOT NULL",
+                Column::Flag(_) => "INTEGER NOT NULL",
+                Column::Value(_) => "REAL",
+                Column::Label(_) => "TEXT",
+            };
+            format!("{} {}", name, sql_type)
+        })
+        .collect::<Vec<_>>()
+        .join(",\n    ");
+
+    format!(
+        "CREATE TABLE IF NOT EXISTS physiological (\n    {});
+        CREATE INDEX IF NOT EXISTS idx_physiological_timestamp ON physiological (timestamp);
+        CREATE TABLE IF NOT EXISTS waveform_samples (
+            timestamp INTEGER NOT NULL,
+            waveform_type TEXT NOT NULL,
+            plug_id INTEGER NOT NULL,
+            sample_index INTEGER NOT NULL,
+            value INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_waveform_samples_timestamp ON waveform_samples (timestamp);",
+        columns
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::WaveformType;
+    use crate::constants::dri_types::{PhdbClass, PhdbSubrecordType};
+    use crate::decode::waveforms::WaveformStatus;
+    use chrono::Utc;
+
+    #[test]
+    fn test_sqlite_roundtrip() {
+        let path = std::env::temp_dir().join(format!("dri_sqlite_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut writer = SqliteWriter::new(&path).unwrap().with_batch_size(2);
+        for hr in [60.0, 61.0, 62.0] {
+            let mut phys =
+                PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+            phys.ecg_hr = Some(hr);
+            phys.ecg_status.active = true;
+            writer.write_physiological(&phys).unwrap();
+        }
+        writer
+            .write_waveform(&WaveformData {
+                timestamp: Utc::now(),
+                waveform_type: WaveformType::Ecg1,
+                plug_id: 5,
+                samples: vec![1, -2, 3],
+                nominal_rate: 300,
+                effective_rate: None,
+                status: WaveformStatus::from_u16(0),
+                pacer_indices: Vec::new(),
+            })
+            .unwrap();
+        writer.finish().unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let hrs: Vec<f64> = conn
+            .prepare("SELECT ecg_hr FROM physiological ORDER BY ecg_hr")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(hrs, vec![60.0, 61.0, 62.0]);
+
+        let (active, spo2, class): (bool, Option<f64>, String) = conn
+            .query_row(
+                "SELECT ecg_active, spo2_percent, class FROM physiological LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert!(active);
+        assert_eq!(spo2, None);
+        assert_eq!(class, "Basic");
+
+        let samples: Vec<(i64, i64, i64)> = conn
+            .prepare(
+                "SELECT plug_id, sample_index, value FROM waveform_samples \
+                 WHERE waveform_type = 'ECG1' ORDER BY sample_index",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(samples, vec![(5, 0, 1), (5, 1, -2), (5, 2, 3)]);
+
+        drop(conn);
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,236 @@
+//! Summary statistics over a capture session
+
+use super::columns::{Column, PHYS_COLUMNS};
+use crate::constants::WaveformType;
+use crate::decode::physiological::{PhysiologicalData, is_measured};
+use crate::decode::waveforms::WaveformData;
+use serde::Serialize;
+use std::fmt;
+
+/// Running statistics of one parameter
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    last: f64,
+}
+
+impl Accumulator {
+    fn new(value: f64) -> Self {
+        Self {
+            count: 1,
+            sum: value,
+            min: value,
+            max: value,
+            last: value,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.last = value;
+    }
+}
+
+/// Accumulates per-parameter statistics over a session
+///
+/// Parameters are the numeric physiological columns (see the CSV header);
+/// missing values are ignored. Waveforms are counted per type.
+#[derive(Debug, Clone)]
+pub struct SessionStats {
+    records: u64,
+    parameters: Vec<Option<Accumulator>>,
+    waveforms: Vec<WaveformSummary>,
+}
+
+impl Default for SessionStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionStats {
+    /// Create an empty accumulator
+    pub fn new() -> Self {
+        Self {
+            records: 0,
+            parameters: vec![None; PHYS_COLUMNS.len()],
+            waveforms: Vec::new(),
+        }
+    }
+
+    /// Add one physiological record
+    pub fn update(&mut self, data: &PhysiologicalData) {
+        self.records += 1;
+        for ((_, column), acc) in PHYS_COLUMNS.iter().zip(&mut self.parameters) {
+            let Column::Value(get) = column else {
+                continue;
+            };
+            if let Some(value) = get(data).filter(|&v| is_measured(v)) {
+                match acc {
+                    Some(acc) => acc.add(value),
+                    None => *acc = Some(Accumulator::new(value)),
+                }
+            }
+        }
+    }
+
+    /// Add one waveform chunk
+    pub fn update_waveform(&mut self, data: &WaveformData) {
+        let summary = match self
+            .waveforms
+            .iter_mut()
+            .position(|w| w.waveform_type == data.waveform_type)
+        {
+            Some(i) => &mut self.waveforms[i],
+            None => {
+                self.waveforms.push(WaveformSummary {
+                    waveform_type: data.waveform_type,
+                    samples: 0,
+                    gaps: 0,
+                });
+                self.waveforms.last_mut().unwrap()
+            }
+        };
+        summary.samples += data.samples.len() as u64;
+        if data.status.gap {
+            summary.gaps += 1;
+        }
+    }
+
+    /// Summarize everything seen so far
+    pub fn report(&self) -> SessionReport {
+        let parameters = PHYS_COLUMNS
+            .iter()
+            .zip(&self.parameters)
+            .filter_map(|((name, _), acc)| {
+                acc.map(|acc| ParameterSummary {
+                    name,
+                    count: acc.count,
+                    min: acc.min,
+                    max: acc.max,
+                    mean: acc.sum / acc.count as f64,
+                    last: acc.last,
+                })
+            })
+            .collect();
+
+        SessionReport {
+            records: self.records,
+            parameters,
+            waveforms: self.waveforms.clone(),
+        }
+    }
+}
+
+/// Statistics of one physiological parameter
+#[derive(Debug, Clone, Serialize)]
+pub struct ParameterSummary {
+    /// Column name, as in the CSV header
+    pub name: &'static str,
+    /// Number of valid values
+    pub count: u64,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub last: f64,
+}
+
+/// Sample and gap counts of one waveform channel
+#[derive(Debug, Clone, Serialize)]
+pub struct WaveformSummary {
+    pub waveform_type: WaveformType,
+    /// Samples received
+    pub samples: u64,
+    /// Chunks flagged with a sampling gap
+    pub gaps: u64,
+}
+
+/// Session summary produced by [`SessionStats::report`]
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionReport {
+    /// Physiological records seen
+    pub records: u64,
+    /// Parameters with at least one valid value, in column order
+    pub parameters: Vec<ParameterSummary>,
+    /// Waveform channels, in order of first appearance
+    pub waveforms: Vec<WaveformSummary>,
+}
+
+impl fmt::Display for SessionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Physiological records: {}", self.records)?;
+        if !self.parameters.is_empty() {
+            writeln!(
+                f,
+                "{:<30} {:>8} {:>10} {:>10} {:>10} {:>10}",
+                "parameter", "count", "min", "max", "mean", "last"
+            )?;
+        }
+        for p in &self.parameters {
+            writeln!(
+                f,
+                "{:<30} {:>8} {:>10.2} {:>10.2} {:>10.2} {:>10.2}",
+                p.name, p.count, p.min, p.max, p.mean, p.last
+            )?;
+        }
+        for w in &self.waveforms {
+            writeln!(
+                f,
+                "{:<30} {:>8} samples, {} gaps",
+                w.waveform_type.name(),
+                w.samples,
+                w.gaps
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::dri_types::{PhdbClass, PhdbSubrecordType};
+    use crate::constants::special_values::DATA_INVALID;
+    use chrono::Utc;
+
+    #[test]
+    fn test_stats_ignore_missing_values() {
+        let mut stats = SessionStats::new();
+        for hr in [Some(60.0), None, Some(90.0), Some(75.0)] {
+            let mut data =
+                PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+            data.ecg_hr = hr;
+            stats.update(&data);
+        }
+
+        let report = stats.report();
+        assert_eq!(report.records, 4);
+        assert_eq!(report.parameters.len(), 1);
+        let hr = &report.parameters[0];
+        assert_eq!(hr.name, "ecg_hr");
+        assert_eq!(hr.count, 3);
+        assert_eq!((hr.min, hr.max, hr.mean, hr.last), (60.0, 90.0, 75.0, 75.0));
+    }
+
+    #[test]
+    fn test_stats_skip_invalid_markers() {
+        let mut stats = SessionStats::new();
+        // As kept by InvalidPolicy::NaN and InvalidPolicy::Sentinel
+        for hr in [72.0, f64::NAN, f64::from(DATA_INVALID)] {
+            let mut data =
+                PhysiologicalData::empty(Utc::now(), PhdbClass::Basic, PhdbSubrecordType::Displ);
+            data.ecg_hr = Some(hr);
+            stats.update(&data);
+        }
+
+        let hr = &stats.report().parameters[0];
+        assert_eq!(hr.count, 1);
+        assert_eq!((hr.min, hr.max, hr.last), (72.0, 72.0, 72.0));
+    }
+}
@@ -0,0 +1,201 @@
+//! Decode frames produced by the simulator
+
+#![cfg(feature = "sim")]
+
+use chrono::{DateTime, Utc};
+use ge_dri_prototype::constants::WaveformType;
+use ge_dri_prototype::decode::waveforms::WaveformStatus;
+use ge_dri_prototype::decode::{CapnogramAnalyzer, DriRecord, decode_record};
+use ge_dri_prototype::protocol::RequestBuilder;
+use ge_dri_prototype::sim::{
+    Request, SimVitals, WaveformStream, create_phdb_frame, create_phdb_frame_at, parse_request,
+};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::time::Duration;
+
+fn assert_close(value: Option<f64>, expected: f64) {
+    let value = value.expect("value missing");
+    assert!((value - expected).abs() < 1e-9, "{} != {}", value, expected);
+}
+
+#[test]
+fn phdb_frame_decodes_to_seeded_vitals() {
+    let vitals = SimVitals {
+        hr: 72.0,
+        spo2: 97.5,
+        nibp_sys: 118.0,
+        nibp_dia: 76.0,
+        temp: 36.8,
+        etco2: 5.1,
+        rr: 14.0,
+        peep: 6.0,
+        ppeak: 22.5,
+        tv: 480.0,
+        asystole: false,
+        apnea: true,
+    };
+
+    let frame = create_phdb_frame(1, &vitals);
+    let Some(DriRecord::Physiological(phys)) = decode_record(&frame).unwrap() else {
+        panic!("expected a physiological record");
+    };
+
+    assert!(phys.ecg_status.active);
+    assert!(!phys.ecg_status.asystole);
+    assert!(phys.co2_status.apnea_co2);
+    assert_close(phys.ecg_hr, 72.0);
+    assert_close(phys.ecg_rr, 14.0);
+    assert_close(phys.spo2, 97.5);
+    assert_close(phys.spo2_pr, 72.0);
+    assert_close(phys.nibp_sys, 118.0);
+    assert_close(phys.nibp_dia, 76.0);
+    assert_close(phys.nibp_mean, 90.0);
+    assert_close(phys.nibp_hr, 72.0);
+    assert!(phys.nibp_status.auto_mode);
+    assert_close(phys.temp1, 36.8);
+    assert_close(phys.co2_et, 5.1);
+    assert_close(phys.co2_fi, 0.4);
+    assert_close(phys.co2_rr, 14.0);
+    assert_close(phys.co2_amb_press, 760.0);
+    assert_close(phys.co2_partial_pressure_mmhg(), 5.1 / 100.0 * 760.0);
+    assert_close(phys.o2_fi, 21.0);
+    assert_close(phys.flow_peep, 6.0);
+    assert_close(phys.flow_ppeak, 22.5);
+    assert_close(phys.flow_tv_exp, 480.0);
+    assert!(phys.invp1_sys.is_none());
+    assert!(phys.temp2.is_none());
+}
+
+#[test]
+fn minute_volume_round_trips() {
+    let vitals = SimVitals {
+        rr: 15.0,
+        tv: 500.0,
+        ..SimVitals::default()
+    };
+
+    let frame = create_phdb_frame(1, &vitals);
+    let Some(DriRecord::Physiological(phys)) = decode_record(&frame).unwrap() else {
+        panic!("expected a physiological record");
+    };
+
+    // 15/min x 500 ml = 7.5 l/min, sent as 750 (1/100 l/min)
+    assert_close(phys.flow_mv_exp, 7.5);
+    assert_close(phys.flow_rr, 15.0);
+}
+
+#[test]
+fn waveform_stream_follows_sample_rates() {
+    let status = WaveformStatus {
+        gap: true,
+        lead_off: true,
+        ..WaveformStatus::default()
+    };
+    // ECG1 (300/s), PLETH (100/s), CO2 (25/s)
+    let mut stream = WaveformStream::new(&[1, 8, 9]);
+
+    let frame = stream.next_frame(0, Duration::from_millis(250), 60.0, status);
+    let Some(DriRecord::Waveform { waveforms }) = decode_record(&frame).unwrap() else {
+        panic!("expected a waveform record");
+    };
+
+    let counts: Vec<usize> = waveforms.iter().map(|wf| wf.samples.len()).collect();
+    assert_eq!(counts, vec![75, 25, 6]);
+    assert!(waveforms.iter().all(|wf| wf.status.gap));
+    // Lead-off only applies to ECG
+    assert!(waveforms[0].status.lead_off);
+    assert!(!waveforms[1].status.lead_off);
+
+    // Every sample is emitted exactly once over a second
+    let mut totals = counts;
+    for (i, millis) in [500, 750, 1000].into_iter().enumerate() {
+        let frame = stream.next_frame(
+            i as u8 + 1,
+            Duration::from_millis(millis),
+            60.0,
+            WaveformStatus::default(),
+        );
+        let Some(DriRecord::Waveform { waveforms }) = decode_record(&frame).unwrap() else {
+            panic!("expected a waveform record");
+        };
+        for (total, wf) in totals.iter_mut().zip(&waveforms) {
+            *total += wf.samples.len();
+        }
+    }
+    assert_eq!(totals, vec![300, 100, 25]);
+}
+
+#[test]
+fn faker_understands_client_requests() {
+    let requests = RequestBuilder::new();
+    assert_eq!(
+        parse_request(&requests.displayed_values(10)),
+        Some(Request::PhdbRequest { interval: 10 })
+    );
+    assert_eq!(
+        parse_request(&requests.waveforms(&[WaveformType::Ecg1, WaveformType::Pleth])),
+        Some(Request::WaveformRequest {
+            waveforms: vec![WaveformType::Ecg1 as u8, WaveformType::Pleth as u8]
+        })
+    );
+    assert_eq!(
+        parse_request(&requests.stop_waveforms()),
+        Some(Request::StopAll)
+    );
+}
+
+#[test]
+fn seeded_runs_produce_identical_frames() {
+    let epoch = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+    let run = |seed: u64| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut vitals = SimVitals::default();
+        let mut stream = WaveformStream::new(&[WaveformType::Ecg1 as u8]).with_epoch(epoch);
+        (0..10u8)
+            .flat_map(|i| {
+                vitals.vary(&mut rng);
+                let elapsed = Duration::from_secs(u64::from(i) + 1);
+                [
+                    create_phdb_frame_at(i, &vitals, epoch + elapsed),
+                    stream.next_frame(i, elapsed, vitals.hr, WaveformStatus::default()),
+                ]
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(run(42), run(42));
+    assert_ne!(run(42), run(43));
+}
+
+#[test]
+fn capnogram_breaths_match_simulated_co2() {
+    // At 60 bpm the simulated capnogram has a 4 s breath: 0.4% then 5.2%
+    let mut stream = WaveformStream::new(&[WaveformType::Co2 as u8]);
+    let mut analyzer = CapnogramAnalyzer::new();
+    let mut breaths = Vec::new();
+    for i in 1..=80u64 {
+        let frame = stream.next_frame(
+            i as u8,
+            Duration::from_millis(i * 250),
+            60.0,
+            WaveformStatus::default(),
+        );
+        let Some(DriRecord::Waveform { waveforms }) = decode_record(&frame).unwrap() else {
+            panic!("expected a waveform record");
+        };
+        breaths.extend(analyzer.analyze(&waveforms[0]).unwrap());
+    }
+
+    assert_eq!(breaths.len(), 4);
+    for breath in &breaths {
+        assert_close(Some(breath.et_co2), 5.2);
+        assert_close(Some(breath.fi_co2), 0.4);
+        // Phase edges fall on the nearest sample, 40 ms apart
+        assert!(
+            (breath.duration.as_secs_f64() - 4.0).abs() < 0.041,
+            "{:?}",
+            breath
+        );
+    }
+}